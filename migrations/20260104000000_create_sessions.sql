@@ -0,0 +1,15 @@
+-- Create sessions table tracking recordings and their transcription status
+CREATE TABLE IF NOT EXISTS sessions (
+    guild_id TEXT NOT NULL,
+    session_id TEXT NOT NULL,
+    path TEXT NOT NULL,
+    duration_secs INTEGER,
+    transcription_status TEXT NOT NULL DEFAULT 'none',
+    transcribed_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY(guild_id, session_id)
+);
+
+-- Create index for listing a guild's sessions newest first
+CREATE INDEX IF NOT EXISTS idx_sessions_guild_created ON sessions(guild_id, created_at);
@@ -0,0 +1,7 @@
+-- Create guild_settings table for per-guild bot configuration
+CREATE TABLE IF NOT EXISTS guild_settings (
+    guild_id TEXT NOT NULL PRIMARY KEY,
+    prefix TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
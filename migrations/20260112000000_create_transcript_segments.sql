@@ -0,0 +1,10 @@
+-- Full-text index of transcribed segments across all sessions of a guild
+CREATE VIRTUAL TABLE IF NOT EXISTS transcript_segments USING fts5(
+    text,
+    guild_id UNINDEXED,
+    session_id UNINDEXED,
+    user_id UNINDEXED,
+    speaker UNINDEXED,
+    start_secs UNINDEXED,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
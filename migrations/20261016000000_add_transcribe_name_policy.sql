@@ -0,0 +1,15 @@
+-- How set-transcribe-name treats users who already have a name: overwrite, history or locked
+ALTER TABLE guild_settings ADD COLUMN name_policy TEXT NOT NULL DEFAULT 'overwrite';
+
+-- Previous transcription names, kept for guilds with the 'history' policy
+CREATE TABLE IF NOT EXISTS transcribe_name_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    guild_id TEXT NOT NULL,
+    old_name TEXT NOT NULL,
+    new_name TEXT NOT NULL,
+    changed_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Create index for looking up a user's previous names
+CREATE INDEX IF NOT EXISTS idx_transcribe_name_history_user_guild ON transcribe_name_history(user_id, guild_id);
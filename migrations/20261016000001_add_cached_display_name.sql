@@ -0,0 +1,2 @@
+-- Discord name fetched when a user has no transcription name, kept apart so name policies don't apply to it
+ALTER TABLE user_settings ADD COLUMN cached_display_name TEXT;
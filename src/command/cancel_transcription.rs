@@ -0,0 +1,45 @@
+use crate::Context;
+use crate::Error;
+use std::sync::atomic::Ordering;
+use tracing::info;
+
+/// Stop this guild's running transcription after the current chunk
+///
+/// Chunks finished so far are kept as a partial transcript.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "cancel-transcription",
+    guild_only
+)]
+pub async fn cancel_transcription(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command must be used in a guild")?;
+
+    let cancel_flag = ctx
+        .data()
+        .transcription_jobs
+        .lock()
+        .map_err(|_| "Transcription job registry is poisoned")?
+        .get(&guild_id.to_string())
+        .cloned();
+
+    match cancel_flag {
+        Some(flag) => {
+            flag.store(true, Ordering::Relaxed);
+            info!(
+                "Cancellation requested for transcription in guild {}",
+                guild_id
+            );
+            ctx.say("⏹️ Cancelling transcription after the current chunk...")
+                .await?;
+        }
+        None => {
+            ctx.say("No transcription is running for this guild.")
+                .await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,438 @@
+use crate::Context;
+use crate::Error;
+use crate::command::long_message::say_long;
+use crate::paths::{exports_root, guild_exports_dir};
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude as serenity;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// How long the confirm button stays usable
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+/// Directories named in the confirmation, the rest are only counted
+const MAX_LISTED_DELETIONS: usize = 15;
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// One directory directly under a guild's exports, usually a session's transcripts
+#[derive(Debug, Clone)]
+struct ExportDir {
+    path: PathBuf,
+    size_bytes: u64,
+    /// Newest modification of anything inside
+    modified: SystemTime,
+}
+
+impl ExportDir {
+    fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map_or_else(String::new, |n| n.to_string_lossy().into_owned())
+    }
+
+    /// `2026_01_03_18_49_53: 12.4 MB, modified 2026-01-03 19:02 UTC`
+    fn describe(&self) -> String {
+        format!(
+            "`{}`: {}, modified {}",
+            self.name(),
+            format_size(self.size_bytes),
+            DateTime::<Utc>::from(self.modified).format("%Y-%m-%d %H:%M UTC")
+        )
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / BYTES_PER_MB as f64)
+}
+
+/// Total size and newest modification time of a directory tree, symlinks aren't followed
+fn dir_usage(dir: &Path) -> io::Result<(u64, SystemTime)> {
+    let metadata = fs::symlink_metadata(dir)?;
+    let mut size = 0;
+    let mut modified = metadata.modified()?;
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            let (dir_size, dir_modified) = dir_usage(&entry.path())?;
+            size += dir_size;
+            modified = modified.max(dir_modified);
+        } else {
+            size += metadata.len();
+            modified = modified.max(metadata.modified()?);
+        }
+    }
+
+    Ok((size, modified))
+}
+
+/// Every export directory directly under `root`, oldest first
+fn scan_exports(root: &Path) -> io::Result<Vec<ExportDir>> {
+    let mut exports = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        // Symlinks may point anywhere, they're neither sized nor deleted
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        match dir_usage(&entry.path()) {
+            Ok((size_bytes, modified)) => exports.push(ExportDir {
+                path: entry.path(),
+                size_bytes,
+                modified,
+            }),
+            Err(e) => warn!("Skipping export {:?}: {}", entry.path(), e),
+        }
+    }
+
+    exports.sort_by_key(|export| export.modified);
+    Ok(exports)
+}
+
+/// Pick exports to delete from `exports` (oldest first)
+///
+/// Everything last modified more than `older_than` before `now` goes, then the
+/// oldest of the rest until the total fits in `max_total_bytes`.
+fn select_for_deletion(
+    exports: &[ExportDir],
+    older_than: Option<Duration>,
+    max_total_bytes: Option<u64>,
+    now: SystemTime,
+) -> Vec<ExportDir> {
+    let cutoff = older_than.and_then(|age| now.checked_sub(age));
+    let mut remaining: u64 = exports.iter().map(|e| e.size_bytes).sum();
+
+    exports
+        .iter()
+        .filter(|export| {
+            let too_old = cutoff.is_some_and(|cutoff| export.modified < cutoff);
+            let over_budget = max_total_bytes.is_some_and(|budget| remaining > budget);
+            if too_old || over_budget {
+                remaining -= export.size_bytes;
+                true
+            } else {
+                false
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+/// Delete an export directory, refusing anything that isn't directly under `root`
+fn remove_export(root: &Path, export: &Path) -> io::Result<()> {
+    let root = root.canonicalize()?;
+    let is_dir = fs::symlink_metadata(export)?.is_dir();
+    if !is_dir || export.canonicalize()?.parent() != Some(root.as_path()) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not an export directory", export.display()),
+        ));
+    }
+    fs::remove_dir_all(export)
+}
+
+/// List this guild's transcript exports with their sizes, optionally deleting old ones
+///
+/// Without a limit this only lists. With `older_than_days` or `max_total_mb`
+/// the matching exports are shown first and only deleted once confirmed. Only
+/// the guild's own exports are ever listed or deleted, never other guilds' in
+/// the same root.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "clean-exports",
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn clean_exports(
+    ctx: Context<'_>,
+    #[description = "Delete exports last modified more than this many days ago"]
+    older_than_days: Option<u32>,
+    #[description = "Delete the oldest exports until all of them fit in this many MB"] max_total_mb: Option<u64>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command must be used in a guild")?;
+    ctx.defer().await?;
+
+    let root = guild_exports_dir(&exports_root(), guild_id.get());
+    if !root.is_dir() {
+        ctx.say("Nothing has been exported from this server yet.")
+            .await?;
+        return Ok(());
+    }
+
+    let scan_root = root.clone();
+    let exports = tokio::task::spawn_blocking(move || scan_exports(&scan_root)).await??;
+    let total_bytes: u64 = exports.iter().map(|e| e.size_bytes).sum();
+
+    if older_than_days.is_none() && max_total_mb.is_none() {
+        if exports.is_empty() {
+            ctx.say("This server has no exports.").await?;
+            return Ok(());
+        }
+        let lines: Vec<String> = exports
+            .iter()
+            .map(|e| format!("• {}", e.describe()))
+            .collect();
+        let summary = format!(
+            "**{} export(s), {} total** (oldest first)",
+            exports.len(),
+            format_size(total_bytes)
+        );
+        return say_long(
+            ctx,
+            format!("{}\n{}", summary, lines.join("\n")),
+            &format!("{}, list attached.", summary),
+            "exports.txt",
+        )
+        .await;
+    }
+
+    let older_than = older_than_days.map(|days| Duration::from_secs(days as u64 * SECS_PER_DAY));
+    let budget = max_total_mb.map(|mb| mb.saturating_mul(BYTES_PER_MB));
+    let doomed = select_for_deletion(&exports, older_than, budget, SystemTime::now());
+    if doomed.is_empty() {
+        ctx.say(format!(
+            "Nothing to delete, {} export(s) using {}.",
+            exports.len(),
+            format_size(total_bytes)
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let doomed_bytes: u64 = doomed.iter().map(|e| e.size_bytes).sum();
+    let mut lines: Vec<String> = doomed
+        .iter()
+        .take(MAX_LISTED_DELETIONS)
+        .map(|e| format!("• {}", e.describe()))
+        .collect();
+    if doomed.len() > MAX_LISTED_DELETIONS {
+        lines.push(format!("…and {} more", doomed.len() - MAX_LISTED_DELETIONS));
+    }
+    let question = format!(
+        "🗑️ **Delete {} of {} export(s), {} of {}?**\n{}",
+        doomed.len(),
+        exports.len(),
+        format_size(doomed_bytes),
+        format_size(total_bytes),
+        lines.join("\n")
+    );
+
+    // Unique per invocation, so a stale button can't confirm a later cleanup
+    let confirm_id = format!("clean-exports-confirm-{}", ctx.id());
+    let cancel_id = format!("clean-exports-cancel-{}", ctx.id());
+    let buttons = serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(&confirm_id)
+            .label("Delete")
+            .style(serenity::ButtonStyle::Danger),
+        serenity::CreateButton::new(&cancel_id)
+            .label("Cancel")
+            .style(serenity::ButtonStyle::Secondary),
+    ]);
+    let status = ctx
+        .send(
+            poise::CreateReply::default()
+                .content(&question)
+                .components(vec![buttons]),
+        )
+        .await?;
+
+    let interaction = serenity::ComponentInteractionCollector::new(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .custom_ids(vec![confirm_id.clone(), cancel_id])
+        .timeout(CONFIRM_TIMEOUT)
+        .await;
+    if let Some(interaction) = &interaction
+        && let Err(e) = interaction
+            .create_response(
+                &ctx.serenity_context().http,
+                serenity::CreateInteractionResponse::Acknowledge,
+            )
+            .await
+    {
+        warn!("Failed to acknowledge clean-exports button: {}", e);
+    }
+
+    let confirmed = interaction.is_some_and(|i| i.data.custom_id == confirm_id);
+    if !confirmed {
+        let reply = poise::CreateReply::default()
+            .content(format!(
+                "{}\n\n❌ Cancelled, nothing was deleted.",
+                question
+            ))
+            .components(vec![]);
+        status.edit(ctx, reply).await?;
+        return Ok(());
+    }
+
+    let (reclaimed, failures) = tokio::task::spawn_blocking(move || {
+        let mut reclaimed = 0;
+        let mut failures = Vec::new();
+        for export in &doomed {
+            match remove_export(&root, &export.path) {
+                Ok(()) => {
+                    info!(
+                        "Deleted export {:?} ({} bytes)",
+                        export.path, export.size_bytes
+                    );
+                    reclaimed += export.size_bytes;
+                }
+                Err(e) => {
+                    warn!("Failed to delete export {:?}: {}", export.path, e);
+                    failures.push(format!("• `{}`: {}", export.name(), e));
+                }
+            }
+        }
+        (reclaimed, failures)
+    })
+    .await?;
+
+    let mut response = format!("✅ Reclaimed {}.", format_size(reclaimed));
+    if !failures.is_empty() {
+        response.push_str(&format!("\n⚠️ Not deleted:\n{}", failures.join("\n")));
+    }
+    status
+        .edit(
+            ctx,
+            poise::CreateReply::default()
+                .content(response)
+                .components(vec![]),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::EXPORTS_DIR;
+
+    fn export(name: &str, size_mb: u64, days_old: u64, now: SystemTime) -> ExportDir {
+        ExportDir {
+            path: guild_exports_dir(Path::new(EXPORTS_DIR), 42).join(name),
+            size_bytes: size_mb * BYTES_PER_MB,
+            modified: now - Duration::from_secs(days_old * SECS_PER_DAY),
+        }
+    }
+
+    fn names(exports: &[ExportDir]) -> Vec<String> {
+        exports.iter().map(ExportDir::name).collect()
+    }
+
+    #[test]
+    fn test_select_for_deletion() {
+        let now = SystemTime::now();
+        // Oldest first, as scanned
+        let exports = vec![
+            export("a", 30, 40, now),
+            export("b", 20, 10, now),
+            export("c", 10, 2, now),
+        ];
+        let days = |d: u64| Some(Duration::from_secs(d * SECS_PER_DAY));
+
+        assert!(select_for_deletion(&exports, None, None, now).is_empty());
+        assert_eq!(
+            names(&select_for_deletion(&exports, days(30), None, now)),
+            ["a"]
+        );
+        assert_eq!(
+            names(&select_for_deletion(&exports, days(5), None, now)),
+            ["a", "b"]
+        );
+        // 60 MB in total, the oldest go until 25 MB are left
+        assert_eq!(
+            names(&select_for_deletion(
+                &exports,
+                None,
+                Some(25 * BYTES_PER_MB),
+                now
+            )),
+            ["a", "b"]
+        );
+        assert_eq!(
+            names(&select_for_deletion(
+                &exports,
+                None,
+                Some(60 * BYTES_PER_MB),
+                now
+            )),
+            Vec::<String>::new()
+        );
+        // Age and budget together: `a` is old enough, which already brings it under budget
+        assert_eq!(
+            names(&select_for_deletion(
+                &exports,
+                days(30),
+                Some(30 * BYTES_PER_MB),
+                now
+            )),
+            ["a"]
+        );
+    }
+
+    #[test]
+    fn test_scan_and_remove_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = guild_exports_dir(&dir.path().join(EXPORTS_DIR), 42);
+        let session = root.join("2026_01_03_18_49_53");
+        fs::create_dir_all(session.join("1_alice")).unwrap();
+        fs::write(session.join("manifest.json"), vec![b'x'; 100]).unwrap();
+        fs::write(session.join("1_alice/transcript.txt"), vec![b'x'; 50]).unwrap();
+        fs::write(root.join("stray.txt"), b"not an export").unwrap();
+
+        let exports = scan_exports(&root).unwrap();
+        assert_eq!(exports.len(), 1);
+        assert_eq!(exports[0].size_bytes, 150);
+
+        // Nothing outside the exports root, nor the root itself, nor files in it
+        let outside = dir.path().join("recordings");
+        fs::create_dir_all(&outside).unwrap();
+        assert!(remove_export(&root, &outside).is_err());
+        assert!(remove_export(&root, &root).is_err());
+        assert!(remove_export(&root, &root.join("stray.txt")).is_err());
+        assert!(remove_export(&root, &session.join("1_alice")).is_err());
+        assert!(outside.exists() && session.join("1_alice").exists());
+
+        remove_export(&root, &session).unwrap();
+        assert!(!session.exists());
+        assert!(scan_exports(&root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_other_guilds_exports_are_left_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let exports = dir.path().join(EXPORTS_DIR);
+        let ours = guild_exports_dir(&exports, 42);
+        let theirs = guild_exports_dir(&exports, 7);
+        fs::create_dir_all(ours.join("2026_01_03_18_49_53")).unwrap();
+        fs::create_dir_all(theirs.join("2026_02_01_10_00_00")).unwrap();
+
+        let scanned = scan_exports(&ours).unwrap();
+        assert_eq!(names(&scanned), ["2026_01_03_18_49_53"]);
+
+        // Not the other guild's exports, nor their whole folder
+        assert!(remove_export(&ours, &theirs.join("2026_02_01_10_00_00")).is_err());
+        assert!(remove_export(&ours, &theirs).is_err());
+        assert!(remove_export(&ours, &ours.join("../7")).is_err());
+        assert!(theirs.join("2026_02_01_10_00_00").is_dir());
+
+        for export in select_for_deletion(
+            &scanned,
+            Some(Duration::ZERO),
+            None,
+            SystemTime::now() + Duration::from_secs(1),
+        ) {
+            remove_export(&ours, &export.path).unwrap();
+        }
+        assert!(scan_exports(&ours).unwrap().is_empty());
+        assert!(theirs.join("2026_02_01_10_00_00").is_dir());
+    }
+}
@@ -0,0 +1,94 @@
+use crate::Context;
+use crate::Error;
+use crate::command::get_transcript::load_user_transcription;
+use crate::command::session::guild_session;
+use crate::paths::SessionPaths;
+use crate::transcribe::{UserTranscription, diff_transcripts};
+use poise::serenity_prelude as serenity;
+use tracing::info;
+
+/// A user's transcription in a session, or why there isn't one
+fn load_transcription(
+    paths: &SessionPaths,
+    session: &str,
+    user: &serenity::User,
+) -> Result<UserTranscription, Error> {
+    if !paths.transcribe_dir().exists() {
+        return Err(format!("No transcription found for `{}`", session).into());
+    }
+
+    load_user_transcription(paths, user.id.get())
+        .map_err(|e| format!("Can't read the transcript in `{}`: {}", session, e))?
+        .ok_or_else(|| format!("No transcript for **{}** in `{}`", user.name, session).into())
+}
+
+/// Compare two transcriptions of the same audio, e.g. from different model sizes
+#[poise::command(prefix_command, slash_command, rename = "compare-transcripts", guild_only)]
+pub async fn compare_transcripts(
+    ctx: Context<'_>,
+    #[description = "Session id with the first transcription (e.g. 2026_01_03_18_49_53)"] left: String,
+    #[description = "Session id with the transcription to compare against the first"] right: String,
+    #[description = "Speaker whose transcripts to compare"] user: serenity::User,
+    #[description = "Format: txt (default), html"] format: Option<String>,
+) -> Result<(), Error> {
+    let format = format
+        .as_deref()
+        .map_or("txt".to_string(), str::to_lowercase);
+    if !matches!(format.as_str(), "txt" | "html") {
+        ctx.say(format!("Unknown format: {}. Use txt or html", format))
+            .await?;
+        return Ok(());
+    }
+
+    let Some(left_paths) = guild_session(ctx, &left).await? else {
+        return Ok(());
+    };
+    let Some(right_paths) = guild_session(ctx, &right).await? else {
+        return Ok(());
+    };
+
+    let (left, right) = match (
+        load_transcription(&left_paths, &left, &user),
+        load_transcription(&right_paths, &right, &user),
+    ) {
+        (Ok(left), Ok(right)) => (left, right),
+        (Err(e), _) | (_, Err(e)) => {
+            ctx.say(format!("❌ {}", e)).await?;
+            return Ok(());
+        }
+    };
+
+    let diff = diff_transcripts(&left, &right);
+    let content = match format.as_str() {
+        "html" => diff.to_html(),
+        _ => diff.to_text(),
+    };
+    let filename = format!(
+        "compare_{}_{}.{}",
+        diff.left_label, diff.right_label, format
+    );
+
+    info!(
+        "Uploading transcript comparison {} ({} bytes)",
+        filename,
+        content.len()
+    );
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format!(
+                "🔍 **{}** vs **{}** for **{}**: {}",
+                diff.left_label,
+                diff.right_label,
+                left.display_name,
+                diff.summary()
+            ))
+            .attachment(serenity::CreateAttachment::bytes(
+                content.into_bytes(),
+                filename,
+            )),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,304 @@
+use crate::Context;
+use crate::Error;
+use crate::voice::SharedRecordingState;
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude as serenity;
+use serenity::model::id::{ChannelId, GuildId, UserId};
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// How long everyone in the channel has to consent before the recording is called off
+const CONSENT_TIMEOUT: Duration = Duration::from_secs(120);
+/// How often the background collector checks whether the recording has stopped
+const COLLECTOR_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Whose audio a recording keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConsentMode {
+    /// Record everyone in the channel
+    #[default]
+    Off,
+    /// Record only users who press the consent button
+    OptIn,
+    /// Like `OptIn`, but the recording only starts once everyone in the channel has consented
+    Everyone,
+}
+
+impl std::fmt::Display for ConsentMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConsentMode::Off => write!(f, "off"),
+            ConsentMode::OptIn => write!(f, "opt-in"),
+            ConsentMode::Everyone => write!(f, "everyone"),
+        }
+    }
+}
+
+impl std::str::FromStr for ConsentMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(ConsentMode::Off),
+            "opt-in" | "optin" => Ok(ConsentMode::OptIn),
+            "everyone" | "all" => Ok(ConsentMode::Everyone),
+            _ => Err(format!(
+                "Unknown consent mode: {}. Use off, opt-in or everyone",
+                s
+            )),
+        }
+    }
+}
+
+/// Consent mode of a recording, given the requested one and what a resumed session stored
+///
+/// A session recorded with consent stays consent-gated: without a mode, or with
+/// `off`, it continues as `OptIn`, so nobody who never agreed is recorded.
+pub fn consent_mode_for(
+    requested: Option<ConsentMode>,
+    stored: Option<&BTreeMap<u64, DateTime<Utc>>>,
+) -> ConsentMode {
+    match (requested, stored) {
+        (requested, None) => requested.unwrap_or_default(),
+        (Some(ConsentMode::Everyone), Some(_)) => ConsentMode::Everyone,
+        (_, Some(_)) => ConsentMode::OptIn,
+    }
+}
+
+/// The consent button, unique per recording so old buttons don't count for new ones
+pub fn consent_button(custom_id: &str) -> serenity::CreateActionRow {
+    serenity::CreateActionRow::Buttons(vec![
+        serenity::CreateButton::new(custom_id)
+            .label("I consent to being recorded")
+            .emoji('✅')
+            .style(serenity::ButtonStyle::Success),
+    ])
+}
+
+/// Users in a voice channel other than bots, who would need to consent
+pub fn channel_members(ctx: Context<'_>, guild_id: GuildId, channel_id: ChannelId) -> Vec<UserId> {
+    let Some(guild) = ctx.serenity_context().cache.guild(guild_id) else {
+        return Vec::new();
+    };
+    guild
+        .voice_states
+        .values()
+        .filter(|vs| vs.channel_id == Some(channel_id))
+        .filter(|vs| !vs.member.as_ref().is_some_and(|m| m.user.bot))
+        .map(|vs| vs.user_id)
+        .collect()
+}
+
+/// Acknowledge a button press privately, so only the presser sees it
+async fn acknowledge(
+    ctx: &serenity::Context,
+    interaction: &serenity::ComponentInteraction,
+    content: &str,
+) {
+    let response = serenity::CreateInteractionResponse::Message(
+        serenity::CreateInteractionResponseMessage::new()
+            .content(content)
+            .ephemeral(true),
+    );
+    if let Err(e) = interaction.create_response(&ctx.http, response).await {
+        warn!(
+            "Failed to acknowledge consent from {}: {}",
+            interaction.user.id, e
+        );
+    }
+}
+
+/// `Waiting for consent from @a, @b`
+fn format_waiting(missing: &[UserId]) -> String {
+    let mentions: Vec<String> = missing.iter().map(|id| format!("<@{}>", id)).collect();
+    format!("Waiting for consent from {}", mentions.join(", "))
+}
+
+/// Ask everyone in the channel to consent, before anything is recorded
+///
+/// Returns who consented and when, or `None` if someone didn't within
+/// `CONSENT_TIMEOUT`, after saying so.
+pub async fn wait_for_everyone(
+    ctx: Context<'_>,
+    members: &[UserId],
+    custom_id: &str,
+) -> Result<Option<BTreeMap<u64, DateTime<Utc>>>, Error> {
+    let intro = format!(
+        "🔒 **Consent required.** Recording starts once everyone in the channel presses the button \
+        (within {} seconds).",
+        CONSENT_TIMEOUT.as_secs()
+    );
+    let status = ctx
+        .send(
+            poise::CreateReply::default()
+                .content(format!("{}\n{}", intro, format_waiting(members)))
+                .components(vec![consent_button(custom_id)]),
+        )
+        .await?;
+
+    let mut consented = BTreeMap::new();
+    let deadline = Instant::now() + CONSENT_TIMEOUT;
+    loop {
+        let missing: Vec<UserId> = members
+            .iter()
+            .filter(|id| !consented.contains_key(&id.get()))
+            .copied()
+            .collect();
+        if missing.is_empty() {
+            return Ok(Some(consented));
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let interaction = serenity::ComponentInteractionCollector::new(ctx.serenity_context())
+            .custom_ids(vec![custom_id.to_string()])
+            .timeout(remaining)
+            .await;
+        let Some(interaction) = interaction else {
+            let reply = poise::CreateReply::default()
+                .content(format!(
+                    "❌ **Recording not started.** {}",
+                    format_waiting(&missing)
+                ))
+                .components(vec![]);
+            status.edit(ctx, reply).await?;
+            return Ok(None);
+        };
+
+        consented
+            .entry(interaction.user.id.get())
+            .or_insert_with(Utc::now);
+        acknowledge(
+            ctx.serenity_context(),
+            &interaction,
+            "✅ Thanks, your consent is recorded.",
+        )
+        .await;
+
+        let missing: Vec<UserId> = missing
+            .into_iter()
+            .filter(|id| *id != interaction.user.id)
+            .collect();
+        if !missing.is_empty() {
+            let reply = poise::CreateReply::default().content(format!(
+                "{}\n{}",
+                intro,
+                format_waiting(&missing)
+            ));
+            if let Err(e) = status.edit(ctx, reply).await {
+                warn!("Failed to update consent status: {}", e);
+            }
+        }
+    }
+}
+
+/// Record consent from button presses for as long as the recording runs
+///
+/// Lets people who join later, or didn't press in time, opt in mid-recording.
+/// Their audio is kept from the moment they consent.
+pub async fn collect_consent(
+    ctx: serenity::Context,
+    state: SharedRecordingState,
+    custom_id: String,
+) {
+    loop {
+        let interaction = serenity::ComponentInteractionCollector::new(&ctx)
+            .custom_ids(vec![custom_id.clone()])
+            .timeout(COLLECTOR_CHECK_INTERVAL)
+            .await;
+
+        let newly_consented = {
+            let mut state = state.lock().await;
+            if !state.active {
+                return;
+            }
+            let Some(interaction) = &interaction else {
+                continue;
+            };
+            match state
+                .consented
+                .get_or_insert_with(BTreeMap::new)
+                .entry(interaction.user.id.get())
+            {
+                Entry::Occupied(_) => false,
+                Entry::Vacant(entry) => {
+                    entry.insert(Utc::now());
+                    true
+                }
+            }
+        };
+
+        if let Some(interaction) = interaction {
+            let content = if newly_consented {
+                info!("User {} consented to being recorded", interaction.user.id);
+                "✅ Thanks, you're being recorded from now on."
+            } else {
+                "You already consented to this recording."
+            };
+            acknowledge(&ctx, &interaction, content).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::SessionMetadata;
+
+    #[test]
+    fn test_consent_mode_parse() {
+        assert_eq!("opt-in".parse::<ConsentMode>(), Ok(ConsentMode::OptIn));
+        assert_eq!("ALL".parse::<ConsentMode>(), Ok(ConsentMode::Everyone));
+        assert_eq!(ConsentMode::default().to_string(), "off");
+        assert!("maybe".parse::<ConsentMode>().is_err());
+    }
+
+    #[test]
+    fn test_resumed_session_keeps_consent() {
+        let before_restart = Utc::now() - chrono::Duration::minutes(10);
+        let mut metadata = SessionMetadata {
+            guild_id: 42,
+            session_id: "2026_01_03_18_49_53".to_string(),
+            started_at: before_restart,
+            channel_bitrate: None,
+            rtc_region: None,
+            packet_stats: Default::default(),
+            gaps: Vec::new(),
+            consent: Some(BTreeMap::from([(7, before_restart)])),
+        };
+
+        // Resumed without the consent argument, or with it off, still only records who agreed
+        let stored = metadata.consent.as_ref();
+        assert_eq!(consent_mode_for(None, stored), ConsentMode::OptIn);
+        assert_eq!(
+            consent_mode_for(Some(ConsentMode::Off), stored),
+            ConsentMode::OptIn
+        );
+        assert_eq!(
+            consent_mode_for(Some(ConsentMode::Everyone), stored),
+            ConsentMode::Everyone
+        );
+        assert_eq!(consent_mode_for(None, None), ConsentMode::Off);
+        assert_eq!(
+            consent_mode_for(Some(ConsentMode::OptIn), None),
+            ConsentMode::OptIn
+        );
+
+        // Saving an ungated state never erases the audit list
+        metadata.record_consent(None);
+        assert_eq!(
+            metadata.consent,
+            Some(BTreeMap::from([(7, before_restart)]))
+        );
+
+        // New consent is added, earlier consent keeps its time
+        let now = Utc::now();
+        metadata.record_consent(Some(BTreeMap::from([(7, now), (8, now)])));
+        assert_eq!(
+            metadata.consent,
+            Some(BTreeMap::from([(7, before_restart), (8, now)]))
+        );
+    }
+}
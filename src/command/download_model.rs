@@ -0,0 +1,168 @@
+use crate::Context;
+use crate::Error;
+use crate::UserError;
+use crate::command::job_slot::acquire_job_slot;
+use crate::command::stop_recording::format_duration;
+use crate::transcribe::{
+    WhisperModel, download_model_with_progress, is_model_downloaded, model_path,
+};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+/// Minimum time between edits of the download status message
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// `412/1500 MB (27%), ETA 3m 12s`, or just the size while the total is unknown
+fn format_download_progress(downloaded: u64, total: u64, elapsed: Duration) -> String {
+    if total == 0 {
+        return format!("{} MB", downloaded / BYTES_PER_MB);
+    }
+
+    let percent = downloaded as f64 * 100.0 / total as f64;
+    let eta = if downloaded > 0 {
+        let remaining_secs =
+            elapsed.as_secs_f64() * (total - downloaded.min(total)) as f64 / downloaded as f64;
+        format!(
+            ", ETA {}",
+            format_duration(chrono::Duration::seconds(remaining_secs.round() as i64))
+        )
+    } else {
+        String::new()
+    };
+    format!(
+        "{}/{} MB ({:.0}%){}",
+        downloaded / BYTES_PER_MB,
+        total / BYTES_PER_MB,
+        percent,
+        eta
+    )
+}
+
+/// Download a Whisper model ahead of transcription, with progress posted to the channel
+///
+/// Needed when `WHISPER_NO_DOWNLOAD` is set, and otherwise pre-warms a model so
+/// the first transcription doesn't wait on a multi-gigabyte download.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "download-model",
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn download_model(
+    ctx: Context<'_>,
+    #[description = "Model size: tiny, base, small (default), medium, large"] model: Option<String>,
+) -> Result<(), Error> {
+    let whisper_model = match model.as_deref() {
+        Some(m) => m
+            .parse::<WhisperModel>()
+            .map_err(UserError)?,
+        None => WhisperModel::Small,
+    };
+
+    if is_model_downloaded(whisper_model) {
+        ctx.say(format!(
+            "✅ Whisper {} model is already present at `{}`",
+            whisper_model,
+            model_path(whisper_model).display()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    ctx.defer().await?;
+    let _slot = acquire_job_slot(ctx).await?;
+    let status_text = format!(
+        "⏳ Downloading Whisper {} model (~{} MB)...",
+        whisper_model,
+        whisper_model.size_mb()
+    );
+    let status = ctx.say(&status_text).await?;
+
+    info!("Downloading Whisper {} model on request", whisper_model);
+    let downloaded = Arc::new(AtomicU64::new(0));
+    let total = Arc::new(AtomicU64::new(0));
+    let started = Instant::now();
+
+    // The download and checksum block, keep them off the async worker
+    let mut task = {
+        let downloaded = downloaded.clone();
+        let total = total.clone();
+        tokio::task::spawn_blocking(move || {
+            download_model_with_progress(whisper_model, &|done, size| {
+                downloaded.store(done, Ordering::Relaxed);
+                total.store(size, Ordering::Relaxed);
+            })
+        })
+    };
+
+    let mut progress = tokio::time::interval_at(
+        tokio::time::Instant::now() + PROGRESS_INTERVAL,
+        PROGRESS_INTERVAL,
+    );
+    progress.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let result = loop {
+        tokio::select! {
+            result = &mut task => break result?,
+            _ = progress.tick() => {
+                let line = format_download_progress(
+                    downloaded.load(Ordering::Relaxed),
+                    total.load(Ordering::Relaxed),
+                    started.elapsed(),
+                );
+                let reply = poise::CreateReply::default().content(format!("{}\n📥 {}", status_text, line));
+                if let Err(e) = status.edit(ctx, reply).await {
+                    warn!("Failed to update download status: {}", e);
+                }
+            }
+        }
+    };
+
+    let response = match result {
+        Ok(path) => {
+            let size_mb = std::fs::metadata(&path).map_or(0, |m| m.len() / BYTES_PER_MB);
+            format!(
+                "✅ Downloaded Whisper {} model ({} MB) to `{}` in {}",
+                whisper_model,
+                size_mb,
+                path.display(),
+                format_duration(chrono::Duration::seconds(started.elapsed().as_secs() as i64))
+            )
+        }
+        Err(e) => format!("❌ Failed to download the {} model: {}", whisper_model, e),
+    };
+    status
+        .edit(ctx, poise::CreateReply::default().content(response))
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_download_progress() {
+        let total = 1000 * BYTES_PER_MB;
+        assert_eq!(
+            format_download_progress(250 * BYTES_PER_MB, total, Duration::from_secs(60)),
+            "250/1000 MB (25%), ETA 3m 0s"
+        );
+        assert_eq!(
+            format_download_progress(0, total, Duration::from_secs(1)),
+            "0/1000 MB (0%)"
+        );
+        // Servers that don't send a length only get the running size
+        assert_eq!(
+            format_download_progress(5 * BYTES_PER_MB, 0, Duration::from_secs(1)),
+            "5 MB"
+        );
+    }
+}
@@ -2,22 +2,25 @@ use crate::Context;
 use crate::Error;
 use crate::db;
 
-#[poise::command(prefix_command, slash_command, rename = "get-transcribe-name")]
+#[poise::command(prefix_command, slash_command, rename = "get-transcribe-name", guild_only)]
 pub async fn get_transcribe_name(ctx: Context<'_>) -> Result<(), Error> {
     let user_id = &ctx.author().id.to_string();
-    let guild_id = &ctx.guild_id().unwrap().to_string();
+    let guild_id = &ctx
+        .guild_id()
+        .ok_or("This command must be used in a guild")?
+        .to_string();
 
     let user_setting = db::get_user_setting(&ctx.data().db, user_id, guild_id).await?;
 
-    if user_setting.is_none() {
-        ctx.say("No Transcribtion Name set on this server.").await?;
-        return Ok(());
-    }
-
-    let transcribe_name = user_setting.unwrap().transcribe_name;
-    match transcribe_name {
+    match user_setting.and_then(|setting| setting.transcribe_name) {
         Some(name) => {
-            ctx.say(format!("Transcribtion Name is {name}!")).await?;
+            let previous = db::transcribe_name_history(&ctx.data().db, user_id, guild_id).await?;
+            if previous.is_empty() {
+                ctx.say(format!("Transcribtion Name is {name}!")).await?;
+            } else {
+                ctx.say(format!("Transcribtion Name is {name}! Previously: {}", previous.join(", ")))
+                    .await?;
+            }
         }
         None => {
             ctx.say("No Transcribtion Name set on this server.").await?;
@@ -0,0 +1,220 @@
+use crate::Context;
+use crate::Error;
+use crate::command::search_transcript::{load_combined_transcriptions, load_transcriptions};
+use crate::command::session::guild_session;
+use crate::paths::SessionPaths;
+use crate::transcribe::{
+    ConversationConfig, UserTranscription, transcript_markdown, transcript_text, user_srt, user_vtt,
+};
+use poise::serenity_prelude as serenity;
+use std::fs;
+use std::path::PathBuf;
+use tracing::info;
+
+/// Find a user's transcription directory (`<user_id>_<name>`) in a session
+pub fn find_user_dir(paths: &SessionPaths, user_id: u64) -> Option<PathBuf> {
+    let prefix = format!("{}_", user_id);
+
+    fs::read_dir(paths.transcribe_dir())
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| {
+            p.is_dir()
+                && p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with(&prefix))
+        })
+}
+
+/// Read one user's transcription from a session, `None` when they have none
+///
+/// Sessions transcribed with the combined layout have no user folders.
+pub fn load_user_transcription(
+    paths: &SessionPaths,
+    user_id: u64,
+) -> Result<Option<UserTranscription>, Error> {
+    if let Some(tracks) = load_combined_transcriptions(paths) {
+        return Ok(tracks
+            .into_iter()
+            .map(|(t, _)| t)
+            .find(|t| t.user_id == user_id));
+    }
+
+    let Some(dir) = find_user_dir(paths, user_id) else {
+        return Ok(None);
+    };
+    let json = fs::read_to_string(dir.join("transcription.json"))?;
+    Ok(Some(serde_json::from_str(&json)?))
+}
+
+/// Upload every speaker's transcript as one Markdown or plain text document
+async fn send_session_transcript(
+    ctx: Context<'_>,
+    paths: &SessionPaths,
+    format: &str,
+    by_speaker: bool,
+    config: &ConversationConfig,
+) -> Result<(), Error> {
+    let transcriptions = load_transcriptions(paths);
+    if transcriptions.is_empty() {
+        ctx.say("No readable transcripts in this session.").await?;
+        return Ok(());
+    }
+
+    let session_name = paths.session_id().unwrap_or("session");
+    let tracks: Vec<(&UserTranscription, u64)> =
+        transcriptions.iter().map(|(t, tick)| (t, *tick)).collect();
+    let content = if format == "txt" {
+        transcript_text(&tracks, config)
+    } else {
+        transcript_markdown(session_name, &tracks, by_speaker, config)
+    };
+    let filename = format!("{}.{}", session_name, format);
+
+    info!(
+        "Uploading transcript {} ({} bytes)",
+        filename,
+        content.len()
+    );
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format!(
+                "📝 Transcript of **{}** speaker(s)",
+                transcriptions.len()
+            ))
+            .attachment(serenity::CreateAttachment::bytes(
+                content.into_bytes(),
+                filename,
+            )),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Upload a transcript from a transcribed session
+#[poise::command(prefix_command, slash_command, rename = "get-transcript", guild_only)]
+pub async fn get_transcript(
+    ctx: Context<'_>,
+    #[description = "Session id from /list-sessions (e.g. 2026_01_03_18_49_53)"] session: String,
+    #[description = "User whose transcript to get (leave empty for the whole session as Markdown)"]
+    user: Option<serenity::User>,
+    #[description = "Format: srt (default), vtt, txt, json, md"] format: Option<String>,
+    #[description = "Markdown only: one collapsible section per speaker (default: false)"]
+    by_speaker: Option<bool>,
+    #[description = "Whole session: pause in seconds after which a speaker starts a new paragraph (default: 2)"]
+    turn_gap_secs: Option<f32>,
+    #[description = "Whole session: mark speakers starting while someone else still talks (default: true)"]
+    mark_overlaps: Option<bool>,
+) -> Result<(), Error> {
+    let defaults = ConversationConfig::default();
+    let conversation = match ConversationConfig::new(
+        turn_gap_secs.unwrap_or(defaults.turn_gap_secs),
+        mark_overlaps.unwrap_or(defaults.mark_overlaps),
+    ) {
+        Ok(conversation) => conversation,
+        Err(e) => {
+            ctx.say(e).await?;
+            return Ok(());
+        }
+    };
+    let format = match format.as_deref().map(str::to_lowercase) {
+        // A whole session only makes sense as a single document
+        None if user.is_none() => "md".to_string(),
+        None => "srt".to_string(),
+        Some(f) if f == "markdown" => "md".to_string(),
+        Some(f) => f,
+    };
+    if !matches!(format.as_str(), "srt" | "vtt" | "txt" | "json" | "md") {
+        ctx.say(format!(
+            "Unknown format: {}. Use srt, vtt, txt, json, or md",
+            format
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let Some(paths) = guild_session(ctx, &session).await? else {
+        return Ok(());
+    };
+    if !paths.transcribe_dir().exists() {
+        ctx.say(format!(
+            "No transcription found for `{}`. Run `/transcribe-session` first.",
+            session
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let Some(user) = user else {
+        if format != "md" && format != "txt" {
+            ctx.say("Pick a user for srt, vtt and json transcripts, only md and txt cover the whole session.")
+                .await?;
+            return Ok(());
+        }
+        return send_session_transcript(
+            ctx,
+            &paths,
+            &format,
+            by_speaker.unwrap_or(false),
+            &conversation,
+        )
+        .await;
+    };
+
+    let transcription = match load_user_transcription(&paths, user.id.get()) {
+        Ok(t) => t,
+        Err(e) => {
+            ctx.say(format!("❌ Failed to read transcript: {}", e))
+                .await?;
+            return Ok(());
+        }
+    };
+    let Some(transcription) = transcription else {
+        ctx.say(format!(
+            "No transcript for **{}** in this session.",
+            user.name
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    let content = match format.as_str() {
+        "txt" => transcription.full_transcript.clone(),
+        "json" => serde_json::to_string_pretty(&transcription)?,
+        "md" => transcript_markdown(
+            paths.session_id().unwrap_or("session"),
+            &[(&transcription, 0)],
+            by_speaker.unwrap_or(false),
+            &conversation,
+        ),
+        "vtt" => user_vtt(&transcription),
+        _ => user_srt(&transcription),
+    };
+
+    let session_name = paths.session_id().unwrap_or("session");
+    let filename = format!("{}_{}.{}", session_name, user.id, format);
+
+    info!(
+        "Uploading transcript {} ({} bytes)",
+        filename,
+        content.len()
+    );
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format!(
+                "📝 Transcript for **{}**",
+                transcription.display_name
+            ))
+            .attachment(serenity::CreateAttachment::bytes(
+                content.into_bytes(),
+                filename,
+            )),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,160 @@
+use crate::Context;
+use crate::Error;
+use crate::command::get_transcript::find_user_dir;
+use crate::command::search_transcript::{
+    index_session, load_combined_transcriptions, load_transcriptions, write_combined_transcriptions,
+};
+use crate::command::session::guild_session;
+use crate::command::transcribe_session::write_user_transcript;
+use crate::transcribe::{
+    ConversationConfig, UserTranscription, segments_from_srt, segments_from_vtt, session_vtt,
+    transcript_markdown, transcript_text,
+};
+use poise::serenity_prelude as serenity;
+use std::fs;
+use tracing::{info, warn};
+
+/// Subtitle files are small, anything bigger is not one
+const MAX_IMPORT_BYTES: u32 = 10 * 1024 * 1024;
+
+/// Replace a user's transcript with subtitles fixed up in an external editor
+///
+/// Rewrites the user's transcript files (or the combined layout's `transcriptions.json`
+/// and merged transcript), the session subtitles and the search index, so search and
+/// exports pick up the corrections.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "import-transcript",
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn import_transcript(
+    ctx: Context<'_>,
+    #[description = "Session id from /list-sessions (e.g. 2026_01_03_18_49_53)"] session: String,
+    #[description = "User whose transcript to replace"] user: serenity::User,
+    #[description = "Corrected subtitles (.srt or .vtt)"] file: serenity::Attachment,
+) -> Result<(), Error> {
+    let is_vtt = file.filename.to_lowercase().ends_with(".vtt");
+    if !is_vtt && !file.filename.to_lowercase().ends_with(".srt") {
+        ctx.say("Attach an `.srt` or `.vtt` subtitle file.").await?;
+        return Ok(());
+    }
+    if file.size > MAX_IMPORT_BYTES {
+        ctx.say(format!(
+            "`{}` is too large for a subtitle file.",
+            file.filename
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let Some(paths) = guild_session(ctx, &session).await? else {
+        return Ok(());
+    };
+    // Sessions transcribed with the combined layout keep everyone in one file
+    let combined = load_combined_transcriptions(&paths);
+    let user_dir = find_user_dir(&paths, user.id.get());
+    let existing = match (&combined, &user_dir) {
+        (Some(tracks), _) => Ok(tracks
+            .iter()
+            .map(|(t, _)| t)
+            .find(|t| t.user_id == user.id.get())
+            .cloned()),
+        (None, Some(dir)) => fs::read_to_string(dir.join("transcription.json"))
+            .map_err(Error::from)
+            .and_then(|json| serde_json::from_str(&json).map_err(Error::from))
+            .map(Some),
+        (None, None) => Ok(None),
+    };
+    let mut transcription: UserTranscription = match existing {
+        Ok(Some(t)) => t,
+        Ok(None) => {
+            ctx.say(format!(
+                "No transcript for **{}** in this session.",
+                user.name
+            ))
+            .await?;
+            return Ok(());
+        }
+        Err(e) => {
+            ctx.say(format!("❌ Failed to read transcript: {}", e))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let content = String::from_utf8_lossy(&file.download().await?).into_owned();
+    let segments = match if is_vtt {
+        segments_from_vtt(&content)
+    } else {
+        segments_from_srt(&content)
+    } {
+        Ok(segments) => segments,
+        Err(e) => {
+            ctx.say(format!("❌ Can't read `{}`: {}", file.filename, e))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let previous = transcription.all_segments.len();
+    transcription.replace_segments(segments);
+    if let Some(mut tracks) = combined {
+        for (t, _) in tracks
+            .iter_mut()
+            .filter(|(t, _)| t.user_id == transcription.user_id)
+        {
+            *t = transcription.clone();
+        }
+        let tracks: Vec<(&UserTranscription, u64)> =
+            tracks.iter().map(|(t, tick)| (t, *tick)).collect();
+        write_combined_transcriptions(&paths, &tracks)?;
+        let session_id = paths.session_id().unwrap_or("session");
+        let config = ConversationConfig::default();
+        fs::write(
+            paths.session_transcript("md"),
+            transcript_markdown(session_id, &tracks, false, &config),
+        )?;
+        fs::write(
+            paths.session_transcript("txt"),
+            transcript_text(&tracks, &config),
+        )?;
+    } else if let Some(dir) = user_dir {
+        write_user_transcript(&dir, &transcription)?;
+    }
+
+    // The merged subtitles span every speaker, so they go stale with any one of them
+    let transcriptions = load_transcriptions(&paths);
+    let tracks: Vec<(&UserTranscription, u64)> =
+        transcriptions.iter().map(|(t, tick)| (t, *tick)).collect();
+    if !tracks.is_empty() {
+        fs::write(paths.session_vtt(), session_vtt(&tracks))?;
+    }
+
+    // The guild's archive is searched across sessions, a failed reindex leaves the old segments there
+    if let Some(guild_id) = ctx.guild_id()
+        && let Err(e) = index_session(&ctx.data().db, &guild_id.to_string(), &paths).await
+    {
+        warn!("Failed to reindex session {} after import: {}", session, e);
+    }
+
+    info!(
+        "Imported {} segments from {} for user {} in {}",
+        transcription.all_segments.len(),
+        file.filename,
+        user.id,
+        session
+    );
+
+    ctx.say(format!(
+        "✅ Replaced **{}**'s transcript with {} segments from `{}` (was {}).",
+        transcription.display_name,
+        transcription.all_segments.len(),
+        file.filename,
+        previous
+    ))
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use crate::Context;
+use crate::Error;
+use crate::command::search_transcript::index_session;
+use crate::paths::{RECORDINGS_DIR, SessionPaths};
+use std::fs;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Add every transcribed session of this guild to the search archive
+///
+/// New transcriptions are indexed automatically; this rebuilds the index for
+/// sessions transcribed earlier or edited by hand.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "index-transcripts",
+    guild_only
+)]
+pub async fn index_transcripts(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command must be used in a guild")?;
+
+    ctx.defer().await?;
+
+    let guild_dir = Path::new(RECORDINGS_DIR).join(guild_id.to_string());
+    let mut sessions: Vec<SessionPaths> = match fs::read_dir(&guild_dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| SessionPaths::new(e.path()))
+            .filter(|paths| paths.transcribe_manifest().exists())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    sessions.sort_by(|a, b| a.root().cmp(b.root()));
+
+    let mut indexed_sessions = 0;
+    let mut indexed_segments = 0;
+    for paths in &sessions {
+        match index_session(&ctx.data().db, &guild_id.to_string(), paths).await {
+            Ok(segments) => {
+                indexed_sessions += 1;
+                indexed_segments += segments;
+            }
+            Err(e) => warn!("Failed to index {:?}: {}", paths.root(), e),
+        }
+    }
+
+    info!(
+        "Indexed {} sessions ({} segments) for guild {}",
+        indexed_sessions, indexed_segments, guild_id
+    );
+
+    ctx.say(format!(
+        "🗂️ Indexed {} of {} transcribed session(s), {} segments. Use `/search-transcript` without a session to search them.",
+        indexed_sessions,
+        sessions.len(),
+        indexed_segments
+    ))
+    .await?;
+    Ok(())
+}
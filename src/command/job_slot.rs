@@ -0,0 +1,20 @@
+use crate::Context;
+use crate::Error;
+use tokio::sync::{OwnedSemaphorePermit, TryAcquireError};
+
+/// Wait for a free slot to run a heavy job (transcription or audio reconstruction)
+///
+/// Jobs hold the slot until the permit is dropped. When every slot is taken the
+/// job queues, and the user is told so it doesn't look stuck.
+pub(crate) async fn acquire_job_slot(ctx: Context<'_>) -> Result<OwnedSemaphorePermit, Error> {
+    let slots = ctx.data().job_slots.clone();
+    match slots.clone().try_acquire_owned() {
+        Ok(permit) => Ok(permit),
+        Err(TryAcquireError::NoPermits) => {
+            ctx.say("⏳ The bot is busy with other transcriptions or exports, this job is queued and starts when one finishes.")
+                .await?;
+            Ok(slots.acquire_owned().await?)
+        }
+        Err(TryAcquireError::Closed) => Err("Job slots are closed".into()),
+    }
+}
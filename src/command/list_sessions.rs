@@ -0,0 +1,71 @@
+use crate::Context;
+use crate::Error;
+use crate::command::stop_recording::format_duration;
+use crate::db::{self, TranscriptionStatus};
+use crate::paths::SessionPaths;
+use crate::voice::storage::scan_session;
+
+const MAX_LISTED_SESSIONS: i64 = 15;
+
+#[poise::command(prefix_command, slash_command, rename = "list-sessions", guild_only)]
+pub async fn list_sessions(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command must be used in a guild")?;
+
+    let sessions =
+        db::list_sessions(&ctx.data().db, &guild_id.to_string(), MAX_LISTED_SESSIONS).await?;
+
+    if sessions.is_empty() {
+        ctx.say("No recorded sessions on this server.").await?;
+        return Ok(());
+    }
+
+    let active_session_id = ctx
+        .data()
+        .active_sessions
+        .lock()
+        .await
+        .get(&guild_id.get())
+        .map(|session| session.session_id.clone());
+
+    let mut response = format!("**Recent sessions ({}):**\n", sessions.len());
+    for session in sessions {
+        let duration = match session.duration_secs {
+            Some(secs) => format_duration(chrono::Duration::seconds(secs)),
+            None if active_session_id.as_deref() == Some(session.session_id.as_str()) => {
+                "in progress".to_string()
+            }
+            // Never stopped (e.g. the bot went down), estimate from the recorded ticks
+            None => {
+                let paths = SessionPaths::new(&session.path);
+                match tokio::task::spawn_blocking(move || scan_session(&paths)).await? {
+                    Ok(Some(scan)) => format!(
+                        "~{} (interrupted)",
+                        format_duration(chrono::Duration::seconds(scan.duration_secs() as i64))
+                    ),
+                    _ => "interrupted".to_string(),
+                }
+            }
+        };
+
+        let status = match session.status() {
+            TranscriptionStatus::Completed => format!(
+                "✅ transcribed {}",
+                session.transcribed_at.as_deref().unwrap_or("")
+            ),
+            TranscriptionStatus::InProgress => "⏳ transcribing".to_string(),
+            TranscriptionStatus::Failed => "❌ transcription failed".to_string(),
+            TranscriptionStatus::Cancelled => "⏹️ transcription cancelled".to_string(),
+            TranscriptionStatus::None => "not transcribed".to_string(),
+        };
+
+        response.push_str(&format!(
+            "- `{}` - {} - {}\n",
+            session.session_id, duration, status
+        ));
+    }
+
+    ctx.say(response).await?;
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use crate::Context;
 use crate::Error;
+use crate::command::long_message::say_long;
 use poise::serenity_prelude as serenity;
 
 #[poise::command(prefix_command, slash_command, rename = "list-voice-users", guild_only)]
@@ -98,6 +99,7 @@ pub async fn list_voice_users(
         return Ok(());
     }
 
+    let user_count = users_in_channel.len();
     let mut response = format!("**Users in <#{}>:**\n", voice_channel_id);
     for (user_id, display_name, username) in users_in_channel {
         response.push_str(&format!(
@@ -106,6 +108,11 @@ pub async fn list_voice_users(
         ));
     }
 
-    ctx.say(response).await?;
-    Ok(())
+    say_long(
+        ctx,
+        response,
+        &format!("**{} users in <#{}>**, list attached.", user_count, voice_channel_id),
+        "voice_users.txt",
+    )
+    .await
 }
@@ -0,0 +1,93 @@
+use crate::Context;
+use crate::Error;
+use poise::serenity_prelude as serenity;
+
+/// Discord rejects message content longer than this many characters
+const MAX_MESSAGE_CHARS: usize = 2000;
+/// Longer responses are uploaded as a file instead of flooding the channel
+const MAX_SPLIT_MESSAGES: usize = 3;
+
+/// Split text into parts of at most `limit` characters, at line breaks where possible
+pub(crate) fn split_message(text: &str, limit: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut current_chars = 0;
+
+    for line in text.split_inclusive('\n') {
+        let line_chars = line.chars().count();
+        if current_chars + line_chars > limit && !current.is_empty() {
+            parts.push(std::mem::take(&mut current));
+            current_chars = 0;
+        }
+
+        if line_chars <= limit {
+            current.push_str(line);
+            current_chars += line_chars;
+            continue;
+        }
+
+        // A single line over the limit is cut between characters
+        let chars: Vec<char> = line.chars().collect();
+        for piece in chars.chunks(limit) {
+            if piece.len() == limit {
+                parts.push(piece.iter().collect());
+            } else {
+                current = piece.iter().collect();
+                current_chars = piece.len();
+            }
+        }
+    }
+
+    if !current.trim().is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Send a response that may exceed Discord's message limit
+///
+/// Short overflows are split over a few messages at line breaks, anything longer
+/// is attached as `filename` with `summary` as the message.
+pub(crate) async fn say_long(
+    ctx: Context<'_>,
+    content: String,
+    summary: &str,
+    filename: &str,
+) -> Result<(), Error> {
+    let parts = split_message(&content, MAX_MESSAGE_CHARS);
+
+    if parts.len() > MAX_SPLIT_MESSAGES {
+        ctx.send(poise::CreateReply::default().content(summary).attachment(
+            serenity::CreateAttachment::bytes(content.into_bytes(), filename),
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    for part in parts {
+        ctx.say(part).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_message_at_line_breaks() {
+        assert_eq!(split_message("short\n", 2000), vec!["short\n"]);
+
+        let text = "aaaa\nbbbb\ncccc\n";
+        assert_eq!(split_message(text, 10), vec!["aaaa\nbbbb\n", "cccc\n"]);
+
+        // An overlong line is cut, and its remainder can be joined by following lines
+        let parts = split_message("0123456789abc\nde", 5);
+        assert_eq!(parts, vec!["01234", "56789", "abc\n", "de"]);
+        assert_eq!(split_message("0123456\nx", 5), vec!["01234", "56\nx"]);
+        assert!(parts.iter().all(|p| p.chars().count() <= 5));
+
+        // Limits count characters, not bytes
+        assert_eq!(split_message("ééé", 2), vec!["éé", "é"]);
+    }
+}
@@ -1,15 +1,48 @@
+pub mod cancel_transcription;
+pub mod clean_exports;
+pub mod compare_transcripts;
+mod consent;
+pub mod download_model;
 pub mod get_transcribe_name;
+pub mod get_transcript;
+pub mod import_transcript;
+pub mod index_transcripts;
+mod job_slot;
+pub mod list_sessions;
 pub mod list_voice_users;
+mod long_message;
+mod permissions;
 pub mod reconstruct_audio;
+pub mod recording_status;
+pub mod search_transcript;
+mod session;
+pub mod set_name_policy;
+pub mod set_prefix;
 pub mod set_transcribe_name;
+pub mod share_audio;
 pub mod start_recording;
 pub mod stop_recording;
+pub mod transcribe_file;
 pub mod transcribe_session;
 
+pub use cancel_transcription::cancel_transcription;
+pub use clean_exports::clean_exports;
+pub use compare_transcripts::compare_transcripts;
+pub use download_model::download_model;
 pub use get_transcribe_name::get_transcribe_name;
+pub use get_transcript::get_transcript;
+pub use import_transcript::import_transcript;
+pub use index_transcripts::index_transcripts;
+pub use list_sessions::list_sessions;
 pub use list_voice_users::list_voice_users;
 pub use reconstruct_audio::reconstruct_audio;
+pub use recording_status::recording_status;
+pub use search_transcript::search_transcript;
+pub use set_name_policy::set_name_policy;
+pub use set_prefix::set_prefix;
 pub use set_transcribe_name::set_transcribe_name;
+pub use share_audio::share_audio;
 pub use start_recording::start_recording;
 pub use stop_recording::stop_recording;
+pub use transcribe_file::transcribe_file;
 pub use transcribe_session::transcribe_session;
\ No newline at end of file
@@ -0,0 +1,17 @@
+use crate::Context;
+
+/// Whether the author may manage the guild, for options that reach beyond one session
+///
+/// `required_permissions` gates whole commands, this is for single options of
+/// commands everyone can use.
+pub(crate) async fn author_can_manage_guild(ctx: Context<'_>) -> bool {
+    let Some(member) = ctx.author_member().await else {
+        return false;
+    };
+    // Slash commands come with the resolved permissions, prefix commands need the cache
+    if let Some(permissions) = member.permissions {
+        return permissions.manage_guild();
+    }
+    ctx.guild()
+        .is_some_and(|guild| guild.member_permissions(&member).manage_guild())
+}
@@ -1,19 +1,143 @@
+use crate::command::job_slot::acquire_job_slot;
+use crate::paths::SessionPaths;
+use crate::transcribe::load_ssrc_map;
+use crate::voice::storage::{AudioFrame, compact_frames};
+use crate::paths::{FilenameTemplate, FilenameValues, SessionMetadata};
+use crate::voice::audio::{
+    ClipMode, MP3_BYTES_PER_SEC, WavDepth, WavInfo, encode_mp3, f32_to_i16, i16_to_f32, is_all_silence,
+    mix_tracks_weighted, save_flac, save_mp3, write_wav_sample,
+};
+use crate::voice::waveform::render_waveform_png;
 use crate::Context;
 use crate::Error;
-use hound::{WavSpec, WavWriter};
-use std::collections::BTreeMap;
+use crate::UserError;
+use hound::WavWriter;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
-use tracing::info;
+use std::io::{BufRead, BufReader, BufWriter};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{error, info, warn};
 
 const SAMPLE_RATE: u32 = 48000;
 const SAMPLES_PER_FRAME: usize = 960;
+const TICKS_PER_SEC: usize = SAMPLE_RATE as usize / SAMPLES_PER_FRAME;
+/// Bounds on the per-user gain applied when balancing loudness in the merged mix
+const MIN_BALANCE_WEIGHT: f32 = 0.25;
+const MAX_BALANCE_WEIGHT: f32 = 4.0;
+/// Upper bound on the default number of users reconstructed at once
+const MAX_DEFAULT_CONCURRENCY: usize = 4;
 
-#[derive(Debug)]
-struct AudioFrame {
-    tick_index: u64,
-    samples: Vec<i16>,
+/// A user's decoded frames keyed by tick, with their label and first tick
+type UserFrames = (String, BTreeMap<u64, Vec<i16>>, u64);
+
+/// What reconstructing one user produced
+enum UserOutcome {
+    Written(UserFrames),
+    /// No frames at all
+    Empty,
+    /// Only silence, e.g. a user who stayed muted; nothing is written
+    Silent(String),
+}
+
+fn frames_silent(frames: &BTreeMap<u64, Vec<i16>>) -> bool {
+    is_all_silence(frames.values().flatten().map(|&s| i16_to_f32(s)))
+}
+
+/// File format for reconstructed audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Wav(WavDepth),
+    /// Lossless and much smaller, but encoded in memory at the end
+    Flac,
+    /// Lossy 96kbps, small enough to attach to a Discord message
+    Mp3,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Wav(WavDepth::Int16)
+    }
+}
+
+impl OutputFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Wav(_) => "wav",
+            OutputFormat::Flac => "flac",
+            OutputFormat::Mp3 => "mp3",
+        }
+    }
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "wav" | "wav16" => Ok(OutputFormat::Wav(WavDepth::Int16)),
+            "wav24" => Ok(OutputFormat::Wav(WavDepth::Int24)),
+            "wav32f" => Ok(OutputFormat::Wav(WavDepth::Float32)),
+            "flac" => Ok(OutputFormat::Flac),
+            "mp3" => Ok(OutputFormat::Mp3),
+            _ => Err(format!("Unknown format: {}. Use wav, wav24, wav32f, flac or mp3", s)),
+        }
+    }
+}
+
+/// Mono 48kHz sample writer for any output format
+///
+/// Takes floating point samples (full scale at 1.0) so mixes keep their
+/// headroom until they're quantized for the output format.
+enum PcmWriter {
+    Wav {
+        writer: WavWriter<BufWriter<File>>,
+        depth: WavDepth,
+    },
+    Flac { path: PathBuf, samples: Vec<i16> },
+    Mp3 { path: PathBuf, samples: Vec<i16> },
+}
+
+impl PcmWriter {
+    fn create(
+        output_path: &PathBuf,
+        format: OutputFormat,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        match format {
+            OutputFormat::Wav(depth) => Ok(PcmWriter::Wav {
+                writer: WavWriter::create(output_path, depth.spec(SAMPLE_RATE, 1))?,
+                depth,
+            }),
+            OutputFormat::Flac => Ok(PcmWriter::Flac {
+                path: output_path.clone(),
+                samples: Vec::new(),
+            }),
+            OutputFormat::Mp3 => Ok(PcmWriter::Mp3 {
+                path: output_path.clone(),
+                samples: Vec::new(),
+            }),
+        }
+    }
+
+    fn write_sample(&mut self, sample: f32) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            PcmWriter::Wav { writer, depth } => write_wav_sample(writer, sample, *depth)?,
+            PcmWriter::Flac { samples, .. } | PcmWriter::Mp3 { samples, .. } => {
+                samples.push(f32_to_i16(sample))
+            }
+        }
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            PcmWriter::Wav { writer, .. } => writer.finalize()?,
+            PcmWriter::Flac { path, samples } => save_flac(&path, &samples, SAMPLE_RATE, 1)?,
+            PcmWriter::Mp3 { path, samples } => save_mp3(&path, &samples, SAMPLE_RATE, 1)?,
+        }
+        Ok(())
+    }
 }
 
 fn parse_log_file(path: &PathBuf) -> Result<Vec<AudioFrame>, Box<dyn std::error::Error + Send + Sync>> {
@@ -45,7 +169,7 @@ fn parse_log_file(path: &PathBuf) -> Result<Vec<AudioFrame>, Box<dyn std::error:
 }
 
 fn load_user_audio(user_dir: &PathBuf) -> Result<BTreeMap<u64, Vec<i16>>, Box<dyn std::error::Error + Send + Sync>> {
-    let mut all_frames: BTreeMap<u64, Vec<i16>> = BTreeMap::new();
+    let mut all_frames = Vec::new();
 
     // Find all chunk-*.log files
     let mut chunk_files: Vec<PathBuf> = fs::read_dir(user_dir)?
@@ -73,48 +197,54 @@ fn load_user_audio(user_dir: &PathBuf) -> Result<BTreeMap<u64, Vec<i16>>, Box<dy
 
     for chunk_path in chunk_files {
         info!("Loading {:?}", chunk_path);
-        let frames = parse_log_file(&chunk_path)?;
-        for frame in frames {
-            all_frames.insert(frame.tick_index, frame.samples);
-        }
+        all_frames.extend(parse_log_file(&chunk_path)?);
     }
 
-    Ok(all_frames)
+    // Fragmented ticks would otherwise overwrite each other
+    Ok(compact_frames(all_frames)
+        .into_iter()
+        .map(|frame| (frame.tick_index, frame.samples))
+        .collect())
 }
 
-fn write_wav(
+fn write_audio(
     frames: &BTreeMap<u64, Vec<i16>>,
     output_path: &PathBuf,
+    format: OutputFormat,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if frames.is_empty() {
         return Err("No frames to write".into());
     }
 
-    let spec = WavSpec {
-        channels: 1,
-        sample_rate: SAMPLE_RATE,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
-    };
-
-    let mut writer = WavWriter::create(output_path, spec)?;
-
     let first_tick = *frames.keys().next().unwrap();
     let last_tick = *frames.keys().next_back().unwrap();
 
+    write_audio_range(frames, first_tick..=last_tick, output_path, format)
+}
+
+/// Write a user's frames over a tick range, with silence for ticks they have no audio in
+fn write_audio_range(
+    frames: &BTreeMap<u64, Vec<i16>>,
+    ticks: RangeInclusive<u64>,
+    output_path: &PathBuf,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut writer = PcmWriter::create(output_path, format)?;
+
     info!(
-        "Writing WAV from tick {} to {} ({} unique frames)",
-        first_tick,
-        last_tick,
+        "Writing {:?} from tick {} to {} ({} unique frames)",
+        format,
+        ticks.start(),
+        ticks.end(),
         frames.len()
     );
 
     let silence = vec![0i16; SAMPLES_PER_FRAME];
 
-    for tick in first_tick..=last_tick {
+    for tick in ticks {
         let samples = frames.get(&tick).unwrap_or(&silence);
         for &sample in samples {
-            writer.write_sample(sample)?;
+            writer.write_sample(i16_to_f32(sample))?;
         }
     }
 
@@ -122,180 +252,1045 @@ fn write_wav(
     Ok(())
 }
 
+/// Tick range covering every user's audio, from the earliest first tick to the latest last tick
+fn session_tick_range(user_audio: &[UserFrames]) -> Option<RangeInclusive<u64>> {
+    let first = user_audio.iter().map(|(_, _, first_tick)| *first_tick).min()?;
+    let last = user_audio
+        .iter()
+        .filter_map(|(_, frames, _)| frames.keys().next_back().copied())
+        .max()?;
+    (last >= first).then_some(first..=last)
+}
+
+/// Entry in `aligned/tracks.json`
+#[derive(Debug, serde::Serialize)]
+struct AlignedTrack {
+    file: String,
+    ssrc: String,
+    user_id: Option<u64>,
+    /// Where the user's first audio is in the track
+    first_audio_secs: f64,
+}
+
+/// Write every user's audio over the whole session's tick range
+///
+/// All tracks start at the earliest user's first tick and have the same
+/// length, so they line up when imported together into Audacity or a DAW.
+fn write_aligned_tracks(
+    paths: &SessionPaths,
+    user_audio: &[UserFrames],
+    format: OutputFormat,
+    names: &OutputNames,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let ticks = session_tick_range(user_audio).ok_or("No audio to align")?;
+    let aligned_dir = paths.aligned_dir();
+    fs::create_dir_all(&aligned_dir)?;
+
+    let ssrc_map = load_ssrc_map(paths.root()).unwrap_or_else(|e| {
+        warn!("No SSRC map for aligned tracks: {}", e);
+        Default::default()
+    });
+
+    let mut tracks = Vec::with_capacity(user_audio.len());
+    for (ssrc, frames, first_tick) in user_audio {
+        let file = format!("{}.{}", names.user(ssrc), format.extension());
+        write_audio_range(frames, ticks.clone(), &aligned_dir.join(&file), format)?;
+
+        tracks.push(AlignedTrack {
+            file,
+            ssrc: ssrc.clone(),
+            user_id: ssrc.parse().ok().and_then(|ssrc: u32| ssrc_map.get(&ssrc).copied()),
+            first_audio_secs: ((first_tick - ticks.start()) as usize * SAMPLES_PER_FRAME) as f64
+                / SAMPLE_RATE as f64,
+        });
+    }
+
+    fs::write(paths.aligned_manifest(), serde_json::to_string_pretty(&tracks)?)?;
+    info!("Wrote {} aligned tracks to {:?}", tracks.len(), aligned_dir);
+    Ok(())
+}
+
+/// RMS level of a user's recorded frames
+fn frames_rms(frames: &BTreeMap<u64, Vec<i16>>) -> f32 {
+    let (sum_squares, count) = frames
+        .values()
+        .flatten()
+        .fold((0.0f64, 0usize), |(sum, n), &s| (sum + (s as f64).powi(2), n + 1));
+
+    if count == 0 {
+        0.0
+    } else {
+        (sum_squares / count as f64).sqrt() as f32
+    }
+}
+
+/// Per-user weights that bring every user towards the average loudness
+fn loudness_weights(user_audio: &[UserFrames]) -> Vec<f32> {
+    let levels: Vec<f32> = user_audio.iter().map(|(_, frames, _)| frames_rms(frames)).collect();
+    let audible: Vec<f32> = levels.iter().copied().filter(|&rms| rms > 0.0).collect();
+    if audible.is_empty() {
+        return vec![1.0; levels.len()];
+    }
+    let target = audible.iter().sum::<f32>() / audible.len() as f32;
+
+    levels
+        .iter()
+        .map(|&rms| {
+            if rms > 0.0 {
+                (target / rms).clamp(MIN_BALANCE_WEIGHT, MAX_BALANCE_WEIGHT)
+            } else {
+                1.0
+            }
+        })
+        .collect()
+}
+
 fn merge_wavs(
-    user_audio: &[(String, BTreeMap<u64, Vec<i16>>, u64)],
+    user_audio: &[UserFrames],
+    weights: &[f32],
     output_path: &PathBuf,
+    format: OutputFormat,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     if user_audio.is_empty() {
         return Err("No audio to merge".into());
     }
 
-    // Find the earliest first tick and latest last tick across all users
-    let earliest_first_tick = user_audio
-        .iter()
-        .map(|(_, _, first_tick)| *first_tick)
-        .min()
-        .unwrap();
-    
-    let latest_last_tick = user_audio
+    let (earliest_first_tick, latest_last_tick) = session_tick_range(user_audio)
+        .ok_or("Invalid tick range")?
+        .into_inner();
+
+    info!(
+        "Merging {} users from tick {} to {}",
+        user_audio.len(),
+        earliest_first_tick,
+        latest_last_tick
+    );
+
+    let mut writer = PcmWriter::create(output_path, format)?;
+
+    for tick in earliest_first_tick..=latest_last_tick {
+        for sample in mix_tick(user_audio, weights, tick) {
+            writer.write_sample(sample)?;
+        }
+    }
+
+    writer.finalize()?;
+    Ok(())
+}
+
+/// Weighted sum of all users' samples at a tick, one frame long
+fn mix_tick(user_audio: &[UserFrames], weights: &[f32], tick: u64) -> Vec<f32> {
+    let tracks: Vec<(&[i16], f32)> = user_audio
         .iter()
-        .map(|(_, frames, _)| {
-            frames.keys().next_back().copied().unwrap_or(0)
+        .zip(weights)
+        .filter(|((_, _, first_tick), _)| tick >= *first_tick)
+        .filter_map(|((_, frames, _), &weight)| {
+            frames.get(&tick).map(|samples| {
+                (&samples[..samples.len().min(SAMPLES_PER_FRAME)], weight)
+            })
         })
-        .max()
-        .unwrap();
+        .collect();
+
+    let mut mixed_samples = mix_tracks_weighted(&tracks);
+    mixed_samples.resize(SAMPLES_PER_FRAME, 0.0);
+    mixed_samples
+}
+
+/// The mix over `ticks` as 16-bit parts of at most `part_ticks` ticks each
+///
+/// Parts are mixed as they're taken, so only one is in memory at a time.
+/// Where voices add up past full scale they are soft clipped rather than cut off.
+fn mix_parts<'a>(
+    user_audio: &'a [UserFrames],
+    weights: &'a [f32],
+    ticks: RangeInclusive<u64>,
+    part_ticks: u64,
+) -> impl Iterator<Item = Vec<i16>> + 'a {
+    let part_ticks = part_ticks.max(1);
+    let last = *ticks.end();
+
+    ticks.step_by(part_ticks as usize).map(move |part_start| {
+        let part_end = last.min(part_start + part_ticks - 1);
+        (part_start..=part_end)
+            .flat_map(|tick| mix_tick(user_audio, weights, tick))
+            .map(|sample| f32_to_i16(ClipMode::Soft.apply(sample)))
+            .collect()
+    })
+}
+
+/// Every user who made a sound in the session, in SSRC order
+fn load_session_audio(paths: &SessionPaths) -> Result<Vec<UserFrames>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut user_dirs: Vec<PathBuf> = fs::read_dir(paths.users_dir())?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    user_dirs.sort();
+
+    let mut user_audio = Vec::new();
+    for user_dir in user_dirs {
+        let ssrc = user_dir.file_name().and_then(|n| n.to_str()).unwrap_or("unknown").to_string();
+        let frames = load_user_audio(&user_dir)?;
+        if frames_silent(&frames) {
+            info!("Leaving out {}, only silence was recorded", ssrc);
+            continue;
+        }
+        if let Some(&first_tick) = frames.keys().next() {
+            user_audio.push((ssrc, frames, first_tick));
+        }
+    }
+    Ok(user_audio)
+}
+
+/// SSRCs of the users who opted in to a consent-gated session, `None` if everyone was recorded
+///
+/// Without an SSRC map nobody can be matched to their consent, so nothing is allowed.
+fn consenting_ssrcs(paths: &SessionPaths) -> Option<HashSet<u32>> {
+    let consent = SessionMetadata::load(&paths.metadata())?.consent?;
+    let ssrc_map = load_ssrc_map(paths.root()).unwrap_or_default();
+    Some(
+        ssrc_map
+            .into_iter()
+            .filter(|(_, user_id)| consent.contains_key(user_id))
+            .map(|(ssrc, _)| ssrc)
+            .collect(),
+    )
+}
+
+/// A session mixed down for sharing in chat
+pub(crate) enum SessionMp3 {
+    /// Nobody spoke
+    NoAudio,
+    /// More parts than allowed would be needed to stay under the size limit
+    TooLong { parts: usize },
+    Parts(Vec<Vec<u8>>),
+}
+
+/// Mix the whole session into MP3 files of at most `max_part_bytes` each
+///
+/// Long sessions are split at fixed times into up to `max_parts` files. The
+/// split is estimated from the bitrate, with some room left for frame headers
+/// and the encoder's padding. Sessions recorded with a consent gate only
+/// include the users who consented. Blocking file IO and encoding.
+pub(crate) fn session_mp3_parts(
+    paths: &SessionPaths,
+    balance: bool,
+    max_part_bytes: usize,
+    max_parts: usize,
+) -> Result<SessionMp3, Box<dyn std::error::Error + Send + Sync>> {
+    if !paths.has_audio() {
+        return Ok(SessionMp3::NoAudio);
+    }
+    let mut user_audio = load_session_audio(paths)?;
+    if let Some(consenting) = consenting_ssrcs(paths) {
+        user_audio.retain(|(ssrc, _, _)| ssrc.parse().is_ok_and(|ssrc| consenting.contains(&ssrc)));
+    }
+    let Some(ticks) = session_tick_range(&user_audio) else {
+        return Ok(SessionMp3::NoAudio);
+    };
 
-    if latest_last_tick < earliest_first_tick {
-        return Err("Invalid tick range".into());
+    let part_ticks = (max_part_bytes * 9 / 10 / MP3_BYTES_PER_SEC * TICKS_PER_SEC).max(1) as u64;
+    let total_ticks = ticks.end() - ticks.start() + 1;
+    let parts = total_ticks.div_ceil(part_ticks) as usize;
+    if parts > max_parts {
+        return Ok(SessionMp3::TooLong { parts });
     }
 
+    let weights = if balance {
+        loudness_weights(&user_audio)
+    } else {
+        vec![1.0; user_audio.len()]
+    };
     info!(
-        "Merging {} users from tick {} to {}",
+        "Encoding {} user(s) over {} ticks into {} MP3 part(s)",
         user_audio.len(),
-        earliest_first_tick,
-        latest_last_tick
+        total_ticks,
+        parts
     );
 
-    let spec = WavSpec {
-        channels: 1,
-        sample_rate: SAMPLE_RATE,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
+    let encoded = mix_parts(&user_audio, &weights, ticks, part_ticks)
+        .map(|samples| encode_mp3(&samples, SAMPLE_RATE, 1))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    Ok(SessionMp3::Parts(encoded))
+}
+
+/// Outcome of reconstructing a session's audio
+struct ReconstructSummary {
+    processed: usize,
+    /// Users skipped because only silence was recorded
+    silent: Vec<String>,
+    errors: Vec<String>,
+}
+
+/// How to reconstruct a session
+#[derive(Debug, Clone)]
+struct ReconstructOptions {
+    /// Balance loudness between users in the merged file
+    balance: bool,
+    /// Also write per-user tracks padded to a common start
+    aligned: bool,
+    format: OutputFormat,
+    /// Users loaded and written at the same time
+    concurrency: usize,
+    /// Tag WAV files with speaker, session and recording date
+    wav_info: bool,
+    /// Write a waveform PNG next to every user's audio file
+    waveform: bool,
+    /// Names of the audio files, without extension
+    file_names: FilenameTemplate,
+}
+
+impl Default for ReconstructOptions {
+    fn default() -> Self {
+        Self {
+            balance: false,
+            aligned: false,
+            format: OutputFormat::default(),
+            concurrency: default_concurrency(),
+            wav_info: false,
+            waveform: false,
+            file_names: FilenameTemplate::default(),
+        }
+    }
+}
+
+/// Options as given to `/reconstruct-audio`, unset ones fall back to the defaults
+#[derive(Debug, Default)]
+struct ReconstructArgs {
+    balance: Option<bool>,
+    aligned: Option<bool>,
+    format: Option<String>,
+    concurrency: Option<usize>,
+    wav_info: Option<bool>,
+    waveform: Option<bool>,
+    filename_template: Option<String>,
+}
+
+impl ReconstructArgs {
+    fn into_options(self) -> Result<ReconstructOptions, String> {
+        let defaults = ReconstructOptions::default();
+        let format = match self.format.as_deref() {
+            Some(f) => f.parse::<OutputFormat>()?,
+            None => defaults.format,
+        };
+        // Operators archiving many sessions can set their naming once for every run
+        let file_names = match self
+            .filename_template
+            .or_else(|| std::env::var("WRITEY_FILENAME_TEMPLATE").ok())
+        {
+            Some(t) => t.parse::<FilenameTemplate>()?,
+            None => defaults.file_names,
+        };
+        Ok(ReconstructOptions {
+            balance: self.balance.unwrap_or(defaults.balance),
+            aligned: self.aligned.unwrap_or(defaults.aligned),
+            format,
+            concurrency: self.concurrency.unwrap_or(defaults.concurrency),
+            wav_info: self.wav_info.unwrap_or(defaults.wav_info),
+            waveform: self.waveform.unwrap_or(defaults.waveform),
+            file_names,
+        })
+    }
+}
+
+/// One worker per core, capped since every worker holds a user's whole recording in memory
+fn default_concurrency() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_DEFAULT_CONCURRENCY)
+}
+
+/// Load one user's frames and write their audio file, returning the frames for the merge
+fn reconstruct_user(
+    user_dir: &PathBuf,
+    output_dir: &Path,
+    names: &OutputNames,
+    format: OutputFormat,
+    waveform: bool,
+) -> Result<UserOutcome, String> {
+    let ssrc = user_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+
+    info!("Processing user {} (SSRC: {})", ssrc, ssrc);
+
+    let frames =
+        load_user_audio(user_dir).map_err(|e| format!("Failed to load audio for {}: {}", ssrc, e))?;
+    let Some(&first_tick) = frames.keys().next() else {
+        info!("No frames found for user {}", ssrc);
+        return Ok(UserOutcome::Empty);
     };
+    if frames_silent(&frames) {
+        info!("Skipping user {}, only silence was recorded", ssrc);
+        return Ok(UserOutcome::Silent(ssrc));
+    }
 
-    let mut writer = WavWriter::create(output_path, spec)?;
-    let silence = vec![0i16; SAMPLES_PER_FRAME];
+    let output_path = output_dir.join(format!("{}.{}", names.user(&ssrc), format.extension()));
+    write_audio(&frames, &output_path, format)
+        .map_err(|e| format!("Failed to write audio for {}: {}", ssrc, e))?;
 
-    // For each tick, mix all users' audio
-    for tick in earliest_first_tick..=latest_last_tick {
-        let mut mixed_samples = vec![0i32; SAMPLES_PER_FRAME];
-
-        // Sum all users' samples at this tick
-        for (ssrc, frames, first_tick) in user_audio {
-            // Only include this user's audio if this tick is >= their first tick
-            if tick >= *first_tick {
-                if let Some(samples) = frames.get(&tick) {
-                    for (i, &sample) in samples.iter().enumerate() {
-                        if i < mixed_samples.len() {
-                            mixed_samples[i] += sample as i32;
-                        }
+    let duration_secs = (frames.len() * SAMPLES_PER_FRAME) as f64 / SAMPLE_RATE as f64;
+    info!(
+        "Created {:?} ({:.1}s, {} frames, first tick: {})",
+        output_path,
+        duration_secs,
+        frames.len(),
+        first_tick
+    );
+
+    if waveform {
+        let png_path = output_dir.join(format!("{}.png", names.user(&ssrc)));
+        fs::write(&png_path, render_waveform_png(&frames))
+            .map_err(|e| format!("Failed to write waveform for {}: {}", ssrc, e))?;
+    }
+
+    Ok(UserOutcome::Written((ssrc, frames, first_tick)))
+}
+
+/// Write per-user and merged audio for a session into its output directory
+///
+/// Users are rebuilt on up to `options.concurrency` threads, each writing only
+/// its own file; mixing starts once all of them are done.
+///
+/// Blocking file IO and encoding, use `reconstruct_session_async` from async code.
+fn reconstruct_session(
+    paths: &SessionPaths,
+    options: ReconstructOptions,
+) -> std::io::Result<ReconstructSummary> {
+    let output_dir = paths.output_dir();
+
+    // Nobody spoke: no output directory and no empty files
+    if !paths.has_audio() {
+        info!("No audio was captured in {:?}", paths.root());
+        return Ok(ReconstructSummary { processed: 0, silent: Vec::new(), errors: Vec::new() });
+    }
+    fs::create_dir_all(&output_dir)?;
+
+    let mut user_dirs: Vec<PathBuf> = fs::read_dir(paths.users_dir())?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    user_dirs.sort();
+
+    let names = OutputNames::new(paths, options.file_names.clone());
+    let workers = options.concurrency.clamp(1, user_dirs.len().max(1));
+    info!("Reconstructing {} user(s) on {} thread(s)", user_dirs.len(), workers);
+
+    let next_user = AtomicUsize::new(0);
+    let mut outcomes: Vec<(usize, Result<UserOutcome, String>)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut outcomes = Vec::new();
+                    loop {
+                        let index = next_user.fetch_add(1, Ordering::Relaxed);
+                        let Some(user_dir) = user_dirs.get(index) else {
+                            return outcomes;
+                        };
+                        outcomes.push((index, reconstruct_user(user_dir, &output_dir, &names, options.format, options.waveform)));
                     }
-                }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle.join().unwrap_or_else(|_| {
+                    error!("A reconstruction worker panicked");
+                    Vec::new()
+                })
+            })
+            .collect()
+    });
+    // Keep the merge and the aligned manifest independent of thread scheduling
+    outcomes.sort_by_key(|(index, _)| *index);
+
+    let mut errors = Vec::new();
+    let mut silent = Vec::new();
+    let mut user_audio_data: Vec<UserFrames> = Vec::new();
+    for (_, outcome) in outcomes {
+        match outcome {
+            Ok(UserOutcome::Written(user)) => user_audio_data.push(user),
+            Ok(UserOutcome::Silent(ssrc)) => silent.push(ssrc),
+            Ok(UserOutcome::Empty) => {}
+            Err(e) => errors.push(e),
+        }
+    }
+    let processed = user_audio_data.len();
+
+    if !user_audio_data.is_empty() {
+        let merged_path = output_dir.join(format!("{}.{}", names.merged(), options.format.extension()));
+        let weights = if options.balance {
+            loudness_weights(&user_audio_data)
+        } else {
+            vec![1.0; user_audio_data.len()]
+        };
+        info!("Merge weights: {:?}", weights);
+
+        match merge_wavs(&user_audio_data, &weights, &merged_path, options.format) {
+            Ok(_) => {
+                info!("Created merged audio: {:?}", merged_path);
             }
+            Err(e) => {
+                errors.push(format!("Failed to merge audio: {}", e));
+            }
+        }
+
+        if options.aligned && let Err(e) = write_aligned_tracks(paths, &user_audio_data, options.format, &names) {
+            errors.push(format!("Failed to write aligned tracks: {}", e));
         }
 
-        // Convert mixed samples to i16 with clipping
-        for mixed in mixed_samples {
-            let clipped = mixed.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
-            writer.write_sample(clipped)?;
+        if options.wav_info
+            && matches!(options.format, OutputFormat::Wav(_))
+            && let Err(e) = tag_wav_outputs(paths, &user_audio_data, &options, &names)
+        {
+            errors.push(format!("Failed to tag WAV files: {}", e));
         }
     }
 
-    writer.finalize()?;
+    Ok(ReconstructSummary { processed, silent, errors })
+}
+
+/// Speaker names from a previous transcription of the session, by user id
+fn transcribed_names(paths: &SessionPaths) -> HashMap<u64, String> {
+    let Some(manifest) = fs::read_to_string(paths.transcribe_manifest())
+        .ok()
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+    else {
+        return HashMap::new();
+    };
+
+    manifest["users"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|user| Some((user["user_id"].as_u64()?, user["display_name"].as_str()?.to_string())))
+        .collect()
+}
+
+/// File names of a session's reconstructed audio, from a `FilenameTemplate`
+struct OutputNames {
+    template: FilenameTemplate,
+    session: String,
+    date: String,
+    /// Transcribed name or user id of each speaker, by SSRC
+    speakers: HashMap<String, String>,
+}
+
+impl OutputNames {
+    fn new(paths: &SessionPaths, template: FilenameTemplate) -> Self {
+        let ssrc_map = load_ssrc_map(paths.root()).unwrap_or_default();
+        let names = transcribed_names(paths);
+        let speakers = ssrc_map
+            .into_iter()
+            .map(|(ssrc, user_id)| {
+                let name = names.get(&user_id).cloned().unwrap_or_else(|| user_id.to_string());
+                (ssrc.to_string(), name)
+            })
+            .collect();
+
+        Self {
+            template,
+            session: paths.session_id().unwrap_or("session").to_string(),
+            date: SessionMetadata::load(&paths.metadata())
+                .map(|m| m.started_at.format("%Y-%m-%d").to_string())
+                .unwrap_or_else(|| "undated".to_string()),
+            speakers,
+        }
+    }
+
+    fn render(&self, user: &str, name: &str) -> String {
+        self.template.render(&FilenameValues { session: &self.session, user, name, date: &self.date })
+    }
+
+    /// File name of a user's track, without extension
+    fn user(&self, ssrc: &str) -> String {
+        self.render(ssrc, self.speakers.get(ssrc).map_or(ssrc, String::as_str))
+    }
+
+    /// File name of the mix, without extension
+    fn merged(&self) -> String {
+        self.render("merged", "merged")
+    }
+}
+
+/// Add `LIST`/`INFO` tags to the per-user, merged and aligned WAV files
+///
+/// The artist is the speaker's name when the session was transcribed, their
+/// user id otherwise.
+fn tag_wav_outputs(
+    paths: &SessionPaths,
+    user_audio: &[UserFrames],
+    options: &ReconstructOptions,
+    names: &OutputNames,
+) -> std::io::Result<()> {
+    let base = WavInfo {
+        artist: None,
+        comment: paths.session_id().map(|id| format!("writey session {}", id)),
+        created: SessionMetadata::load(&paths.metadata()).map(|m| m.started_at.format("%Y-%m-%d").to_string()),
+    };
+    let extension = options.format.extension();
+
+    let mut files = vec![(
+        paths.output_dir().join(format!("{}.{}", names.merged(), extension)),
+        Some("All speakers".to_string()),
+    )];
+    for (ssrc, _, _) in user_audio {
+        let artist = names.speakers.get(ssrc).cloned();
+        let file = format!("{}.{}", names.user(ssrc), extension);
+        files.push((paths.output_dir().join(&file), artist.clone()));
+        if options.aligned {
+            files.push((paths.aligned_dir().join(&file), artist));
+        }
+    }
+
+    for (path, artist) in files {
+        // Files are rewritten on every run, but never stack a second tag list
+        if path.exists() && WavInfo::read(&path)?.is_none() {
+            WavInfo { artist, ..base.clone() }.append_to(&path)?;
+        }
+    }
     Ok(())
 }
 
+/// Waveform PNG of every user who spoke, keyed by SSRC
+///
+/// Reads the raw chunk logs, so it works right after a recording is finalized
+/// without reconstructing its audio. Blocking file IO.
+pub(crate) fn render_session_waveforms(paths: &SessionPaths) -> Vec<(u32, Vec<u8>)> {
+    let Ok(entries) = fs::read_dir(paths.users_dir()) else {
+        return Vec::new();
+    };
+    let mut user_dirs: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()).collect();
+    user_dirs.sort();
+
+    user_dirs
+        .iter()
+        .filter_map(|user_dir| {
+            let ssrc: u32 = user_dir.file_name()?.to_str()?.parse().ok()?;
+            match load_user_audio(user_dir) {
+                Ok(frames) if !frames.is_empty() => Some((ssrc, render_waveform_png(&frames))),
+                Ok(_) => None,
+                Err(e) => {
+                    warn!("Failed to load audio for waveform of {}: {}", ssrc, e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Run `reconstruct_session` on the blocking pool so long sessions don't stall the gateway
+async fn reconstruct_session_async(
+    paths: SessionPaths,
+    options: ReconstructOptions,
+) -> Result<ReconstructSummary, Error> {
+    let summary = tokio::task::spawn_blocking(move || reconstruct_session(&paths, options)).await??;
+    Ok(summary)
+}
+
 /// Reconstruct audio from a recording session directory
+///
+/// poise needs one parameter per slash option, everything past the session is
+/// gathered into `ReconstructArgs` right away.
 #[poise::command(prefix_command, slash_command, rename = "reconstruct-audio")]
+#[tracing::instrument(skip_all, fields(session_dir = %session_dir))]
+#[allow(clippy::too_many_arguments)]
 pub async fn reconstruct_audio(
     ctx: Context<'_>,
     #[description = "Session directory path (e.g. recordings/715908438760357910/2026_01_03_18_49_53)"]
     session_dir: String,
+    #[description = "Balance loudness between users in the merged file (default: false)"]
+    balance: Option<bool>,
+    #[description = "Also write per-user tracks padded to a common start for multi-track editors (default: false)"]
+    aligned: Option<bool>,
+    #[description = "Output format: wav (default), wav24, wav32f (float, keeps mix headroom), flac, mp3 (fits Discord uploads)"]
+    format: Option<String>,
+    #[description = "Users processed at the same time (default: one per CPU core, up to 4)"]
+    concurrency: Option<usize>,
+    #[description = "Tag WAV files with speaker name, session and recording date (default: false)"]
+    wav_info: Option<bool>,
+    #[description = "Write a waveform PNG per user, speech highlighted (default: false)"]
+    waveform: Option<bool>,
+    #[description = "File names without extension, from {user} (required), {session}, {name}, {date} (default: {user})"]
+    filename_template: Option<String>,
 ) -> Result<(), Error> {
+    let args = ReconstructArgs {
+        balance,
+        aligned,
+        format,
+        concurrency,
+        wav_info,
+        waveform,
+        filename_template,
+    };
+    run_reconstruction(ctx, &session_dir, args).await
+}
+
+async fn run_reconstruction(ctx: Context<'_>, session_dir: &str, args: ReconstructArgs) -> Result<(), Error> {
     ctx.defer().await?;
 
-    let session_path = PathBuf::from(&session_dir);
-    if !session_path.exists() {
+    let options = args.into_options().map_err(UserError)?;
+
+    let paths = SessionPaths::new(session_dir);
+    if !paths.root().exists() {
         ctx.say(format!("Session directory not found: {}", session_dir))
             .await?;
         return Ok(());
     }
 
-    let users_dir = session_path.join("users");
-    if !users_dir.exists() {
-        ctx.say("No users directory found in session").await?;
+    if !paths.has_audio() {
+        ctx.say("No audio was captured in this session, there is nothing to reconstruct.")
+            .await?;
         return Ok(());
     }
 
-    let output_dir = session_path.join("output");
-    fs::create_dir_all(&output_dir)?;
+    let output_dir = paths.output_dir();
+    let _slot = acquire_job_slot(ctx).await?;
+    let summary = reconstruct_session_async(paths, options).await?;
 
-    let user_dirs: Vec<PathBuf> = fs::read_dir(&users_dir)?
-        .filter_map(|e| e.ok())
-        .map(|e| e.path())
-        .filter(|p| p.is_dir())
-        .collect();
+    let mut response = format!(
+        "Reconstructed audio for {} user(s)\nOutput: `{:?}`",
+        summary.processed, output_dir
+    );
 
-    let mut processed = 0;
-    let mut errors = Vec::new();
-    let mut user_audio_data: Vec<(String, BTreeMap<u64, Vec<i16>>, u64)> = Vec::new();
-
-    for user_dir in &user_dirs {
-        let ssrc = user_dir
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
-
-        info!("Processing user {} (SSRC: {})", ssrc, ssrc);
-
-        match load_user_audio(user_dir) {
-            Ok(frames) => {
-                if frames.is_empty() {
-                    info!("No frames found for user {}", ssrc);
-                    continue;
-                }
+    if !summary.silent.is_empty() {
+        response.push_str(&format!(
+            "\nNo audio (only silence recorded), skipped: {}",
+            summary.silent.join(", ")
+        ));
+    }
 
-                let first_tick = *frames.keys().next().unwrap();
-                
-                let output_path = output_dir.join(format!("{}.wav", ssrc));
-                match write_wav(&frames, &output_path) {
-                    Ok(_) => {
-                        let duration_secs =
-                            (frames.len() * SAMPLES_PER_FRAME) as f64 / SAMPLE_RATE as f64;
-                        info!(
-                            "Created {:?} ({:.1}s, {} frames, first tick: {})",
-                            output_path,
-                            duration_secs,
-                            frames.len(),
-                            first_tick
-                        );
-                        processed += 1;
-                        
-                        user_audio_data.push((ssrc.clone(), frames, first_tick));
-                    }
-                    Err(e) => {
-                        errors.push(format!("Failed to write WAV for {}: {}", ssrc, e));
-                    }
-                }
-            }
-            Err(e) => {
-                errors.push(format!("Failed to load audio for {}: {}", ssrc, e));
-            }
+    if !summary.errors.is_empty() {
+        response.push_str(&format!("\nErrors:\n{}", summary.errors.join("\n")));
+    }
+
+    ctx.say(response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice::storage::write_chunk_log;
+
+    #[test]
+    fn test_reconstruct_session_writes_user_and_merged_audio() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path());
+
+        for (ssrc, first_tick) in [(1111u32, 10u64), (2222, 12)] {
+            write_chunk_log(&paths, ssrc, first_tick..first_tick + 3, 100);
         }
+
+        let summary = reconstruct_session(&paths, ReconstructOptions::default()).unwrap();
+
+        assert_eq!(summary.processed, 2);
+        assert!(summary.errors.is_empty());
+        assert!(paths.output_dir().join("1111.wav").exists());
+        assert!(paths.output_dir().join("2222.wav").exists());
+        assert!(!paths.output_dir().join("1111.png").exists());
+
+        let options = ReconstructOptions { waveform: true, ..ReconstructOptions::default() };
+        reconstruct_session(&paths, options).unwrap();
+        let png = fs::read(paths.output_dir().join("1111.png")).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        assert_eq!(render_session_waveforms(&paths).len(), 2);
+
+        // Merged audio spans ticks 10..=14
+        let merged = hound::WavReader::open(paths.output_dir().join("merged.wav")).unwrap();
+        assert_eq!(merged.len() as usize, 5 * SAMPLES_PER_FRAME);
     }
 
-    if !user_audio_data.is_empty() {
-        let merged_path = output_dir.join("merged.wav");
-        match merge_wavs(&user_audio_data, &merged_path) {
-            Ok(_) => {
-                info!("Created merged WAV: {:?}", merged_path);
-            }
-            Err(e) => {
-                errors.push(format!("Failed to merge WAVs: {}", e));
-            }
+    #[test]
+    fn test_wav_info_tags_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path().join("2026_01_03_18_49_53"));
+        write_chunk_log(&paths, 1111, [10, 11], 100);
+        fs::write(paths.ssrc_map(), r#"{"1111": 42}"#).unwrap();
+
+        reconstruct_session(&paths, ReconstructOptions::default()).unwrap();
+        let untagged = fs::read(paths.output_dir().join("1111.wav")).unwrap();
+        assert_eq!(WavInfo::read(&paths.output_dir().join("1111.wav")).unwrap(), None);
+
+        let options = ReconstructOptions { wav_info: true, ..ReconstructOptions::default() };
+        let summary = reconstruct_session(&paths, options).unwrap();
+        assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+
+        let info = WavInfo::read(&paths.output_dir().join("1111.wav")).unwrap().unwrap();
+        assert_eq!(info.artist.as_deref(), Some("42"));
+        assert_eq!(info.comment.as_deref(), Some("writey session 2026_01_03_18_49_53"));
+        let merged = WavInfo::read(&paths.output_dir().join("merged.wav")).unwrap().unwrap();
+        assert_eq!(merged.artist.as_deref(), Some("All speakers"));
+
+        // The audio data itself is unchanged
+        let tagged = fs::read(paths.output_dir().join("1111.wav")).unwrap();
+        assert_eq!(tagged[8..untagged.len()], untagged[8..]);
+    }
+
+    #[test]
+    fn test_reconstruct_descending_ticks() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path());
+
+        // Ticks 10..=13 logged newest first, each with its own level
+        fs::create_dir_all(paths.ssrc_dir(1111)).unwrap();
+        let log: String = (10u64..14)
+            .rev()
+            .map(|tick| format!("{} {}\n", tick, vec![(tick * 100).to_string(); SAMPLES_PER_FRAME].join(",")))
+            .collect();
+        fs::write(paths.chunk_log(1111, 0), log).unwrap();
+
+        let summary = reconstruct_session(&paths, ReconstructOptions::default()).unwrap();
+        assert_eq!(summary.processed, 1);
+
+        let samples: Vec<i16> = hound::WavReader::open(paths.output_dir().join("1111.wav"))
+            .unwrap()
+            .into_samples::<i16>()
+            .map(|s| s.unwrap())
+            .collect();
+        assert_eq!(samples.len(), 4 * SAMPLES_PER_FRAME);
+        let levels: Vec<i16> = samples.chunks(SAMPLES_PER_FRAME).map(|frame| frame[0]).collect();
+        assert_eq!(levels, [1000, 1100, 1200, 1300]);
+    }
+
+    #[test]
+    fn test_parallel_reconstruction_matches_serial() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path());
+
+        for ssrc in 1000u32..1005 {
+            let first_tick = (ssrc - 1000) as u64 * 2;
+            write_chunk_log(&paths, ssrc, first_tick..first_tick + 4, (ssrc - 990) as i16);
+        }
+
+        let mut merged = Vec::new();
+        for concurrency in [1, 3] {
+            let summary = reconstruct_session(
+                &paths,
+                ReconstructOptions { aligned: true, concurrency, ..ReconstructOptions::default() },
+            )
+            .unwrap();
+            assert_eq!(summary.processed, 5);
+            assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+            merged.push((
+                fs::read(paths.output_dir().join("merged.wav")).unwrap(),
+                fs::read_to_string(paths.aligned_manifest()).unwrap(),
+            ));
         }
+
+        assert_eq!(merged[0], merged[1]);
     }
 
-    let mut response = format!(
-        "Reconstructed audio for {} user(s)\nOutput: `{:?}`",
-        processed, output_dir
-    );
+    #[test]
+    fn test_reconstruct_session_without_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path());
 
-    if !errors.is_empty() {
-        response.push_str(&format!("\nErrors:\n{}", errors.join("\n")));
+        // Stopped right after starting: no users directory at all
+        let summary = reconstruct_session(&paths, ReconstructOptions::default()).unwrap();
+        assert_eq!(summary.processed, 0);
+        assert!(summary.errors.is_empty());
+
+        // An SSRC announced itself but no frame was written
+        write_chunk_log(&paths, 1111, std::iter::empty(), 0);
+        let summary = reconstruct_session(&paths, ReconstructOptions::default()).unwrap();
+        assert_eq!(summary.processed, 0);
+        assert!(summary.errors.is_empty());
+        assert!(!paths.output_dir().exists());
     }
 
-    ctx.say(response).await?;
-    Ok(())
+    #[test]
+    fn test_balanced_merge_evens_out_levels() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path());
+
+        // A quiet user speaks first, then a loud one, four times as loud
+        for (ssrc, first_tick, level) in [(1111u32, 0u64, 500), (2222, 3, 2000)] {
+            write_chunk_log(&paths, ssrc, first_tick..first_tick + 3, level);
+        }
+
+        reconstruct_session(
+            &paths,
+            ReconstructOptions { balance: true, ..ReconstructOptions::default() },
+        )
+        .unwrap();
+
+        let merged: Vec<i16> = hound::WavReader::open(paths.output_dir().join("merged.wav"))
+            .unwrap()
+            .into_samples::<i16>()
+            .map(|s| s.unwrap())
+            .collect();
+        let half = merged.len() / 2;
+        let quiet = merged[..half].iter().map(|&s| s as i32).max().unwrap();
+        let loud = merged[half..].iter().map(|&s| s as i32).max().unwrap();
+
+        assert_eq!(merged.len(), 6 * SAMPLES_PER_FRAME);
+        assert!((quiet - loud).abs() <= 1, "quiet {} vs loud {}", quiet, loud);
+    }
+
+    #[test]
+    fn test_filename_template_names_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path().join("2026_01_03_18_49_53"));
+        fs::create_dir_all(paths.root()).unwrap();
+        fs::write(paths.ssrc_map(), r#"{"1111": 42}"#).unwrap();
+
+        for ssrc in [1111u32, 2222] {
+            write_chunk_log(&paths, ssrc, 0..2, 1000);
+        }
+
+        let options = ReconstructOptions {
+            file_names: "{session}_{name}_{user}".parse().unwrap(),
+            aligned: true,
+            ..ReconstructOptions::default()
+        };
+        let summary = reconstruct_session(&paths, options).unwrap();
+        assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+
+        // Users without a known id keep their SSRC as name
+        for file in [
+            "2026_01_03_18_49_53_42_1111.wav",
+            "2026_01_03_18_49_53_2222_2222.wav",
+            "2026_01_03_18_49_53_merged_merged.wav",
+        ] {
+            assert!(paths.output_dir().join(file).exists(), "{}", file);
+        }
+        assert!(paths.aligned_dir().join("2026_01_03_18_49_53_42_1111.wav").exists());
+        assert!(!paths.output_dir().join("1111.wav").exists());
+    }
+
+    #[test]
+    fn test_reconstruct_args_fall_back_to_defaults() {
+        let options = ReconstructArgs {
+            balance: Some(true),
+            format: Some("flac".to_string()),
+            ..ReconstructArgs::default()
+        }
+        .into_options()
+        .unwrap();
+        assert!(options.balance);
+        assert!(!options.aligned);
+        assert!(matches!(options.format, OutputFormat::Flac));
+        assert_eq!(options.concurrency, default_concurrency());
+
+        let bad_format = ReconstructArgs { format: Some("ogg".to_string()), ..ReconstructArgs::default() };
+        assert!(bad_format.into_options().is_err());
+    }
+
+    #[test]
+    fn test_silent_user_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path());
+
+        // 2222 stayed muted, only dither-level noise came through
+        for (ssrc, level) in [(1111u32, 3000), (2222, 3)] {
+            write_chunk_log(&paths, ssrc, 0..5, level);
+        }
+
+        let summary = reconstruct_session(&paths, ReconstructOptions::default()).unwrap();
+
+        assert_eq!(summary.processed, 1);
+        assert_eq!(summary.silent, vec!["2222".to_string()]);
+        assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+        assert!(paths.output_dir().join("1111.wav").exists());
+        assert!(!paths.output_dir().join("2222.wav").exists());
+    }
+
+    #[test]
+    fn test_mix_parts_split_and_soft_clip() {
+        // Two loud users overlapping for ticks 5..10, with a gap after tick 20
+        let user = |ssrc: &str, ticks: std::ops::Range<u64>| -> UserFrames {
+            let frames: BTreeMap<u64, Vec<i16>> = ticks.clone().map(|t| (t, vec![20000; SAMPLES_PER_FRAME])).collect();
+            (ssrc.to_string(), frames, ticks.start)
+        };
+        let user_audio = vec![user("1111", 0..10), user("2222", 5..20), user("3333", 24..25)];
+        let ticks = session_tick_range(&user_audio).unwrap();
+
+        let parts: Vec<Vec<i16>> = mix_parts(&user_audio, &[1.0; 3], ticks, 10).collect();
+        let frames: Vec<usize> = parts.iter().map(|p| p.len() / SAMPLES_PER_FRAME).collect();
+        assert_eq!(frames, vec![10, 10, 5]);
+
+        let at = |tick: usize| parts[tick / 10][(tick % 10) * SAMPLES_PER_FRAME];
+        assert_eq!(at(2), 20000);
+        // 40000 would clip, it's bent just under full scale instead
+        assert!(at(7) > 30000 && at(7) < i16::MAX);
+        assert_eq!(at(22), 0);
+        assert_eq!(at(24), 20000);
+    }
+
+    #[test]
+    fn test_consenting_ssrcs() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path().join("2026_01_03_18_49_53"));
+        fs::create_dir_all(paths.root()).unwrap();
+        fs::write(paths.ssrc_map(), r#"{"1111": 42, "2222": 7}"#).unwrap();
+        assert_eq!(consenting_ssrcs(&paths), None);
+
+        let mut metadata = SessionMetadata {
+            guild_id: 1,
+            session_id: "2026_01_03_18_49_53".to_string(),
+            started_at: chrono::Utc::now(),
+            channel_bitrate: None,
+            rtc_region: None,
+            packet_stats: BTreeMap::new(),
+            gaps: Vec::new(),
+            consent: None,
+        };
+        metadata.save(&paths.metadata()).unwrap();
+        assert_eq!(consenting_ssrcs(&paths), None);
+
+        metadata.consent = Some(BTreeMap::from([(42, chrono::Utc::now())]));
+        metadata.save(&paths.metadata()).unwrap();
+        assert_eq!(consenting_ssrcs(&paths), Some(HashSet::from([1111])));
+    }
+
+    #[test]
+    fn test_aligned_tracks_share_start_and_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path());
+        fs::write(paths.ssrc_map(), r#"{"1111": 42}"#).unwrap();
+
+        // The second user joins two ticks later and speaks longer
+        for (ssrc, first_tick, frames) in [(1111u32, 10u64, 2u64), (2222, 12, 4)] {
+            write_chunk_log(&paths, ssrc, first_tick..first_tick + frames, 100);
+        }
+
+        let summary = reconstruct_session(
+            &paths,
+            ReconstructOptions { aligned: true, ..ReconstructOptions::default() },
+        )
+        .unwrap();
+        assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+
+        let read = |ssrc: &str| -> Vec<i16> {
+            hound::WavReader::open(paths.aligned_dir().join(format!("{}.wav", ssrc)))
+                .unwrap()
+                .into_samples::<i16>()
+                .map(|s| s.unwrap())
+                .collect()
+        };
+        let first = read("1111");
+        let second = read("2222");
+
+        // Both span ticks 10..=15
+        assert_eq!(first.len(), 6 * SAMPLES_PER_FRAME);
+        assert_eq!(second.len(), first.len());
+        assert!(second[..2 * SAMPLES_PER_FRAME].iter().all(|&s| s == 0));
+        assert_eq!(second[2 * SAMPLES_PER_FRAME], 100);
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(paths.aligned_manifest()).unwrap()).unwrap();
+        let tracks = manifest.as_array().unwrap();
+        assert_eq!(tracks.len(), 2);
+        let track = |ssrc: &str| tracks.iter().find(|t| t["ssrc"] == ssrc).unwrap();
+        assert_eq!(track("1111")["user_id"], 42);
+        assert!(track("2222")["user_id"].is_null());
+        assert_eq!(track("2222")["first_audio_secs"], 0.04);
+    }
 }
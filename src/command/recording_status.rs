@@ -0,0 +1,128 @@
+use crate::Context;
+use crate::Error;
+use crate::command::stop_recording::format_duration;
+use crate::paths::{PacketStats, SessionMetadata, SessionPaths};
+use crate::voice::audio::{LevelMeter, NEAR_SILENT_DBFS};
+use poise::serenity_prelude as serenity;
+use std::collections::BTreeMap;
+
+/// Near-silence longer than this is flagged as a possible dead mic
+const QUIET_WARNING_SECS: f32 = 60.0;
+/// Segments in the level bar, spanning `NEAR_SILENT_DBFS` to 0 dBFS
+const METER_SEGMENTS: usize = 10;
+
+/// Text level bar for a rolling RMS level
+fn level_bar(dbfs: f32) -> String {
+    let fraction = (1.0 - dbfs / NEAR_SILENT_DBFS).clamp(0.0, 1.0);
+    let filled = (fraction * METER_SEGMENTS as f32).round() as usize;
+    format!(
+        "{}{}",
+        "▮".repeat(filled),
+        "▯".repeat(METER_SEGMENTS - filled)
+    )
+}
+
+/// Packet loss above this is flagged, audio starts to sound choppy
+const PACKET_LOSS_WARNING_PERCENT: f32 = 2.0;
+
+/// One line per speaker with their live level, flagging long near-silence and packet loss
+fn format_levels(levels: &BTreeMap<String, (LevelMeter, PacketStats)>) -> String {
+    levels
+        .iter()
+        .map(|(speaker, (meter, packets))| {
+            let quiet_secs = meter.quiet_secs();
+            let warning = if quiet_secs >= QUIET_WARNING_SECS {
+                format!(
+                    " ⚠️ near-silent for {}",
+                    format_duration(chrono::Duration::seconds(quiet_secs as i64))
+                )
+            } else if packets.loss_percent() >= PACKET_LOSS_WARNING_PERCENT {
+                format!(
+                    " ⚠️ {:.1}% packets lost, {} frame(s) undecodable",
+                    packets.loss_percent(),
+                    packets.undecodable
+                )
+            } else if packets.undecodable > 0 {
+                format!(" ({} frame(s) undecodable)", packets.undecodable)
+            } else {
+                String::new()
+            };
+            format!(
+                "• {}: `{}` {:.1} dBFS{}",
+                speaker,
+                level_bar(meter.rms_dbfs()),
+                meter.rms_dbfs(),
+                warning
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Show the running recording with a live level meter per speaker
+#[poise::command(prefix_command, slash_command, rename = "recording-status", guild_only)]
+pub async fn recording_status(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command must be used in a guild")?;
+
+    let (duration, channel_id, session_dir, levels) = {
+        let sessions = ctx.data().active_sessions.lock().await;
+        let Some(session) = sessions.get(&guild_id.get()) else {
+            drop(sessions);
+            ctx.say("No recording is active on this guild.").await?;
+            return Ok(());
+        };
+
+        let state = session.state.lock().await;
+        // A user can have several SSRCs, show the loudest and their combined packet loss
+        let mut levels: BTreeMap<String, (LevelMeter, PacketStats)> = BTreeMap::new();
+        for (ssrc, meter) in &state.levels {
+            let speaker = match state.ssrc_map.get(ssrc) {
+                Some(user_id) => format!("<@{}>", user_id),
+                None => format!("SSRC {}", ssrc),
+            };
+            let packets = state.packet_stats.get(ssrc).copied().unwrap_or_default();
+            let (current, total) = levels
+                .entry(speaker)
+                .or_insert((*meter, PacketStats::default()));
+            if meter.rms_dbfs() > current.rms_dbfs() {
+                *current = *meter;
+            }
+            total.received += packets.received;
+            total.lost += packets.lost;
+            total.undecodable += packets.undecodable;
+        }
+        (
+            session.duration(),
+            session.channel_id,
+            session.session_dir.clone(),
+            levels,
+        )
+    };
+
+    let mut response = format!(
+        "🔴 **Recording** <#{}> for {}",
+        channel_id,
+        format_duration(duration)
+    );
+    if let Some(channel) = SessionMetadata::load(&SessionPaths::new(&session_dir).metadata())
+        .and_then(|metadata| metadata.channel_summary())
+    {
+        response.push_str(&format!("\n📶 Channel: {}", channel));
+    }
+    if levels.is_empty() {
+        response.push_str("\n\nNo audio received yet.");
+    } else {
+        response.push_str(&format!("\n\n**Levels:**\n{}", format_levels(&levels)));
+    }
+
+    // Mentions identify speakers without pinging them
+    ctx.send(
+        poise::CreateReply::default()
+            .content(response)
+            .allowed_mentions(serenity::CreateAllowedMentions::new()),
+    )
+    .await?;
+    Ok(())
+}
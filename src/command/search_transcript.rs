@@ -0,0 +1,388 @@
+use crate::Context;
+use crate::Error;
+use crate::command::session::guild_session;
+use crate::db::{self, DbPool, IndexedSegment};
+use crate::paths::SessionPaths;
+use crate::transcribe::{TranscribedSegment, UserTranscription, format_timestamp};
+use crate::voice::clock::tick_offset_secs;
+use regex::Regex;
+use std::fs;
+use std::ops::Range;
+use tracing::{info, warn};
+
+const MAX_SEARCH_RESULTS: usize = 10;
+/// Characters of context shown on each side of a match
+const SNIPPET_CONTEXT_CHARS: usize = 40;
+
+/// Write every speaker's transcription and first tick to the combined layout's single file
+pub(crate) fn write_combined_transcriptions(
+    paths: &SessionPaths,
+    tracks: &[(&UserTranscription, u64)],
+) -> Result<(), Error> {
+    let entries: Vec<_> = tracks
+        .iter()
+        .map(|(transcription, first_tick)| {
+            serde_json::json!({
+                "first_tick": first_tick,
+                "transcription": transcription,
+            })
+        })
+        .collect();
+    fs::write(
+        paths.combined_transcriptions(),
+        serde_json::to_string_pretty(&entries)?,
+    )?;
+    Ok(())
+}
+
+/// Read the combined layout's `transcriptions.json`, `None` when the session doesn't have one
+pub(crate) fn load_combined_transcriptions(
+    paths: &SessionPaths,
+) -> Option<Vec<(UserTranscription, u64)>> {
+    let json = fs::read_to_string(paths.combined_transcriptions()).ok()?;
+    let entries: Vec<serde_json::Value> = match serde_json::from_str(&json) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!(
+                "Skipping unreadable {:?}: {}",
+                paths.combined_transcriptions(),
+                e
+            );
+            return None;
+        }
+    };
+
+    Some(
+        entries
+            .into_iter()
+            .filter_map(|mut entry| {
+                let first_tick = entry["first_tick"].as_u64().unwrap_or(0);
+                serde_json::from_value(entry["transcription"].take())
+                    .map_err(|e| {
+                        warn!(
+                            "Skipping unreadable transcription in {:?}: {}",
+                            paths.combined_transcriptions(),
+                            e
+                        )
+                    })
+                    .ok()
+                    .map(|transcription| (transcription, first_tick))
+            })
+            .collect(),
+    )
+}
+
+/// Load every user's transcription.json and first tick (from timing.json) in a session
+///
+/// A combined layout's `transcriptions.json` wins over user folders left from an earlier run.
+pub(crate) fn load_transcriptions(paths: &SessionPaths) -> Vec<(UserTranscription, u64)> {
+    if let Some(combined) = load_combined_transcriptions(paths) {
+        return combined;
+    }
+
+    let Ok(entries) = fs::read_dir(paths.transcribe_dir()) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|dir| dir.join("transcription.json").exists())
+        .filter_map(|dir| {
+            let parsed = fs::read_to_string(dir.join("transcription.json"))
+                .map_err(Error::from)
+                .and_then(|json| serde_json::from_str(&json).map_err(Error::from));
+            let transcription: UserTranscription = match parsed {
+                Ok(t) => t,
+                Err(e) => {
+                    warn!("Skipping unreadable transcription in {:?}: {}", dir, e);
+                    return None;
+                }
+            };
+
+            let first_tick = fs::read_to_string(dir.join("timing.json"))
+                .ok()
+                .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+                .and_then(|timing| timing["first_tick"].as_u64())
+                .unwrap_or(0);
+
+            Some((transcription, first_tick))
+        })
+        .collect()
+}
+
+/// Every segment of a session on its shared timeline, for the full-text index
+fn timeline_segments(transcriptions: &[(UserTranscription, u64)]) -> Vec<IndexedSegment> {
+    let origin_tick = transcriptions
+        .iter()
+        .map(|(_, tick)| *tick)
+        .min()
+        .unwrap_or(0);
+
+    transcriptions
+        .iter()
+        .flat_map(|(transcription, first_tick)| {
+            let offset_secs = tick_offset_secs(*first_tick, origin_tick);
+            transcription
+                .all_segments
+                .iter()
+                .map(move |segment| IndexedSegment {
+                    user_id: transcription.user_id,
+                    speaker: transcription.display_name.clone(),
+                    start_secs: offset_secs + segment.start_secs,
+                    text: segment.text.clone(),
+                })
+        })
+        .collect()
+}
+
+/// Replace a transcribed session's segments in the guild's search archive
+///
+/// Returns the number of segments indexed.
+pub(crate) async fn index_session(
+    db: &DbPool,
+    guild_id: &str,
+    paths: &SessionPaths,
+) -> Result<usize, Error> {
+    let session_id = paths.session_id().ok_or("Session directory has no name")?;
+    let segments = timeline_segments(&load_transcriptions(paths));
+    db::index_session_segments(db, guild_id, session_id, &segments).await?;
+    info!(
+        "Indexed {} segments of session {}",
+        segments.len(),
+        session_id
+    );
+    Ok(segments.len())
+}
+
+/// Search every indexed session of the guild
+async fn search_archive(ctx: Context<'_>, query: &str) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("This command must be used in a guild")?;
+
+    let matches = db::search_segments(
+        &ctx.data().db,
+        &guild_id.to_string(),
+        query,
+        MAX_SEARCH_RESULTS as i64,
+    )
+    .await?;
+    info!(
+        "Found {} archive matches for {:?} in guild {}",
+        matches.len(),
+        query,
+        guild_id
+    );
+
+    if matches.is_empty() {
+        ctx.say(format!(
+            "No matches for `{}`. Sessions transcribed before the archive existed can be added with `/index-transcripts`.",
+            query
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let mut response = format!(
+        "🔎 **Best {} match(es) for `{}` across sessions**\n",
+        matches.len(),
+        query
+    );
+    for m in &matches {
+        response.push_str(&format!(
+            "`{}` `[{}]` **{}**: {}\n",
+            m.session_id,
+            format_timestamp(m.start_secs as f32),
+            m.speaker,
+            m.snippet
+        ));
+    }
+
+    ctx.say(response).await?;
+    Ok(())
+}
+
+/// Segment text around a match, with the match in bold
+fn snippet(text: &str, range: Range<usize>) -> String {
+    let before = &text[..range.start];
+    let after = &text[range.end..];
+
+    let before_chars = before.chars().count();
+    let before: String = before
+        .chars()
+        .skip(before_chars.saturating_sub(SNIPPET_CONTEXT_CHARS))
+        .collect();
+    let after_truncated: String = after.chars().take(SNIPPET_CONTEXT_CHARS).collect();
+
+    format!(
+        "{}{}**{}**{}{}",
+        if before_chars > SNIPPET_CONTEXT_CHARS {
+            "…"
+        } else {
+            ""
+        },
+        before,
+        &text[range],
+        after_truncated,
+        if after.chars().count() > SNIPPET_CONTEXT_CHARS {
+            "…"
+        } else {
+            ""
+        },
+    )
+}
+
+/// Search a transcribed session, or every session of the guild, for where something was said
+#[poise::command(prefix_command, slash_command, rename = "search-transcript", guild_only)]
+pub async fn search_transcript(
+    ctx: Context<'_>,
+    #[description = "Text to search for (case-insensitive)"] query: String,
+    #[description = "Session id (leave empty to search all indexed sessions of this guild)"]
+    session: Option<String>,
+    #[description = "Treat the query as a regular expression (default: false)"] regex: Option<bool>,
+) -> Result<(), Error> {
+    let Some(session) = session else {
+        if regex.unwrap_or(false) {
+            ctx.say(
+                "Regex search needs a session, the archive only supports word search.",
+            )
+            .await?;
+            return Ok(());
+        }
+        return search_archive(ctx, &query).await;
+    };
+
+    let pattern = if regex.unwrap_or(false) {
+        match Regex::new(&query) {
+            Ok(p) => Some(p),
+            Err(e) => {
+                ctx.say(format!("Invalid regex: {}", e)).await?;
+                return Ok(());
+            }
+        }
+    } else {
+        None
+    };
+
+    let Some(paths) = guild_session(ctx, &session).await? else {
+        return Ok(());
+    };
+    let transcriptions = load_transcriptions(&paths);
+    if transcriptions.is_empty() {
+        ctx.say(format!(
+            "No transcription found for `{}`. Run `/transcribe-session` first.",
+            session
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    // Segment times are relative to each user's first audio, shift them onto a shared timeline
+    let origin_tick = transcriptions
+        .iter()
+        .map(|(_, tick)| *tick)
+        .min()
+        .unwrap_or(0);
+
+    let mut matches: Vec<(f32, &str, &TranscribedSegment, Range<usize>)> = Vec::new();
+    for (transcription, first_tick) in &transcriptions {
+        let offset_secs = tick_offset_secs(*first_tick, origin_tick);
+        let found = match &pattern {
+            Some(p) => transcription.search_regex(p),
+            None => transcription.search(&query),
+        };
+        matches.extend(found.into_iter().map(|(segment, range)| {
+            (
+                offset_secs + segment.start_secs,
+                transcription.display_name.as_str(),
+                segment,
+                range,
+            )
+        }));
+    }
+    matches.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    info!(
+        "Found {} matches for {:?} in {}",
+        matches.len(),
+        query,
+        session
+    );
+
+    if matches.is_empty() {
+        ctx.say(format!("No matches for `{}`.", query)).await?;
+        return Ok(());
+    }
+
+    let mut response = format!("🔎 **{} match(es) for `{}`**\n", matches.len(), query);
+    for (start_secs, name, segment, range) in matches.iter().take(MAX_SEARCH_RESULTS) {
+        response.push_str(&format!(
+            "`[{}]` **{}**: {}\n",
+            format_timestamp(*start_secs),
+            name,
+            snippet(&segment.text, range.clone())
+        ));
+    }
+    if matches.len() > MAX_SEARCH_RESULTS {
+        response.push_str(&format!(
+            "_…and {} more_",
+            matches.len() - MAX_SEARCH_RESULTS
+        ));
+    }
+
+    ctx.say(response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcription(user_id: u64, name: &str, text: &str) -> UserTranscription {
+        UserTranscription {
+            user_id,
+            display_name: name.to_string(),
+            model: "small".to_string(),
+            total_duration_secs: 2.0,
+            chunk_transcriptions: vec![],
+            all_segments: vec![TranscribedSegment {
+                start_secs: 0.0,
+                end_secs: 2.0,
+                text: text.to_string(),
+                speaker_id: None,
+                speaker_name: None,
+                translation: None,
+            }],
+            full_transcript: text.to_string(),
+            locked_language: None,
+        }
+    }
+
+    #[test]
+    fn test_combined_transcriptions_replace_user_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path());
+        fs::create_dir_all(paths.transcribe_dir()).unwrap();
+        assert!(load_combined_transcriptions(&paths).is_none());
+
+        // A user folder from an earlier per-user run
+        let stale = transcription(1, "Alice", "old");
+        let user_dir = paths.transcribe_user_dir(1, "Alice");
+        fs::create_dir_all(&user_dir).unwrap();
+        fs::write(
+            user_dir.join("transcription.json"),
+            serde_json::to_string(&stale).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(load_transcriptions(&paths).len(), 1);
+
+        let alice = transcription(1, "Alice", "hello");
+        let bob = transcription(2, "Bob", "hi");
+        write_combined_transcriptions(&paths, &[(&alice, 100), (&bob, 250)]).unwrap();
+
+        let loaded = load_transcriptions(&paths);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].0.full_transcript, "hello");
+        assert_eq!(loaded[0].1, 100);
+        assert_eq!((loaded[1].0.user_id, loaded[1].1), (2, 250));
+    }
+}
@@ -0,0 +1,22 @@
+use crate::Context;
+use crate::Error;
+use crate::paths::{SessionPaths, is_session_id};
+
+/// A session recorded in the guild the command was used in
+///
+/// Sessions are looked up by id under the guild's recordings, so commands can't
+/// reach another guild's sessions or other paths on the host. Replies and
+/// returns `None` when there is no such session.
+pub(crate) async fn guild_session(ctx: Context<'_>, session_id: &str) -> Result<Option<SessionPaths>, Error> {
+    let guild_id = ctx.guild_id().ok_or("This command must be used in a guild")?;
+
+    let paths = is_session_id(session_id).then(|| SessionPaths::for_session(guild_id.get(), session_id));
+    match paths {
+        Some(paths) if paths.root().is_dir() => Ok(Some(paths)),
+        _ => {
+            ctx.say(format!("No recording session `{}` on this server.", session_id))
+                .await?;
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::Context;
+use crate::Error;
+use crate::UserError;
+use crate::db::{self, NamePolicy};
+
+/// Choose whether members can change their transcription name once it's set
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "set-name-policy",
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn set_name_policy(
+    ctx: Context<'_>,
+    #[description = "overwrite (default), history (keep previous names) or locked (first name stays)"]
+    policy: String,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command must be used in a guild")?;
+    let policy: NamePolicy = policy.parse().map_err(UserError)?;
+
+    db::set_name_policy(&ctx.data().db, &guild_id.to_string(), policy).await?;
+
+    let explanation = match policy {
+        NamePolicy::Overwrite => "members can change their transcription name freely",
+        NamePolicy::History => {
+            "members can change their transcription name, previous names are kept"
+        }
+        NamePolicy::Locked => "a transcription name can't be changed once it's set",
+    };
+    ctx.say(format!(
+        "Name policy set to `{}`: {}",
+        policy.as_str(),
+        explanation
+    ))
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use crate::Context;
+use crate::Error;
+use crate::db;
+
+/// Longest prefix accepted, keeps typos like pasted sentences out
+const MAX_PREFIX_LEN: usize = 5;
+
+/// Change the prefix for text commands in this guild
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "set-prefix",
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn set_prefix(
+    ctx: Context<'_>,
+    #[description = "New command prefix (e.g. ! or w!)"] prefix: String,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .ok_or("This command must be used in a guild")?;
+
+    if prefix.is_empty()
+        || prefix.chars().count() > MAX_PREFIX_LEN
+        || prefix.chars().any(char::is_whitespace)
+    {
+        ctx.say(format!(
+            "Prefix must be 1-{} characters without spaces.",
+            MAX_PREFIX_LEN
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    db::set_guild_prefix(&ctx.data().db, &guild_id.to_string(), &prefix).await?;
+    ctx.data()
+        .guild_prefixes
+        .lock()
+        .map_err(|_| "Guild prefix cache is poisoned")?
+        .insert(guild_id.get(), prefix.clone());
+
+    ctx.say(format!("Command prefix set to `{}`", prefix))
+        .await?;
+    Ok(())
+}
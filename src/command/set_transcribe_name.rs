@@ -1,18 +1,51 @@
 use crate::Context;
 use crate::Error;
+use crate::command::permissions::author_can_manage_guild;
 use crate::db;
+use poise::serenity_prelude as serenity;
 
-#[poise::command(prefix_command, slash_command, rename = "set-transcribe-name")]
+#[poise::command(prefix_command, slash_command, rename = "set-transcribe-name", guild_only)]
 pub async fn set_transcribe_name(
     ctx: Context<'_>,
     #[description = "The new name for the transcribe"] new_name: String,
+    #[description = "Moderators: fix this user's name instead, even when names are locked"]
+    user: Option<serenity::User>,
 ) -> Result<(), Error> {
-    let user_id = &ctx.author().id.to_string();
-    let guild_id = &ctx.guild_id().unwrap().to_string();
+    let guild_id = &ctx
+        .guild_id()
+        .ok_or("This command must be used in a guild")?
+        .to_string();
 
-    db::set_transcribe_name(&ctx.data().db, user_id, guild_id, &new_name).await?;
+    let change = match &user {
+        Some(user) => {
+            if !author_can_manage_guild(ctx).await {
+                ctx.say("Only members who can manage the server can change someone else's name.")
+                    .await?;
+                return Ok(());
+            }
+            db::moderate_transcribe_name(&ctx.data().db, &user.id.to_string(), guild_id, &new_name).await?
+        }
+        None => db::set_transcribe_name(&ctx.data().db, &ctx.author().id.to_string(), guild_id, &new_name).await?,
+    };
 
-    ctx.say(format!("Set Transcribtion Name to {new_name}!"))
-        .await?;
+    match change {
+        db::NameChange::Set => match user {
+            Some(user) => {
+                ctx.say(format!("Set {}'s Transcribtion Name to {new_name}!", user.name))
+                    .await?;
+            }
+            None => {
+                ctx.say(format!("Set Transcribtion Name to {new_name}!"))
+                    .await?;
+            }
+        },
+        db::NameChange::Rejected { current } => {
+            ctx.say(format!(
+                "Transcribtion Names can't be changed on this server, yours stays {current}. \
+                 Ask a moderator if it needs fixing, they can use the `user` option."
+            ))
+            .await?;
+        }
+    }
     Ok(())
 }
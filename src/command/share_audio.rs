@@ -0,0 +1,104 @@
+use crate::Context;
+use crate::Error;
+use crate::command::job_slot::acquire_job_slot;
+use crate::command::reconstruct_audio::{SessionMp3, session_mp3_parts};
+use crate::command::session::guild_session;
+use poise::serenity_prelude as serenity;
+use tracing::info;
+
+const MIB: usize = 1024 * 1024;
+/// Most messages a shared session is spread over, one part each
+const MAX_SHARE_PARTS: usize = 8;
+
+/// Largest attachment a guild accepts, which grows with server boosts
+fn upload_limit_bytes(tier: Option<serenity::PremiumTier>) -> usize {
+    match tier {
+        Some(serenity::PremiumTier::Tier2) => 50 * MIB,
+        Some(serenity::PremiumTier::Tier3) => 100 * MIB,
+        _ => 10 * MIB,
+    }
+}
+
+/// Mix a recorded session into MP3 and upload it to the channel
+///
+/// Sessions too large for one attachment are split into parts, each sent in its own message.
+#[poise::command(prefix_command, slash_command, rename = "share-audio", guild_only)]
+#[tracing::instrument(skip_all, fields(session = %session))]
+pub async fn share_audio(
+    ctx: Context<'_>,
+    #[description = "Session id from /list-sessions (e.g. 2026_01_03_18_49_53)"] session: String,
+    #[description = "Balance loudness between users (default: true)"] balance: Option<bool>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let Some(paths) = guild_session(ctx, &session).await? else {
+        return Ok(());
+    };
+
+    let tier = ctx.guild_id().and_then(|guild_id| {
+        ctx.serenity_context()
+            .cache
+            .guild(guild_id)
+            .map(|guild| guild.premium_tier)
+    });
+    let max_part_bytes = upload_limit_bytes(tier);
+    let balance = balance.unwrap_or(true);
+
+    let _slot = acquire_job_slot(ctx).await?;
+    let mix_paths = paths.clone();
+    let mp3 = tokio::task::spawn_blocking(move || {
+        session_mp3_parts(&mix_paths, balance, max_part_bytes, MAX_SHARE_PARTS)
+    })
+    .await??;
+
+    let parts = match mp3 {
+        SessionMp3::NoAudio => {
+            ctx.say("No audio was captured in this session, there is nothing to share.")
+                .await?;
+            return Ok(());
+        }
+        SessionMp3::TooLong { parts } => {
+            ctx.say(format!(
+                "This session would take {} uploads of up to {} MiB, more than the {} allowed. \
+                 Use `reconstruct-audio` with format `mp3` and share the file from disk instead.",
+                parts,
+                max_part_bytes / MIB,
+                MAX_SHARE_PARTS
+            ))
+            .await?;
+            return Ok(());
+        }
+        SessionMp3::Parts(parts) => parts,
+    };
+
+    let session_name = paths.session_id().unwrap_or("session");
+    let count = parts.len();
+    for (index, mp3) in parts.into_iter().enumerate() {
+        let (filename, content) = if count == 1 {
+            (
+                format!("{}.mp3", session_name),
+                format!("🎧 Mixed audio of **{}**", session_name),
+            )
+        } else {
+            (
+                format!("{}_part{}.mp3", session_name, index + 1),
+                format!(
+                    "🎧 Mixed audio of **{}**, part {}/{}",
+                    session_name,
+                    index + 1,
+                    count
+                ),
+            )
+        };
+        info!("Uploading {} ({} bytes)", filename, mp3.len());
+
+        ctx.send(
+            poise::CreateReply::default()
+                .content(content)
+                .attachment(serenity::CreateAttachment::bytes(mp3, filename)),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
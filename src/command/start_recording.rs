@@ -1,13 +1,23 @@
 use crate::Context;
 use crate::Error;
+use crate::UserError;
 use crate::RecordingSession;
+use crate::command::consent::{
+    ConsentMode, channel_members, collect_consent, consent_button, consent_mode_for, wait_for_everyone,
+};
+use crate::db;
+use crate::paths::{SessionMetadata, SessionPaths, is_session_id};
+use crate::{finalize_lost_recording, update_recording_presence};
+use crate::voice::audio::StereoDownmix;
+use crate::voice::receiver::{DecodeFailurePolicy, SilencePolicy};
+use crate::voice::reconnect::{ConnectionWatcher, OnConnectionLost};
 use crate::voice::{Receiver, StorageWriter};
 use poise::serenity_prelude as serenity;
 use serenity::model::channel::{Channel, ChannelType};
 use serenity::model::id::{ChannelId, GuildId, UserId};
 use songbird::CoreEvent;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{Instrument, error, info, warn};
 
 async fn get_voice_channel(
     ctx: Context<'_>,
@@ -51,31 +61,110 @@ async fn get_voice_channel(
 }
 
 #[poise::command(prefix_command, slash_command, rename = "start-recording", guild_only)]
+#[tracing::instrument(skip_all, fields(guild_id = ctx.guild_id().map_or(0, |g| g.get())))]
 pub async fn start_recording(
     ctx: Context<'_>,
     #[description = "Voice channel to record (leave empty to auto-detect)"] channel: Option<Channel>,
+    #[description = "Stereo downmix: average (default), left, right, max"] downmix: Option<String>,
+    #[description = "Silent ticks: gaps (default, compact) or write (continuous files)"]
+    silence: Option<String>,
+    #[description = "Undecodable audio from packet loss: skip (default), silence, interpolate"]
+    decode_errors: Option<String>,
+    #[description = "Consent: off (default), opt-in (record only users who agree), everyone (all must agree first)"]
+    consent: Option<String>,
+    #[description = "Session id to continue after a restart, e.g. 2026_01_03_18_49_53 (default: a new session)"]
+    resume: Option<String>,
 ) -> Result<(), Error> {
     let guild_id = ctx
         .guild_id()
         .ok_or("This command must be used in a guild")?;
     let guild_id_u64 = guild_id.get();
     let user_id = ctx.author().id;
+
+    let downmix = match downmix.as_deref() {
+        Some(d) => d.parse::<StereoDownmix>().map_err(UserError)?,
+        None => StereoDownmix::default(),
+    };
+    let silence_policy = match silence.as_deref() {
+        Some(s) => s.parse::<SilencePolicy>().map_err(UserError)?,
+        None => SilencePolicy::default(),
+    };
+    let decode_policy = match decode_errors.as_deref() {
+        Some(d) => d.parse::<DecodeFailurePolicy>().map_err(UserError)?,
+        None => DecodeFailurePolicy::default(),
+    };
+    let requested_consent = match consent.as_deref() {
+        Some(c) => Some(c.parse::<ConsentMode>().map_err(UserError)?),
+        None => None,
+    };
     let user_id_u64 = user_id.get();
 
-    {
+    let voice_channel_id = match get_voice_channel(ctx, guild_id, user_id, channel).await? {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    let recording_channel = {
         let sessions = ctx.data().active_sessions.lock().await;
-        if sessions.contains_key(&guild_id_u64) {
-            ctx.say("A recording is already active on this guild.")
+        sessions.get(&guild_id_u64).map(|session| session.channel_id)
+    };
+    if let Some(recording_channel) = recording_channel {
+        if recording_channel == voice_channel_id.get() {
+            ctx.say(format!("<#{}> is already being recorded.", recording_channel))
                 .await?;
-            return Ok(());
+        } else {
+            ctx.say(format!(
+                "Already recording <#{}> on this server. A bot can only be connected to one voice \
+                channel per server, so <#{}> can't be recorded at the same time. \
+                Use `/stop-recording` first, or run a second bot for the other channel.",
+                recording_channel, voice_channel_id
+            ))
+            .await?;
         }
+        return Ok(());
     }
 
-    let voice_channel_id = match get_voice_channel(ctx, guild_id, user_id, channel).await? {
-        Some(id) => id,
-        None => return Ok(()),
+    let resumed = match resume.as_deref() {
+        Some(session_id) => {
+            let metadata = is_session_id(session_id)
+                .then(|| SessionMetadata::load(&SessionPaths::for_session(guild_id_u64, session_id).metadata()))
+                .flatten();
+            match metadata {
+                Some(metadata) => Some(metadata),
+                None => {
+                    ctx.say(format!("No recording session `{}` on this server to resume.", session_id))
+                        .await?;
+                    return Ok(());
+                }
+            }
+        }
+        None => None,
     };
 
+    // A consent-gated session stays gated when resumed, whatever was passed this time
+    let consent_mode = consent_mode_for(requested_consent, resumed.as_ref().and_then(|m| m.consent.as_ref()));
+
+    // One button per invocation, so presses on an earlier recording's message don't count
+    let consent_id = format!("writey-consent-{}", ctx.id());
+    let mut consented = match consent_mode {
+        ConsentMode::Off => None,
+        ConsentMode::OptIn => Some(Default::default()),
+        ConsentMode::Everyone => {
+            let members = channel_members(ctx, guild_id, voice_channel_id);
+            match wait_for_everyone(ctx, &members, &consent_id).await? {
+                Some(consented) => Some(consented),
+                None => return Ok(()),
+            }
+        }
+    };
+
+    // People who consented before the restart don't have to press the button again
+    if let (Some(consented), Some(previous)) = (&mut consented, resumed.as_ref().and_then(|m| m.consent.as_ref())) {
+        for (user_id, at) in previous {
+            consented.entry(*user_id).or_insert(*at);
+        }
+    }
+
     let manager = songbird::get(ctx.serenity_context())
         .await
         .ok_or("Songbird voice client not initialized")?
@@ -96,9 +185,17 @@ pub async fn start_recording(
         voice_channel_id, guild_id
     );
 
-    let mut session = RecordingSession::new(guild_id_u64);
+    let mut session = match &resumed {
+        Some(metadata) => RecordingSession::resume(guild_id_u64, voice_channel_id.get(), metadata),
+        None => RecordingSession::new(guild_id_u64, voice_channel_id.get()),
+    };
 
-    let (storage_handle, storage_writer) = match StorageWriter::new(session.session_dir.clone()) {
+    let storage = match resumed {
+        Some(_) => StorageWriter::open_append(session.session_dir.clone())
+            .map(|(handle, writer, resume)| (handle, writer, Some(resume))),
+        None => StorageWriter::new(session.session_dir.clone()).map(|(handle, writer)| (handle, writer, None)),
+    };
+    let (storage_handle, storage_writer, resume_point) = match storage {
         Ok(s) => s,
         Err(e) => {
             error!("Failed to create session storage: {:?}", e);
@@ -109,14 +206,63 @@ pub async fn start_recording(
         }
     };
 
-    let storage_task = tokio::spawn(async move {
-        storage_writer.run().await;
-    });
+    let (channel_bitrate, rtc_region) = ctx
+        .serenity_context()
+        .cache
+        .guild(guild_id)
+        .and_then(|guild| {
+            guild
+                .channels
+                .get(&voice_channel_id)
+                .map(|channel| (channel.bitrate, channel.rtc_region.clone()))
+        })
+        .unwrap_or_default();
+    let metadata_path = SessionPaths::new(&session.session_dir).metadata();
+    // A resumed session keeps its metadata, stats and gaps are added to it when it stops
+    let metadata = match SessionMetadata::load(&metadata_path).filter(|_| resume_point.is_some()) {
+        Some(mut metadata) => {
+            metadata.record_consent(consented.clone());
+            metadata
+        }
+        None => SessionMetadata {
+            guild_id: guild_id_u64,
+            session_id: session.session_id.clone(),
+            started_at: session.started_at,
+            channel_bitrate,
+            rtc_region,
+            packet_stats: Default::default(),
+            gaps: Vec::new(),
+            consent: consented.clone(),
+        },
+    };
+    if let Err(e) = metadata.save(&metadata_path) {
+        warn!("Failed to write session metadata: {:?}", e);
+    }
+
+    let storage_span = tracing::info_span!(
+        "recording",
+        guild_id = guild_id_u64,
+        session_id = %session.session_id
+    );
+    let storage_task = tokio::spawn(
+        async move { storage_writer.run().await }.instrument(storage_span),
+    );
     session.storage_task = Some(storage_task);
 
     {
         let mut state = session.state.lock().await;
-        state.start(storage_handle);
+        state.start(storage_handle, downmix, silence_policy, decode_policy);
+        state.consented = consented;
+        if let Some(resume_point) = resume_point {
+            state.continue_from(resume_point);
+        }
+    }
+    if consent_mode != ConsentMode::Off {
+        tokio::spawn(collect_consent(
+            ctx.serenity_context().clone(),
+            Arc::clone(&session.state),
+            consent_id.clone(),
+        ));
     }
 
     let receiver = Receiver::new(Arc::clone(&session.state));
@@ -129,21 +275,74 @@ pub async fn start_recording(
         let voice_tick_receiver = Receiver::new(Arc::clone(&session.state));
 
         handler.add_global_event(CoreEvent::VoiceTick.into(), voice_tick_receiver);
+
+        let on_lost: OnConnectionLost = {
+            let serenity_ctx = ctx.serenity_context().clone();
+            let sessions = ctx.data().active_sessions.clone();
+            let db = ctx.data().db.clone();
+            let text_channel = ctx.channel_id();
+            Arc::new(move |reason| {
+                tokio::spawn(finalize_lost_recording(
+                    serenity_ctx.clone(),
+                    sessions.clone(),
+                    db.clone(),
+                    guild_id_u64,
+                    text_channel,
+                    reason,
+                ));
+            })
+        };
+        for event in [CoreEvent::DriverConnect, CoreEvent::DriverReconnect, CoreEvent::DriverDisconnect] {
+            let watcher = ConnectionWatcher::new(
+                Arc::clone(&session.state),
+                manager.clone(),
+                guild_id,
+                voice_channel_id,
+                on_lost.clone(),
+            );
+            handler.add_global_event(event.into(), watcher);
+        }
     }
 
     let session_dir_display = session.session_dir.display().to_string();
 
+    if let Err(e) = db::create_session(
+        &ctx.data().db,
+        &guild_id_u64.to_string(),
+        &session.session_id,
+        &session_dir_display,
+    )
+    .await
+    {
+        warn!("Failed to record session in database: {:?}", e);
+    }
+
     {
         let mut sessions = ctx.data().active_sessions.lock().await;
         sessions.insert(guild_id_u64, session);
+        update_recording_presence(ctx.serenity_context(), &sessions);
     }
 
-    ctx.say(format!(
-        "🎙️ **Recording started!**\n\
+    let started = format!(
+        "🎙️ **Recording {}!**\n\
         📁 Session: `{}`",
+        if resume_point.is_some() { "resumed" } else { "started" },
         session_dir_display
-    ))
-    .await?;
+    );
+    if consent_mode == ConsentMode::Off {
+        ctx.say(started).await?;
+    } else {
+        // The button stays live, so people joining later can opt in too
+        ctx.send(
+            poise::CreateReply::default()
+                .content(format!(
+                    "{}\n🔒 Only people who press the button are recorded, from the moment they do.",
+                    started
+                ))
+                .components(vec![consent_button(&consent_id)]),
+        )
+        .await?;
+    }
 
     Ok(())
 }
@@ -1,8 +1,23 @@
 use crate::Context;
 use crate::Error;
+use crate::command::reconstruct_audio::render_session_waveforms;
+use crate::db;
+use crate::paths::{RecordingGap, SessionMetadata, SessionPaths};
+use crate::transcribe::load_ssrc_map;
+use crate::update_recording_presence;
+use crate::voice::audio::UserAudioStats;
+use crate::voice::clock::NOMINAL_TICK_SECS;
+use crate::voice::ssrc_history::SsrcHistory;
+use poise::serenity_prelude as serenity;
+use std::collections::HashMap;
 use tracing::{error, info};
 
-fn format_duration(duration: chrono::Duration) -> String {
+/// Integrated level below which a speaker is flagged as too quiet
+const QUIET_RMS_DBFS: f32 = -40.0;
+/// Discord's limit on attachments per message
+const MAX_ATTACHMENTS: usize = 10;
+
+pub fn format_duration(duration: chrono::Duration) -> String {
     let total_secs = duration.num_seconds();
     let hours = total_secs / 3600;
     let minutes = (total_secs % 3600) / 60;
@@ -17,8 +32,84 @@ fn format_duration(duration: chrono::Duration) -> String {
     }
 }
 
+/// Lines for each voice connection drop, empty when there were none
+pub fn format_gaps(gaps: &[RecordingGap]) -> String {
+    gaps.iter()
+        .map(|gap| {
+            let at = (gap.from_tick as f64 * NOMINAL_TICK_SECS) as i64;
+            let missed = (gap.ticks as f64 * NOMINAL_TICK_SECS).round() as i64;
+            format!(
+                "\n⚠️ Voice connection dropped at {} for {} ({}), that stretch is silent.",
+                format_duration(chrono::Duration::seconds(at)),
+                format_duration(chrono::Duration::seconds(missed)),
+                gap.reason
+            )
+        })
+        .collect()
+}
+
+/// One line per speaker with peak/RMS levels and clipping
+fn format_level_summary(stats: &HashMap<u64, UserAudioStats>) -> String {
+    let mut users: Vec<_> = stats.iter().collect();
+    users.sort_by_key(|(user_id, _)| **user_id);
+
+    users
+        .into_iter()
+        .map(|(user_id, s)| {
+            let warning = if s.clipped_samples > 0 {
+                " ⚠️ clipping"
+            } else if s.rms_dbfs() < QUIET_RMS_DBFS {
+                " ⚠️ very quiet"
+            } else {
+                ""
+            };
+            format!(
+                "• <@{}>: peak {:.1} dBFS, RMS {:.1} dBFS, {} clipped samples{}",
+                user_id,
+                s.peak_dbfs(),
+                s.rms_dbfs(),
+                s.clipped_samples,
+                warning
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Waveform PNGs of the session's speakers, named by user id when known
+async fn waveform_attachments(paths: SessionPaths) -> Vec<serenity::CreateAttachment> {
+    let rendered = tokio::task::spawn_blocking(move || {
+        let ssrc_map = load_ssrc_map(paths.root()).unwrap_or_default();
+        render_session_waveforms(&paths)
+            .into_iter()
+            .map(|(ssrc, png)| {
+                let name = ssrc_map.get(&ssrc).map_or_else(|| format!("ssrc-{}", ssrc), |id| id.to_string());
+                (format!("waveform-{}.png", name), png)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await;
+
+    match rendered {
+        Ok(rendered) => rendered
+            .into_iter()
+            .take(MAX_ATTACHMENTS)
+            .map(|(filename, png)| serenity::CreateAttachment::bytes(png, filename))
+            .collect(),
+        Err(e) => {
+            error!("Failed to render waveforms: {:?}", e);
+            Vec::new()
+        }
+    }
+}
+
 #[poise::command(prefix_command, slash_command, rename = "stop-recording", guild_only)]
-pub async fn stop_recording(ctx: Context<'_>) -> Result<(), Error> {
+#[tracing::instrument(skip_all, fields(guild_id = ctx.guild_id().map_or(0, |g| g.get())))]
+pub async fn stop_recording(
+    ctx: Context<'_>,
+    #[description = "Attach a waveform image per speaker, speech highlighted (default: false)"]
+    waveform: Option<bool>,
+) -> Result<(), Error> {
     let guild_id = ctx
         .guild_id()
         .ok_or("This command must be used in a guild")?;
@@ -26,7 +117,9 @@ pub async fn stop_recording(ctx: Context<'_>) -> Result<(), Error> {
 
     let session = {
         let mut sessions = ctx.data().active_sessions.lock().await;
-        sessions.remove(&guild_id_u64)
+        let session = sessions.remove(&guild_id_u64);
+        update_recording_presence(ctx.serenity_context(), &sessions);
+        session
     };
 
     let mut session = match session {
@@ -39,20 +132,7 @@ pub async fn stop_recording(ctx: Context<'_>) -> Result<(), Error> {
 
     ctx.defer().await?;
 
-    let storage_handle = {
-        let mut state = session.state.lock().await;
-        state.stop()
-    };
-
-    if let Some(handle) = storage_handle {
-        handle.shutdown();
-    }
-
-    if let Some(task) = session.storage_task.take() {
-        if let Err(e) = task.await {
-            error!("Storage task panicked: {:?}", e);
-        }
-    }
+    let level_stats = session.finalize().await;
 
     let manager = songbird::get(ctx.serenity_context())
         .await
@@ -68,13 +148,55 @@ pub async fn stop_recording(ctx: Context<'_>) -> Result<(), Error> {
     let duration = session.duration();
     let duration_str = format_duration(duration);
 
-    ctx.say(format!(
+    if let Err(e) = db::finish_session(
+        &ctx.data().db,
+        &guild_id_u64.to_string(),
+        &session.session_id,
+        duration.num_seconds(),
+    )
+    .await
+    {
+        error!("Failed to update session in database: {:?}", e);
+    }
+
+    let mut response = format!(
         "🎙️ **Recording stopped!**\n\
         📁 Session: `{}`\n\
         ⏱️ Duration: {}",
         session.session_dir.display(),
         duration_str
-    ))
-    .await?;
+    );
+    let paths = SessionPaths::new(&session.session_dir);
+    if let Some(history) = SsrcHistory::load(&paths.ssrc_history()) {
+        for (ssrc, owners) in history.reassigned() {
+            let users: Vec<String> = owners.iter().map(|id| format!("<@{}>", id)).collect();
+            response.push_str(&format!(
+                "\n⚠️ Audio stream {} was reused by {}, it is split between them by time.",
+                ssrc,
+                users.join(" → ")
+            ));
+        }
+    }
+
+    if let Some(metadata) = SessionMetadata::load(&paths.metadata()) {
+        response.push_str(&format_gaps(&metadata.gaps));
+    }
+
+    if !paths.has_audio() {
+        response.push_str("\n\n🔇 No audio was captured, nobody spoke while recording.");
+    } else if !level_stats.is_empty() {
+        response.push_str(&format!("\n\n**Levels:**\n{}", format_level_summary(&level_stats)));
+    }
+
+    // Mentions identify speakers without pinging them
+    let mut reply = poise::CreateReply::default()
+        .content(response)
+        .allowed_mentions(serenity::CreateAllowedMentions::new());
+    if waveform.unwrap_or(false) && paths.has_audio() {
+        for attachment in waveform_attachments(paths).await {
+            reply = reply.attachment(attachment);
+        }
+    }
+    ctx.send(reply).await?;
     Ok(())
 }
@@ -0,0 +1,144 @@
+use crate::Context;
+use crate::Error;
+use crate::UserError;
+use crate::command::job_slot::acquire_job_slot;
+use crate::transcribe::{
+    AudioFormat, DEFAULT_CHUNK_OVERLAP_SECS, DEFAULT_MIN_CHUNK_SECS, LanguageConfig, PreparedAudio,
+    Transcriber, UserTranscription, WHISPER_SAMPLE_RATE, WhisperModel, prepare_for_transcription,
+};
+use crate::voice::audio::read_wav;
+use poise::serenity_prelude as serenity;
+use std::fs;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Largest upload accepted, longer recordings should be transcribed from their session
+const MAX_FILE_BYTES: u32 = 100 * 1024 * 1024;
+
+/// Read a WAV file and transcribe it as a single speaker
+fn transcribe_wav(
+    path: &Path,
+    model: WhisperModel,
+    name: &str,
+) -> Result<UserTranscription, Error> {
+    let (samples, sample_rate, channels) = read_wav(path)?;
+    let samples_16khz = prepare_for_transcription(
+        &samples,
+        AudioFormat {
+            sample_rate,
+            channels,
+        },
+    );
+    let duration_secs = samples_16khz.len() as f32 / WHISPER_SAMPLE_RATE as f32;
+    let audio = PreparedAudio {
+        user_id: 0,
+        ssrcs: vec![],
+        samples_16khz,
+        duration_secs,
+        first_tick: 0,
+        last_tick: 0,
+    };
+
+    let chunks = audio.split_for_transcription(
+        model.default_min_silence_secs(),
+        DEFAULT_MIN_CHUNK_SECS,
+        DEFAULT_CHUNK_OVERLAP_SECS,
+        model.default_max_chunk_secs(),
+        None,
+        None,
+    );
+    let transcriber = Transcriber::with_language(model, LanguageConfig::default())?;
+    let transcribed = transcriber.transcribe_chunks(&chunks, &|_, _| {})?;
+
+    Ok(UserTranscription::from_chunks(
+        0,
+        name.to_string(),
+        &model.to_string(),
+        duration_secs,
+        transcribed,
+    ))
+}
+
+/// Transcribe an attached WAV file, e.g. a track from reconstruct-audio
+#[poise::command(prefix_command, slash_command, rename = "transcribe-file")]
+pub async fn transcribe_file(
+    ctx: Context<'_>,
+    #[description = "WAV file to transcribe"] file: serenity::Attachment,
+    #[description = "Whisper model: tiny, base, small (default), medium, large"] model: Option<
+        String,
+    >,
+) -> Result<(), Error> {
+    if !file.filename.to_lowercase().ends_with(".wav") {
+        ctx.say("Attach a `.wav` file.").await?;
+        return Ok(());
+    }
+    if file.size > MAX_FILE_BYTES {
+        ctx.say(format!(
+            "`{}` is too large, files up to {}MB can be transcribed.",
+            file.filename,
+            MAX_FILE_BYTES / 1024 / 1024
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let model = match model.as_deref() {
+        Some(m) => m
+            .parse::<WhisperModel>()
+            .map_err(UserError)?,
+        None => WhisperModel::Small,
+    };
+
+    ctx.defer().await?;
+    let _slot = acquire_job_slot(ctx).await?;
+
+    // hound reads from a path, so the upload goes through a temporary file
+    let path = std::env::temp_dir().join(format!("writey_upload_{}.wav", file.id));
+    fs::write(&path, file.download().await?)?;
+
+    info!(
+        "Transcribing uploaded file {} with {} model",
+        file.filename, model
+    );
+    let result = tokio::task::spawn_blocking({
+        let path = path.clone();
+        let name = file.filename.clone();
+        move || transcribe_wav(&path, model, &name)
+    })
+    .await?;
+
+    if let Err(e) = fs::remove_file(&path) {
+        warn!("Failed to remove {}: {}", path.display(), e);
+    }
+
+    let transcription = match result {
+        Ok(transcription) => transcription,
+        Err(e) => {
+            ctx.say(format!(
+                "❌ Failed to transcribe `{}`: {}",
+                file.filename, e
+            ))
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let stem = file
+        .filename
+        .rsplit_once('.')
+        .map_or(file.filename.as_str(), |(stem, _)| stem);
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format!(
+                "📝 Transcript of `{}` ({:.0}s of audio)",
+                file.filename, transcription.total_duration_secs
+            ))
+            .attachment(serenity::CreateAttachment::bytes(
+                transcription.full_transcript.into_bytes(),
+                format!("{}.txt", stem),
+            )),
+    )
+    .await?;
+
+    Ok(())
+}
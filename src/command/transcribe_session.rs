@@ -1,13 +1,32 @@
-use crate::db;
+use crate::command::job_slot::acquire_job_slot;
+use crate::command::long_message::say_long;
+use crate::command::permissions::author_can_manage_guild;
+use crate::command::search_transcript::{index_session, write_combined_transcriptions};
+use crate::command::stop_recording::format_duration;
+use crate::db::{self, TranscriptionStatus};
+use crate::paths::{
+    PacketStats, SessionMetadata, SessionPaths, TranscriptLayout, exports_root, transcribe_user_dir_name,
+    transcript_export_dir,
+};
 use crate::transcribe::{
-    prepare_session_for_transcription, AudioChunk, LanguageConfig, PreparedAudio, Transcriber,
-    UserTranscription, WhisperModel, MIN_SILENCE_DURATION_SECS,
+    load_session_frames, load_user_audio_original_rate, prepare_session_for_transcription, AudioChunk, ChunkFormat, TimeBound, BackendKind, DecodingConfig, HallucinationConfig, LanguageConfig, LanguageStrategy,
+    ChunkTranscription, PreparedAudio, Transcriber, TranscriptionBackend, UserTranscription, WhisperError, WhisperModel,
+    DEFAULT_CHUNK_OVERLAP_SECS, DEFAULT_MIN_CHUNK_SECS, MIN_SILENCE_DURATION_SECS, OpenAiBackend, SpeakingStats, matroska_chapters_xml, session_vtt, speaker_turns, user_srt, user_vtt,
+    speaking_stats, mix_prepared_audio, SpeakerActivity, attribute_speakers, attribute_speakers_by_overlap, ConversationConfig,
+    transcript_markdown, transcript_text, UserFrames,
 };
-use crate::Context;
-use crate::Error;
+use crate::voice::audio::{AdaptiveVadConfig, ClipMode};
+use crate::voice::clock::{NOMINAL_TICK_SECS, SessionClock};
+use crate::voice::storage::SAMPLES_PER_TICK;
+use crate::{Context, Data, Error, TranscriptionJobs, UserError};
+use poise::serenity_prelude as serenity;
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use tracing::info;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
 
 /// Parse language mode string into LanguageConfig
 fn parse_language_mode(mode: Option<&str>) -> LanguageConfig {
@@ -15,10 +34,20 @@ fn parse_language_mode(mode: Option<&str>) -> LanguageConfig {
         Some("de") | Some("german") => LanguageConfig::german_primary(),
         Some("en") | Some("english") => LanguageConfig::english_primary(),
         Some("translate") => LanguageConfig::translate_to_english(),
+        Some("bilingual") => LanguageConfig::bilingual(),
         _ => LanguageConfig::german_english_mixed(), // Default: auto-detect mixed
     }
 }
 
+/// Parse hallucination filter preset into HallucinationConfig
+fn parse_hallucination_mode(mode: Option<&str>) -> HallucinationConfig {
+    match mode {
+        Some("lenient") => HallucinationConfig::lenient(),
+        Some("strict") => HallucinationConfig::strict(),
+        _ => HallucinationConfig::default(),
+    }
+}
+
 #[derive(Debug)]
 struct ResolvedUser {
     user_id: u64,
@@ -34,7 +63,20 @@ struct UserChunks {
     total_duration_secs: f32,
 }
 
+/// Look up a user's Discord display name from the cache, falling back to the API
+async fn fetch_discord_name(ctx: &serenity::Context, user_id: u64) -> Option<String> {
+    let user_id = serenity::model::id::UserId::new(user_id);
+
+    if let Some(user) = ctx.cache.user(user_id) {
+        return Some(user.global_name.clone().unwrap_or_else(|| user.name.clone()));
+    }
+
+    let user = ctx.http.get_user(user_id).await.ok()?;
+    Some(user.global_name.unwrap_or(user.name))
+}
+
 async fn resolve_user_names(
+    serenity_ctx: &serenity::Context,
     db: &db::DbPool,
     guild_id: &str,
     prepared_audio: Vec<PreparedAudio>,
@@ -44,13 +86,20 @@ async fn resolve_user_names(
     for audio in prepared_audio {
         let user_id_str = audio.user_id.to_string();
 
+        // The fetched Discord name is cached apart from the transcribe name, which stays the user's choice
         let display_name = match db::get_user_setting(db, &user_id_str, guild_id).await {
-            Ok(Some(setting)) if setting.transcribe_name.is_some() => {
-                setting.transcribe_name.unwrap()
-            }
-            _ => {
-                format!("User_{}", audio.user_id)
-            }
+            Ok(Some(db::UserSetting { transcribe_name: Some(name), .. })) => name,
+            Ok(Some(db::UserSetting { cached_display_name: Some(name), .. })) => name,
+            _ => match fetch_discord_name(serenity_ctx, audio.user_id).await {
+                Some(name) => {
+                    // Remember the Discord name so the next run doesn't need the API
+                    if let Err(e) = db::cache_display_name(db, &user_id_str, guild_id, &name).await {
+                        warn!("Failed to cache name for user {}: {}", audio.user_id, e);
+                    }
+                    name
+                }
+                None => format!("User_{}", audio.user_id),
+            },
         };
 
         info!("Resolved user {} -> '{}'", audio.user_id, display_name);
@@ -65,51 +114,630 @@ async fn resolve_user_names(
     resolved
 }
 
-/// Transcribe a recording session using Whisper AI
-/// 
-/// Prepares audio for all users, splits on silence gaps, and transcribes
-/// using a local Whisper model (downloaded from Hugging Face if needed).
-/// 
-/// Supports mixed German/English speech with auto-detection.
-#[poise::command(prefix_command, slash_command, rename = "transcribe-session")]
-pub async fn transcribe_session(
+/// Prepare every user's audio within the `since`/`until` bounds
+///
+/// Wall-clock bounds are resolved against the session's first clock anchor, or
+/// the start time in session.json for recordings without one. Only the ticks in
+/// the range are reconstructed and resampled. Returns the audio, dropping users
+/// who said nothing in the range, and the range in seconds from the session
+/// start for the manifest.
+fn prepare_time_range(
+    paths: &SessionPaths,
+    since: Option<TimeBound>,
+    until: Option<TimeBound>,
+) -> Result<(Vec<PreparedAudio>, (f64, f64)), String> {
+    let users = load_session_frames(paths.root()).map_err(|e| format!("Failed to prepare session: {}", e))?;
+
+    let clock = SessionClock::load(&paths.clock());
+    let start = clock
+        .as_ref()
+        .and_then(SessionClock::start_unix_ms)
+        .and_then(chrono::DateTime::from_timestamp_millis)
+        .or_else(|| SessionMetadata::load(&paths.metadata()).map(|m| m.started_at));
+    let secs_per_tick = clock.as_ref().map_or(NOMINAL_TICK_SECS, SessionClock::secs_per_tick);
+
+    let last_tick = users.iter().map(UserFrames::last_tick).max().unwrap_or(0);
+    let duration_secs = (last_tick + 1) as f64 * secs_per_tick;
+
+    let from_secs = since.map_or(Ok(0.0), |b| b.resolve(start, duration_secs))?;
+    let to_secs = until.map_or(Ok(duration_secs), |b| b.resolve(start, duration_secs))?;
+    if from_secs >= to_secs {
+        return Err("The start of the time range must be before its end".to_string());
+    }
+
+    let from_tick = (from_secs / secs_per_tick).floor() as u64;
+    let to_tick = ((to_secs / secs_per_tick).ceil() as u64).saturating_sub(1);
+    info!(
+        "Preparing session from {:.1}s to {:.1}s (ticks {}-{})",
+        from_secs, to_secs, from_tick, to_tick
+    );
+
+    let prepared = users
+        .iter()
+        .filter_map(|user| user.prepare_range(from_tick, to_tick))
+        .filter(|audio| audio.samples_16khz.iter().any(|&s| s != 0.0))
+        .collect();
+    Ok((prepared, (from_secs, to_secs)))
+}
+
+/// Minimum time between edits of the transcription status message
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Packet loss below this is not worth mentioning in the completion message
+const REPORTED_PACKET_LOSS_PERCENT: f32 = 0.1;
+
+/// A running transcription registered for `/cancel-transcription`, unregistered on drop
+struct TranscriptionJob {
+    jobs: Arc<Mutex<TranscriptionJobs>>,
+    guild_id: String,
+    cancel_flag: Arc<AtomicBool>,
+}
+
+impl TranscriptionJob {
+    /// Register a job for a guild, or `None` if one is already running there
+    fn register(jobs: &Arc<Mutex<TranscriptionJobs>>, guild_id: &str) -> Option<Self> {
+        let mut running = jobs.lock().ok()?;
+        if running.contains_key(guild_id) {
+            return None;
+        }
+
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        running.insert(guild_id.to_string(), cancel_flag.clone());
+        Some(Self {
+            jobs: jobs.clone(),
+            guild_id: guild_id.to_string(),
+            cancel_flag,
+        })
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel_flag.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for TranscriptionJob {
+    fn drop(&mut self) {
+        if let Ok(mut running) = self.jobs.lock() {
+            running.remove(&self.guild_id);
+        }
+    }
+}
+
+/// Write a user's `transcription.json`, `transcript.txt`, `transcript.srt` and `transcript.vtt`
+pub fn write_user_transcript(user_dir: &std::path::Path, transcription: &UserTranscription) -> Result<(), Error> {
+    fs::write(user_dir.join("transcription.json"), serde_json::to_string_pretty(transcription)?)?;
+    fs::write(user_dir.join("transcript.txt"), &transcription.full_transcript)?;
+    fs::write(user_dir.join("transcript.srt"), user_srt(transcription))?;
+    fs::write(user_dir.join("transcript.vtt"), user_vtt(transcription))?;
+    Ok(())
+}
+
+/// Transcribe one user's chunks, editing the status message with progress until done
+///
+/// Long sessions take hours, and a status message that never changes looks
+/// like the bot hung. Edits are spaced by `HEARTBEAT_INTERVAL` to stay clear of
+/// Discord's rate limits.
+async fn transcribe_with_heartbeat(
+    ctx: Context<'_>,
+    status: &poise::ReplyHandle<'_>,
+    status_text: &str,
+    started: Instant,
+    transcriber: &Arc<dyn TranscriptionBackend>,
+    chunks: &Arc<Vec<AudioChunk>>,
+    speaker: &str,
+) -> Result<Result<Vec<ChunkTranscription>, WhisperError>, Error> {
+    let chunks_done = Arc::new(AtomicUsize::new(0));
+
+    // Inference and uploads block, keep them off the async worker
+    let mut task = {
+        let transcriber = transcriber.clone();
+        let chunks = chunks.clone();
+        let chunks_done = chunks_done.clone();
+        tokio::task::spawn_blocking(move || {
+            transcriber.transcribe_chunks(&chunks, &|done, _| chunks_done.store(done, Ordering::Relaxed))
+        })
+    };
+
+    let mut heartbeat = tokio::time::interval_at(
+        tokio::time::Instant::now() + HEARTBEAT_INTERVAL,
+        HEARTBEAT_INTERVAL,
+    );
+    heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            result = &mut task => return Ok(result?),
+            _ = heartbeat.tick() => {
+                let progress = format!(
+                    "⏱️ {} elapsed, **{}**: chunk {}/{}",
+                    format_duration(chrono::Duration::seconds(started.elapsed().as_secs() as i64)),
+                    speaker,
+                    chunks_done.load(Ordering::Relaxed),
+                    chunks.len()
+                );
+                let reply = poise::CreateReply::default().content(format!("{}\n\n{}", status_text, progress));
+                if let Err(e) = status.edit(ctx, reply).await {
+                    warn!("Failed to update transcription status: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Store a session's transcription status, logging rather than failing on DB errors
+async fn record_transcription_status(
+    db: &db::DbPool,
+    guild_id: &str,
+    session_id: &str,
+    path: &str,
+    status: TranscriptionStatus,
+) {
+    if let Err(e) = db::set_transcription_status(db, guild_id, session_id, path, status).await {
+        warn!("Failed to record transcription status {:?}: {}", status, e);
+    }
+}
+
+/// A session's transcription status while it runs, recorded as failed unless finished
+///
+/// Created once the session is marked in progress, so an early `?` return or a
+/// panic doesn't leave it in progress for good.
+struct TranscriptionStatusGuard {
+    db: db::DbPool,
+    guild_id: String,
+    session_id: String,
+    path: String,
+    finished: bool,
+}
+
+impl TranscriptionStatusGuard {
+    async fn start(db: &db::DbPool, guild_id: &str, session_id: &str, path: &str) -> Self {
+        record_transcription_status(db, guild_id, session_id, path, TranscriptionStatus::InProgress).await;
+        Self {
+            db: db.clone(),
+            guild_id: guild_id.to_string(),
+            session_id: session_id.to_string(),
+            path: path.to_string(),
+            finished: false,
+        }
+    }
+
+    /// Record how the transcription ended
+    async fn finish(mut self, status: TranscriptionStatus) {
+        self.finished = true;
+        record_transcription_status(&self.db, &self.guild_id, &self.session_id, &self.path, status).await;
+    }
+}
+
+impl Drop for TranscriptionStatusGuard {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let db = self.db.clone();
+        let (guild_id, session_id, path) =
+            (self.guild_id.clone(), self.session_id.clone(), self.path.clone());
+        tokio::spawn(async move {
+            record_transcription_status(&db, &guild_id, &session_id, &path, TranscriptionStatus::Failed).await;
+        });
+    }
+}
+
+/// One `key=value` option, with the type of its field
+fn option_value<T: std::str::FromStr>(key: &str, value: &str) -> Result<Option<T>, String> {
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("Invalid value for {}: {}", key, value))
+}
+
+/// Defines `TranscribeOptions`, its `key=value` parser and the slash command from one list
+///
+/// Every option is listed once with its slash command description, so the three
+/// can't drift apart.
+macro_rules! transcribe_options {
+    ($( #[description = $description:tt] $name:ident: $ty:ident, )*) => {
+        /// Everything `transcribe-session` takes besides the session, all optional
+        ///
+        /// Parsed from `key=value` pairs for the prefix command: poise's prefix parser
+        /// tries every combination of optional arguments, which doesn't compile in
+        /// reasonable memory with this many.
+        #[derive(Debug, Default, PartialEq)]
+        pub struct TranscribeOptions {
+            $( pub $name: Option<$ty>, )*
+        }
+
+        impl std::str::FromStr for TranscribeOptions {
+            type Err = String;
+
+            /// `model=medium dry-run=true`, keys as in the slash command with `_` or `-`
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let mut options = TranscribeOptions::default();
+                for pair in s.split_whitespace() {
+                    let (key, value) = pair
+                        .split_once('=')
+                        .ok_or_else(|| format!("Expected key=value, got: {}", pair))?;
+                    match key.replace('-', "_").as_str() {
+                        $( stringify!($name) => options.$name = option_value(key, value)?, )*
+                        _ => return Err(format!("Unknown option: {}", key)),
+                    }
+                }
+                Ok(options)
+            }
+        }
+
+        /// Transcribe a recording session using Whisper AI
+        ///
+        /// Prepares audio for all users, splits on silence gaps, and transcribes
+        /// using a local Whisper model (downloaded from Hugging Face if needed).
+        ///
+        /// Supports mixed German/English speech with auto-detection.
+        #[poise::command(slash_command, rename = "transcribe-session", guild_only)]
+        #[allow(clippy::too_many_arguments)]
+        async fn transcribe_session_slash(
+            ctx: Context<'_>,
+            #[description = "Session directory path (e.g. recordings/715908438760357910/2026_01_03_18_49_53)"]
+            session_dir: String,
+            $( #[description = $description] $name: Option<$ty>, )*
+        ) -> Result<(), Error> {
+            run_transcription(ctx, session_dir, TranscribeOptions { $( $name, )* }).await
+        }
+    };
+}
+
+transcribe_options! {
+    #[description = "Whisper model size: tiny, base, small, medium, large, auto (by amount of speech) (default: small)"]
+    model: String,
+    #[description = "Language mode: auto (mixed de/en), de (German), en (English), translate (to English), bilingual (original + English)"]
+    language: String,
+    #[description = "Minimum silence duration to split chunks (default: 1-2 seconds depending on model)"]
+    min_silence_secs: f32,
+    #[description = "Hallucination filter: default, lenient (keeps repeats), strict (drops more)"]
+    hallucination_filter: String,
+    #[description = "Initial decoding temperature, 0.0 is deterministic (0.0-1.0, default: 0.0)"]
+    temperature: f32,
+    #[description = "Temperature added when decoding fails, 0.0 disables fallback (0.0-1.0, default: 0.2)"]
+    temperature_inc: f32,
+    #[description = "Candidates sampled per pass when the temperature is above 0 (1-10, default: 1)"]
+    best_of: i32,
+    #[description = "Only report how the audio would be chunked, without running Whisper"]
+    dry_run: bool,
+    #[description = "Audio profile: none (default), speech (EQ for muffled low-bitrate voice)"]
+    audio_profile: String,
+    #[description = "Split subtitle segments longer than this many seconds (default: no splitting)"]
+    max_segment_secs: f32,
+    #[description = "Auto-detect language on each user's first chunk, then keep it (for mostly-monolingual speakers)"]
+    lock_language: bool,
+    #[description = "Seconds of audio repeated between chunks to catch cut-off words (default: 0.2)"]
+    chunk_overlap_secs: f32,
+    #[description = "Maximum chunk length in seconds (default: 30-120 depending on model)"]
+    max_chunk_secs: f32,
+    #[description = "Speech shorter than this many seconds joins the previous chunk (default: 0.5)"]
+    min_chunk_secs: f32,
+    #[description = "Join short chunks up to this many seconds, fewer Whisper calls for choppy speech (default: off)"]
+    target_chunk_secs: f32,
+    #[description = "Seconds of silence added before each chunk to catch soft word onsets (default: 0)"]
+    pad_start_secs: f32,
+    #[description = "Seconds of silence added after each chunk to catch trailing consonants (default: 0)"]
+    pad_end_secs: f32,
+    #[description = "Silence detection: fixed (default), adaptive (tracks rising background noise)"]
+    vad: String,
+    #[description = "Adaptive VAD: how fast the noise floor follows background noise (0-1, default: 0.05)"]
+    vad_adaptation_rate: f32,
+    #[description = "Adaptive VAD: dB above the noise floor that counts as speech (default: 10)"]
+    vad_margin_db: f32,
+    #[description = "Replace an existing transcription of this session (default: false)"]
+    overwrite: bool,
+    #[description = "Correct timestamps for tick drift using the session's wall-clock anchors (default: false)"]
+    wall_clock: bool,
+    #[description = "Transcription backend: whisper (local, default), openai (uploads audio to OpenAI)"]
+    backend: String,
+    #[description = "Also save each chunk at the original 48kHz as chunk_NNNN_48k.wav, for non-Whisper tools (default: false)"]
+    keep_original_rate: bool,
+    #[description = "Transcribe everyone's mixed audio once and attribute lines by who was speaking (faster, less accurate on crosstalk)"]
+    mixed: bool,
+    #[description = "Folder under the server's exports to write transcripts to (needs Manage Server)"]
+    output_dir: String,
+    #[description = "Saved chunks: hard (default, clamp levels over full scale), soft (tanh, less distortion)"]
+    clip: String,
+    #[description = "Saved chunks: wav (default), mp3 (smaller, can be attached to messages)"]
+    chunk_format: String,
+    #[description = "Start at a time of day (14:05, 14:05+02:00) or an offset from the start (+15:00)"]
+    since: String,
+    #[description = "Stop at a time of day (15:30, 15:30+02:00) or an offset from the start (+45:00)"]
+    until: String,
+    #[description = "Output: per-user (default, a folder per speaker), combined (one merged transcript, no chunk WAVs)"]
+    layout: String,
+}
+
+/// `transcribe-session <session_dir> [key=value ...]`, the prefix form of the slash command
+#[poise::command(prefix_command, rename = "transcribe-session", guild_only)]
+async fn transcribe_session_prefix(
     ctx: Context<'_>,
-    #[description = "Session directory path (e.g. recordings/715908438760357910/2026_01_03_18_49_53)"]
     session_dir: String,
-    #[description = "Whisper model size: tiny, base, small, medium, large (default: small)"]
-    model: Option<String>,
-    #[description = "Language mode: auto (mixed de/en), de (German), en (English), translate (to English)"]
-    language: Option<String>,
-    #[description = "Minimum silence duration to split chunks (default: 2.0 seconds)"]
-    min_silence_secs: Option<f32>,
+    #[rest] options: Option<String>,
 ) -> Result<(), Error> {
+    let options = match options.as_deref().unwrap_or_default().parse::<TranscribeOptions>() {
+        Ok(options) => options,
+        Err(e) => {
+            ctx.say(format!("❌ {}", e)).await?;
+            return Ok(());
+        }
+    };
+    run_transcription(ctx, session_dir, options).await
+}
+
+/// `transcribe-session` as one command with both entry points
+///
+/// poise looks commands up by name alone, so a slash and a prefix command of the
+/// same name can't be registered separately. The prefix form takes the options
+/// as `key=value` pairs, see `TranscribeOptions`.
+pub fn transcribe_session() -> poise::Command<Data, Error> {
+    poise::Command {
+        prefix_action: transcribe_session_prefix().prefix_action,
+        ..transcribe_session_slash()
+    }
+}
+
+#[tracing::instrument(skip_all, fields(guild_id = tracing::field::Empty, session_id = tracing::field::Empty))]
+async fn run_transcription(ctx: Context<'_>, session_dir: String, options: TranscribeOptions) -> Result<(), Error> {
+    let TranscribeOptions {
+        model,
+        language,
+        min_silence_secs,
+        hallucination_filter,
+        temperature,
+        temperature_inc,
+        best_of,
+        dry_run,
+        audio_profile,
+        max_segment_secs,
+        lock_language,
+        chunk_overlap_secs,
+        max_chunk_secs,
+        min_chunk_secs,
+        target_chunk_secs,
+        pad_start_secs,
+        pad_end_secs,
+        vad,
+        vad_adaptation_rate,
+        vad_margin_db,
+        overwrite,
+        wall_clock,
+        backend,
+        keep_original_rate,
+        mixed,
+        output_dir,
+        clip,
+        chunk_format,
+        since,
+        until,
+        layout,
+    } = options;
     ctx.defer().await?;
 
-    let min_silence = min_silence_secs.unwrap_or(MIN_SILENCE_DURATION_SECS);
-    
-    // Parse model selection
-    let whisper_model = match model.as_deref() {
-        Some(m) => m.parse::<WhisperModel>().map_err(|e| -> Error { e.into() })?,
-        None => WhisperModel::Small,
+    // Parse model selection, `None` picks one from the amount of speech once the audio is loaded
+    let model_choice = match model.as_deref() {
+        Some(m) if m.eq_ignore_ascii_case("auto") => None,
+        Some(m) => Some(m.parse::<WhisperModel>().map_err(UserError)?),
+        None => Some(WhisperModel::Small),
+    };
+
+    let clip_mode = match clip.as_deref() {
+        Some(c) => c.parse::<ClipMode>().map_err(UserError)?,
+        None => ClipMode::default(),
+    };
+
+    let chunk_format = match chunk_format.as_deref() {
+        Some(f) => f.parse::<ChunkFormat>().map_err(UserError)?,
+        None => ChunkFormat::default(),
+    };
+
+    let since = match since.as_deref() {
+        Some(s) => Some(s.parse::<TimeBound>().map_err(UserError)?),
+        None => None,
+    };
+    let until = match until.as_deref() {
+        Some(s) => Some(s.parse::<TimeBound>().map_err(UserError)?),
+        None => None,
+    };
+
+    let layout = match layout.as_deref() {
+        Some(l) => l.parse::<TranscriptLayout>().map_err(UserError)?,
+        None => TranscriptLayout::default(),
+    };
+    let per_user = layout == TranscriptLayout::PerUser;
+
+    let backend_kind = match backend.as_deref() {
+        Some(b) => b.parse::<BackendKind>().map_err(UserError)?,
+        None => BackendKind::default(),
+    };
+
+    let min_chunk = min_chunk_secs.unwrap_or(DEFAULT_MIN_CHUNK_SECS);
+    let chunk_overlap = chunk_overlap_secs.unwrap_or(DEFAULT_CHUNK_OVERLAP_SECS);
+    // Tuning the adaptive VAD implies using it
+    let vad_tuned = vad_adaptation_rate.is_some() || vad_margin_db.is_some();
+    let vad_config = match vad.as_deref() {
+        None if !vad_tuned => None,
+        Some("fixed") if vad_tuned => {
+            ctx.say("`vad_adaptation_rate` and `vad_margin_db` only apply to the adaptive VAD.")
+                .await?;
+            return Ok(());
+        }
+        Some("fixed") => None,
+        None | Some("adaptive") => {
+            let defaults = AdaptiveVadConfig::default();
+            let config = AdaptiveVadConfig {
+                adaptation_rate: vad_adaptation_rate.unwrap_or(defaults.adaptation_rate),
+                margin_db: vad_margin_db.unwrap_or(defaults.margin_db),
+                ..defaults
+            };
+            if let Err(e) = config.validate() {
+                ctx.say(format!("❌ {}", e)).await?;
+                return Ok(());
+            }
+            Some(config)
+        }
+        Some(other) => {
+            ctx.say(format!("Unknown VAD mode: {}. Use fixed or adaptive", other))
+                .await?;
+            return Ok(());
+        }
     };
     
     // Parse language mode (default: auto-detect mixed German/English)
     let language_config = parse_language_mode(language.as_deref());
 
-    let session_path = PathBuf::from(&session_dir);
-    if !session_path.exists() {
+    let hallucination_config = parse_hallucination_mode(hallucination_filter.as_deref());
+
+    let defaults = DecodingConfig::default();
+    let decoding_config = DecodingConfig {
+        temperature: temperature.unwrap_or(defaults.temperature),
+        temperature_inc: temperature_inc.unwrap_or(defaults.temperature_inc),
+        best_of: best_of.unwrap_or(defaults.best_of),
+    };
+    if let Err(e) = decoding_config.validate() {
+        ctx.say(format!("❌ {}", e)).await?;
+        return Ok(());
+    }
+
+    // Locking only applies when the language is auto-detected
+    let language_strategy = if lock_language.unwrap_or(false) && language_config.language.is_none() {
+        LanguageStrategy::DetectThenLock
+    } else {
+        LanguageStrategy::PerChunk
+    };
+
+    let dry_run = dry_run.unwrap_or(false);
+    let keep_original_rate = keep_original_rate.unwrap_or(false);
+    let mixed = mixed.unwrap_or(false);
+    if keep_original_rate && !per_user {
+        ctx.say("❌ `keep_original_rate` saves chunk WAVs into user folders, use the per-user layout for it.")
+            .await?;
+        return Ok(());
+    }
+
+    let mut paths = SessionPaths::new(&session_dir);
+    if !paths.root().exists() {
         ctx.say(format!("Session directory not found: {}", session_dir))
             .await?;
         return Ok(());
     }
 
-    // Extract guild ID from path (recordings/GUILD_ID/TIMESTAMP)
-    let guild_id = session_path
-        .parent()
-        .and_then(|p| p.file_name())
-        .and_then(|n| n.to_str())
-        .unwrap_or("0")
+    // Exports are shared with the host's other guilds, so they're kept per guild and for admins
+    if let Some(dir) = &output_dir {
+        let export_guild = ctx.guild_id().ok_or("Exporting transcripts must be done in a guild")?;
+        if !author_can_manage_guild(ctx).await {
+            ctx.say("❌ `output_dir` needs the Manage Server permission.").await?;
+            return Ok(());
+        }
+        match transcript_export_dir(&exports_root(), export_guild.get(), dir, &paths) {
+            Ok(export_dir) => paths = paths.with_transcribe_dir(export_dir),
+            Err(e) => {
+                ctx.say(format!("❌ Can't use output directory: {}", e)).await?;
+                return Ok(());
+            }
+        }
+    }
+
+    if !paths.has_audio() {
+        ctx.say("No audio was captured in this session, there is nothing to transcribe.")
+            .await?;
+        return Ok(());
+    }
+
+    // Refuse to clobber a previous run, which may contain manual corrections
+    let manifest_path = paths.transcribe_manifest();
+    if !dry_run && !overwrite.unwrap_or(false) && manifest_path.exists() {
+        ctx.say(format!(
+            "📁 This session is already transcribed: `{}`\n\
+            Use `/get-transcript` to fetch it, or pass `overwrite: true` to transcribe again.",
+            paths.transcribe_dir().display()
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    // Recordings from before clock anchors were added have no clock.json
+    let clock = if wall_clock.unwrap_or(false) {
+        let clock = SessionClock::load(&paths.clock());
+        if clock.is_none() {
+            ctx.say("⚠️ No clock data for this session, timestamps stay uncorrected.")
+                .await?;
+        }
+        clock
+    } else {
+        None
+    };
+
+    // Sessions without metadata or the usual layout are assumed to be from this guild
+    let guild_id = paths
+        .resolve_guild_id()
+        .or(ctx.guild_id().map(|g| g.get()))
+        .unwrap_or(0)
         .to_string();
+    let session_id = paths.session_id().unwrap_or(&session_dir).to_string();
+
+    tracing::Span::current()
+        .record("guild_id", guild_id.as_str())
+        .record("session_id", session_id.as_str());
+
+    // Prepare audio for all users, only the requested part of the session if limited
+    let (prepared, time_range) = if since.is_some() || until.is_some() {
+        match prepare_time_range(&paths, since, until) {
+            Ok((prepared, range)) => (prepared, Some(range)),
+            Err(e) => {
+                ctx.say(format!("❌ {}", e)).await?;
+                return Ok(());
+            }
+        }
+    } else {
+        match prepare_session_for_transcription(paths.root()) {
+            Ok(prepared) => (prepared, None),
+            Err(e) => {
+                ctx.say(format!("❌ Failed to prepare session: {}", e)).await?;
+                return Ok(());
+            }
+        }
+    };
+
+    info!("Prepared {} users for transcription", prepared.len());
+
+    if prepared.is_empty() {
+        ctx.say("Nobody spoke in the requested time range, there is nothing to transcribe.")
+            .await?;
+        return Ok(());
+    }
+
+    let (whisper_model, auto_reason) = match model_choice {
+        Some(model) => (model, None),
+        None => {
+            let speech_secs: f32 = prepared
+                .iter()
+                .flat_map(|audio| audio.speech_ranges(MIN_SILENCE_DURATION_SECS))
+                .map(|(start, end)| end - start)
+                .sum();
+            let threads = std::thread::available_parallelism().map_or(4, |n| n.get());
+            let (model, reason) = WhisperModel::auto_select(speech_secs, threads);
+            info!("Auto-selected Whisper {} model: {}", model, reason);
+            (model, Some(reason))
+        }
+    };
+
+    // Chunking adapts to the model unless given explicitly
+    let min_silence = min_silence_secs.unwrap_or(whisper_model.default_min_silence_secs());
+    let max_chunk = max_chunk_secs.unwrap_or(whisper_model.default_max_chunk_secs());
+    let (pad_start, pad_end) = (pad_start_secs.unwrap_or(0.0), pad_end_secs.unwrap_or(0.0));
+    let split_chunks = |audio: &PreparedAudio| {
+        let chunks = audio.split_for_transcription(
+            min_silence,
+            min_chunk,
+            chunk_overlap,
+            max_chunk,
+            target_chunk_secs,
+            vad_config.as_ref(),
+        );
+        audio.pad_chunks(chunks, pad_start, pad_end)
+    };
 
     info!("Transcribing session: {} (guild: {}, model: {})", session_dir, guild_id, whisper_model);
 
@@ -118,76 +746,318 @@ pub async fn transcribe_session(
         Some("de") | Some("german") => "German (primary)",
         Some("en") | Some("english") => "English (primary)",
         Some("translate") => "Translate to English",
+        Some("bilingual") => "Original + English translation",
         _ => "Auto-detect (German/English mixed)",
     };
 
-    // Send initial status
-    ctx.say(format!(
-        "🎙️ **Starting transcription...**\n\
-        Model: `{}` (~{}MB)\n\
+    // Send initial status, kept to be edited with progress while transcribing
+    let status_text = format!(
+        "🎙️ **{}**\n\
+        Model: `{}` (~{}MB){}\n\
         Language: `{}`\n\
-        Silence threshold: `{:.1}s`\n\n\
+        Silence threshold: `{:.1}s`\n\
+        Max chunk length: `{:.0}s`\n\
+        Silence detection: `{}`\n\
+        Hallucination filter: `{}`\n\
+        Decoding: `temperature {:.1} (+{:.1}), best of {}`\n\n\
         _This may take a while for long recordings..._",
+        if dry_run { "Analyzing session (dry run)..." } else { "Starting transcription..." },
         whisper_model,
         whisper_model.size_mb(),
+        auto_reason.map(|reason| format!(", picked automatically: {}", reason)).unwrap_or_default(),
         lang_desc,
-        min_silence
-    )).await?;
+        min_silence,
+        max_chunk,
+        vad.as_deref().unwrap_or("fixed"),
+        hallucination_filter.as_deref().unwrap_or("default"),
+        decoding_config.temperature,
+        decoding_config.temperature_inc,
+        decoding_config.best_of
+    );
+    let status = ctx.say(status_text.clone()).await?;
+    let started = Instant::now();
 
-    // Prepare audio for all users
-    let prepared = match prepare_session_for_transcription(&session_path) {
-        Ok(p) => p,
-        Err(e) => {
-            ctx.say(format!("❌ Failed to prepare session: {}", e)).await?;
-            return Ok(());
+    // Resolve user names from database, falling back to Discord display names
+    let resolved =
+        resolve_user_names(ctx.serenity_context(), &ctx.data().db, &guild_id, prepared).await;
+
+    // Users who stayed muted only sent silence, there is nothing to transcribe for them
+    let (silent_users, mut resolved): (Vec<ResolvedUser>, Vec<ResolvedUser>) =
+        resolved.into_iter().partition(|user| user.audio.is_all_silence());
+    let silent_notes: Vec<String> = silent_users
+        .iter()
+        .map(|user| {
+            info!("Skipping {}, only silence was recorded", user.display_name);
+            format!("• **{}**: no audio", user.display_name)
+        })
+        .collect();
+    if resolved.is_empty() {
+        ctx.say("Only silence was recorded in this session, there is nothing to transcribe.")
+            .await?;
+        return Ok(());
+    }
+
+    // Channel bitrate and packet loss help explain sessions that transcribe poorly
+    let metadata = SessionMetadata::load(&paths.metadata());
+    let packet_loss: HashMap<u64, f32> = metadata
+        .as_ref()
+        .map(|metadata| {
+            resolved
+                .iter()
+                .map(|user| (user.user_id, user_packet_loss(metadata, &user.audio.ssrcs)))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if audio_profile.as_deref() == Some("speech") {
+        info!("Applying speech audio profile");
+        for user in &mut resolved {
+            user.audio.apply_speech_profile();
         }
+    }
+
+    if dry_run {
+        let mut lines = Vec::new();
+        let mut total_audio_secs = 0.0f32;
+        let mut total_chunks = 0;
+
+        for user in &resolved {
+            let chunks = split_chunks(&user.audio);
+            let speech_secs: f32 = chunks.iter().map(|c| c.duration_secs).sum();
+            total_audio_secs += speech_secs;
+            total_chunks += chunks.len();
+            lines.push(format!(
+                "• **{}**: {} chunks, {:.1}s speech of {:.1}s recorded",
+                user.display_name,
+                chunks.len(),
+                speech_secs,
+                user.audio.duration_secs
+            ));
+        }
+        lines.extend(silent_notes);
+
+        let estimated_secs = total_audio_secs / whisper_model.approx_realtime_factor();
+
+        ctx.say(format!(
+            "🔍 **Dry run** (no transcription performed)\n\n\
+            {}\n\n\
+            **Total:** {} chunks, {:.1}s of speech\n\
+            **Estimated time with `{}`:** ~{:.0}s",
+            lines.join("\n"),
+            total_chunks,
+            total_audio_secs,
+            whisper_model,
+            estimated_secs
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    // Keyed by the guild the command runs in, where `/cancel-transcription` looks for it
+    let job_guild_id = ctx.guild_id().ok_or("This command must be used in a guild")?.to_string();
+    let Some(job) = TranscriptionJob::register(&ctx.data().transcription_jobs, &job_guild_id) else {
+        ctx.say("A transcription is already running for this guild. Use `/cancel-transcription` to stop it.")
+            .await?;
+        return Ok(());
     };
 
-    info!("Prepared {} users for transcription", prepared.len());
+    // Each run loads its own model, so the number running at once is capped host-wide
+    let _slot = acquire_job_slot(ctx).await?;
+    if job.is_cancelled() {
+        ctx.say("⏹️ Transcription cancelled before it started.").await?;
+        return Ok(());
+    }
 
-    // Resolve user names from database
-    let resolved = resolve_user_names(&ctx.data().db, &guild_id, prepared).await;
+    let status_guard = TranscriptionStatusGuard::start(&ctx.data().db, &guild_id, &session_id, &session_dir).await;
 
     // Create output directory
-    let output_dir = session_path.join("transcribe");
+    let output_dir = paths.transcribe_dir();
     fs::create_dir_all(&output_dir)?;
 
-    // Initialize Whisper (downloads model if needed)
-    ctx.channel_id()
-        .say(&ctx.http(), format!("⏳ Loading Whisper {} model...", whisper_model))
-        .await?;
+    let transcriber: Result<Arc<dyn TranscriptionBackend>, WhisperError> = match backend_kind {
+        BackendKind::Whisper => {
+            // Initialize Whisper (downloads model if needed)
+            ctx.channel_id()
+                .say(&ctx.http(), format!("⏳ Loading Whisper {} model...", whisper_model))
+                .await?;
 
-    let transcriber = match Transcriber::with_language(whisper_model, language_config) {
+            Transcriber::with_language(whisper_model, language_config).map(|t| {
+                Arc::new(
+                    t.with_hallucination_config(hallucination_config)
+                        .with_decoding_config(decoding_config)
+                        .with_language_strategy(language_strategy)
+                        .with_cancel_flag(job.cancel_flag.clone()),
+                ) as Arc<dyn TranscriptionBackend>
+            })
+        }
+        BackendKind::OpenAi => {
+            ctx.channel_id()
+                .say(&ctx.http(), "☁️ Using the OpenAI Whisper API, audio is uploaded to OpenAI")
+                .await?;
+
+            OpenAiBackend::from_env(language_config).map(|b| {
+                Arc::new(b.with_cancel_flag(job.cancel_flag.clone())) as Arc<dyn TranscriptionBackend>
+            })
+        }
+    };
+
+    let transcriber = match transcriber {
         Ok(t) => t,
         Err(e) => {
-            ctx.say(format!("❌ Failed to initialize Whisper: {}", e)).await?;
+            status_guard.finish(TranscriptionStatus::Failed).await;
+            ctx.say(format!("❌ Failed to initialize {} backend: {}", backend_kind, e)).await?;
             return Ok(());
         }
     };
+    let model_name = transcriber.name();
+
+    // Pay the first inference's setup cost up front, not in the first chunk's timing
+    let warmup = {
+        let transcriber = transcriber.clone();
+        tokio::task::spawn_blocking(move || {
+            let started = Instant::now();
+            transcriber.warmup().map(|_| started.elapsed())
+        })
+        .await?
+    };
+    match warmup {
+        Ok(elapsed) => info!("Warmed up {} in {:.2}s", model_name, elapsed.as_secs_f64()),
+        Err(e) => warn!("Warmup of {} failed, continuing without: {}", model_name, e),
+    }
 
     // Process each user
     let mut all_transcriptions: Vec<UserTranscription> = Vec::new();
     let mut user_info = Vec::new();
+    let mut chunks_planned = 0;
+    let mut chunks_done = 0;
+    // Segment timestamps are relative to this tick, per user
+    let mut first_ticks: HashMap<u64, u64> = resolved.iter().map(|u| (u.user_id, u.audio.first_tick)).collect();
 
-    for user in &resolved {
-        let safe_name = user
-            .display_name
-            .chars()
-            .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
-            .collect::<String>();
+    // Mixed mode transcribes everyone in one pass, then splits the result by speaker
+    let mixed_audio = if mixed {
+        mix_prepared_audio(&resolved.iter().map(|u| &u.audio).collect::<Vec<_>>())
+    } else {
+        None
+    };
+    if let Some(mixed_audio) = &mixed_audio {
+        let mixed_dir = paths.transcribe_dir().join("mixed");
+        if per_user {
+            fs::create_dir_all(&mixed_dir)?;
+        }
 
-        // Create user directory
-        let user_dir = output_dir.join(format!("{}_{}", user.user_id, safe_name));
-        fs::create_dir_all(&user_dir)?;
+        let chunks = Arc::new(split_chunks(mixed_audio));
+        ctx.channel_id()
+            .say(&ctx.http(), format!(
+                "🔄 Transcribing the mixed audio of {} user(s): {} chunks ({:.1}s)...",
+                resolved.len(),
+                chunks.len(),
+                mixed_audio.duration_secs
+            ))
+            .await?;
+        for chunk in chunks.iter().filter(|_| per_user) {
+            fs::write(
+                mixed_dir.join(format!("chunk_{:04}.{}", chunk.index, chunk_format.extension())),
+                chunk.encode(chunk_format, clip_mode)?,
+            )?;
+        }
+
+        let transcribed =
+            transcribe_with_heartbeat(ctx, &status, &status_text, started, &transcriber, &chunks, "everyone").await?;
+        match transcribed {
+            Ok(chunk_transcriptions) => {
+                chunks_planned += chunks.len();
+                chunks_done += chunk_transcriptions.len();
+
+                let mut transcription = UserTranscription::from_chunks(
+                    0,
+                    "Mixed".to_string(),
+                    &model_name,
+                    mixed_audio.duration_secs,
+                    chunk_transcriptions,
+                );
+                if let Some(max_secs) = max_segment_secs {
+                    transcription.split_long_segments(max_secs);
+                }
+                let speakers: Vec<SpeakerActivity> = resolved
+                    .iter()
+                    .map(|u| SpeakerActivity::from_audio(&u.audio, &u.display_name, mixed_audio.first_tick, min_silence))
+                    .collect();
+                attribute_speakers_by_overlap(&mut transcription.all_segments, &speakers);
+                // Segments between detected speech ranges go to whoever is loudest there
+                let tracks: Vec<(&PreparedAudio, &str)> =
+                    resolved.iter().map(|u| (&u.audio, u.display_name.as_str())).collect();
+                attribute_speakers(&mut transcription.all_segments, &tracks, mixed_audio.first_tick);
+                if let Some(clock) = &clock {
+                    transcription.correct_clock(clock, mixed_audio.first_tick);
+                }
+
+                // Not `transcription.json`, so the mixed track isn't read back as a speaker
+                if per_user {
+                    fs::write(mixed_dir.join("mixed_transcription.json"), serde_json::to_string_pretty(&transcription)?)?;
+                    fs::write(mixed_dir.join("transcript.srt"), user_srt(&transcription))?;
+                    fs::write(mixed_dir.join("transcript.vtt"), user_vtt(&transcription))?;
+                }
+
+                let unattributed = transcription.all_segments.iter().filter(|s| s.speaker_id.is_none()).count();
+                if unattributed > 0 {
+                    user_info.push(format!("• _{} segment(s) could not be attributed to a speaker_", unattributed));
+                }
+
+                for user_transcription in transcription.split_by_speaker() {
+                    if per_user {
+                        let user_dir =
+                            paths.transcribe_user_dir(user_transcription.user_id, &user_transcription.display_name);
+                        fs::create_dir_all(&user_dir)?;
+                        write_user_transcript(&user_dir, &user_transcription)?;
+
+                        let timing_data = serde_json::json!({
+                            "user_id": user_transcription.user_id,
+                            "display_name": user_transcription.display_name,
+                            "first_tick": mixed_audio.first_tick,
+                            "mixed": true,
+                            "wall_clock_corrected": clock.is_some(),
+                            "model": model_name,
+                        });
+                        fs::write(user_dir.join("timing.json"), serde_json::to_string_pretty(&timing_data)?)?;
+                    }
+
+                    first_ticks.insert(user_transcription.user_id, mixed_audio.first_tick);
+                    user_info.push(format!(
+                        "• **{}**: {} segments, ~{} words",
+                        user_transcription.display_name,
+                        user_transcription.all_segments.len(),
+                        user_transcription.full_transcript.split_whitespace().count()
+                    ));
+                    all_transcriptions.push(user_transcription);
+                }
+            }
+            Err(e) => {
+                warn!("Failed to transcribe mixed audio: {}", e);
+                user_info.push("• Mixed audio: ❌ transcription failed".to_string());
+            }
+        }
+    }
+
+    let separate_users: &[ResolvedUser] = if mixed_audio.is_some() { &[] } else { &resolved };
+    for user in separate_users {
+        if job.is_cancelled() {
+            break;
+        }
 
         // Split audio on silence
-        let chunks = user.audio.split_on_silence(min_silence);
+        let chunks = Arc::new(split_chunks(&user.audio));
 
         if chunks.is_empty() {
             info!("No audio chunks for user {} (all silence?)", user.display_name);
             continue;
         }
 
+        // Create user directory, the combined layout keeps everything in memory until the end
+        let user_dir = paths.transcribe_user_dir(user.user_id, &user.display_name);
+        if per_user {
+            fs::create_dir_all(&user_dir)?;
+        }
+
         ctx.channel_id()
             .say(&ctx.http(), format!(
                 "🔄 Transcribing **{}**: {} chunks ({:.1}s)...",
@@ -197,15 +1067,41 @@ pub async fn transcribe_session(
             ))
             .await?;
 
-        // Write WAV chunks
-        for chunk in &chunks {
-            let chunk_filename = format!("chunk_{:04}.wav", chunk.index);
+        // Write the chunks
+        for chunk in chunks.iter().filter(|_| per_user) {
+            let chunk_filename = format!("chunk_{:04}.{}", chunk.index, chunk_format.extension());
             let chunk_path = user_dir.join(&chunk_filename);
-            fs::write(&chunk_path, chunk.as_wav_bytes())?;
+            fs::write(&chunk_path, chunk.encode(chunk_format, clip_mode)?)?;
+        }
+
+        // Whisper only needs 16kHz, the original rate is reloaded for other tools
+        if keep_original_rate {
+            match load_user_audio_original_rate(paths.root(), user.user_id, &user.audio.ssrcs) {
+                Ok((audio_48k, first_tick, _)) => {
+                    // The 16kHz audio may start later when clipped to a time range
+                    let offset = ((user.audio.first_tick.saturating_sub(first_tick)) as usize * SAMPLES_PER_TICK)
+                        .min(audio_48k.len());
+                    for chunk in chunks.iter() {
+                        let chunk_path = user_dir.join(format!("chunk_{:04}_48k.wav", chunk.index));
+                        chunk.save_original_rate(&chunk_path, &audio_48k[offset..])?;
+                    }
+                }
+                Err(e) => warn!("Failed to load 48kHz audio for {}: {}", user.display_name, e),
+            }
         }
 
         // Transcribe all chunks
-        let chunk_transcriptions = match transcriber.transcribe_chunks(&chunks) {
+        let transcribed = transcribe_with_heartbeat(
+            ctx,
+            &status,
+            &status_text,
+            started,
+            &transcriber,
+            &chunks,
+            &user.display_name,
+        )
+        .await?;
+        let chunk_transcriptions = match transcribed {
             Ok(t) => t,
             Err(e) => {
                 tracing::warn!("Failed to transcribe {}: {}", user.display_name, e);
@@ -213,31 +1109,33 @@ pub async fn transcribe_session(
                 continue;
             }
         };
+        chunks_planned += chunks.len();
+        chunks_done += chunk_transcriptions.len();
+
+        if job.is_cancelled() && chunk_transcriptions.is_empty() {
+            break;
+        }
 
         // Create user transcription with absolute timestamps
-        let user_transcription = UserTranscription::from_chunks(
+        let mut user_transcription = UserTranscription::from_chunks(
             user.user_id,
             user.display_name.clone(),
-            &whisper_model.to_string(),
+            &model_name,
             user.audio.duration_secs,
             chunk_transcriptions,
         );
 
-        // Write transcription JSON
-        let transcription_path = user_dir.join("transcription.json");
-        fs::write(
-            &transcription_path,
-            serde_json::to_string_pretty(&user_transcription)?,
-        )?;
+        if language_strategy == LanguageStrategy::DetectThenLock {
+            user_transcription.lock_language_from_chunks();
+        }
 
-        // Write plain text transcript
-        let transcript_path = user_dir.join("transcript.txt");
-        fs::write(&transcript_path, &user_transcription.full_transcript)?;
+        if let Some(max_secs) = max_segment_secs {
+            user_transcription.split_long_segments(max_secs);
+        }
 
-        // Write SRT subtitles
-        let srt_path = user_dir.join("transcript.srt");
-        let srt_content = generate_srt(&user_transcription);
-        fs::write(&srt_path, &srt_content)?;
+        if let Some(clock) = &clock {
+            user_transcription.correct_clock(clock, user.audio.first_tick);
+        }
 
         // Write timing metadata
         let timing_data = serde_json::json!({
@@ -246,29 +1144,47 @@ pub async fn transcribe_session(
             "total_duration_secs": user.audio.duration_secs,
             "first_tick": user.audio.first_tick,
             "last_tick": user.audio.last_tick,
+            "wall_clock_corrected": clock.is_some(),
+            "secs_per_tick": clock.as_ref().map(|c| c.secs_per_tick()),
+            "session_start_unix_ms": clock.as_ref().and_then(|c| c.start_unix_ms()),
             "ssrcs": user.audio.ssrcs,
             "min_silence_secs": min_silence,
-            "model": whisper_model.to_string(),
+            "max_chunk_secs": max_chunk,
+            "chunk_overlap_secs": chunk_overlap,
+            "overlap_audio_secs": chunks.iter().map(|c| c.overlap_secs).sum::<f32>(),
+            "pad_start_secs": pad_start,
+            "pad_end_secs": pad_end,
+            "model": model_name,
             "chunks": chunks.iter().map(|c| {
                 serde_json::json!({
                     "index": c.index,
-                    "file": format!("chunk_{:04}.wav", c.index),
+                    "file": format!("chunk_{:04}.{}", c.index, chunk_format.extension()),
+                    "original_rate_file": keep_original_rate.then(|| format!("chunk_{:04}_48k.wav", c.index)),
                     "start_time_secs": c.start_time_secs,
                     "end_time_secs": c.end_time_secs,
                     "duration_secs": c.duration_secs,
+                    "overlap_secs": c.overlap_secs,
                 })
             }).collect::<Vec<_>>()
         });
 
-        let timing_path = user_dir.join("timing.json");
-        fs::write(&timing_path, serde_json::to_string_pretty(&timing_data)?)?;
+        if per_user {
+            write_user_transcript(&user_dir, &user_transcription)?;
+            fs::write(user_dir.join("timing.json"), serde_json::to_string_pretty(&timing_data)?)?;
+        }
 
         let word_count = user_transcription.full_transcript.split_whitespace().count();
+        let locked_desc = user_transcription
+            .locked_language
+            .as_deref()
+            .map(|lang| format!(" (language locked: `{}`)", lang))
+            .unwrap_or_default();
         user_info.push(format!(
-            "• **{}**: {} chunks, ~{} words",
+            "• **{}**: {} chunks, ~{} words{}",
             user.display_name,
             user_transcription.chunk_transcriptions.len(),
-            word_count
+            word_count,
+            locked_desc
         ));
 
         all_transcriptions.push(user_transcription);
@@ -278,8 +1194,16 @@ pub async fn transcribe_session(
     let manifest = serde_json::json!({
         "session": session_dir,
         "guild_id": guild_id,
-        "model": whisper_model.to_string(),
+        "model": model_name,
         "min_silence_secs": min_silence,
+        "mixed": mixed_audio.is_some(),
+        "layout": layout.to_string(),
+        "cancelled": job.is_cancelled(),
+        "channel_bitrate": metadata.as_ref().and_then(|m| m.channel_bitrate),
+        "time_range": time_range.map(|(from_secs, to_secs)| serde_json::json!({
+            "from_secs": from_secs,
+            "to_secs": to_secs,
+        })),
         "users": all_transcriptions.iter().map(|u| {
             serde_json::json!({
                 "user_id": u.user_id,
@@ -287,70 +1211,219 @@ pub async fn transcribe_session(
                 "chunk_count": u.chunk_transcriptions.len(),
                 "total_duration_secs": u.total_duration_secs,
                 "word_count": u.full_transcript.split_whitespace().count(),
-                "directory": format!("{}_{}", u.user_id, u.display_name
-                    .chars()
-                    .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
-                    .collect::<String>()),
+                "locked_language": u.locked_language,
+                "packet_loss_percent": packet_loss.get(&u.user_id),
+                "directory": per_user.then(|| transcribe_user_dir_name(u.user_id, &u.display_name)),
             })
         }).collect::<Vec<_>>()
     });
 
-    let manifest_path = output_dir.join("manifest.json");
     fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
 
+    // Chapters for navigating the merged audio by speaker turn
+    let tracks: Vec<(&UserTranscription, u64)> = all_transcriptions
+        .iter()
+        .filter_map(|t| first_ticks.get(&t.user_id).map(|tick| (t, *tick)))
+        .collect();
+    let turns = speaker_turns(&tracks);
+    if !turns.is_empty() {
+        fs::create_dir_all(paths.output_dir())?;
+        fs::write(paths.chapters(), matroska_chapters_xml(&turns))?;
+        info!("Wrote {} chapters to {:?}", turns.len(), paths.chapters());
+    }
+
+    if !tracks.is_empty() {
+        fs::write(paths.session_vtt(), session_vtt(&tracks))?;
+    }
+
+    // Readers prefer `transcriptions.json`, so one left from an earlier combined run has to go
+    if per_user {
+        match fs::remove_file(paths.combined_transcriptions()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    } else {
+        write_combined_transcriptions(&paths, &tracks)?;
+        let config = ConversationConfig::default();
+        fs::write(paths.session_transcript("md"), transcript_markdown(&session_id, &tracks, false, &config))?;
+        fs::write(paths.session_transcript("txt"), transcript_text(&tracks, &config))?;
+    }
+
+    let stats = speaking_stats(&tracks);
+    if !stats.is_empty() {
+        fs::create_dir_all(paths.output_dir())?;
+        fs::write(paths.speaking_stats(), serde_json::to_string_pretty(&stats)?)?;
+    }
+
+    // A failed index only affects searching across sessions, the transcript is already saved
+    if let Err(e) = index_session(&ctx.data().db, &guild_id, &paths).await {
+        warn!("Failed to index session {} for search: {}", session_id, e);
+    }
+
     // Build final response
+    user_info.extend(silent_notes);
     let total_words: usize = all_transcriptions
         .iter()
         .map(|t| t.full_transcript.split_whitespace().count())
         .sum();
 
+    let cancelled = job.is_cancelled();
+    if !cancelled {
+        crate::metrics::TRANSCRIPTION_DURATION.observe(started.elapsed());
+    }
+    let header = if cancelled {
+        format!(
+            "⏹️ **Transcription cancelled** after {} of {} chunk(s), partial transcript saved.",
+            chunks_done, chunks_planned
+        )
+    } else {
+        "✅ **Transcription complete!**".to_string()
+    };
+
+    let contents = match layout {
+        TranscriptLayout::PerUser => {
+            "_Each user folder contains:_\n\
+            • `transcript.txt` - Plain text\n\
+            • `transcript.srt`, `transcript.vtt` - Subtitles with timestamps\n\
+            • `transcription.json` - Full data with timing\n\
+            _All speakers together, color-coded: `transcript.vtt`_"
+        }
+        TranscriptLayout::Combined => {
+            "_The folder contains:_\n\
+            • `transcript.md`, `transcript.txt` - The conversation in speaking order\n\
+            • `transcript.vtt` - Subtitles with speakers color-coded\n\
+            • `transcriptions.json` - Full data with timing for every speaker"
+        }
+    };
     let response = format!(
-        "✅ **Transcription complete!**\n\n\
+        "{}\n\n\
         {}\n\n\
+        {}\
+        {}\
         **Model:** `{}`\n\
         **Total:** ~{} words from {} user(s)\n\
-        **Output:** `{}`\n\n\
-        _Each user folder contains:_\n\
-        • `transcript.txt` - Plain text\n\
-        • `transcript.srt` - Subtitles with timestamps\n\
-        • `transcription.json` - Full data with timing",
+        **Output:** `{}` ({} layout)\n\n\
+        {}\n\
+        _Speaker-turn chapters for the merged audio are in `output/chapters.xml`, speaking time in `output/speaking_stats.json`_",
+        header,
         user_info.join("\n"),
+        format_leaderboard(&stats),
+        format_recording_quality(metadata.as_ref(), &packet_loss, &all_transcriptions),
         whisper_model,
         total_words,
         all_transcriptions.len(),
-        output_dir.display()
+        output_dir.display(),
+        layout,
+        contents
     );
 
-    ctx.say(response).await?;
-    Ok(())
+    status_guard
+        .finish(if cancelled {
+            TranscriptionStatus::Cancelled
+        } else {
+            TranscriptionStatus::Completed
+        })
+        .await;
+
+    say_long(
+        ctx,
+        response,
+        &format!(
+            "{}\n**Total:** ~{} words from {} user(s), summary attached.",
+            header,
+            total_words,
+            all_transcriptions.len()
+        ),
+        "transcription_summary.txt",
+    )
+    .await
 }
 
-/// Generate SRT subtitle format from transcription
-fn generate_srt(transcription: &UserTranscription) -> String {
-    let mut srt = String::new();
-    
-    for (i, segment) in transcription.all_segments.iter().enumerate() {
-        let start = format_srt_time(segment.start_secs);
-        let end = format_srt_time(segment.end_secs);
-        
-        srt.push_str(&format!(
-            "{}\n{} --> {}\n{}\n\n",
-            i + 1,
-            start,
-            end,
-            segment.text
-        ));
+/// Speaking-time ranking for the completion message, empty when nobody spoke
+fn format_leaderboard(stats: &[SpeakingStats]) -> String {
+    let lines: Vec<String> = stats
+        .iter()
+        .filter(|s| s.talk_secs > 0.0)
+        .enumerate()
+        .map(|(i, s)| {
+            format!(
+                "{}. **{}**: {} ({:.0}%, {} turn(s))",
+                i + 1,
+                s.display_name,
+                format_duration(chrono::Duration::seconds(s.talk_secs.round() as i64)),
+                s.session_percent,
+                s.turns
+            )
+        })
+        .collect();
+
+    if lines.is_empty() {
+        String::new()
+    } else {
+        format!("**Speaking time:**\n{}\n\n", lines.join("\n"))
     }
-    
-    srt
 }
 
-/// Format seconds as SRT timestamp (HH:MM:SS,mmm)
-fn format_srt_time(secs: f32) -> String {
-    let hours = (secs / 3600.0) as u32;
-    let minutes = ((secs % 3600.0) / 60.0) as u32;
-    let seconds = (secs % 60.0) as u32;
-    let millis = ((secs % 1.0) * 1000.0) as u32;
-    
-    format!("{:02}:{:02}:{:02},{:03}", hours, minutes, seconds, millis)
+/// Percentage of a user's RTP packets lost over all their SSRCs
+fn user_packet_loss(metadata: &SessionMetadata, ssrcs: &[u32]) -> f32 {
+    let mut total = PacketStats::default();
+    for packets in ssrcs.iter().filter_map(|ssrc| metadata.packet_stats.get(ssrc)) {
+        total.received += packets.received;
+        total.lost += packets.lost;
+    }
+    total.loss_percent()
+}
+
+/// Channel bitrate and per-user packet loss for the completion message, empty when unknown
+fn format_recording_quality(
+    metadata: Option<&SessionMetadata>,
+    packet_loss: &HashMap<u64, f32>,
+    transcriptions: &[UserTranscription],
+) -> String {
+    let mut lines = Vec::new();
+    if let Some(channel) = metadata.and_then(|m| m.channel_summary()) {
+        lines.push(format!("📶 Channel: {}", channel));
+    }
+    let lossy: Vec<String> = transcriptions
+        .iter()
+        .filter_map(|t| {
+            let loss = *packet_loss.get(&t.user_id)?;
+            (loss >= REPORTED_PACKET_LOSS_PERCENT).then(|| format!("{} {:.1}%", t.display_name, loss))
+        })
+        .collect();
+    if !lossy.is_empty() {
+        lines.push(format!("📉 Packet loss: {}", lossy.join(", ")));
+    }
+
+    if lines.is_empty() {
+        String::new()
+    } else {
+        format!("**Recording:**\n{}\n\n", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_options_parse() {
+        assert_eq!("".parse::<TranscribeOptions>(), Ok(TranscribeOptions::default()));
+
+        let options: TranscribeOptions = "model=medium dry-run=true best_of=3 vad=adaptive vad_margin_db=6.5 since=+15:00"
+            .parse()
+            .unwrap();
+        assert_eq!(options.model.as_deref(), Some("medium"));
+        assert_eq!(options.dry_run, Some(true));
+        assert_eq!(options.best_of, Some(3));
+        assert_eq!(options.vad.as_deref(), Some("adaptive"));
+        assert_eq!(options.vad_margin_db, Some(6.5));
+        assert_eq!(options.since.as_deref(), Some("+15:00"));
+        assert_eq!(options.until, None);
+
+        assert!("medium".parse::<TranscribeOptions>().is_err());
+        assert!("modle=medium".parse::<TranscribeOptions>().is_err());
+        assert!("dry_run=maybe".parse::<TranscribeOptions>().is_err());
+        assert!("temperature=hot".parse::<TranscribeOptions>().is_err());
+    }
 }
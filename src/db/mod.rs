@@ -1,27 +1,197 @@
-use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
+use sqlx::{
+    SqlitePool,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+};
 use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
 
 pub type DbPool = SqlitePool;
 
+/// Connection pool settings for the SQLite database
+#[derive(Debug, Clone)]
+pub struct DbConfig {
+    pub max_connections: u32,
+    /// How long a connection waits on a locked database before failing
+    pub busy_timeout: Duration,
+}
+
+impl Default for DbConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            busy_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl DbConfig {
+    /// Read settings from `DATABASE_MAX_CONNECTIONS` and `DATABASE_BUSY_TIMEOUT_SECS`,
+    /// falling back to the defaults for unset or invalid values (a pool needs at
+    /// least one connection)
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        let max_connections = std::env::var("DATABASE_MAX_CONNECTIONS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&n: &u32| n >= 1)
+            .unwrap_or(default.max_connections);
+
+        let busy_timeout = std::env::var("DATABASE_BUSY_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(default.busy_timeout);
+
+        Self {
+            max_connections,
+            busy_timeout,
+        }
+    }
+}
+
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct UserSetting {
     pub user_id: String,
     pub guild_id: String,
     pub transcribe_name: Option<String>,
+    /// Discord name last fetched for a user without a transcription name
+    pub cached_display_name: Option<String>,
     pub created_at: String,
     pub updated_at: String,
 }
 
-pub async fn init_db(database_url: &str) -> Result<DbPool, sqlx::Error> {
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SessionRecord {
+    pub session_id: String,
+    pub path: String,
+    pub duration_secs: Option<i64>,
+    pub transcription_status: String,
+    pub transcribed_at: Option<String>,
+}
+
+impl SessionRecord {
+    /// The transcription status, `None` for values this version doesn't know
+    pub fn status(&self) -> TranscriptionStatus {
+        self.transcription_status.parse().unwrap_or(TranscriptionStatus::None)
+    }
+}
+
+/// A transcribed segment to add to the full-text index
+#[derive(Debug, Clone)]
+pub struct IndexedSegment {
+    pub user_id: u64,
+    pub speaker: String,
+    /// Start on the session's shared timeline
+    pub start_secs: f32,
+    pub text: String,
+}
+
+/// A full-text search hit, best matches first
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SegmentMatch {
+    pub session_id: String,
+    pub speaker: String,
+    pub start_secs: f64,
+    /// Segment text with the matched terms in bold
+    pub snippet: String,
+}
+
+/// Transcription state of a recorded session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptionStatus {
+    None,
+    InProgress,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl TranscriptionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TranscriptionStatus::None => "none",
+            TranscriptionStatus::InProgress => "in_progress",
+            TranscriptionStatus::Completed => "completed",
+            TranscriptionStatus::Failed => "failed",
+            TranscriptionStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl FromStr for TranscriptionStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(TranscriptionStatus::None),
+            "in_progress" => Ok(TranscriptionStatus::InProgress),
+            "completed" => Ok(TranscriptionStatus::Completed),
+            "failed" => Ok(TranscriptionStatus::Failed),
+            "cancelled" => Ok(TranscriptionStatus::Cancelled),
+            _ => Err(format!("Unknown transcription status: {}", s)),
+        }
+    }
+}
+
+/// What `set_transcribe_name` does when a user already has a different name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NamePolicy {
+    /// Replace the old name
+    #[default]
+    Overwrite,
+    /// Replace the old name and keep it in `transcribe_name_history`
+    History,
+    /// Keep the first name, so a speaker reads the same across all transcripts
+    Locked,
+}
+
+impl NamePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NamePolicy::Overwrite => "overwrite",
+            NamePolicy::History => "history",
+            NamePolicy::Locked => "locked",
+        }
+    }
+}
+
+impl FromStr for NamePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "overwrite" => Ok(NamePolicy::Overwrite),
+            "history" => Ok(NamePolicy::History),
+            "locked" => Ok(NamePolicy::Locked),
+            _ => Err(format!("Unknown name policy: {}. Use overwrite, history or locked", s)),
+        }
+    }
+}
+
+/// Outcome of `set_transcribe_name`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameChange {
+    Set,
+    /// The guild locks names and the user already has `current`
+    Rejected { current: String },
+}
+
+pub async fn init_db(database_url: &str, config: &DbConfig) -> Result<DbPool, sqlx::Error> {
     if let Some(path) = database_url.strip_prefix("sqlite:") {
         if let Some(parent) = Path::new(path).parent() {
             std::fs::create_dir_all(parent).ok();
         }
     }
 
+    let connect_options = SqliteConnectOptions::from_str(database_url)?
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(config.busy_timeout);
+
     let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect(database_url)
+        .max_connections(config.max_connections)
+        .connect_with(connect_options)
         .await?;
 
     sqlx::migrate!("./migrations").run(&pool).await?;
@@ -50,7 +220,63 @@ pub async fn set_transcribe_name(
     user_id: &str,
     guild_id: &str,
     transcribe_name: &str,
-) -> Result<(), sqlx::Error> {
+) -> Result<NameChange, sqlx::Error> {
+    change_transcribe_name(pool, user_id, guild_id, transcribe_name, false).await
+}
+
+/// A moderator's correction of someone's name, which goes through even when names are locked
+///
+/// Corrections of a locked name are kept in the name history.
+pub async fn moderate_transcribe_name(
+    pool: &DbPool,
+    user_id: &str,
+    guild_id: &str,
+    transcribe_name: &str,
+) -> Result<NameChange, sqlx::Error> {
+    change_transcribe_name(pool, user_id, guild_id, transcribe_name, true).await
+}
+
+async fn change_transcribe_name(
+    pool: &DbPool,
+    user_id: &str,
+    guild_id: &str,
+    transcribe_name: &str,
+    moderated: bool,
+) -> Result<NameChange, sqlx::Error> {
+    // Take the write lock up front, the old name is read before it's replaced
+    let mut tx = pool.begin_with("BEGIN IMMEDIATE").await?;
+
+    let policy: Option<String> = sqlx::query_scalar("SELECT name_policy FROM guild_settings WHERE guild_id = ?")
+        .bind(guild_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    let policy: NamePolicy = policy.and_then(|p| p.parse().ok()).unwrap_or_default();
+
+    let current: Option<Option<String>> =
+        sqlx::query_scalar("SELECT transcribe_name FROM user_settings WHERE user_id = ? AND guild_id = ?")
+            .bind(user_id)
+            .bind(guild_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    if let Some(current) = current.flatten().filter(|current| current != transcribe_name) {
+        match policy {
+            NamePolicy::Overwrite => {}
+            NamePolicy::Locked if !moderated => return Ok(NameChange::Rejected { current }),
+            NamePolicy::History | NamePolicy::Locked => {
+                sqlx::query(
+                    "INSERT INTO transcribe_name_history (user_id, guild_id, old_name, new_name) VALUES (?, ?, ?, ?)",
+                )
+                .bind(user_id)
+                .bind(guild_id)
+                .bind(&current)
+                .bind(transcribe_name)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+    }
+
     sqlx::query(
         r#"
         INSERT INTO user_settings (user_id, guild_id, transcribe_name, updated_at)
@@ -62,8 +288,434 @@ pub async fn set_transcribe_name(
     .bind(user_id)
     .bind(guild_id)
     .bind(transcribe_name)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(NameChange::Set)
+}
+
+/// Remember the Discord name fetched for a user, leaving their transcription name alone
+pub async fn cache_display_name(
+    pool: &DbPool,
+    user_id: &str,
+    guild_id: &str,
+    display_name: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO user_settings (user_id, guild_id, cached_display_name, updated_at)
+        VALUES (?, ?, ?, datetime('now'))
+        ON CONFLICT(user_id, guild_id)
+        DO UPDATE SET cached_display_name = excluded.cached_display_name, updated_at = datetime('now')
+        "#,
+    )
+    .bind(user_id)
+    .bind(guild_id)
+    .bind(display_name)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// A user's previous transcription names in a guild, oldest first
+pub async fn transcribe_name_history(
+    pool: &DbPool,
+    user_id: &str,
+    guild_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT old_name FROM transcribe_name_history WHERE user_id = ? AND guild_id = ? ORDER BY id",
+    )
+    .bind(user_id)
+    .bind(guild_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn set_name_policy(pool: &DbPool, guild_id: &str, policy: NamePolicy) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO guild_settings (guild_id, name_policy, updated_at)
+        VALUES (?, ?, datetime('now'))
+        ON CONFLICT(guild_id)
+        DO UPDATE SET name_policy = excluded.name_policy, updated_at = datetime('now')
+        "#,
+    )
+    .bind(guild_id)
+    .bind(policy.as_str())
     .execute(pool)
     .await?;
 
     Ok(())
 }
+
+/// Command prefix configured for a guild, if any
+pub async fn get_guild_prefix(pool: &DbPool, guild_id: &str) -> Result<Option<String>, sqlx::Error> {
+    let prefix: Option<Option<String>> =
+        sqlx::query_scalar("SELECT prefix FROM guild_settings WHERE guild_id = ?")
+            .bind(guild_id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(prefix.flatten())
+}
+
+pub async fn set_guild_prefix(pool: &DbPool, guild_id: &str, prefix: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO guild_settings (guild_id, prefix, updated_at)
+        VALUES (?, ?, datetime('now'))
+        ON CONFLICT(guild_id)
+        DO UPDATE SET prefix = excluded.prefix, updated_at = datetime('now')
+        "#,
+    )
+    .bind(guild_id)
+    .bind(prefix)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn create_session(
+    pool: &DbPool,
+    guild_id: &str,
+    session_id: &str,
+    path: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO sessions (guild_id, session_id, path)
+        VALUES (?, ?, ?)
+        ON CONFLICT(guild_id, session_id) DO NOTHING
+        "#,
+    )
+    .bind(guild_id)
+    .bind(session_id)
+    .bind(path)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn finish_session(
+    pool: &DbPool,
+    guild_id: &str,
+    session_id: &str,
+    duration_secs: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE sessions SET duration_secs = ?, updated_at = datetime('now')
+        WHERE guild_id = ? AND session_id = ?
+        "#,
+    )
+    .bind(duration_secs)
+    .bind(guild_id)
+    .bind(session_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record a session's transcription status, creating the row for sessions
+/// recorded before sessions were tracked
+pub async fn set_transcription_status(
+    pool: &DbPool,
+    guild_id: &str,
+    session_id: &str,
+    path: &str,
+    status: TranscriptionStatus,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO sessions (guild_id, session_id, path, transcription_status, transcribed_at)
+        VALUES (?, ?, ?, ?, CASE WHEN ? = 'completed' THEN datetime('now') END)
+        ON CONFLICT(guild_id, session_id)
+        DO UPDATE SET
+            transcription_status = excluded.transcription_status,
+            transcribed_at = COALESCE(excluded.transcribed_at, sessions.transcribed_at),
+            updated_at = datetime('now')
+        "#,
+    )
+    .bind(guild_id)
+    .bind(session_id)
+    .bind(path)
+    .bind(status.as_str())
+    .bind(status.as_str())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn list_sessions(
+    pool: &DbPool,
+    guild_id: &str,
+    limit: i64,
+) -> Result<Vec<SessionRecord>, sqlx::Error> {
+    let sessions = sqlx::query_as::<_, SessionRecord>(
+        r#"
+        SELECT session_id, path, duration_secs, transcription_status, transcribed_at
+        FROM sessions WHERE guild_id = ? ORDER BY created_at DESC, session_id DESC LIMIT ?
+        "#,
+    )
+    .bind(guild_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sessions)
+}
+
+/// Replace a session's segments in the full-text index
+pub async fn index_session_segments(
+    pool: &DbPool,
+    guild_id: &str,
+    session_id: &str,
+    segments: &[IndexedSegment],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM transcript_segments WHERE guild_id = ? AND session_id = ?")
+        .bind(guild_id)
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for segment in segments {
+        sqlx::query(
+            r#"
+            INSERT INTO transcript_segments (text, guild_id, session_id, user_id, speaker, start_secs)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&segment.text)
+        .bind(guild_id)
+        .bind(session_id)
+        .bind(segment.user_id.to_string())
+        .bind(&segment.speaker)
+        .bind(segment.start_secs as f64)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await
+}
+
+/// Quote every word so user input is matched literally instead of as FTS5 query syntax
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Search all indexed sessions of a guild for segments containing every word of `query`
+pub async fn search_segments(
+    pool: &DbPool,
+    guild_id: &str,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<SegmentMatch>, sqlx::Error> {
+    let query = fts_query(query);
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let matches = sqlx::query_as::<_, SegmentMatch>(
+        r#"
+        SELECT session_id, speaker, start_secs,
+            snippet(transcript_segments, 0, '**', '**', '…', 16) AS snippet
+        FROM transcript_segments
+        WHERE transcript_segments MATCH ? AND guild_id = ?
+        ORDER BY rank
+        LIMIT ?
+        "#,
+    )
+    .bind(query)
+    .bind(guild_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn test_pool(dir: &tempfile::TempDir) -> DbPool {
+        let url = format!("sqlite:{}?mode=rwc", dir.path().join("test.db").display());
+        init_db(&url, &DbConfig::default()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_set_transcribe_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_pool(&dir).await;
+
+        let tasks: Vec<_> = (0..20)
+            .map(|i| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    set_transcribe_name(&pool, &i.to_string(), "1", &format!("User {}", i)).await
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        let setting = get_user_setting(&pool, "7", "1").await.unwrap().unwrap();
+        assert_eq!(setting.transcribe_name.as_deref(), Some("User 7"));
+    }
+
+    #[tokio::test]
+    async fn test_name_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_pool(&dir).await;
+
+        // Overwrite by default, without history
+        set_transcribe_name(&pool, "1", "1", "Al").await.unwrap();
+        assert_eq!(set_transcribe_name(&pool, "1", "1", "Alice").await.unwrap(), NameChange::Set);
+        assert!(transcribe_name_history(&pool, "1", "1").await.unwrap().is_empty());
+
+        set_name_policy(&pool, "1", NamePolicy::History).await.unwrap();
+        set_transcribe_name(&pool, "1", "1", "Alice").await.unwrap();
+        set_transcribe_name(&pool, "1", "1", "Ally").await.unwrap();
+        assert_eq!(transcribe_name_history(&pool, "1", "1").await.unwrap(), vec!["Alice"]);
+
+        set_name_policy(&pool, "1", NamePolicy::Locked).await.unwrap();
+        assert_eq!(
+            set_transcribe_name(&pool, "1", "1", "Bob").await.unwrap(),
+            NameChange::Rejected { current: "Ally".to_string() }
+        );
+        // Setting the same name again, or a first name, is still fine
+        assert_eq!(set_transcribe_name(&pool, "1", "1", "Ally").await.unwrap(), NameChange::Set);
+        assert_eq!(set_transcribe_name(&pool, "2", "1", "Bob").await.unwrap(), NameChange::Set);
+
+        let setting = get_user_setting(&pool, "1", "1").await.unwrap().unwrap();
+        assert_eq!(setting.transcribe_name.as_deref(), Some("Ally"));
+
+        // A moderator can still fix a locked name, and the old one is kept
+        assert_eq!(moderate_transcribe_name(&pool, "1", "1", "Alicia").await.unwrap(), NameChange::Set);
+        let setting = get_user_setting(&pool, "1", "1").await.unwrap().unwrap();
+        assert_eq!(setting.transcribe_name.as_deref(), Some("Alicia"));
+        assert_eq!(transcribe_name_history(&pool, "1", "1").await.unwrap(), vec!["Alice", "Ally"]);
+        assert_eq!(get_guild_prefix(&pool, "1").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_cached_display_name_ignores_name_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_pool(&dir).await;
+        set_name_policy(&pool, "1", NamePolicy::Locked).await.unwrap();
+
+        cache_display_name(&pool, "1", "1", "alice_discord").await.unwrap();
+        let setting = get_user_setting(&pool, "1", "1").await.unwrap().unwrap();
+        assert_eq!(setting.transcribe_name, None);
+        assert_eq!(setting.cached_display_name.as_deref(), Some("alice_discord"));
+
+        // The user can still pick their own name, and a later fetch doesn't replace it
+        assert_eq!(set_transcribe_name(&pool, "1", "1", "Alice").await.unwrap(), NameChange::Set);
+        cache_display_name(&pool, "1", "1", "alice_renamed").await.unwrap();
+        let setting = get_user_setting(&pool, "1", "1").await.unwrap().unwrap();
+        assert_eq!(setting.transcribe_name.as_deref(), Some("Alice"));
+        assert!(transcribe_name_history(&pool, "1", "1").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_pool(&dir).await;
+
+        create_session(&pool, "1", "2026_01_01_10_00_00", "recordings/1/2026_01_01_10_00_00")
+            .await
+            .unwrap();
+        finish_session(&pool, "1", "2026_01_01_10_00_00", 125).await.unwrap();
+        set_transcription_status(
+            &pool,
+            "1",
+            "2026_01_01_10_00_00",
+            "recordings/1/2026_01_01_10_00_00",
+            TranscriptionStatus::Completed,
+        )
+        .await
+        .unwrap();
+
+        let sessions = list_sessions(&pool, "1", 10).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].duration_secs, Some(125));
+        assert_eq!(sessions[0].status(), TranscriptionStatus::Completed);
+        assert!(sessions[0].transcribed_at.is_some());
+        assert!(list_sessions(&pool, "2", 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_guild_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_pool(&dir).await;
+
+        assert_eq!(get_guild_prefix(&pool, "1").await.unwrap(), None);
+
+        set_guild_prefix(&pool, "1", "!").await.unwrap();
+        set_guild_prefix(&pool, "1", "w!").await.unwrap();
+
+        assert_eq!(get_guild_prefix(&pool, "1").await.unwrap().as_deref(), Some("w!"));
+        assert_eq!(get_guild_prefix(&pool, "2").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_search_segments_across_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = test_pool(&dir).await;
+
+        let segment = |speaker: &str, start_secs, text: &str| IndexedSegment {
+            user_id: 1,
+            speaker: speaker.to_string(),
+            start_secs,
+            text: text.to_string(),
+        };
+        index_session_segments(&pool, "1", "a", &[segment("Alice", 5.0, "Let's ship the release on Friday")])
+            .await
+            .unwrap();
+        index_session_segments(
+            &pool,
+            "1",
+            "b",
+            &[
+                segment("Bob", 12.5, "The release is blocked"),
+                segment("Bob", 20.0, "Lunch?"),
+            ],
+        )
+        .await
+        .unwrap();
+        index_session_segments(&pool, "2", "c", &[segment("Eve", 1.0, "release notes")])
+            .await
+            .unwrap();
+
+        let matches = search_segments(&pool, "1", "release", 10).await.unwrap();
+        assert_eq!(matches.len(), 2);
+        assert!(matches.iter().all(|m| m.snippet.contains("**release**")));
+
+        let matches = search_segments(&pool, "1", "release blocked", 10).await.unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].session_id.as_str(), matches[0].speaker.as_str()), ("b", "Bob"));
+        assert_eq!(matches[0].start_secs, 12.5);
+
+        // Query syntax in user input is matched literally
+        assert!(search_segments(&pool, "1", "\"release OR", 10).await.unwrap().is_empty());
+
+        // Re-indexing a session replaces its segments
+        index_session_segments(&pool, "1", "b", &[]).await.unwrap();
+        assert_eq!(search_segments(&pool, "1", "release", 10).await.unwrap().len(), 1);
+    }
+}
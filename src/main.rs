@@ -10,39 +10,54 @@ use songbird::{Config, SerenityInit, driver::DecodeMode};
 use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
-use tracing::info;
+use tracing::{error, info, warn};
 use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
 mod command;
 mod db;
+mod metrics;
+mod paths;
 mod transcribe;
 mod voice;
 
 use command::*;
 use db::DbPool;
 use voice::SharedRecordingState;
+use voice::audio::UserAudioStats;
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Context<'a> = poise::Context<'a, Data, Error>;
 
+/// A mistake in how a command was used, e.g. an unknown option value
+///
+/// Shown to the user as is, any other error a command returns is only logged.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct UserError(pub String);
+
 pub struct RecordingSession {
     pub guild_id: u64,
+    /// Voice channel being recorded
+    pub channel_id: u64,
+    pub session_id: String,
     pub session_dir: PathBuf,
     pub state: SharedRecordingState,
     pub started_at: chrono::DateTime<chrono::Utc>,
-    pub storage_task: Option<JoinHandle<()>>,
+    pub storage_task: Option<JoinHandle<HashMap<u64, UserAudioStats>>>,
 }
 
 impl RecordingSession {
-    pub fn new(guild_id: u64) -> Self {
+    pub fn new(guild_id: u64, channel_id: u64) -> Self {
         let timestamp = chrono::Utc::now();
         let timestamp_str = timestamp.format("%Y_%m_%d_%H_%M_%S").to_string();
-        let session_dir = PathBuf::from("recordings")
-            .join(guild_id.to_string())
-            .join(&timestamp_str);
+        let session_dir = paths::SessionPaths::for_session(guild_id, &timestamp_str)
+            .root()
+            .to_path_buf();
 
         Self {
             guild_id,
+            channel_id,
+            session_id: timestamp_str,
             session_dir,
             state: voice::create_recording_session(),
             started_at: timestamp,
@@ -50,15 +65,303 @@ impl RecordingSession {
         }
     }
 
+    /// A session continuing in an existing directory, see `StorageWriter::open_append`
+    pub fn resume(guild_id: u64, channel_id: u64, metadata: &paths::SessionMetadata) -> Self {
+        Self {
+            guild_id,
+            channel_id,
+            session_id: metadata.session_id.clone(),
+            session_dir: paths::SessionPaths::for_session(guild_id, &metadata.session_id)
+                .root()
+                .to_path_buf(),
+            state: voice::create_recording_session(),
+            started_at: metadata.started_at,
+            storage_task: None,
+        }
+    }
+
     pub fn duration(&self) -> chrono::Duration {
         chrono::Utc::now() - self.started_at
     }
+
+    /// Stop capturing and wait for the storage writer to flush everything to disk
+    ///
+    /// Returns per-user level statistics for the recording.
+    pub async fn finalize(&mut self) -> HashMap<u64, UserAudioStats> {
+        let (storage_handle, packet_stats, gaps, consent) = {
+            let mut state = self.state.lock().await;
+            (
+                state.stop(),
+                std::mem::take(&mut state.packet_stats),
+                std::mem::take(&mut state.gaps),
+                state.consented.take(),
+            )
+        };
+
+        let metadata_path = paths::SessionPaths::new(&self.session_dir).metadata();
+        if let Some(mut metadata) = paths::SessionMetadata::load(&metadata_path) {
+            // Extended, so a resumed session keeps what was recorded before the restart
+            metadata.packet_stats.extend(packet_stats);
+            metadata.gaps.extend(gaps);
+            metadata.record_consent(consent);
+            if let Err(e) = metadata.save(&metadata_path) {
+                warn!("Failed to save packet stats: {:?}", e);
+            }
+        }
+
+        if let Some(handle) = storage_handle {
+            handle.shutdown();
+        }
+
+        let Some(task) = self.storage_task.take() else {
+            return HashMap::new();
+        };
+
+        task.await.unwrap_or_else(|e| {
+            error!("Storage task panicked: {:?}", e);
+            HashMap::new()
+        })
+    }
+}
+
+/// Which parts of the bot a deployment runs, and so which gateway intents it asks for
+///
+/// - Voice recording (`WRITEY_VOICE`, default on): `GUILD_VOICE_STATES`, and songbird
+///   is registered. Off for deployments that only work with existing sessions.
+/// - Prefix commands (`WRITEY_PREFIX_COMMANDS`, default on): `GUILD_MESSAGES` and the
+///   privileged `MESSAGE_CONTENT`. Off for slash-only bots that can't get approval for it.
+///
+/// `GUILDS` is always requested. The minimal, unprivileged set is both off: `GUILDS` only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GatewayConfig {
+    voice: bool,
+    prefix_commands: bool,
+}
+
+impl GatewayConfig {
+    fn from_env() -> Self {
+        Self {
+            voice: env_flag("WRITEY_VOICE", true),
+            prefix_commands: env_flag("WRITEY_PREFIX_COMMANDS", true),
+        }
+    }
+
+    fn intents(&self) -> GatewayIntents {
+        let mut intents = GatewayIntents::GUILDS;
+        if self.voice {
+            intents |= GatewayIntents::GUILD_VOICE_STATES;
+        }
+        if self.prefix_commands {
+            intents |= GatewayIntents::GUILD_MESSAGES | GatewayIntents::MESSAGE_CONTENT;
+        }
+        intents
+    }
+}
+
+/// Boolean environment variable, where `0`, `false`, `no` and `off` disable
+pub(crate) fn env_flag(name: &str, default: bool) -> bool {
+    std::env::var(name).map_or(default, |v| {
+        !matches!(v.trim().to_lowercase().as_str(), "0" | "false" | "no" | "off")
+    })
+}
+
+/// Transcriptions and audio reconstructions running at once, unless set by `WRITEY_MAX_CONCURRENT_JOBS`
+///
+/// Every transcription loads its own Whisper model, which can take several GB.
+const DEFAULT_MAX_CONCURRENT_JOBS: usize = 1;
+
+/// Maximum time to spend finalizing recordings when the process is shutting down
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Stop a recording, leave its voice channel and record its duration
+async fn finalize_session(session: &mut RecordingSession, songbird: Option<&songbird::Songbird>, db: &DbPool) {
+    session.finalize().await;
+
+    if let Some(songbird) = songbird
+        && let Err(e) = songbird
+            .remove(serenity::model::id::GuildId::new(session.guild_id))
+            .await
+    {
+        warn!("Failed to leave voice channel in guild {}: {:?}", session.guild_id, e);
+    }
+
+    if let Err(e) = db::finish_session(
+        db,
+        &session.guild_id.to_string(),
+        &session.session_id,
+        session.duration().num_seconds(),
+    )
+    .await
+    {
+        warn!("Failed to update session in database: {:?}", e);
+    }
+
+    info!("Finalized recording {:?}", session.session_dir);
+}
+
+/// Finalize every active recording and leave its voice channel
+///
+/// Clears the recording presence on every shard and the active recordings metric.
+async fn finalize_all_sessions(
+    sessions: &Mutex<ActiveSessions>,
+    songbird: Option<&songbird::Songbird>,
+    db: &DbPool,
+    shard_manager: &serenity::ShardManager,
+) {
+    let sessions: Vec<RecordingSession> = sessions.lock().await.drain().map(|(_, s)| s).collect();
+    info!("Finalizing {} active recording(s)", sessions.len());
+
+    for mut session in sessions {
+        finalize_session(&mut session, songbird, db).await;
+    }
+
+    metrics::ACTIVE_RECORDINGS.set(0);
+    for runner in shard_manager.runners.lock().await.values() {
+        runner.runner_tx.set_activity(None);
+    }
+}
+
+/// Wait for Ctrl+C, or SIGTERM on Unix (what `docker stop` and systemd send)
+async fn shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await
+}
+
+/// Finalize a recording whose voice connection couldn't be restored, and say so in `channel`
+pub async fn finalize_lost_recording(
+    ctx: serenity::Context,
+    sessions: Arc<Mutex<ActiveSessions>>,
+    db: DbPool,
+    guild_id: u64,
+    channel: serenity::ChannelId,
+    reason: String,
+) {
+    let session = {
+        let mut sessions = sessions.lock().await;
+        let session = sessions.remove(&guild_id);
+        update_recording_presence(&ctx, &sessions);
+        session
+    };
+    // Already stopped by a command or shutdown
+    let Some(mut session) = session else {
+        return;
+    };
+
+    let songbird = songbird::get(&ctx).await;
+    finalize_session(&mut session, songbird.as_deref(), &db).await;
+
+    let gaps = paths::SessionMetadata::load(&paths::SessionPaths::new(&session.session_dir).metadata())
+        .map(|metadata| command::stop_recording::format_gaps(&metadata.gaps))
+        .unwrap_or_default();
+    let message = format!(
+        "⚠️ **Recording stopped:** lost the voice connection ({}) and couldn't rejoin.\n\
+        📁 Session: `{}`\n\
+        ⏱️ Duration: {}{}",
+        reason,
+        session.session_dir.display(),
+        command::stop_recording::format_duration(session.duration()),
+        gaps
+    );
+    if let Err(e) = channel.say(&ctx.http, message).await {
+        warn!("Failed to report lost recording in guild {}: {:?}", guild_id, e);
+    }
 }
 
+/// Running recordings, keyed by guild id
+///
+/// Songbird holds one voice connection per guild, so each guild records at most
+/// one voice channel at a time.
 type ActiveSessions = HashMap<u64, RecordingSession>;
 
+/// Cancel flags of running transcriptions, keyed by the id of the guild they were started in
+pub type TranscriptionJobs = HashMap<String, Arc<std::sync::atomic::AtomicBool>>;
+
+/// Prefix for text commands in DMs and guilds that haven't set one
+pub const DEFAULT_PREFIX: &str = "/";
+
+/// Cached command prefixes, keyed by guild id (filled lazily from `guild_settings`)
+pub type GuildPrefixes = HashMap<u64, String>;
+
+/// Command prefix for the guild a message was sent in
+///
+/// Prefixes are looked up once per guild and cached, `/set-prefix` keeps the cache current.
+async fn guild_prefix(ctx: poise::PartialContext<'_, Data, Error>) -> Result<Option<String>, Error> {
+    let Some(guild_id) = ctx.guild_id else {
+        return Ok(Some(DEFAULT_PREFIX.to_string()));
+    };
+
+    let cached = ctx
+        .data
+        .guild_prefixes
+        .lock()
+        .map_err(|_| "Guild prefix cache is poisoned")?
+        .get(&guild_id.get())
+        .cloned();
+    if let Some(prefix) = cached {
+        return Ok(Some(prefix));
+    }
+
+    let prefix = match db::get_guild_prefix(&ctx.data.db, &guild_id.to_string()).await {
+        Ok(prefix) => prefix.unwrap_or_else(|| DEFAULT_PREFIX.to_string()),
+        Err(e) => {
+            // Don't cache, so the next message retries
+            warn!("Failed to load prefix for guild {}: {:?}", guild_id, e);
+            return Ok(Some(DEFAULT_PREFIX.to_string()));
+        }
+    };
+    ctx.data
+        .guild_prefixes
+        .lock()
+        .map_err(|_| "Guild prefix cache is poisoned")?
+        .insert(guild_id.get(), prefix.clone());
+
+    Ok(Some(prefix))
+}
+
+/// Reflect the active recordings in the bot's presence
+///
+/// Presence is global to the bot, so this aggregates across all guilds.
+pub fn update_recording_presence(ctx: &serenity::Context, sessions: &ActiveSessions) {
+    metrics::ACTIVE_RECORDINGS.set(sessions.len() as i64);
+    let activity = match sessions.len() {
+        0 => None,
+        1 => {
+            let guild_id = *sessions.keys().next().unwrap();
+            let guild_name = ctx
+                .cache
+                .guild(serenity::model::id::GuildId::new(guild_id))
+                .map(|g| g.name.clone())
+                .unwrap_or_else(|| guild_id.to_string());
+            Some(serenity::ActivityData::custom(format!(
+                "🔴 Recording in {}",
+                guild_name
+            )))
+        }
+        n => Some(serenity::ActivityData::custom(format!(
+            "🔴 Recording in {} channels",
+            n
+        ))),
+    };
+
+    ctx.set_activity(activity);
+}
+
 pub struct Data {
-    pub active_sessions: Mutex<ActiveSessions>,
+    pub active_sessions: Arc<Mutex<ActiveSessions>>,
+    pub transcription_jobs: Arc<std::sync::Mutex<TranscriptionJobs>>,
+    pub guild_prefixes: Arc<std::sync::Mutex<GuildPrefixes>>,
+    /// Slots for heavy jobs across all guilds, see `DEFAULT_MAX_CONCURRENT_JOBS`
+    pub job_slots: Arc<tokio::sync::Semaphore>,
     pub db: DbPool,
 }
 
@@ -66,7 +369,18 @@ async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
     match error {
         poise::FrameworkError::Setup { error, .. } => panic!("Failed to start bot: {:?}", error),
         poise::FrameworkError::Command { error, ctx, .. } => {
-            println!("Error in command `{}`: {:?}", ctx.command().name, error,);
+            // Otherwise the user only sees the command fail. Other errors can name
+            // paths or internals, so they stay in the log.
+            let reply = match error.downcast_ref::<UserError>() {
+                Some(e) => format!("❌ {}", e),
+                None => {
+                    error!("Error in command `{}`: {:?}", ctx.command().name, error);
+                    "❌ Something went wrong running this command.".to_string()
+                }
+            };
+            if let Err(e) = ctx.say(reply).await {
+                error!("Failed to report error in command `{}`: {}", ctx.command().name, e);
+            }
         }
         error => {
             if let Err(e) = poise::builtins::on_error(error).await {
@@ -100,32 +414,79 @@ async fn main() -> anyhow::Result<()> {
         }
     });
 
+    // WRITEY_LOG_FORMAT=json emits machine-parseable logs (with span fields) for log aggregation
+    let json_logs = std::env::var("WRITEY_LOG_FORMAT").is_ok_and(|f| f.eq_ignore_ascii_case("json"));
+
     tracing_subscriber::registry()
         .with(env_filter)
         .with(udp_rx_filter)
-        .with(fmt::layer())
+        .with((!json_logs).then(fmt::layer))
+        .with(json_logs.then(|| fmt::layer().json().with_current_span(true).with_span_list(false)))
         .init();
 
     let database_url = std::env::var("DATABASE_URL").unwrap();
-    let db_pool = db::init_db(&database_url)
+    let db_pool = db::init_db(&database_url, &db::DbConfig::from_env())
         .await
         .context("Failed to initialize database")?;
     info!("Database initialized successfully");
 
-    std::fs::create_dir_all("recordings").ok();
+    std::fs::create_dir_all(paths::RECORDINGS_DIR).ok();
+
+    let active_sessions: Arc<Mutex<ActiveSessions>> = Arc::new(Mutex::new(HashMap::new()));
+    let shutdown_sessions = Arc::clone(&active_sessions);
+    let shutdown_db = db_pool.clone();
+
+    let max_concurrent_jobs = std::env::var("WRITEY_MAX_CONCURRENT_JOBS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&n: &usize| n > 0)
+        .unwrap_or(DEFAULT_MAX_CONCURRENT_JOBS);
+    info!("Running up to {} transcription/export job(s) at once", max_concurrent_jobs);
+
+    // WRITEY_METRICS_ADDR=0.0.0.0:9100 serves Prometheus metrics, off when unset
+    if let Ok(addr) = std::env::var("WRITEY_METRICS_ADDR") {
+        let addr = addr.parse().context("WRITEY_METRICS_ADDR must be an address like 0.0.0.0:9100")?;
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(addr).await {
+                error!("Metrics server stopped: {}", e);
+            }
+        });
+    }
+
+    let gateway = GatewayConfig::from_env();
+    info!(
+        "Voice recording {}, prefix commands {}",
+        if gateway.voice { "enabled" } else { "disabled" },
+        if gateway.prefix_commands { "enabled" } else { "disabled" }
+    );
+
+    let mut commands = vec![
+        set_transcribe_name(),
+        get_transcribe_name(),
+        reconstruct_audio(),
+        share_audio(),
+        transcribe_session(),
+        transcribe_file(),
+        list_sessions(),
+        get_transcript(),
+        import_transcript(),
+        compare_transcripts(),
+        search_transcript(),
+        index_transcripts(),
+        cancel_transcription(),
+        clean_exports(),
+        download_model(),
+        set_prefix(),
+        set_name_policy(),
+    ];
+    if gateway.voice {
+        commands.extend([list_voice_users(), start_recording(), stop_recording(), recording_status()]);
+    }
 
     let options = poise::FrameworkOptions {
-        commands: vec![
-            set_transcribe_name(),
-            get_transcribe_name(),
-            list_voice_users(),
-            start_recording(),
-            stop_recording(),
-            reconstruct_audio(),
-            transcribe_session(),
-        ],
+        commands,
         prefix_options: poise::PrefixFrameworkOptions {
-            prefix: Some("/".into()),
+            dynamic_prefix: Some(|ctx| Box::pin(guild_prefix(ctx))),
             edit_tracker: Some(Arc::new(poise::EditTracker::for_timespan(
                 Duration::from_secs(3600),
             ))),
@@ -171,10 +532,7 @@ async fn main() -> anyhow::Result<()> {
 
     let token = std::env::var("DISCORD_TOKEN").context("Set DISCORD_TOKEN environment variable")?;
 
-    let intents = GatewayIntents::GUILDS
-        | GatewayIntents::GUILD_MESSAGES
-        | GatewayIntents::MESSAGE_CONTENT
-        | GatewayIntents::GUILD_VOICE_STATES;
+    let intents = gateway.intents();
 
     let framework = poise::Framework::builder()
         .setup(move |ctx, _ready, framework| {
@@ -199,7 +557,10 @@ async fn main() -> anyhow::Result<()> {
                 }
 
                 Ok(Data {
-                    active_sessions: Mutex::new(HashMap::new()),
+                    active_sessions,
+                    transcription_jobs: Arc::new(std::sync::Mutex::new(HashMap::new())),
+                    guild_prefixes: Arc::new(std::sync::Mutex::new(HashMap::new())),
+                    job_slots: Arc::new(tokio::sync::Semaphore::new(max_concurrent_jobs)),
                     db,
                 })
             })
@@ -207,12 +568,37 @@ async fn main() -> anyhow::Result<()> {
         .options(options)
         .build();
 
-    let songbird_config = Config::default().decode_mode(DecodeMode::Decode);
+    let songbird = gateway.voice.then(|| {
+        // The receiver turns every decoded 20ms packet into one mono 48kHz frame
+        let songbird_config = Config::default()
+            .decode_mode(DecodeMode::Decode)
+            .decode_channels(voice::receiver::DECODE_CHANNELS)
+            .decode_sample_rate(voice::receiver::DECODE_SAMPLE_RATE);
+        songbird::Songbird::serenity_from_config(songbird_config)
+    });
+
+    let mut builder = Client::builder(token, intents).framework(framework);
+    if let Some(songbird) = &songbird {
+        builder = builder.register_songbird_with(Arc::clone(songbird));
+    }
+    let mut client = builder.await?;
 
-    let mut client = Client::builder(token, intents)
-        .framework(framework)
-        .register_songbird_from_config(songbird_config)
-        .await?;
+    // Finalize in-flight recordings on Ctrl+C / container stop so no audio is lost
+    let shard_manager = client.shard_manager.clone();
+    tokio::spawn(async move {
+        if let Err(e) = shutdown_signal().await {
+            error!("Failed to listen for shutdown signal: {:?}", e);
+            return;
+        }
+
+        info!("Shutdown signal received");
+        let finalize = finalize_all_sessions(&shutdown_sessions, songbird.as_deref(), &shutdown_db, &shard_manager);
+        if tokio::time::timeout(SHUTDOWN_TIMEOUT, finalize).await.is_err() {
+            warn!("Timed out finalizing recordings after {:?}", SHUTDOWN_TIMEOUT);
+        }
+
+        shard_manager.shutdown_all().await;
+    });
 
     client.start().await?;
     Ok(())
@@ -0,0 +1,229 @@
+//! Prometheus metrics, served as text on `/metrics` when `WRITEY_METRICS_ADDR` is set
+//!
+//! Off by default. Set `WRITEY_METRICS_ADDR=0.0.0.0:9100` to expose:
+//!
+//! - `writey_active_recordings` (gauge): recordings in progress
+//! - `writey_frames_written_total` (counter): audio frames written to chunk logs
+//! - `writey_transcription_duration_seconds` (histogram): wall time of completed transcriptions
+//! - `writey_model_download_bytes_total` (counter): bytes of Whisper models downloaded
+
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+
+/// Upper bounds of the transcription duration buckets, in seconds
+const TRANSCRIPTION_BUCKETS_SECS: [f64; 9] = [
+    10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0, 1800.0, 3600.0,
+];
+/// Time a client gets to send its request and take the response
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Connections answered at once, a scraper needs one and anything more is dropped
+const MAX_CONNECTIONS: usize = 8;
+
+pub static ACTIVE_RECORDINGS: Gauge = Gauge::new();
+pub static FRAMES_WRITTEN: Counter = Counter::new();
+pub static TRANSCRIPTION_DURATION: Histogram<9> = Histogram::new(TRANSCRIPTION_BUCKETS_SECS);
+pub static MODEL_DOWNLOAD_BYTES: Counter = Counter::new();
+
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    pub fn inc_by(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter", name, help, name);
+        let _ = writeln!(out, "{} {}", name, self.0.load(Ordering::Relaxed));
+    }
+}
+
+pub struct Gauge(AtomicI64);
+
+impl Gauge {
+    const fn new() -> Self {
+        Self(AtomicI64::new(0))
+    }
+
+    pub fn set(&self, value: i64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge", name, help, name);
+        let _ = writeln!(out, "{} {}", name, self.0.load(Ordering::Relaxed));
+    }
+}
+
+/// Observation counts per bucket, with the sum kept in microseconds so it fits an atomic
+pub struct Histogram<const N: usize> {
+    buckets: [f64; N],
+    counts: [AtomicU64; N],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl<const N: usize> Histogram<N> {
+    const fn new(buckets: [f64; N]) -> Self {
+        Self {
+            buckets,
+            counts: [const { AtomicU64::new(0) }; N],
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        if let Some(bucket) = self.buckets.iter().position(|&bound| secs <= bound) {
+            self.counts[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Buckets are cumulative in the exposition format, each counts everything up to its bound
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} histogram", name, help, name);
+        let mut cumulative = 0;
+        for (bound, count) in self.buckets.iter().zip(&self.counts) {
+            cumulative += count.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{}_sum {}\n{}_count {}", name, sum, name, count);
+    }
+}
+
+/// All metrics in the Prometheus text exposition format
+pub fn render() -> String {
+    let mut out = String::new();
+    ACTIVE_RECORDINGS.render(
+        &mut out,
+        "writey_active_recordings",
+        "Recordings in progress",
+    );
+    FRAMES_WRITTEN.render(
+        &mut out,
+        "writey_frames_written_total",
+        "Audio frames written to chunk logs",
+    );
+    TRANSCRIPTION_DURATION.render(
+        &mut out,
+        "writey_transcription_duration_seconds",
+        "Wall time of completed transcriptions",
+    );
+    MODEL_DOWNLOAD_BYTES.render(
+        &mut out,
+        "writey_model_download_bytes_total",
+        "Bytes of Whisper models downloaded",
+    );
+    out
+}
+
+/// HTTP response for a raw request: the metrics for `GET /metrics`, 404 for anything else
+fn respond(request: &[u8]) -> String {
+    let request_line = request
+        .split(|&b| b == b'\r' || b == b'\n')
+        .next()
+        .unwrap_or_default();
+    let mut parts = request_line.split(|&b| b == b' ');
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some(b"GET"), Some(b"/metrics")) => ("200 OK", render()),
+        _ => ("404 Not Found", "Not found\n".to_string()),
+    };
+    format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+async fn handle(mut stream: TcpStream) -> std::io::Result<()> {
+    // The request line is all that's needed, and fits easily
+    let mut request = [0u8; 1024];
+    let read = stream.read(&mut request).await?;
+    stream
+        .write_all(respond(&request[..read]).as_bytes())
+        .await?;
+    stream.shutdown().await
+}
+
+/// `handle`, giving up on clients that stall instead of holding the connection open
+async fn handle_with_timeout(stream: TcpStream) -> std::io::Result<()> {
+    tokio::time::timeout(REQUEST_TIMEOUT, handle(stream))
+        .await
+        .unwrap_or_else(|_| {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "client took too long",
+            ))
+        })
+}
+
+/// Serve `/metrics` until the process exits
+pub async fn serve(addr: SocketAddr) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("Serving metrics on http://{}/metrics", addr);
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                let Ok(permit) = connections.clone().try_acquire_owned() else {
+                    warn!("Dropping metrics connection from {}, too many open", peer);
+                    continue;
+                };
+                tokio::spawn(async move {
+                    if let Err(e) = handle_with_timeout(stream).await {
+                        warn!("Failed to answer metrics request: {}", e);
+                    }
+                    drop(permit);
+                });
+            }
+            Err(e) => warn!("Failed to accept metrics connection: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_exposition() {
+        let histogram = Histogram::new(TRANSCRIPTION_BUCKETS_SECS);
+        histogram.observe(Duration::from_secs(45));
+        histogram.observe(Duration::from_secs(90));
+        histogram.observe(Duration::from_secs(7200));
+        let mut out = String::new();
+        histogram.render(&mut out, "duration_seconds", "Test");
+
+        assert!(out.contains("# TYPE duration_seconds histogram\n"));
+        assert!(out.contains("duration_seconds_bucket{le=\"30\"} 0\n"));
+        assert!(out.contains("duration_seconds_bucket{le=\"60\"} 1\n"));
+        assert!(out.contains("duration_seconds_bucket{le=\"3600\"} 2\n"));
+        assert!(out.contains("duration_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(out.contains("duration_seconds_sum 7335\nduration_seconds_count 3\n"));
+
+        let response = respond(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("# TYPE writey_active_recordings gauge\n"));
+        assert!(response.contains("writey_model_download_bytes_total "));
+        assert!(respond(b"GET / HTTP/1.1\r\n\r\n").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -0,0 +1,700 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Directory all recording sessions are stored under
+pub const RECORDINGS_DIR: &str = "recordings";
+/// Directory transcript exports go under unless `WRITEY_EXPORTS_DIR` is set
+pub const EXPORTS_DIR: &str = "exports";
+
+/// Whether `id` can be a session directory name, so joining it can't leave the guild's recordings
+pub fn is_session_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Canonical file layout of a recording session
+///
+/// ```text
+/// recordings/<guild_id>/<session_id>/
+///   session.json                 (guild, start time and channel info, see `SessionMetadata`)
+///   ssrc_map.json                (last user of each SSRC)
+///   ssrc_history.json            (every SSRC owner with the tick it started)
+///   clock.json                   (tick to wall-clock anchors)
+///   users/<ssrc>/chunk-<n>.log
+///   output/                      (reconstruct-audio)
+///   output/aligned/              (reconstruct-audio aligned, one track per user)
+///   output/chapters.xml          (transcribe-session)
+///   output/speaking_stats.json   (transcribe-session)
+///   transcribe/manifest.json     (transcribe-session)
+///   transcribe/transcript.vtt    (transcribe-session, all speakers)
+///   transcribe/<user_id>_<name>/
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionPaths {
+    root: PathBuf,
+    /// Replaces `transcribe/` when transcripts are written elsewhere
+    transcribe_dir: Option<PathBuf>,
+}
+
+impl SessionPaths {
+    /// Wrap an existing session directory
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            transcribe_dir: None,
+        }
+    }
+
+    /// Put transcription output in `dir` instead of the session's `transcribe/`
+    pub fn with_transcribe_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.transcribe_dir = Some(dir.into());
+        self
+    }
+
+    /// Session directory for a new recording in a guild
+    pub fn for_session(guild_id: u64, session_id: &str) -> Self {
+        Self::new(
+            PathBuf::from(RECORDINGS_DIR)
+                .join(guild_id.to_string())
+                .join(session_id),
+        )
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Session id (the directory name)
+    pub fn session_id(&self) -> Option<&str> {
+        self.root.file_name().and_then(|n| n.to_str())
+    }
+
+    /// Guild the session was recorded in
+    ///
+    /// Read from `session.json`, falling back to the directory layout for
+    /// recordings made before it existed.
+    pub fn resolve_guild_id(&self) -> Option<u64> {
+        SessionMetadata::load(&self.metadata())
+            .map(|metadata| metadata.guild_id)
+            .or_else(|| self.guild_id().and_then(|id| id.parse().ok()))
+    }
+
+    /// Guild id (the parent directory name)
+    pub fn guild_id(&self) -> Option<&str> {
+        self.root
+            .parent()
+            .and_then(|p| p.file_name())
+            .and_then(|n| n.to_str())
+    }
+
+    pub fn metadata(&self) -> PathBuf {
+        self.root.join("session.json")
+    }
+
+    pub fn ssrc_map(&self) -> PathBuf {
+        self.root.join("ssrc_map.json")
+    }
+
+    pub fn ssrc_history(&self) -> PathBuf {
+        self.root.join("ssrc_history.json")
+    }
+
+    pub fn clock(&self) -> PathBuf {
+        self.root.join("clock.json")
+    }
+
+    /// Raw per-SSRC audio logs
+    pub fn users_dir(&self) -> PathBuf {
+        self.root.join("users")
+    }
+
+    pub fn ssrc_dir(&self, ssrc: u32) -> PathBuf {
+        self.users_dir().join(ssrc.to_string())
+    }
+
+    /// One text line per 20ms frame: `<tick_index> <sample>,<sample>,...`
+    ///
+    /// Written by the recorder's storage task, read by `reconstruct-audio` and
+    /// `transcribe-session`.
+    pub fn chunk_log(&self, ssrc: u32, chunk: u32) -> PathBuf {
+        self.ssrc_dir(ssrc).join(format!("chunk-{}.log", chunk))
+    }
+
+    /// Whether the recorder wrote any audio frames
+    ///
+    /// The storage task only creates chunk logs for frames it receives, so a
+    /// session stopped before anyone spoke has none (or only empty ones).
+    pub fn has_audio(&self) -> bool {
+        let Ok(ssrc_dirs) = std::fs::read_dir(self.users_dir()) else {
+            return false;
+        };
+
+        ssrc_dirs
+            .filter_map(|e| e.ok())
+            .filter_map(|e| std::fs::read_dir(e.path()).ok())
+            .flatten()
+            .filter_map(|e| e.ok())
+            .any(|log| log.metadata().is_ok_and(|m| m.is_file() && m.len() > 0))
+    }
+
+    /// Reconstructed audio files
+    pub fn output_dir(&self) -> PathBuf {
+        self.root.join("output")
+    }
+
+    /// Per-user tracks padded to a common start, for multi-track editors
+    pub fn aligned_dir(&self) -> PathBuf {
+        self.output_dir().join("aligned")
+    }
+
+    pub fn aligned_manifest(&self) -> PathBuf {
+        self.aligned_dir().join("tracks.json")
+    }
+
+    /// Matroska chapters for the merged audio, one per speaker turn
+    pub fn chapters(&self) -> PathBuf {
+        self.output_dir().join("chapters.xml")
+    }
+
+    /// Talk time and turns per user, see `SpeakingStats`
+    pub fn speaking_stats(&self) -> PathBuf {
+        self.output_dir().join("speaking_stats.json")
+    }
+
+    /// Transcription output
+    pub fn transcribe_dir(&self) -> PathBuf {
+        self.transcribe_dir
+            .clone()
+            .unwrap_or_else(|| self.root.join("transcribe"))
+    }
+
+    pub fn transcribe_manifest(&self) -> PathBuf {
+        self.transcribe_dir().join("manifest.json")
+    }
+
+    /// Subtitles for the whole session with styled speakers
+    pub fn session_vtt(&self) -> PathBuf {
+        self.transcribe_dir().join("transcript.vtt")
+    }
+
+    /// The conversation of all speakers in speaking order, `md` or `txt`
+    pub fn session_transcript(&self, extension: &str) -> PathBuf {
+        self.transcribe_dir()
+            .join(format!("transcript.{}", extension))
+    }
+
+    /// Every speaker's transcription in one file, written by `TranscriptLayout::Combined`
+    pub fn combined_transcriptions(&self) -> PathBuf {
+        self.transcribe_dir().join("transcriptions.json")
+    }
+
+    /// Per-user transcription directory
+    pub fn transcribe_user_dir(&self, user_id: u64, display_name: &str) -> PathBuf {
+        self.transcribe_dir()
+            .join(transcribe_user_dir_name(user_id, display_name))
+    }
+}
+
+/// RTP packets clocked out for one SSRC over a recording
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PacketStats {
+    pub received: u64,
+    /// Ticks the stream was speaking but its packet never arrived
+    pub lost: u64,
+    /// Ticks without decoded audio, from lost or corrupt packets
+    #[serde(default)]
+    pub undecodable: u64,
+}
+
+impl PacketStats {
+    pub fn loss_percent(&self) -> f32 {
+        let total = self.received + self.lost;
+        if total == 0 {
+            return 0.0;
+        }
+        self.lost as f32 * 100.0 / total as f32
+    }
+}
+
+/// Ticks skipped while the voice connection was down
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordingGap {
+    /// Tick the connection was lost at
+    pub from_tick: u64,
+    /// Ticks without audio before the connection came back
+    pub ticks: u64,
+    pub reason: String,
+}
+
+/// Recording metadata written when a session starts (`session.json`)
+///
+/// Packet stats, connection gaps and consent are added when the recording stops.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionMetadata {
+    pub guild_id: u64,
+    pub session_id: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    /// Configured bitrate of the recorded voice channel, in bits per second
+    #[serde(default)]
+    pub channel_bitrate: Option<u32>,
+    /// Voice region override of the channel, `None` for automatic
+    #[serde(default)]
+    pub rtc_region: Option<String>,
+    #[serde(default)]
+    pub packet_stats: BTreeMap<u32, PacketStats>,
+    #[serde(default)]
+    pub gaps: Vec<RecordingGap>,
+    /// Users who opted in and when, for consent-gated recordings; `None` if everyone was recorded
+    #[serde(default)]
+    pub consent: Option<BTreeMap<u64, chrono::DateTime<chrono::Utc>>>,
+}
+
+impl SessionMetadata {
+    /// Add who consented during a recording to the stored list
+    ///
+    /// `None` (no consent gate) never erases a stored list, and the first time
+    /// someone consented is kept.
+    pub fn record_consent(
+        &mut self,
+        consented: Option<BTreeMap<u64, chrono::DateTime<chrono::Utc>>>,
+    ) {
+        let Some(consented) = consented else {
+            return;
+        };
+        let stored = self.consent.get_or_insert_with(BTreeMap::new);
+        for (user_id, at) in consented {
+            stored.entry(user_id).or_insert(at);
+        }
+    }
+
+    pub fn load(path: &Path) -> Option<Self> {
+        let json = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// `64 kbps, region rotterdam`, or `None` when nothing about the channel is known
+    pub fn channel_summary(&self) -> Option<String> {
+        let bitrate = self
+            .channel_bitrate
+            .map(|bps| format!("{} kbps", bps / 1000));
+        let region = self
+            .rtc_region
+            .as_ref()
+            .map(|region| format!("region {}", region));
+        let parts: Vec<String> = bitrate.into_iter().chain(region).collect();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+/// The only directory transcripts are exported to, `WRITEY_EXPORTS_DIR` or `exports`
+pub fn exports_root() -> PathBuf {
+    std::env::var_os("WRITEY_EXPORTS_DIR").map_or_else(|| PathBuf::from(EXPORTS_DIR), PathBuf::from)
+}
+
+/// A guild's share of the exports root, which is all of it that guild's commands can reach
+pub fn guild_exports_dir(root: &Path, guild_id: u64) -> PathBuf {
+    root.join(guild_id.to_string())
+}
+
+/// Validate and create a directory to write a session's transcripts into
+///
+/// `subpath` is relative to the guild's directory under the exports `root`, and
+/// transcripts go into a `<session_id>` subdirectory of it, so several sessions
+/// can share one export. Absolute paths, `..` and symlinks leading out of the
+/// guild's directory are refused, and the directory must be writable.
+pub fn transcript_export_dir(
+    root: &Path,
+    guild_id: u64,
+    subpath: &str,
+    session: &SessionPaths,
+) -> Result<PathBuf, String> {
+    let subpath = Path::new(subpath);
+    if subpath
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(format!(
+            "`{}` must be a relative path without `..`",
+            subpath.display()
+        ));
+    }
+
+    let guild_dir = guild_exports_dir(root, guild_id);
+    let export_dir = guild_dir.join(subpath).join(
+        session
+            .session_id()
+            .ok_or("Session directory has no name")?,
+    );
+    std::fs::create_dir_all(&export_dir)
+        .map_err(|e| format!("Can't create {}: {}", export_dir.display(), e))?;
+
+    let canonical = |p: &Path| {
+        p.canonicalize()
+            .map_err(|e| format!("Invalid path {}: {}", p.display(), e))
+    };
+    if !canonical(&export_dir)?.starts_with(canonical(&guild_dir)?) {
+        return Err(format!(
+            "`{}` leads outside the exports directory",
+            subpath.display()
+        ));
+    }
+
+    let probe = export_dir.join(".write_test");
+    std::fs::write(&probe, b"")
+        .and_then(|_| std::fs::remove_file(&probe))
+        .map_err(|e| format!("`{}` is not writable: {}", export_dir.display(), e))?;
+
+    Ok(export_dir)
+}
+
+/// How a session's transcription is laid out on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TranscriptLayout {
+    /// A `<user_id>_<name>` folder per speaker with their chunks and transcripts
+    #[default]
+    PerUser,
+    /// One folder with the merged conversation and `transcriptions.json`, no chunk WAVs
+    Combined,
+}
+
+impl std::fmt::Display for TranscriptLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TranscriptLayout::PerUser => write!(f, "per-user"),
+            TranscriptLayout::Combined => write!(f, "combined"),
+        }
+    }
+}
+
+impl std::str::FromStr for TranscriptLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "per-user" | "per_user" | "user" => Ok(TranscriptLayout::PerUser),
+            "combined" | "single" => Ok(TranscriptLayout::Combined),
+            _ => Err(format!("Unknown layout: {}. Use per-user or combined", s)),
+        }
+    }
+}
+
+/// Directory name of a user's transcription: `<user_id>_<name>` with the name made filesystem-safe
+pub fn transcribe_user_dir_name(user_id: u64, display_name: &str) -> String {
+    let safe_name: String = display_name
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+        .collect();
+    format!("{}_{}", user_id, safe_name)
+}
+
+/// Placeholders a `FilenameTemplate` can use
+const FILENAME_PLACEHOLDERS: [&str; 4] = ["session", "user", "name", "date"];
+
+/// Template for output file names, without the extension
+///
+/// `{session}` is the session id, `{user}` the speaker's SSRC (`merged` for the
+/// mix), `{name}` their transcribed name or user id and `{date}` the recording
+/// date. The default `{user}` gives the usual `<ssrc>.wav` and `merged.wav`.
+///
+/// Every template contains `{user}`: a user who reconnects gets a second SSRC
+/// with the same name, and a user may well be called "merged", so only the
+/// SSRC keeps their files apart from each other and from the mix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilenameTemplate(String);
+
+impl Default for FilenameTemplate {
+    fn default() -> Self {
+        Self("{user}".to_string())
+    }
+}
+
+/// What a `FilenameTemplate`'s placeholders stand for in one file name
+pub struct FilenameValues<'a> {
+    pub session: &'a str,
+    pub user: &'a str,
+    pub name: &'a str,
+    pub date: &'a str,
+}
+
+impl FilenameTemplate {
+    /// Fill in the placeholders and make the result a valid file name
+    pub fn render(&self, values: &FilenameValues) -> String {
+        let mut out = String::new();
+        let mut rest = self.0.as_str();
+        while let Some(open) = rest.find('{') {
+            out.push_str(&rest[..open]);
+            let Some(close) = rest[open..].find('}').map(|i| open + i) else {
+                break;
+            };
+            out.push_str(match &rest[open + 1..close] {
+                "session" => values.session,
+                "user" => values.user,
+                "name" => values.name,
+                "date" => values.date,
+                _ => "",
+            });
+            rest = &rest[close + 1..];
+        }
+        out.push_str(rest);
+        sanitize_filename(&out)
+    }
+}
+
+impl std::str::FromStr for FilenameTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut placeholders = Vec::new();
+        let mut rest = s;
+        while let Some(open) = rest.find(['{', '}']) {
+            if rest[open..].starts_with('}') {
+                return Err(format!("Unmatched `}}` in filename template `{}`", s));
+            }
+            let close = rest[open..]
+                .find('}')
+                .map(|i| open + i)
+                .ok_or_else(|| format!("Unclosed `{{` in filename template `{}`", s))?;
+            let placeholder = &rest[open + 1..close];
+            if !FILENAME_PLACEHOLDERS.contains(&placeholder) {
+                return Err(format!(
+                    "Unknown placeholder `{{{}}}` in filename template. Use {{session}}, {{user}}, {{name}} or {{date}}",
+                    placeholder
+                ));
+            }
+            placeholders.push(placeholder);
+            rest = &rest[close + 1..];
+        }
+
+        // Every track gets its own file
+        if !placeholders.contains(&"user") {
+            return Err("Filename template must contain {user}, the SSRC that keeps every track's file apart".to_string());
+        }
+        Ok(Self(s.to_string()))
+    }
+}
+
+/// Make a string safe as a file name: path separators, reserved and control characters become `_`
+pub fn sanitize_filename(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || r#"/\:*?"<>|"#.contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    // Leading dots would hide the file, trailing dots and spaces are dropped on Windows
+    let trimmed = replaced.trim_matches(|c: char| c == '.' || c.is_whitespace());
+    if trimmed.is_empty() {
+        "_".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_paths() {
+        let paths = SessionPaths::for_session(42, "2026_01_03_18_49_53");
+        let root = PathBuf::from("recordings/42/2026_01_03_18_49_53");
+
+        assert_eq!(paths.root(), root);
+        assert_eq!(paths.guild_id(), Some("42"));
+        assert_eq!(paths.session_id(), Some("2026_01_03_18_49_53"));
+        assert!(is_session_id("2026_01_03_18_49_53"));
+        assert!(!is_session_id("../715908438760357910/2026_01_03_18_49_53"));
+        assert!(!is_session_id(""));
+        assert_eq!(paths.metadata(), root.join("session.json"));
+        assert_eq!(paths.ssrc_map(), root.join("ssrc_map.json"));
+        assert_eq!(paths.clock(), root.join("clock.json"));
+        assert_eq!(paths.users_dir(), root.join("users"));
+        assert_eq!(
+            paths.chunk_log(1234, 2),
+            root.join("users/1234/chunk-2.log")
+        );
+        assert_eq!(paths.output_dir(), root.join("output"));
+        assert_eq!(paths.chapters(), root.join("output/chapters.xml"));
+        assert_eq!(
+            paths.transcribe_manifest(),
+            root.join("transcribe/manifest.json")
+        );
+        assert_eq!(
+            paths.session_transcript("md"),
+            root.join("transcribe/transcript.md")
+        );
+        assert_eq!(
+            paths.combined_transcriptions(),
+            root.join("transcribe/transcriptions.json")
+        );
+
+        let exported = paths.with_transcribe_dir("/exports/2026_01_03_18_49_53");
+        assert_eq!(
+            exported.transcribe_manifest(),
+            PathBuf::from("/exports/2026_01_03_18_49_53/manifest.json")
+        );
+        assert_eq!(exported.root(), root);
+    }
+
+    #[test]
+    fn test_transcript_export_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = SessionPaths::new(dir.path().join("recordings/42/2026_01_03_18_49_53"));
+
+        let root = dir.path().join(EXPORTS_DIR);
+
+        let export_dir = transcript_export_dir(&root, 42, "team/weekly", &session).unwrap();
+        assert!(export_dir.ends_with("exports/42/team/weekly/2026_01_03_18_49_53"));
+        assert!(export_dir.is_dir());
+        assert_eq!(std::fs::read_dir(&export_dir).unwrap().count(), 0);
+        let export_dir = transcript_export_dir(&root, 42, "", &session).unwrap();
+        assert!(export_dir.ends_with("exports/42/2026_01_03_18_49_53"));
+
+        assert!(transcript_export_dir(&root, 42, "/", &session).is_err());
+        assert!(transcript_export_dir(&root, 42, "/etc/writey", &session).is_err());
+        assert!(transcript_export_dir(&root, 42, "../7", &session).is_err());
+        assert!(transcript_export_dir(&root, 42, "team/../../../recordings", &session).is_err());
+
+        // A symlink inside the exports can't be used to get out of them
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path(), root.join("42/escape")).unwrap();
+            assert!(transcript_export_dir(&root, 42, "escape", &session).is_err());
+        }
+    }
+
+    #[test]
+    fn test_resolve_guild_id_prefers_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Copied somewhere that doesn't follow the recordings/<guild>/<session> layout
+        let moved = SessionPaths::new(dir.path().join("exports").join("meeting"));
+        std::fs::create_dir_all(moved.root()).unwrap();
+        assert_eq!(moved.resolve_guild_id(), None);
+
+        SessionMetadata {
+            guild_id: 42,
+            session_id: "2026_01_03_18_49_53".to_string(),
+            started_at: chrono::Utc::now(),
+            channel_bitrate: None,
+            rtc_region: None,
+            packet_stats: BTreeMap::new(),
+            gaps: Vec::new(),
+            consent: None,
+        }
+        .save(&moved.metadata())
+        .unwrap();
+        assert_eq!(moved.resolve_guild_id(), Some(42));
+
+        // Legacy sessions without metadata fall back to the directory layout
+        let legacy = SessionPaths::new(dir.path().join("recordings/7/s"));
+        assert_eq!(legacy.resolve_guild_id(), Some(7));
+    }
+
+    #[test]
+    fn test_session_metadata_channel_info() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+
+        // Sessions recorded before channel info was captured still load
+        std::fs::write(
+            &path,
+            r#"{"guild_id": 42, "session_id": "s", "started_at": "2026-01-03T18:49:53Z"}"#,
+        )
+        .unwrap();
+        let mut metadata = SessionMetadata::load(&path).unwrap();
+        assert_eq!(metadata.channel_bitrate, None);
+        assert!(metadata.packet_stats.is_empty());
+        assert_eq!(metadata.channel_summary(), None);
+
+        metadata.channel_bitrate = Some(64000);
+        metadata.rtc_region = Some("rotterdam".to_string());
+        metadata.packet_stats.insert(
+            1234,
+            PacketStats {
+                received: 970,
+                lost: 30,
+                undecodable: 12,
+            },
+        );
+        metadata.gaps.push(RecordingGap {
+            from_tick: 500,
+            ticks: 250,
+            reason: "Io".to_string(),
+        });
+        metadata.consent = Some(BTreeMap::from([(7, chrono::Utc::now())]));
+        metadata.save(&path).unwrap();
+
+        let loaded = SessionMetadata::load(&path).unwrap();
+        assert_eq!(loaded, metadata);
+        assert_eq!(
+            loaded.channel_summary().as_deref(),
+            Some("64 kbps, region rotterdam")
+        );
+        assert!((loaded.packet_stats[&1234].loss_percent() - 3.0).abs() < 1e-4);
+        assert_eq!(PacketStats::default().loss_percent(), 0.0);
+    }
+
+    #[test]
+    fn test_filename_template() {
+        let values = FilenameValues {
+            session: "2026_01_03_18_49_53",
+            user: "1111",
+            name: "Jo: \"The Boss\" / Doe",
+            date: "2026-01-03",
+        };
+        assert_eq!(FilenameTemplate::default().render(&values), "1111");
+
+        let template: FilenameTemplate = "{date}_{session}_{name}_{user}".parse().unwrap();
+        assert_eq!(
+            template.render(&values),
+            "2026-01-03_2026_01_03_18_49_53_Jo_ _The Boss_ _ Doe_1111"
+        );
+        // A user named like the mix still gets a file of their own
+        let merged = FilenameValues {
+            user: "merged",
+            name: "merged",
+            ..values
+        };
+        let named_merged = FilenameValues {
+            name: "merged",
+            ..values
+        };
+        assert_ne!(template.render(&merged), template.render(&named_merged));
+        let template: FilenameTemplate = "..{user} {name}..".parse().unwrap();
+        assert_eq!(
+            template.render(&FilenameValues {
+                name: "{date}",
+                ..values
+            }),
+            "1111 {date}"
+        );
+
+        assert!("{session}".parse::<FilenameTemplate>().is_err());
+        // Two SSRCs of a reconnecting user would write the same file
+        assert!("{session}_{name}".parse::<FilenameTemplate>().is_err());
+        assert!("{user}_{speaker}".parse::<FilenameTemplate>().is_err());
+        assert!("{user".parse::<FilenameTemplate>().is_err());
+        assert!("user}_{name}".parse::<FilenameTemplate>().is_err());
+        assert_eq!(sanitize_filename("../.."), "_");
+    }
+
+    #[test]
+    fn test_transcribe_user_dir_sanitizes_name() {
+        let paths = SessionPaths::new("recordings/1/s");
+
+        assert_eq!(transcribe_user_dir_name(7, "Jo Doe/../x"), "7_JoDoex");
+        assert_eq!(
+            paths.transcribe_user_dir(7, "max_m-1"),
+            PathBuf::from("recordings/1/s/transcribe/7_max_m-1")
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use super::{AudioChunk, ChunkTranscription, WhisperError};
+use tracing::{info, warn};
+
+/// Which speech-to-text engine `transcribe-session` uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackendKind {
+    /// Local whisper.cpp
+    #[default]
+    Whisper,
+    /// OpenAI's hosted Whisper API (uploads audio)
+    OpenAi,
+}
+
+impl std::fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BackendKind::Whisper => write!(f, "whisper"),
+            BackendKind::OpenAi => write!(f, "openai"),
+        }
+    }
+}
+
+impl std::str::FromStr for BackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "whisper" | "local" => Ok(BackendKind::Whisper),
+            "openai" => Ok(BackendKind::OpenAi),
+            _ => Err(format!("Unknown backend: {}. Use whisper or openai", s)),
+        }
+    }
+}
+
+/// A speech-to-text engine that transcribes prepared audio chunks
+pub trait TranscriptionBackend: Send + Sync {
+    /// Model name recorded in transcripts and the manifest
+    fn name(&self) -> String;
+
+    /// Transcribe one chunk, with timestamps relative to the chunk start
+    fn transcribe(&self, chunk: &AudioChunk) -> Result<ChunkTranscription, WhisperError>;
+
+    /// Prepare for the first chunk, so it runs as fast as the ones after it
+    ///
+    /// Nothing to do for remote backends.
+    fn warmup(&self) -> Result<(), WhisperError> {
+        Ok(())
+    }
+
+    /// Whether the caller asked to stop before the next chunk
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+
+    /// Transcribe chunks in order, skipping chunks that fail
+    ///
+    /// `on_progress` is called with (chunks processed, total chunks) after every chunk.
+    fn transcribe_chunks(
+        &self,
+        chunks: &[AudioChunk],
+        on_progress: &dyn Fn(usize, usize),
+    ) -> Result<Vec<ChunkTranscription>, WhisperError> {
+        let mut transcriptions = Vec::with_capacity(chunks.len());
+
+        for (i, chunk) in chunks.iter().enumerate() {
+            if self.is_cancelled() {
+                info!("Cancelled after {}/{} chunks", i, chunks.len());
+                break;
+            }
+
+            match self.transcribe(chunk) {
+                Ok(t) => transcriptions.push(t),
+                Err(e) => warn!("Failed to transcribe chunk {}: {}", chunk.index, e),
+            }
+            info!("Progress: {}/{} chunks", i + 1, chunks.len());
+            on_progress(i + 1, chunks.len());
+        }
+
+        Ok(transcriptions)
+    }
+}
@@ -0,0 +1,206 @@
+use super::UserTranscription;
+use crate::voice::clock::tick_offset_secs;
+
+/// Consecutive segments by one speaker on the session timeline
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeakerTurn {
+    pub user_id: u64,
+    pub speaker: String,
+    pub start_secs: f32,
+    pub end_secs: f32,
+}
+
+/// Merge all users' segments onto one timeline and coalesce consecutive segments by the same speaker
+///
+/// Each track is a user's transcription with the first tick of their audio.
+/// Times are relative to the earliest track, which is where `reconstruct-audio`'s
+/// merged file starts.
+pub fn speaker_turns(tracks: &[(&UserTranscription, u64)]) -> Vec<SpeakerTurn> {
+    let origin_tick = tracks.iter().map(|(_, tick)| *tick).min().unwrap_or(0);
+
+    let mut segments: Vec<(f32, f32, &UserTranscription)> = tracks
+        .iter()
+        .flat_map(|(transcription, first_tick)| {
+            let offset_secs = tick_offset_secs(*first_tick, origin_tick);
+            transcription.all_segments.iter().map(move |segment| {
+                (
+                    offset_secs + segment.start_secs,
+                    offset_secs + segment.end_secs,
+                    *transcription,
+                )
+            })
+        })
+        .collect();
+    segments.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // Turns are told apart by user, two speakers can share a display name
+    let mut turns: Vec<SpeakerTurn> = Vec::new();
+    for (start_secs, end_secs, transcription) in segments {
+        match turns.last_mut() {
+            Some(turn) if turn.user_id == transcription.user_id => {
+                turn.end_secs = turn.end_secs.max(end_secs);
+            }
+            _ => turns.push(SpeakerTurn {
+                user_id: transcription.user_id,
+                speaker: transcription.display_name.clone(),
+                start_secs,
+                end_secs,
+            }),
+        }
+    }
+
+    turns
+}
+
+/// Format seconds as MM:SS (or H:MM:SS for long sessions)
+pub fn format_timestamp(secs: f32) -> String {
+    let total = secs as u64;
+    if total >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            total / 3600,
+            (total % 3600) / 60,
+            total % 60
+        )
+    } else {
+        format!("{:02}:{:02}", total / 60, total % 60)
+    }
+}
+
+/// Format seconds as a Matroska chapter time (HH:MM:SS.nnnnnnnnn)
+fn format_chapter_time(secs: f32) -> String {
+    let nanos = (secs.max(0.0) as f64 * 1e9).round() as u64;
+    let total = nanos / 1_000_000_000;
+    format!(
+        "{:02}:{:02}:{:02}.{:09}",
+        total / 3600,
+        (total % 3600) / 60,
+        total % 60,
+        nanos % 1_000_000_000
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Matroska chapters XML (as read by mkvmerge) with one chapter per speaker turn
+pub fn matroska_chapters_xml(turns: &[SpeakerTurn]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <!DOCTYPE Chapters SYSTEM \"matroskachapters.dtd\">\n\
+        <Chapters>\n  <EditionEntry>\n",
+    );
+
+    for turn in turns {
+        xml.push_str(&format!(
+            r#"    <ChapterAtom>
+      <ChapterTimeStart>{}</ChapterTimeStart>
+      <ChapterTimeEnd>{}</ChapterTimeEnd>
+      <ChapterDisplay>
+        <ChapterString>{} {}</ChapterString>
+        <ChapterLanguage>und</ChapterLanguage>
+      </ChapterDisplay>
+    </ChapterAtom>
+"#,
+            format_chapter_time(turn.start_secs),
+            format_chapter_time(turn.end_secs),
+            escape_xml(&turn.speaker),
+            format_timestamp(turn.start_secs)
+        ));
+    }
+
+    xml.push_str("  </EditionEntry>\n</Chapters>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcribe::TranscribedSegment;
+
+    fn transcription(user_id: u64, name: &str, times: &[(f32, f32)]) -> UserTranscription {
+        UserTranscription {
+            user_id,
+            display_name: name.to_string(),
+            model: "small".to_string(),
+            total_duration_secs: 0.0,
+            chunk_transcriptions: vec![],
+            all_segments: times
+                .iter()
+                .map(|&(start_secs, end_secs)| TranscribedSegment {
+                    start_secs,
+                    end_secs,
+                    text: "hello".to_string(),
+                    speaker_id: None,
+                    speaker_name: None,
+                    translation: None,
+                })
+                .collect(),
+            full_transcript: String::new(),
+            locked_language: None,
+        }
+    }
+
+    #[test]
+    fn test_speaker_turns_coalesce_on_shared_timeline() {
+        let alice = transcription(1, "Alice", &[(0.0, 2.0), (2.5, 4.0), (133.0, 135.0)]);
+        // Bob's audio starts one second (50 ticks) after Alice's
+        let bob = transcription(2, "Bob", &[(5.0, 6.0)]);
+
+        let turns = speaker_turns(&[(&alice, 100), (&bob, 150)]);
+
+        assert_eq!(turns.len(), 3);
+        assert_eq!(turns[0].speaker, "Alice");
+        assert_eq!((turns[0].start_secs, turns[0].end_secs), (0.0, 4.0));
+        assert_eq!(turns[1].speaker, "Bob");
+        assert_eq!((turns[1].start_secs, turns[1].end_secs), (6.0, 7.0));
+        assert_eq!(turns[2].start_secs, 133.0);
+    }
+
+    #[test]
+    fn test_speaker_turns_tell_apart_speakers_with_the_same_name() {
+        let alex = transcription(1, "Alex", &[(0.0, 2.0)]);
+        let other_alex = transcription(2, "Alex", &[(2.5, 4.0)]);
+
+        let turns = speaker_turns(&[(&alex, 100), (&other_alex, 100)]);
+
+        assert_eq!(
+            turns.iter().map(|turn| turn.user_id).collect::<Vec<_>>(),
+            [1, 2]
+        );
+    }
+
+    #[test]
+    fn test_matroska_chapters_xml() {
+        let turns = vec![
+            SpeakerTurn {
+                user_id: 1,
+                speaker: "Alice".to_string(),
+                start_secs: 133.5,
+                end_secs: 140.0,
+            },
+            SpeakerTurn {
+                user_id: 2,
+                speaker: "R&D <Bob>".to_string(),
+                start_secs: 140.0,
+                end_secs: 3725.25,
+            },
+        ];
+
+        let xml = matroska_chapters_xml(&turns);
+
+        assert!(xml.starts_with("<?xml version=\"1.0\""));
+        assert!(xml.contains("<Chapters>") && xml.trim_end().ends_with("</Chapters>"));
+        assert_eq!(xml.matches("<EditionEntry>").count(), 1);
+        assert_eq!(xml.matches("<ChapterAtom>").count(), 2);
+        assert_eq!(xml.matches("</ChapterAtom>").count(), 2);
+        assert!(xml.contains("<ChapterTimeStart>00:02:13.500000000</ChapterTimeStart>"));
+        assert!(xml.contains("<ChapterString>Alice 02:13</ChapterString>"));
+        assert!(xml.contains("<ChapterTimeEnd>01:02:05.250000000</ChapterTimeEnd>"));
+        assert!(xml.contains("<ChapterString>R&amp;D &lt;Bob&gt; 02:20</ChapterString>"));
+    }
+}
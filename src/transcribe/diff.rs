@@ -0,0 +1,350 @@
+use super::{UserTranscription, format_timestamp};
+
+/// A word of a diff between two transcripts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordChange {
+    Same(String),
+    /// Only in the left transcript
+    Removed(String),
+    /// Only in the right transcript
+    Added(String),
+}
+
+/// A span of time where segments of either transcript overlap
+#[derive(Debug, Clone)]
+pub struct AlignedSegment {
+    pub start_secs: f32,
+    pub end_secs: f32,
+    pub words: Vec<WordChange>,
+}
+
+impl AlignedSegment {
+    pub fn differs(&self) -> bool {
+        self.words.iter().any(|w| !matches!(w, WordChange::Same(_)))
+    }
+
+    /// Words substituted, dropped or inserted, counting a substitution once
+    fn changed_words(&self) -> usize {
+        let removed = self
+            .words
+            .iter()
+            .filter(|w| matches!(w, WordChange::Removed(_)))
+            .count();
+        let added = self
+            .words
+            .iter()
+            .filter(|w| matches!(w, WordChange::Added(_)))
+            .count();
+        removed.max(added)
+    }
+}
+
+/// Segment-aligned comparison of two transcriptions of the same audio
+///
+/// Segments are grouped wherever they overlap in time, so a sentence one model
+/// splits in two still lines up with the other model's single segment.
+#[derive(Debug, Clone)]
+pub struct TranscriptDiff {
+    pub left_label: String,
+    pub right_label: String,
+    pub segments: Vec<AlignedSegment>,
+}
+
+/// Lowercase alphanumerics only, so punctuation and capitalization aren't differences
+fn normalize_word(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Word-level diff of two texts by longest common subsequence
+fn word_diff(left: &str, right: &str) -> Vec<WordChange> {
+    let left: Vec<&str> = left.split_whitespace().collect();
+    let right: Vec<&str> = right.split_whitespace().collect();
+    let a: Vec<String> = left.iter().map(|w| normalize_word(w)).collect();
+    let b: Vec<String> = right.iter().map(|w| normalize_word(w)).collect();
+
+    // lcs[i][j]: common subsequence length of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::with_capacity(a.len().max(b.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            changes.push(WordChange::Same(right[j].to_string()));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            changes.push(WordChange::Removed(left[i].to_string()));
+            i += 1;
+        } else {
+            changes.push(WordChange::Added(right[j].to_string()));
+            j += 1;
+        }
+    }
+    changes.extend(left[i..].iter().map(|w| WordChange::Removed(w.to_string())));
+    changes.extend(right[j..].iter().map(|w| WordChange::Added(w.to_string())));
+    changes
+}
+
+/// Align two transcriptions by timestamp overlap and diff each aligned span
+pub fn diff_transcripts(left: &UserTranscription, right: &UserTranscription) -> TranscriptDiff {
+    // (start, end, is_left, text) for every segment of both sides
+    let mut segments: Vec<(f32, f32, bool, &str)> = left
+        .all_segments
+        .iter()
+        .map(|s| (s.start_secs, s.end_secs, true, s.text.as_str()))
+        .chain(
+            right
+                .all_segments
+                .iter()
+                .map(|s| (s.start_secs, s.end_secs, false, s.text.as_str())),
+        )
+        .collect();
+    segments.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // Sweep into spans of overlapping segments
+    let mut spans: Vec<(f32, f32, Vec<&str>, Vec<&str>)> = Vec::new();
+    for (start, end, is_left, text) in segments {
+        match spans.last_mut() {
+            Some(span) if start < span.1 => span.1 = span.1.max(end),
+            _ => spans.push((start, end, Vec::new(), Vec::new())),
+        }
+        let span = spans.last_mut().expect("span was just pushed");
+        if is_left {
+            span.2.push(text);
+        } else {
+            span.3.push(text);
+        }
+    }
+
+    TranscriptDiff {
+        left_label: left.model.clone(),
+        right_label: right.model.clone(),
+        segments: spans
+            .into_iter()
+            .map(
+                |(start_secs, end_secs, left_texts, right_texts)| AlignedSegment {
+                    start_secs,
+                    end_secs,
+                    words: word_diff(&left_texts.join(" "), &right_texts.join(" ")),
+                },
+            )
+            .collect(),
+    }
+}
+
+/// Consecutive words of the same kind, so markup wraps phrases instead of single words
+fn word_runs(words: &[WordChange]) -> Vec<(&WordChange, String)> {
+    let mut runs: Vec<(&WordChange, String)> = Vec::new();
+    for word in words {
+        let text = match word {
+            WordChange::Same(w) | WordChange::Removed(w) | WordChange::Added(w) => w,
+        };
+        match runs.last_mut() {
+            Some((kind, run)) if std::mem::discriminant(*kind) == std::mem::discriminant(word) => {
+                run.push(' ');
+                run.push_str(text);
+            }
+            _ => runs.push((word, text.clone())),
+        }
+    }
+    runs
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl TranscriptDiff {
+    pub fn differing_segments(&self) -> usize {
+        self.segments.iter().filter(|s| s.differs()).count()
+    }
+
+    /// Share of the left transcript's words that changed, in percent
+    pub fn changed_word_percent(&self) -> f32 {
+        let left_words = self
+            .segments
+            .iter()
+            .flat_map(|s| &s.words)
+            .filter(|w| !matches!(w, WordChange::Added(_)))
+            .count();
+        let changed: usize = self
+            .segments
+            .iter()
+            .map(AlignedSegment::changed_words)
+            .sum();
+        if left_words == 0 {
+            return if changed == 0 { 0.0 } else { 100.0 };
+        }
+        changed as f32 * 100.0 / left_words as f32
+    }
+
+    /// `N of M segments differ, X% of words changed`
+    pub fn summary(&self) -> String {
+        format!(
+            "{} of {} segments differ, {:.1}% of words changed",
+            self.differing_segments(),
+            self.segments.len(),
+            self.changed_word_percent()
+        )
+    }
+
+    /// Plain text diff, marking differing spans with `!` and words as `[-left-]{+right+}`
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "Compare: [-{}-] vs {{+{}+}}\n{}\n\n",
+            self.left_label,
+            self.right_label,
+            self.summary()
+        );
+        for segment in &self.segments {
+            let words: Vec<String> = word_runs(&segment.words)
+                .into_iter()
+                .map(|(kind, run)| match kind {
+                    WordChange::Same(_) => run,
+                    WordChange::Removed(_) => format!("[-{}-]", run),
+                    WordChange::Added(_) => format!("{{+{}+}}", run),
+                })
+                .collect();
+            text.push_str(&format!(
+                "{} [{}-{}] {}\n",
+                if segment.differs() { "!" } else { " " },
+                format_timestamp(segment.start_secs),
+                format_timestamp(segment.end_secs),
+                words.join(" ")
+            ));
+        }
+        text
+    }
+
+    /// Standalone HTML page with removed words struck out and added words highlighted
+    pub fn to_html(&self) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+            <title>{left} vs {right}</title>\n\
+            <style>\n\
+            body {{ font-family: sans-serif; }}\n\
+            td {{ padding: 2px 8px; vertical-align: top; }}\n\
+            tr.differs {{ background: #fff8e0; }}\n\
+            del {{ background: #fdd; }}\n\
+            ins {{ background: #dfd; text-decoration: none; }}\n\
+            </style>\n</head>\n<body>\n\
+            <h1><del>{left}</del> vs <ins>{right}</ins></h1>\n\
+            <p>{summary}</p>\n<table>\n",
+            left = escape_html(&self.left_label),
+            right = escape_html(&self.right_label),
+            summary = escape_html(&self.summary())
+        );
+        for segment in &self.segments {
+            let words: Vec<String> = word_runs(&segment.words)
+                .into_iter()
+                .map(|(kind, run)| match kind {
+                    WordChange::Same(_) => escape_html(&run),
+                    WordChange::Removed(_) => format!("<del>{}</del>", escape_html(&run)),
+                    WordChange::Added(_) => format!("<ins>{}</ins>", escape_html(&run)),
+                })
+                .collect();
+            html.push_str(&format!(
+                "<tr{}><td>{}-{}</td><td>{}</td></tr>\n",
+                if segment.differs() {
+                    " class=\"differs\""
+                } else {
+                    ""
+                },
+                format_timestamp(segment.start_secs),
+                format_timestamp(segment.end_secs),
+                words.join(" ")
+            ));
+        }
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcribe::TranscribedSegment;
+
+    fn transcription(model: &str, segments: &[(f32, f32, &str)]) -> UserTranscription {
+        UserTranscription {
+            user_id: 1,
+            display_name: "Alice".to_string(),
+            model: model.to_string(),
+            total_duration_secs: 0.0,
+            chunk_transcriptions: vec![],
+            all_segments: segments
+                .iter()
+                .map(|&(start_secs, end_secs, text)| TranscribedSegment {
+                    start_secs,
+                    end_secs,
+                    text: text.to_string(),
+                    speaker_id: None,
+                    speaker_name: None,
+                    translation: None,
+                })
+                .collect(),
+            full_transcript: String::new(),
+            locked_language: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_transcripts_aligns_by_overlap() {
+        let small = transcription(
+            "small",
+            &[(0.0, 2.0, "hello wrold again"), (5.0, 7.0, "same text")],
+        );
+        // large splits the first sentence in two and hears an extra line
+        let large = transcription(
+            "large",
+            &[
+                (0.0, 1.0, "hello"),
+                (1.0, 2.2, "world again"),
+                (5.0, 7.0, "Same text."),
+                (9.0, 10.0, "a < b"),
+            ],
+        );
+
+        let diff = diff_transcripts(&small, &large);
+        assert_eq!(diff.segments.len(), 3);
+        assert_eq!(
+            diff.segments[0].words,
+            vec![
+                WordChange::Same("hello".to_string()),
+                WordChange::Removed("wrold".to_string()),
+                WordChange::Added("world".to_string()),
+                WordChange::Same("again".to_string()),
+            ]
+        );
+        // Capitalization and punctuation aren't differences
+        assert!(!diff.segments[1].differs());
+        assert_eq!(diff.differing_segments(), 2);
+        // One substitution and three inserted words against five words of small
+        assert!((diff.changed_word_percent() - 80.0).abs() < 1e-4);
+
+        let text = diff.to_text();
+        assert!(text.starts_with("Compare: [-small-] vs {+large+}\n2 of 3 segments differ"));
+        assert!(text.contains("! [00:00-00:02] hello [-wrold-] {+world+} again\n"));
+        assert!(text.contains("  [00:05-00:07] Same text.\n"));
+        assert!(text.contains("! [00:09-00:10] {+a < b+}\n"));
+
+        let html = diff.to_html();
+        assert!(html.contains("<td>hello <del>wrold</del> <ins>world</ins> again</td>"));
+        assert!(html.contains("<ins>a &lt; b</ins>"));
+    }
+}
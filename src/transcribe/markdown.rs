@@ -0,0 +1,367 @@
+use super::{TranscribedSegment, UserTranscription, format_timestamp};
+use crate::voice::clock::tick_offset_secs;
+
+/// Pause after which a speaker's next words start a new paragraph
+pub const DEFAULT_TURN_GAP_SECS: f32 = 2.0;
+/// Segment times are approximate, a start this close to the other speaker's end isn't an interruption
+const OVERLAP_TOLERANCE_SECS: f32 = 0.25;
+
+/// How the session-wide transcript groups segments into paragraphs
+#[derive(Debug, Clone, Copy)]
+pub struct ConversationConfig {
+    /// A speaker's segments closer together than this stay in one paragraph
+    pub turn_gap_secs: f32,
+    /// Note when a paragraph starts while someone else is still talking
+    pub mark_overlaps: bool,
+}
+
+impl ConversationConfig {
+    /// Config with a turn gap that is a finite, non-negative number of seconds
+    pub fn new(turn_gap_secs: f32, mark_overlaps: bool) -> Result<Self, String> {
+        if !turn_gap_secs.is_finite() || turn_gap_secs < 0.0 {
+            return Err(format!(
+                "Invalid turn gap: {}. Use a number of seconds of 0 or more",
+                turn_gap_secs
+            ));
+        }
+        Ok(Self {
+            turn_gap_secs,
+            mark_overlaps,
+        })
+    }
+}
+
+impl Default for ConversationConfig {
+    fn default() -> Self {
+        Self {
+            turn_gap_secs: DEFAULT_TURN_GAP_SECS,
+            mark_overlaps: true,
+        }
+    }
+}
+
+/// One speaker's paragraph on the session timeline
+struct Paragraph<'a> {
+    start_secs: f32,
+    end_secs: f32,
+    speaker: &'a str,
+    /// The previous paragraph was someone else's, so this one names its speaker
+    speaker_changed: bool,
+    /// Who was still talking when this paragraph started
+    interrupts: Option<&'a str>,
+    segments: Vec<&'a TranscribedSegment>,
+}
+
+impl Paragraph<'_> {
+    fn text(&self, escape: fn(&str) -> String) -> String {
+        let texts: Vec<String> = self
+            .segments
+            .iter()
+            .map(|s| escape(s.text.trim()))
+            .collect();
+        texts.join(" ")
+    }
+
+    fn translation(&self, escape: fn(&str) -> String) -> Option<String> {
+        let translations: Vec<String> = self
+            .segments
+            .iter()
+            .filter_map(|s| s.translation.as_deref())
+            .map(|t| escape(t.trim()))
+            .collect();
+        (!translations.is_empty()).then(|| translations.join(" "))
+    }
+}
+
+/// All segments in order of their start, grouped into paragraphs
+///
+/// The speaker is only named when it changes; their next words stay in the same
+/// paragraph unless they paused for `turn_gap_secs`.
+fn conversation<'a>(
+    tracks: &[(&'a UserTranscription, u64)],
+    config: &ConversationConfig,
+) -> Vec<Paragraph<'a>> {
+    let origin_tick = tracks.iter().map(|(_, tick)| *tick).min().unwrap_or(0);
+    let mut segments: Vec<(f32, f32, &str, &TranscribedSegment)> = tracks
+        .iter()
+        .flat_map(|(transcription, first_tick)| {
+            let offset = tick_offset_secs(*first_tick, origin_tick);
+            transcription.all_segments.iter().map(move |segment| {
+                (
+                    offset + segment.start_secs,
+                    offset + segment.end_secs,
+                    transcription.display_name.as_str(),
+                    segment,
+                )
+            })
+        })
+        .collect();
+    segments.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut paragraphs: Vec<Paragraph> = Vec::new();
+    for (start_secs, end_secs, speaker, segment) in segments {
+        if let Some(last) = paragraphs.last_mut()
+            && last.speaker == speaker
+            && start_secs - last.end_secs < config.turn_gap_secs
+        {
+            last.end_secs = last.end_secs.max(end_secs);
+            last.segments.push(segment);
+            continue;
+        }
+
+        let interrupts = paragraphs
+            .iter()
+            .rev()
+            .find(|p| p.speaker != speaker && p.end_secs > start_secs + OVERLAP_TOLERANCE_SECS)
+            .map(|p| p.speaker);
+        paragraphs.push(Paragraph {
+            start_secs,
+            end_secs,
+            speaker,
+            speaker_changed: paragraphs.last().is_none_or(|p| p.speaker != speaker),
+            interrupts: interrupts.filter(|_| config.mark_overlaps),
+            segments: vec![segment],
+        });
+    }
+    paragraphs
+}
+
+/// Backslash-escape characters Markdown would turn into formatting, links or HTML
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '|' | '~' | '#'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// `- **[MM:SS] Name:** text` for one segment
+fn segment_line(start_secs: f32, speaker: &str, segment: &TranscribedSegment) -> String {
+    let mut line = format!(
+        "- **[{}] {}:** {}\n",
+        format_timestamp(start_secs),
+        escape_markdown(speaker),
+        escape_markdown(&segment.text)
+    );
+    if let Some(translation) = &segment.translation {
+        line.push_str(&format!("  _{}_\n", escape_markdown(translation)));
+    }
+    line
+}
+
+/// Markdown transcript for pasting into wikis and notes
+///
+/// Each track is a user's transcription with the first tick of their audio,
+/// placed on the session timeline like the chapters and VTT. Paragraphs are in
+/// speaking order, or with `by_speaker` in one collapsible section per speaker.
+pub fn transcript_markdown(
+    title: &str,
+    tracks: &[(&UserTranscription, u64)],
+    by_speaker: bool,
+    config: &ConversationConfig,
+) -> String {
+    let origin_tick = tracks.iter().map(|(_, tick)| *tick).min().unwrap_or(0);
+    let offset_secs = |first_tick: u64| tick_offset_secs(first_tick, origin_tick);
+
+    let speakers: Vec<String> = tracks
+        .iter()
+        .map(|(t, _)| escape_markdown(&t.display_name))
+        .collect();
+    let duration_secs = tracks
+        .iter()
+        .filter_map(|(t, first_tick)| {
+            t.all_segments
+                .last()
+                .map(|segment| offset_secs(*first_tick) + segment.end_secs)
+        })
+        .fold(0.0, f32::max);
+    let mut models: Vec<&str> = tracks.iter().map(|(t, _)| t.model.as_str()).collect();
+    models.dedup();
+
+    let mut md = format!(
+        "# Transcript: {}\n\n\
+        - **Speakers:** {}\n\
+        - **Duration:** {}\n\
+        - **Model:** {}\n\n",
+        escape_markdown(title),
+        speakers.join(", "),
+        format_timestamp(duration_secs),
+        escape_markdown(&models.join(", "))
+    );
+
+    if by_speaker {
+        for (transcription, first_tick) in tracks {
+            md.push_str(&format!(
+                "<details>\n<summary>{} ({} segments)</summary>\n\n",
+                escape_markdown(&transcription.display_name),
+                transcription.all_segments.len()
+            ));
+            for segment in &transcription.all_segments {
+                md.push_str(&segment_line(
+                    offset_secs(*first_tick) + segment.start_secs,
+                    &transcription.display_name,
+                    segment,
+                ));
+            }
+            md.push_str("\n</details>\n\n");
+        }
+        return md;
+    }
+
+    for paragraph in conversation(tracks, config) {
+        let label = if paragraph.speaker_changed {
+            format!(
+                "**[{}] {}:**",
+                format_timestamp(paragraph.start_secs),
+                escape_markdown(paragraph.speaker)
+            )
+        } else {
+            format!("**[{}]**", format_timestamp(paragraph.start_secs))
+        };
+        let marker = paragraph
+            .interrupts
+            .map(|other| format!("_(interrupting {})_ ", escape_markdown(other)))
+            .unwrap_or_default();
+        md.push_str(&format!(
+            "- {} {}{}\n",
+            label,
+            marker,
+            paragraph.text(escape_markdown)
+        ));
+        if let Some(translation) = paragraph.translation(escape_markdown) {
+            md.push_str(&format!("  _{}_\n", translation));
+        }
+    }
+    md
+}
+
+/// Plain text transcript of the whole session, one paragraph per line
+///
+/// Same order and paragraphs as the Markdown transcript:
+/// `[MM:SS] Name: (interrupting Other) text`, without the name while the speaker stays the same.
+pub fn transcript_text(
+    tracks: &[(&UserTranscription, u64)],
+    config: &ConversationConfig,
+) -> String {
+    let mut text = String::new();
+    for paragraph in conversation(tracks, config) {
+        text.push_str(&format!("[{}] ", format_timestamp(paragraph.start_secs)));
+        if paragraph.speaker_changed {
+            text.push_str(&format!("{}: ", paragraph.speaker));
+        }
+        if let Some(other) = paragraph.interrupts {
+            text.push_str(&format!("(interrupting {}) ", other));
+        }
+        text.push_str(&paragraph.text(str::to_string));
+        text.push('\n');
+        if let Some(translation) = paragraph.translation(str::to_string) {
+            text.push_str(&format!("    {}\n", translation));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcription(user_id: u64, name: &str, segments: &[(f32, f32, &str)]) -> UserTranscription {
+        UserTranscription {
+            user_id,
+            display_name: name.to_string(),
+            model: "small".to_string(),
+            total_duration_secs: 0.0,
+            chunk_transcriptions: vec![],
+            all_segments: segments
+                .iter()
+                .map(|&(start_secs, end_secs, text)| TranscribedSegment {
+                    start_secs,
+                    end_secs,
+                    text: text.to_string(),
+                    speaker_id: None,
+                    speaker_name: None,
+                    translation: None,
+                })
+                .collect(),
+            full_transcript: String::new(),
+            locked_language: None,
+        }
+    }
+
+    #[test]
+    fn test_transcript_markdown() {
+        let alice = transcription(1, "Alice", &[(0.0, 2.0, "Hi *all*"), (70.0, 75.0, "bye")]);
+        // Bob's audio starts two seconds (100 ticks) after Alice's
+        let bob = transcription(2, "bob_the_builder", &[(1.0, 3.0, "see [docs]")]);
+
+        let config = ConversationConfig::default();
+        let md = transcript_markdown(
+            "2026_01_03_18_49_53",
+            &[(&alice, 100), (&bob, 200)],
+            false,
+            &config,
+        );
+
+        assert!(md.starts_with("# Transcript: 2026\\_01\\_03\\_18\\_49\\_53\n\n"));
+        assert!(md.contains("- **Speakers:** Alice, bob\\_the\\_builder\n"));
+        assert!(md.contains("- **Duration:** 01:15\n"));
+        assert!(md.contains("- **[00:00] Alice:** Hi \\*all\\*\n"));
+        assert!(md.contains("- **[00:03] bob\\_the\\_builder:** see \\[docs\\]\n"));
+        assert!(md.find("Hi \\*all\\*").unwrap() < md.find("see \\[docs\\]").unwrap());
+        assert!(md.find("see \\[docs\\]").unwrap() < md.find("bye").unwrap());
+        assert!(!md.contains("<details>"));
+
+        let grouped = transcript_markdown("meeting", &[(&alice, 100), (&bob, 200)], true, &config);
+        assert!(
+            grouped.contains(
+                "<details>\n<summary>Alice (2 segments)</summary>\n\n- **[00:00] Alice:**"
+            )
+        );
+        assert_eq!(grouped.matches("</details>").count(), 2);
+    }
+
+    #[test]
+    fn test_conversation_paragraphs_and_interruptions() {
+        let alice = transcription(
+            1,
+            "Alice",
+            &[
+                (0.0, 4.0, " So the plan"),
+                (4.5, 7.0, "is simple"),
+                (7.5, 9.0, "as I said"),
+                (20.0, 21.0, "anyway"),
+            ],
+        );
+        let bob = transcription(2, "Bob", &[(5.0, 6.0, "wait")]);
+        let tracks = [(&alice, 0), (&bob, 0)];
+
+        let text = transcript_text(&tracks, &ConversationConfig::default());
+        assert_eq!(
+            text,
+            "[00:00] Alice: So the plan is simple\n\
+             [00:05] Bob: (interrupting Alice) wait\n\
+             [00:07] Alice: as I said\n\
+             [00:20] anyway\n"
+        );
+
+        let md = transcript_markdown("s", &tracks, false, &ConversationConfig::default());
+        assert!(md.contains("- **[00:05] Bob:** _(interrupting Alice)_ wait\n"));
+        assert!(md.contains("- **[00:20]** anyway\n"));
+
+        // A larger gap keeps Alice's last words in her paragraph, overlaps can go unmarked
+        let config = ConversationConfig::new(15.0, false).unwrap();
+        let text = transcript_text(&tracks, &config);
+        assert!(text.contains("[00:05] Bob: wait\n[00:07] Alice: as I said anyway\n"));
+
+        assert!(ConversationConfig::new(0.0, true).is_ok());
+        assert!(ConversationConfig::new(-1.0, true).is_err());
+        assert!(ConversationConfig::new(f32::NAN, true).is_err());
+        assert!(ConversationConfig::new(f32::INFINITY, true).is_err());
+    }
+}
@@ -1,14 +1,41 @@
+mod backend;
+mod chapters;
+mod diff;
+mod markdown;
+mod openai;
 mod prepare;
+mod speaker;
+mod stats;
+mod time_range;
+mod vtt;
 mod whisper;
 
 pub use prepare::{
-    AudioChunk, PreparedAudio, TranscribeError, 
-    MIN_SILENCE_DURATION_SECS, WHISPER_SAMPLE_RATE,
-    group_ssrcs_by_user, load_ssrc_map, load_user_audio_for_transcription,
-    prepare_session_for_transcription,
+    AudioChunk, AudioFormat, ChunkFormat, PreparedAudio, TranscribeError, UserFrames,
+    DEFAULT_CHUNK_OVERLAP_SECS, DEFAULT_MIN_CHUNK_SECS, MIN_SILENCE_DURATION_SECS, WHISPER_SAMPLE_RATE,
+    group_ssrcs_by_user, load_ssrc_map, load_user_audio_original_rate,
+    load_session_frames, mix_prepared_audio, prepare_for_transcription, prepare_session_for_transcription,
 };
 
+pub use backend::{BackendKind, TranscriptionBackend};
+
+pub use openai::OpenAiBackend;
+
+pub use chapters::{format_timestamp, matroska_chapters_xml, speaker_turns};
+
+pub use stats::{SpeakingStats, speaking_stats};
+
+pub use speaker::{SpeakerActivity, attribute_speakers, attribute_speakers_by_overlap};
+
+pub use diff::diff_transcripts;
+
+pub use time_range::TimeBound;
+
+pub use markdown::{ConversationConfig, transcript_markdown, transcript_text};
+
+pub use vtt::{segments_from_srt, segments_from_vtt, session_vtt, user_srt, user_vtt};
+
 pub use whisper::{
-    ChunkTranscription, LanguageConfig, Transcriber, TranscribedSegment, UserTranscription,
-    WhisperError, WhisperModel, download_model, is_model_downloaded, model_path,
+    ChunkTranscription, DecodingConfig, HallucinationConfig, LanguageConfig, LanguageStrategy, Transcriber, TranscribedSegment, UserTranscription,
+    WhisperError, WhisperModel, download_model_with_progress, is_model_downloaded, model_path,
 };
@@ -0,0 +1,215 @@
+use super::whisper::attach_translations;
+use super::{
+    AudioChunk, ChunkTranscription, LanguageConfig, TranscribedSegment, TranscriptionBackend,
+    WhisperError,
+};
+use crate::voice::audio::ClipMode;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{info, warn};
+
+const TRANSCRIPTIONS_URL: &str = "https://api.openai.com/v1/audio/transcriptions";
+const TRANSLATIONS_URL: &str = "https://api.openai.com/v1/audio/translations";
+const OPENAI_MODEL: &str = "whisper-1";
+/// The API rejects larger uploads; chunking keeps WAVs far below this
+const MAX_UPLOAD_BYTES: usize = 25 * 1024 * 1024;
+/// Attempts per chunk when rate limited or the API is unavailable
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Deserialize)]
+struct VerboseTranscription {
+    text: String,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    segments: Vec<VerboseSegment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VerboseSegment {
+    start: f32,
+    end: f32,
+    text: String,
+}
+
+/// Transcription through OpenAI's hosted Whisper API
+///
+/// Every chunk's audio is uploaded to OpenAI, so recordings leave this machine
+/// and are handled under OpenAI's data retention policy. Only use it when all
+/// participants agreed to that.
+pub struct OpenAiBackend {
+    api_key: String,
+    language_config: LanguageConfig,
+    cancel_flag: Option<Arc<AtomicBool>>,
+}
+
+impl OpenAiBackend {
+    /// Create a backend using the API key in `OPENAI_API_KEY`
+    pub fn from_env(language_config: LanguageConfig) -> Result<Self, WhisperError> {
+        let api_key = std::env::var("OPENAI_API_KEY")
+            .ok()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| WhisperError::Init("OPENAI_API_KEY is not set".to_string()))?;
+
+        Ok(Self {
+            api_key,
+            language_config,
+            cancel_flag: None,
+        })
+    }
+
+    /// Stop transcribing between chunks once `flag` is set
+    pub fn with_cancel_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancel_flag = Some(flag);
+        self
+    }
+
+    /// Upload one WAV, retrying with exponential backoff on rate limits and server errors
+    fn request(&self, wav: Vec<u8>, translate: bool) -> Result<VerboseTranscription, WhisperError> {
+        let url = if translate {
+            TRANSLATIONS_URL
+        } else {
+            TRANSCRIPTIONS_URL
+        };
+
+        // A blocking client must not outlive the blocking section it's used in
+        let client = reqwest::blocking::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| {
+                WhisperError::Transcription(format!("Failed to build HTTP client: {}", e))
+            })?;
+
+        let mut backoff = INITIAL_BACKOFF;
+        for attempt in 1..=MAX_ATTEMPTS {
+            let part = reqwest::blocking::multipart::Part::bytes(wav.clone())
+                .file_name("chunk.wav")
+                .mime_str("audio/wav")
+                .map_err(|e| WhisperError::Transcription(e.to_string()))?;
+            let mut form = reqwest::blocking::multipart::Form::new()
+                .text("model", OPENAI_MODEL)
+                .text("response_format", "verbose_json")
+                .part("file", part);
+            if let Some(language) = &self.language_config.language
+                && !translate
+            {
+                form = form.text("language", language.clone());
+            }
+
+            let response = client
+                .post(url)
+                .bearer_auth(&self.api_key)
+                .multipart(form)
+                .send()
+                .map_err(|e| {
+                    WhisperError::Transcription(format!("OpenAI request failed: {}", e))
+                })?;
+
+            let status = response.status();
+            if status.is_success() {
+                return response.json().map_err(|e| {
+                    WhisperError::Transcription(format!("Invalid OpenAI response: {}", e))
+                });
+            }
+
+            let retryable =
+                status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+            if !retryable || attempt == MAX_ATTEMPTS {
+                let body = response.text().unwrap_or_default();
+                return Err(WhisperError::Transcription(format!(
+                    "OpenAI returned {}: {}",
+                    status, body
+                )));
+            }
+
+            let wait = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(backoff);
+            warn!(
+                "OpenAI returned {} (attempt {}/{}), retrying in {:?}",
+                status, attempt, MAX_ATTEMPTS, wait
+            );
+            std::thread::sleep(wait);
+            backoff *= 2;
+        }
+
+        unreachable!("the last attempt always returns")
+    }
+}
+
+fn to_segments(segments: Vec<VerboseSegment>) -> Vec<TranscribedSegment> {
+    segments
+        .into_iter()
+        .map(|s| TranscribedSegment {
+            start_secs: s.start,
+            end_secs: s.end,
+            text: s.text.trim().to_string(),
+            speaker_id: None,
+            speaker_name: None,
+            translation: None,
+        })
+        .filter(|s| !s.text.is_empty())
+        .collect()
+}
+
+impl TranscriptionBackend for OpenAiBackend {
+    fn name(&self) -> String {
+        format!("openai-{}", OPENAI_MODEL)
+    }
+
+    fn transcribe(&self, chunk: &AudioChunk) -> Result<ChunkTranscription, WhisperError> {
+        let wav = chunk.as_wav_bytes(ClipMode::Hard);
+        if wav.len() > MAX_UPLOAD_BYTES {
+            return Err(WhisperError::Transcription(format!(
+                "Chunk {} is {}MB, over OpenAI's 25MB upload limit",
+                chunk.index,
+                wav.len() / (1024 * 1024)
+            )));
+        }
+
+        info!(
+            "Uploading chunk {} ({:.2}s audio, {}KB) to OpenAI",
+            chunk.index,
+            chunk.duration_secs,
+            wav.len() / 1024
+        );
+        let response = self.request(wav.clone(), self.language_config.translate)?;
+
+        let mut segments = to_segments(response.segments);
+        if self
+            .language_config
+            .needs_translation(response.language.as_deref())
+        {
+            match self.request(wav, true) {
+                Ok(translated) => {
+                    attach_translations(&mut segments, &to_segments(translated.segments))
+                }
+                Err(e) => warn!("Failed to translate chunk {}: {}", chunk.index, e),
+            }
+        }
+
+        Ok(ChunkTranscription {
+            chunk_index: chunk.index,
+            chunk_start_secs: chunk.start_time_secs,
+            chunk_end_secs: chunk.end_time_secs,
+            language: response.language,
+            overlap_secs: chunk.overlap_secs,
+            segments,
+            full_text: response.text.trim().to_string(),
+        })
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel_flag
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+}
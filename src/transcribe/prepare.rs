@@ -1,6 +1,13 @@
+use crate::paths::SessionPaths;
+use crate::voice::audio::{
+    AdaptiveVadConfig, ClipMode, SILENCE_DBFS, WavDepth, detect_voice_activity_adaptive, encode_mp3, f32_to_i16,
+    f32_to_i24, i16_to_f32, is_all_silence, is_silent, save_wav,
+};
+use crate::voice::ssrc_history::SsrcHistory;
+use crate::voice::storage::{AudioFrame, compact_frames};
 use std::collections::{BTreeMap, HashMap};
 use std::fs::{self, File};
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use thiserror::Error;
 use tracing::info;
@@ -14,9 +21,14 @@ const SAMPLES_PER_FRAME: usize = 960;
 
 /// Minimum silence duration to split chunks (in seconds)
 pub const MIN_SILENCE_DURATION_SECS: f32 = 2.0;
-/// Silence threshold - samples below this (absolute) are considered silence
-/// This is normalized, so 0.01 = about -40dB
-const SILENCE_THRESHOLD: f32 = 0.01;
+/// Audio from the end of the previous chunk repeated at the start of the next (in seconds)
+/// so words cut at a boundary are still heard whole. That audio is transcribed twice (the
+/// repeated words are dropped afterwards), the total is `overlap_audio_secs` in timing.json
+pub const DEFAULT_CHUNK_OVERLAP_SECS: f32 = 0.2;
+/// Chunks shorter than this (in seconds) are merged into the previous chunk or dropped
+pub const DEFAULT_MIN_CHUNK_SECS: f32 = 0.5;
+/// Silence between chunks (in seconds) that `merge_short_chunks` never bridges
+pub const MAX_MERGE_GAP_SECS: f32 = 3.0;
 /// Window size for silence detection (in samples at 16kHz)
 const SILENCE_WINDOW_SIZE: usize = 1600; // 100ms windows
 
@@ -70,21 +82,83 @@ pub struct AudioChunk {
     pub end_time_secs: f32,
     /// Duration in seconds
     pub duration_secs: f32,
+    /// Leading seconds that repeat the end of the previous chunk
+    pub overlap_secs: f32,
+}
+
+/// File format of the chunks saved with a transcript
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkFormat {
+    #[default]
+    Wav,
+    /// Lossy 96kbps, small enough to attach chunks to a Discord message
+    Mp3,
+}
+
+impl ChunkFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ChunkFormat::Wav => "wav",
+            ChunkFormat::Mp3 => "mp3",
+        }
+    }
+}
+
+impl std::str::FromStr for ChunkFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "wav" => Ok(ChunkFormat::Wav),
+            "mp3" => Ok(ChunkFormat::Mp3),
+            _ => Err(format!("Unknown chunk format: {}. Use wav or mp3", s)),
+        }
+    }
 }
 
 impl AudioChunk {
-    /// Get the audio as WAV bytes
-    pub fn as_wav_bytes(&self) -> Vec<u8> {
-        samples_to_wav_bytes(&self.samples)
+    /// Get the audio as WAV bytes, with overs brought into range by `clip`
+    pub fn as_wav_bytes(&self, clip: ClipMode) -> Vec<u8> {
+        samples_to_wav_bytes(&self.samples, WHISPER_SAMPLE_RATE, WavDepth::Int16, clip)
+    }
+
+    /// Encode the audio as a file in `format`, with overs brought into range by `clip`
+    pub fn encode(&self, format: ChunkFormat, clip: ClipMode) -> io::Result<Vec<u8>> {
+        match format {
+            ChunkFormat::Wav => Ok(self.as_wav_bytes(clip)),
+            ChunkFormat::Mp3 => {
+                let samples: Vec<i16> = self.samples.iter().map(|&s| f32_to_i16(clip.apply(s))).collect();
+                encode_mp3(&samples, WHISPER_SAMPLE_RATE, 1)
+            }
+        }
+    }
+
+    /// Save this chunk's span of the user's 48kHz audio as a WAV file, without resampling
+    ///
+    /// `audio_48k` is the user's audio from `load_user_audio_original_rate`,
+    /// which starts at the same tick as the audio the chunk was cut from. The
+    /// speech profile, if any, only applies to the 16kHz chunk.
+    pub fn save_original_rate(&self, path: &Path, audio_48k: &[i16]) -> Result<(), hound::Error> {
+        let to_index = |secs: f32| ((secs * SOURCE_SAMPLE_RATE as f32).round() as usize).min(audio_48k.len());
+        let (start, end) = (to_index(self.start_time_secs), to_index(self.end_time_secs));
+        save_wav(path, &audio_48k[start..end.max(start)], SOURCE_SAMPLE_RATE, 1, WavDepth::Int16)
     }
 }
 
-/// Convert samples to WAV bytes
-fn samples_to_wav_bytes(samples: &[f32]) -> Vec<u8> {
+/// Convert mono samples to WAV bytes at the given rate and depth
+///
+/// Integer depths bring samples beyond full scale into range with `clip`,
+/// `Float32` keeps them as they are.
+fn samples_to_wav_bytes(samples: &[f32], sample_rate: u32, depth: WavDepth, clip: ClipMode) -> Vec<u8> {
     let mut buffer = Vec::new();
     
-    let data_size = (samples.len() * 2) as u32;
+    let bytes_per_sample = depth.bits_per_sample() / 8;
+    let data_size = samples.len() as u32 * bytes_per_sample as u32;
     let file_size = 36 + data_size;
+    let format_tag: u16 = match depth {
+        WavDepth::Float32 => 3, // IEEE float
+        WavDepth::Int16 | WavDepth::Int24 => 1, // PCM
+    };
     
     // RIFF header
     buffer.extend_from_slice(b"RIFF");
@@ -94,51 +168,59 @@ fn samples_to_wav_bytes(samples: &[f32]) -> Vec<u8> {
     // fmt chunk
     buffer.extend_from_slice(b"fmt ");
     buffer.extend_from_slice(&16u32.to_le_bytes());
-    buffer.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    buffer.extend_from_slice(&format_tag.to_le_bytes());
     buffer.extend_from_slice(&1u16.to_le_bytes()); // mono
-    buffer.extend_from_slice(&WHISPER_SAMPLE_RATE.to_le_bytes());
-    buffer.extend_from_slice(&(WHISPER_SAMPLE_RATE * 2).to_le_bytes());
-    buffer.extend_from_slice(&2u16.to_le_bytes());
-    buffer.extend_from_slice(&16u16.to_le_bytes());
+    buffer.extend_from_slice(&sample_rate.to_le_bytes());
+    buffer.extend_from_slice(&(sample_rate * bytes_per_sample as u32).to_le_bytes());
+    buffer.extend_from_slice(&bytes_per_sample.to_le_bytes());
+    buffer.extend_from_slice(&depth.bits_per_sample().to_le_bytes());
     
     // data chunk
     buffer.extend_from_slice(b"data");
     buffer.extend_from_slice(&data_size.to_le_bytes());
     
-    for sample in samples {
-        let clamped = sample.clamp(-1.0, 1.0);
-        let i16_sample = (clamped * 32767.0) as i16;
-        buffer.extend_from_slice(&i16_sample.to_le_bytes());
+    for &sample in samples {
+        match depth {
+            WavDepth::Int16 => buffer.extend_from_slice(&f32_to_i16(clip.apply(sample)).to_le_bytes()),
+            WavDepth::Int24 => {
+                buffer.extend_from_slice(&f32_to_i24(clip.apply(sample)).to_le_bytes()[..3]);
+            }
+            WavDepth::Float32 => buffer.extend_from_slice(&sample.to_le_bytes()),
+        }
     }
     
     buffer
 }
 
-/// Check if a window of samples is silence
+/// Check if a window of samples is silence (RMS below `SILENCE_DBFS`)
 fn is_silence_window(samples: &[f32]) -> bool {
-    if samples.is_empty() {
-        return true;
-    }
-    
-    // Calculate RMS (root mean square) for the window
-    let sum_squares: f32 = samples.iter().map(|s| s * s).sum();
-    let rms = (sum_squares / samples.len() as f32).sqrt();
-    
-    rms < SILENCE_THRESHOLD
+    is_silent(samples, SILENCE_DBFS)
 }
 
 /// Find silence regions in the audio
 /// Returns a list of (start_sample, end_sample) for each silence region >= min_duration
-fn find_silence_regions(samples: &[f32], min_silence_samples: usize) -> Vec<(usize, usize)> {
+///
+/// Windows are judged against the fixed `SILENCE_DBFS`, or against an adaptive
+/// noise floor when a VAD config is given.
+fn find_silence_regions(
+    samples: &[f32],
+    min_silence_samples: usize,
+    vad: Option<&AdaptiveVadConfig>,
+) -> Vec<(usize, usize)> {
+    let silent_windows: Vec<bool> = match vad {
+        Some(config) => detect_voice_activity_adaptive(samples, SILENCE_WINDOW_SIZE, config)
+            .into_iter()
+            .map(|active| !active)
+            .collect(),
+        None => samples.chunks(SILENCE_WINDOW_SIZE).map(is_silence_window).collect(),
+    };
+
     let mut regions = Vec::new();
     let mut in_silence = false;
     let mut silence_start = 0;
     
-    let mut i = 0;
-    while i < samples.len() {
-        let window_end = (i + SILENCE_WINDOW_SIZE).min(samples.len());
-        let window = &samples[i..window_end];
-        let is_silent = is_silence_window(window);
+    for (window_index, &is_silent) in silent_windows.iter().enumerate() {
+        let i = window_index * SILENCE_WINDOW_SIZE;
         
         if is_silent && !in_silence {
             // Start of silence region
@@ -152,8 +234,6 @@ fn find_silence_regions(samples: &[f32], min_silence_samples: usize) -> Vec<(usi
                 regions.push((silence_start, i));
             }
         }
-        
-        i += SILENCE_WINDOW_SIZE;
     }
     
     // Handle case where audio ends in silence
@@ -168,13 +248,22 @@ fn find_silence_regions(samples: &[f32], min_silence_samples: usize) -> Vec<(usi
 }
 
 /// Split samples into chunks based on silence regions
-fn split_on_silence(samples: &[f32], min_silence_secs: f32) -> Vec<AudioChunk> {
+///
+/// Speech shorter than `min_chunk_secs` (a quick "yes" between pauses) is
+/// appended to the chunk right before it, and only dropped when there is none.
+fn split_on_silence(
+    samples: &[f32],
+    min_silence_secs: f32,
+    min_chunk_secs: f32,
+    overlap_secs: f32,
+    vad: Option<&AdaptiveVadConfig>,
+) -> Vec<AudioChunk> {
     if samples.is_empty() {
         return Vec::new();
     }
     
     let min_silence_samples = (min_silence_secs * WHISPER_SAMPLE_RATE as f32) as usize;
-    let silence_regions = find_silence_regions(samples, min_silence_samples);
+    let silence_regions = find_silence_regions(samples, min_silence_samples, vad);
     
     if silence_regions.is_empty() {
         // No silence gaps found, return whole audio as single chunk
@@ -184,33 +273,32 @@ fn split_on_silence(samples: &[f32], min_silence_secs: f32) -> Vec<AudioChunk> {
             start_time_secs: 0.0,
             end_time_secs: samples.len() as f32 / WHISPER_SAMPLE_RATE as f32,
             duration_secs: samples.len() as f32 / WHISPER_SAMPLE_RATE as f32,
+            overlap_secs: 0.0,
         }];
     }
     
-    let mut chunks = Vec::new();
+    let overlap_samples = (overlap_secs.max(0.0) * WHISPER_SAMPLE_RATE as f32) as usize;
+    let min_chunk_samples = (min_chunk_secs.max(0.0) * WHISPER_SAMPLE_RATE as f32) as usize;
+    let mut chunks: Vec<AudioChunk> = Vec::new();
     let mut chunk_start = 0;
+    // End of the last emitted chunk, overlap never reaches further back than this
+    let mut previous_end: Option<usize> = None;
     
     for (silence_start, silence_end) in &silence_regions {
         // Create chunk from chunk_start to middle of silence region
         let split_point = silence_start + (silence_end - silence_start) / 2;
         
         if split_point > chunk_start {
-            let chunk_samples = samples[chunk_start..split_point].to_vec();
+            let chunk_samples = &samples[chunk_start..split_point];
             
-            // Skip chunks that are too short (< 0.5 seconds) or all silence
-            if chunk_samples.len() >= (WHISPER_SAMPLE_RATE / 2) as usize 
-                && !is_silence_window(&chunk_samples) 
-            {
-                let start_time = chunk_start as f32 / WHISPER_SAMPLE_RATE as f32;
-                let end_time = split_point as f32 / WHISPER_SAMPLE_RATE as f32;
-                
-                chunks.push(AudioChunk {
-                    index: chunks.len(),
-                    samples: chunk_samples,
-                    start_time_secs: start_time,
-                    end_time_secs: end_time,
-                    duration_secs: end_time - start_time,
-                });
+            // Skip chunks that are all silence, merge short ones into the previous chunk
+            if is_silence_window(chunk_samples) {
+                // Nothing to transcribe
+            } else if chunk_samples.len() >= min_chunk_samples {
+                chunks.push(overlapping_chunk(samples, chunk_start, split_point, previous_end, overlap_samples));
+                previous_end = Some(split_point);
+            } else if merge_into_previous(&mut chunks, samples, chunk_start, split_point, previous_end) {
+                previous_end = Some(split_point);
             }
         }
         
@@ -219,21 +307,14 @@ fn split_on_silence(samples: &[f32], min_silence_secs: f32) -> Vec<AudioChunk> {
     
     // Handle remaining audio after last silence
     if chunk_start < samples.len() {
-        let chunk_samples = samples[chunk_start..].to_vec();
+        let chunk_samples = &samples[chunk_start..];
         
-        if chunk_samples.len() >= (WHISPER_SAMPLE_RATE / 2) as usize
-            && !is_silence_window(&chunk_samples)
-        {
-            let start_time = chunk_start as f32 / WHISPER_SAMPLE_RATE as f32;
-            let end_time = samples.len() as f32 / WHISPER_SAMPLE_RATE as f32;
-            
-            chunks.push(AudioChunk {
-                index: chunks.len(),
-                samples: chunk_samples,
-                start_time_secs: start_time,
-                end_time_secs: end_time,
-                duration_secs: end_time - start_time,
-            });
+        if is_silence_window(chunk_samples) {
+            // Trailing silence
+        } else if chunk_samples.len() >= min_chunk_samples {
+            chunks.push(overlapping_chunk(samples, chunk_start, samples.len(), previous_end, overlap_samples));
+        } else {
+            merge_into_previous(&mut chunks, samples, chunk_start, samples.len(), previous_end);
         }
     }
     
@@ -245,23 +326,233 @@ fn split_on_silence(samples: &[f32], min_silence_secs: f32) -> Vec<AudioChunk> {
     chunks
 }
 
+/// Extend the last chunk over `start..end` if it ends right at `start`
+///
+/// Returns false (the audio is dropped) when the previous chunk was skipped or there is none.
+fn merge_into_previous(
+    chunks: &mut [AudioChunk],
+    samples: &[f32],
+    start: usize,
+    end: usize,
+    previous_end: Option<usize>,
+) -> bool {
+    match chunks.last_mut() {
+        Some(last) if previous_end == Some(start) => {
+            last.samples.extend_from_slice(&samples[start..end]);
+            last.end_time_secs = end as f32 / WHISPER_SAMPLE_RATE as f32;
+            last.duration_secs = last.end_time_secs - last.start_time_secs;
+            true
+        }
+        _ => {
+            info!(
+                "Dropping {:.2}s of speech at {:.2}s, too short for its own chunk",
+                (end - start) as f32 / WHISPER_SAMPLE_RATE as f32,
+                start as f32 / WHISPER_SAMPLE_RATE as f32
+            );
+            false
+        }
+    }
+}
+
+/// Build a chunk for `start..end`, extended back into the previous chunk by up to `overlap_samples`
+fn overlapping_chunk(
+    samples: &[f32],
+    start: usize,
+    end: usize,
+    previous_end: Option<usize>,
+    overlap_samples: usize,
+) -> AudioChunk {
+    // Only overlap when the previous chunk ends right here (not after a skipped chunk)
+    let overlap = match previous_end {
+        Some(prev) if prev == start => overlap_samples.min(start),
+        _ => 0,
+    };
+    let start = start - overlap;
+
+    let start_time = start as f32 / WHISPER_SAMPLE_RATE as f32;
+    let end_time = end as f32 / WHISPER_SAMPLE_RATE as f32;
+
+    AudioChunk {
+        index: 0, // Re-indexed by the caller
+        samples: samples[start..end].to_vec(),
+        start_time_secs: start_time,
+        end_time_secs: end_time,
+        duration_secs: end_time - start_time,
+        overlap_secs: overlap as f32 / WHISPER_SAMPLE_RATE as f32,
+    }
+}
+
+/// Seconds of silence at the start (or end) of a chunk's samples
+fn silent_edge_secs(samples: &[f32], from_end: bool) -> f32 {
+    let silent = |window: &&[f32]| is_silence_window(window);
+    let len: usize = if from_end {
+        samples.rchunks(SILENCE_WINDOW_SIZE).take_while(silent).map(|w| w.len()).sum()
+    } else {
+        samples.chunks(SILENCE_WINDOW_SIZE).take_while(silent).map(|w| w.len()).sum()
+    };
+    len as f32 / WHISPER_SAMPLE_RATE as f32
+}
+
+/// Extend each chunk by up to `pad_start_secs` before and `pad_end_secs` after
+///
+/// Chunks are split mid-silence, but a soft onset or a trailing consonant can
+/// fall below the silence threshold and sit right at a chunk's edge. Padding
+/// only reaches across silence: never into the speech of the chunk before or
+/// after, nor past the ends of the audio. Leading padding counts as overlap.
+fn pad_chunks(chunks: Vec<AudioChunk>, samples: &[f32], pad_start_secs: f32, pad_end_secs: f32) -> Vec<AudioChunk> {
+    if pad_start_secs <= 0.0 && pad_end_secs <= 0.0 {
+        return chunks;
+    }
+
+    let rate = WHISPER_SAMPLE_RATE as f32;
+    let to_sample = |secs: f32| ((secs * rate).round() as usize).min(samples.len());
+    let pad_start = (pad_start_secs.max(0.0) * rate) as usize;
+    let pad_end = (pad_end_secs.max(0.0) * rate) as usize;
+
+    // Where each chunk's speech starts and ends, before any of them is padded
+    let speech: Vec<(usize, usize)> = chunks
+        .iter()
+        .map(|chunk| {
+            let overlap = (chunk.overlap_secs * rate).round() as usize;
+            let fresh = &chunk.samples[overlap.min(chunk.samples.len())..];
+            (
+                to_sample(chunk.start_time_secs + chunk.overlap_secs + silent_edge_secs(fresh, false)),
+                to_sample(chunk.end_time_secs - silent_edge_secs(&chunk.samples, true)),
+            )
+        })
+        .collect();
+
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let (start, end) = (to_sample(chunk.start_time_secs), to_sample(chunk.end_time_secs));
+            let earliest = if i == 0 { 0 } else { speech[i - 1].1 };
+            let latest = speech.get(i + 1).map_or(samples.len(), |next| next.0);
+            let padded_start = start.saturating_sub(pad_start).max(earliest).min(start);
+            let padded_end = (end + pad_end).min(latest).max(end);
+
+            let start_time = padded_start as f32 / rate;
+            let end_time = padded_end as f32 / rate;
+            AudioChunk {
+                samples: samples[padded_start..padded_end].to_vec(),
+                start_time_secs: start_time,
+                end_time_secs: end_time,
+                duration_secs: end_time - start_time,
+                overlap_secs: chunk.overlap_secs + (start - padded_start) as f32 / rate,
+                ..chunk
+            }
+        })
+        .collect()
+}
+
+/// Join adjacent chunks while the result stays within `target_secs`
+///
+/// Choppy speech splits into many short chunks, and every Whisper call has a
+/// fixed overhead. Chunks are only joined when they touch (no silent chunk was
+/// skipped between them) and the silence at the boundary is at most
+/// `max_gap_secs`, so long pauses still separate chunks.
+fn merge_short_chunks(chunks: Vec<AudioChunk>, target_secs: f32, max_gap_secs: f32) -> Vec<AudioChunk> {
+    let mut merged: Vec<AudioChunk> = Vec::with_capacity(chunks.len());
+
+    for chunk in chunks {
+        let overlap_samples = (chunk.overlap_secs * WHISPER_SAMPLE_RATE as f32).round() as usize;
+        let fresh_samples = &chunk.samples[overlap_samples.min(chunk.samples.len())..];
+
+        if let Some(last) = merged.last_mut() {
+            let touches = (chunk.start_time_secs + chunk.overlap_secs - last.end_time_secs).abs() < 1e-3;
+            let gap_secs = silent_edge_secs(&last.samples, true) + silent_edge_secs(fresh_samples, false);
+            if touches && chunk.end_time_secs - last.start_time_secs <= target_secs && gap_secs <= max_gap_secs {
+                last.samples.extend_from_slice(fresh_samples);
+                last.end_time_secs = chunk.end_time_secs;
+                last.duration_secs = last.end_time_secs - last.start_time_secs;
+                continue;
+            }
+        }
+        merged.push(chunk);
+    }
+
+    for (i, chunk) in merged.iter_mut().enumerate() {
+        chunk.index = i;
+    }
+
+    merged
+}
+
+/// Split chunks longer than `max_secs` into equal pieces, each overlapping the previous by `overlap_secs`
+fn cap_chunk_duration(chunks: Vec<AudioChunk>, max_secs: f32, overlap_secs: f32) -> Vec<AudioChunk> {
+    if max_secs <= 0.0 {
+        return chunks;
+    }
+
+    let overlap_samples = (overlap_secs.max(0.0) * WHISPER_SAMPLE_RATE as f32) as usize;
+    let mut capped = Vec::with_capacity(chunks.len());
+
+    for chunk in chunks {
+        if chunk.duration_secs <= max_secs {
+            capped.push(chunk);
+            continue;
+        }
+
+        let pieces = (chunk.duration_secs / max_secs).ceil() as usize;
+        let piece_len = chunk.samples.len().div_ceil(pieces);
+
+        for piece in 0..pieces {
+            let end = ((piece + 1) * piece_len).min(chunk.samples.len());
+            let (start, overlap_secs) = if piece == 0 {
+                (0, chunk.overlap_secs)
+            } else {
+                let start = piece * piece_len;
+                let overlap = overlap_samples.min(start);
+                (start - overlap, overlap as f32 / WHISPER_SAMPLE_RATE as f32)
+            };
+            if start >= end {
+                break;
+            }
+
+            let start_time = chunk.start_time_secs + start as f32 / WHISPER_SAMPLE_RATE as f32;
+            let end_time = chunk.start_time_secs + end as f32 / WHISPER_SAMPLE_RATE as f32;
+
+            capped.push(AudioChunk {
+                index: 0,
+                samples: chunk.samples[start..end].to_vec(),
+                start_time_secs: start_time,
+                end_time_secs: end_time,
+                duration_secs: end_time - start_time,
+                overlap_secs,
+            });
+        }
+    }
+
+    for (i, chunk) in capped.iter_mut().enumerate() {
+        chunk.index = i;
+    }
+
+    capped
+}
+
 impl PreparedAudio {
-    /// Get the audio as WAV bytes (for file writing or API calls)
-    pub fn as_wav_bytes(&self) -> Vec<u8> {
-        samples_to_wav_bytes(&self.samples_16khz)
+    /// Whether the user only sent silence, e.g. because they stayed muted
+    pub fn is_all_silence(&self) -> bool {
+        is_all_silence(self.samples_16khz.iter().copied())
     }
-    
-    /// Split the audio into chunks based on silence gaps
-    /// 
-    /// Chunks are split when there is silence for at least `min_silence_secs`.
-    /// Each chunk contains timing metadata for later timestamp reconstruction.
-    pub fn split_on_silence(&self, min_silence_secs: f32) -> Vec<AudioChunk> {
+
+    fn split_with_vad(
+        &self,
+        min_silence_secs: f32,
+        min_chunk_secs: f32,
+        overlap_secs: f32,
+        vad: Option<&AdaptiveVadConfig>,
+    ) -> Vec<AudioChunk> {
         info!(
-            "Splitting {:.1}s of audio on silence gaps >= {:.1}s",
-            self.duration_secs, min_silence_secs
+            "Splitting {:.1}s of audio on silence gaps >= {:.1}s ({:.2}s overlap, {} VAD)",
+            self.duration_secs,
+            min_silence_secs,
+            overlap_secs,
+            if vad.is_some() { "adaptive" } else { "fixed" }
         );
         
-        let chunks = split_on_silence(&self.samples_16khz, min_silence_secs);
+        let chunks = split_on_silence(&self.samples_16khz, min_silence_secs, min_chunk_secs, overlap_secs, vad);
         
         info!(
             "Split into {} chunks",
@@ -278,16 +569,96 @@ impl PreparedAudio {
         chunks
     }
     
-    /// Split using the default silence duration (2 seconds)
-    pub fn split_on_silence_default(&self) -> Vec<AudioChunk> {
-        self.split_on_silence(MIN_SILENCE_DURATION_SECS)
+    /// Split on silence with overlap, then cut chunks longer than `max_chunk_secs` into equal pieces
+    ///
+    /// With `vad` set, silence is detected against an adaptive noise floor instead of a fixed threshold.
+    /// With `target_chunk_secs` set, adjacent short chunks are first joined up to that length.
+    pub fn split_for_transcription(
+        &self,
+        min_silence_secs: f32,
+        min_chunk_secs: f32,
+        overlap_secs: f32,
+        max_chunk_secs: f32,
+        target_chunk_secs: Option<f32>,
+        vad: Option<&AdaptiveVadConfig>,
+    ) -> Vec<AudioChunk> {
+        let mut chunks = self.split_with_vad(min_silence_secs, min_chunk_secs, overlap_secs, vad);
+        if let Some(target_secs) = target_chunk_secs {
+            let split_count = chunks.len();
+            chunks = merge_short_chunks(chunks, target_secs.min(max_chunk_secs), MAX_MERGE_GAP_SECS);
+            info!("Merged {} chunks into {} of up to {:.0}s", split_count, chunks.len(), target_secs);
+        }
+        let capped = cap_chunk_duration(chunks, max_chunk_secs, overlap_secs);
+        info!("Capped to {} chunks of at most {:.0}s", capped.len(), max_chunk_secs);
+        capped
+    }
+
+    /// Pad chunks split from this audio into the silence around them, see `pad_chunks`
+    pub fn pad_chunks(&self, chunks: Vec<AudioChunk>, pad_start_secs: f32, pad_end_secs: f32) -> Vec<AudioChunk> {
+        let padded = pad_chunks(chunks, &self.samples_16khz, pad_start_secs, pad_end_secs);
+        if pad_start_secs > 0.0 || pad_end_secs > 0.0 {
+            info!("Padded chunks by up to {:.2}s before and {:.2}s after", pad_start_secs, pad_end_secs);
+        }
+        padded
+    }
+
+    /// Apply the speech intelligibility EQ profile (for muffled low-bitrate audio)
+    pub fn apply_speech_profile(&mut self) {
+        crate::voice::audio::apply_speech_profile(&mut self.samples_16khz, WHISPER_SAMPLE_RATE);
+    }
+
+    /// Time ranges (in seconds from the first tick) with speech between pauses of `min_silence_secs`
+    pub fn speech_ranges(&self, min_silence_secs: f32) -> Vec<(f32, f32)> {
+        let min_silence_samples = (min_silence_secs * WHISPER_SAMPLE_RATE as f32) as usize;
+        let to_secs = |sample: usize| sample as f32 / WHISPER_SAMPLE_RATE as f32;
+
+        let mut ranges = Vec::new();
+        let mut speech_start = 0;
+        for (silence_start, silence_end) in find_silence_regions(&self.samples_16khz, min_silence_samples, None) {
+            if silence_start > speech_start {
+                ranges.push((to_secs(speech_start), to_secs(silence_start)));
+            }
+            speech_start = silence_end;
+        }
+        if speech_start < self.samples_16khz.len() {
+            ranges.push((to_secs(speech_start), to_secs(self.samples_16khz.len())));
+        }
+        ranges
     }
 }
 
-#[derive(Debug)]
-struct AudioFrame {
-    tick_index: u64,
-    samples: Vec<i16>,
+/// Mix every user's audio into one track starting at the earliest first tick
+///
+/// The result belongs to no user (`user_id` 0) and carries all SSRCs. Samples
+/// are summed and clipped, Discord's per-user levels are low enough that
+/// speakers rarely clip each other.
+pub fn mix_prepared_audio(tracks: &[&PreparedAudio]) -> Option<PreparedAudio> {
+    let origin_tick = tracks.iter().map(|t| t.first_tick).min()?;
+    let samples_per_tick = SAMPLES_PER_FRAME * WHISPER_SAMPLE_RATE as usize / SOURCE_SAMPLE_RATE as usize;
+
+    let mut samples: Vec<f32> = Vec::new();
+    for track in tracks {
+        let offset = (track.first_tick - origin_tick) as usize * samples_per_tick;
+        if samples.len() < offset + track.samples_16khz.len() {
+            samples.resize(offset + track.samples_16khz.len(), 0.0);
+        }
+        for (mixed, sample) in samples[offset..].iter_mut().zip(&track.samples_16khz) {
+            *mixed = (*mixed + sample).clamp(-1.0, 1.0);
+        }
+    }
+
+    let mut ssrcs: Vec<u32> = tracks.iter().flat_map(|t| t.ssrcs.iter().copied()).collect();
+    ssrcs.sort_unstable();
+    ssrcs.dedup();
+
+    Some(PreparedAudio {
+        user_id: 0,
+        ssrcs,
+        duration_secs: samples.len() as f32 / WHISPER_SAMPLE_RATE as f32,
+        samples_16khz: samples,
+        first_tick: origin_tick,
+        last_tick: tracks.iter().map(|t| t.last_tick).max().unwrap_or(origin_tick),
+    })
 }
 
 /// Load and parse a single log file
@@ -331,7 +702,7 @@ fn parse_log_file(path: &Path) -> Result<Vec<AudioFrame>, TranscribeError> {
 
 /// Load all chunks for a user directory and return ordered frames
 fn load_user_chunks(user_dir: &Path) -> Result<BTreeMap<u64, Vec<i16>>, TranscribeError> {
-    let mut all_frames: BTreeMap<u64, Vec<i16>> = BTreeMap::new();
+    let mut all_frames = Vec::new();
 
     // Find all chunk-*.log files
     let mut chunk_files: Vec<PathBuf> = fs::read_dir(user_dir)?
@@ -358,25 +729,57 @@ fn load_user_chunks(user_dir: &Path) -> Result<BTreeMap<u64, Vec<i16>>, Transcri
     });
 
     for chunk_path in chunk_files {
-        let frames = parse_log_file(&chunk_path)?;
-        for frame in frames {
-            all_frames.insert(frame.tick_index, frame.samples);
-        }
+        all_frames.extend(parse_log_file(&chunk_path)?);
     }
 
-    Ok(all_frames)
+    // Fragmented ticks would otherwise overwrite each other
+    Ok(compact_frames(all_frames)
+        .into_iter()
+        .map(|frame| (frame.tick_index, frame.samples))
+        .collect())
 }
 
-/// Downsample from 48kHz to 16kHz using averaging
-fn downsample_48k_to_16k(samples_48k: &[i16]) -> Vec<f32> {
-    let ratio = SOURCE_SAMPLE_RATE as usize / WHISPER_SAMPLE_RATE as usize;
-    
-    samples_48k
-        .chunks(ratio)
-        .map(|chunk| {
-            let sum: i32 = chunk.iter().map(|&s| s as i32).sum();
-            let avg = sum / chunk.len() as i32;
-            avg as f32 / 32768.0
+/// Sample rate and channel count of interleaved PCM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl AudioFormat {
+    /// What chunk logs hold, the receiver stores each tick as one mono 48kHz frame
+    pub const RECORDED: AudioFormat = AudioFormat { sample_rate: SOURCE_SAMPLE_RATE, channels: 1 };
+}
+
+/// Convert interleaved PCM in `source_format` to 16kHz mono for Whisper
+///
+/// Channels are averaged. Rates that are a multiple of 16kHz average each group
+/// of samples, any other rate (44.1kHz) is linearly interpolated.
+pub fn prepare_for_transcription(samples: &[i16], source_format: AudioFormat) -> Vec<f32> {
+    let channels = source_format.channels.max(1) as usize;
+    let mono: Vec<f32> = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().map(|&s| i16_to_f32(s)).sum::<f32>() / frame.len() as f32)
+        .collect();
+
+    let rate = source_format.sample_rate;
+    if rate.is_multiple_of(WHISPER_SAMPLE_RATE) {
+        let ratio = (rate / WHISPER_SAMPLE_RATE).max(1) as usize;
+        return mono
+            .chunks(ratio)
+            .map(|group| group.iter().sum::<f32>() / group.len() as f32)
+            .collect();
+    }
+
+    let step = rate as f64 / WHISPER_SAMPLE_RATE as f64;
+    let len = (mono.len() as u64 * WHISPER_SAMPLE_RATE as u64 / rate as u64) as usize;
+    (0..len)
+        .map(|i| {
+            let position = i as f64 * step;
+            let index = position as usize;
+            let fraction = (position - index as f64) as f32;
+            let next = mono.get(index + 1).copied().unwrap_or(mono[index]);
+            mono[index] + (next - mono[index]) * fraction
         })
         .collect()
 }
@@ -389,21 +792,33 @@ fn reconstruct_audio(frames: &BTreeMap<u64, Vec<i16>>) -> (Vec<i16>, u64, u64) {
 
     let first_tick = *frames.keys().next().unwrap();
     let last_tick = *frames.keys().next_back().unwrap();
-    
-    let silence = vec![0i16; SAMPLES_PER_FRAME];
-    let mut audio = Vec::new();
 
-    for tick in first_tick..=last_tick {
-        let samples = frames.get(&tick).unwrap_or(&silence);
-        audio.extend_from_slice(samples);
+    (reconstruct_audio_range(frames, first_tick, last_tick), first_tick, last_tick)
+}
+
+/// Continuous audio for the ticks `start_tick..=end_tick` only, one frame per tick
+///
+/// Only frames inside the window are visited. Ticks without a frame, including
+/// any before the first or after the last frame, become silence.
+fn reconstruct_audio_range(frames: &BTreeMap<u64, Vec<i16>>, start_tick: u64, end_tick: u64) -> Vec<i16> {
+    if start_tick > end_tick {
+        return Vec::new();
+    }
+
+    let ticks = (end_tick - start_tick + 1) as usize;
+    let mut audio = vec![0i16; ticks * SAMPLES_PER_FRAME];
+    for (&tick, samples) in frames.range(start_tick..=end_tick) {
+        let offset = (tick - start_tick) as usize * SAMPLES_PER_FRAME;
+        let len = samples.len().min(SAMPLES_PER_FRAME);
+        audio[offset..offset + len].copy_from_slice(&samples[..len]);
     }
 
-    (audio, first_tick, last_tick)
+    audio
 }
 
 /// Load SSRC to user ID mapping from session directory
 pub fn load_ssrc_map(session_dir: &Path) -> Result<HashMap<u32, u64>, TranscribeError> {
-    let ssrc_map_path = session_dir.join("ssrc_map.json");
+    let ssrc_map_path = SessionPaths::new(session_dir).ssrc_map();
     if !ssrc_map_path.exists() {
         return Err(TranscribeError::SsrcMapError);
     }
@@ -441,6 +856,21 @@ pub fn group_ssrcs_by_user(ssrc_map: &HashMap<u32, u64>) -> HashMap<u64, Vec<u32
     user_ssrcs
 }
 
+/// Mix `samples` into `existing`, both starting at the beginning of the same tick
+///
+/// If the incoming frame is longer (e.g. the existing one is a partial last
+/// frame), the buffer is extended so no samples are dropped.
+fn mix_into_frame(existing: &mut Vec<i16>, samples: &[i16]) {
+    if samples.len() > existing.len() {
+        existing.resize(samples.len(), 0);
+    }
+
+    for (offset, &sample) in samples.iter().enumerate() {
+        let mixed = existing[offset] as i32 + sample as i32;
+        existing[offset] = mixed.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    }
+}
+
 /// Merge multiple frame maps into one, combining overlapping ticks
 fn merge_frame_maps(maps: Vec<BTreeMap<u64, Vec<i16>>>) -> BTreeMap<u64, Vec<i16>> {
     if maps.is_empty() {
@@ -456,15 +886,7 @@ fn merge_frame_maps(maps: Vec<BTreeMap<u64, Vec<i16>>>) -> BTreeMap<u64, Vec<i16
         for (tick, samples) in map {
             merged
                 .entry(tick)
-                .and_modify(|existing| {
-                    // Mix the samples together
-                    for (i, &sample) in samples.iter().enumerate() {
-                        if i < existing.len() {
-                            let mixed = existing[i] as i32 + sample as i32;
-                            existing[i] = mixed.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
-                        }
-                    }
-                })
+                .and_modify(|existing| mix_into_frame(existing, &samples))
                 .or_insert(samples);
         }
     }
@@ -472,44 +894,45 @@ fn merge_frame_maps(maps: Vec<BTreeMap<u64, Vec<i16>>>) -> BTreeMap<u64, Vec<i16
     merged
 }
 
-/// Load and prepare a single user's audio for Whisper transcription
-/// 
-/// This function merges audio from all SSRCs belonging to the same user.
-/// 
-/// # Arguments
-/// * `session_dir` - Path to the recording session directory
-/// * `user_id` - The Discord user ID
-/// * `ssrcs` - All SSRCs belonging to this user
-/// 
-/// # Returns
-/// * `PreparedAudio` containing 16kHz audio ready for Whisper
-pub fn load_user_audio_for_transcription(
+/// Load a user's frames from all their SSRCs, merged by tick
+fn load_user_frames(
     session_dir: &Path,
     user_id: u64,
     ssrcs: &[u32],
-) -> Result<PreparedAudio, TranscribeError> {
+) -> Result<BTreeMap<u64, Vec<i16>>, TranscribeError> {
     if !session_dir.exists() {
         return Err(TranscribeError::SessionNotFound(session_dir.to_path_buf()));
     }
 
-    let users_dir = session_dir.join("users");
-    if !users_dir.exists() {
+    let paths = SessionPaths::new(session_dir);
+    if !paths.users_dir().exists() {
         return Err(TranscribeError::UsersNotFound);
     }
 
     info!("Loading audio for user {} with SSRCs {:?}", user_id, ssrcs);
 
+    // Sessions recorded before the history existed attribute whole SSRCs
+    let history = SsrcHistory::load(&paths.ssrc_history());
+
     // Load frames from all SSRCs
     let mut all_frame_maps = Vec::new();
     
     for &ssrc in ssrcs {
-        let user_dir = users_dir.join(ssrc.to_string());
+        let user_dir = paths.ssrc_dir(ssrc);
         if !user_dir.exists() {
             tracing::warn!("SSRC directory not found: {}", ssrc);
             continue;
         }
 
-        match load_user_chunks(&user_dir) {
+        let frames = load_user_chunks(&user_dir).map(|mut frames| {
+            // A reused SSRC carries other users' audio outside this user's ticks
+            if let Some(history) = &history {
+                frames.retain(|&tick, _| history.owner_at(ssrc, tick).is_none_or(|owner| owner == user_id));
+            }
+            frames
+        });
+
+        match frames {
             Ok(frames) if !frames.is_empty() => {
                 info!("Loaded {} frames from SSRC {}", frames.len(), ssrc);
                 all_frame_maps.push(frames);
@@ -528,7 +951,18 @@ pub fn load_user_audio_for_transcription(
     }
 
     // Merge all frame maps
-    let merged_frames = merge_frame_maps(all_frame_maps);
+    Ok(merge_frame_maps(all_frame_maps))
+}
+
+/// Load a user's audio from all their SSRCs at the original 48kHz
+///
+/// Returns the samples with gaps filled by silence, and the first and last tick.
+pub fn load_user_audio_original_rate(
+    session_dir: &Path,
+    user_id: u64,
+    ssrcs: &[u32],
+) -> Result<(Vec<i16>, u64, u64), TranscribeError> {
+    let merged_frames = load_user_frames(session_dir, user_id, ssrcs)?;
     
     // Reconstruct continuous audio
     let (audio_48k, first_tick, last_tick) = reconstruct_audio(&merged_frames);
@@ -541,53 +975,90 @@ pub fn load_user_audio_for_transcription(
         last_tick
     );
 
-    // Downsample to 16kHz for Whisper
-    let samples_16khz = downsample_48k_to_16k(&audio_48k);
-    let duration_secs = samples_16khz.len() as f32 / WHISPER_SAMPLE_RATE as f32;
+    Ok((audio_48k, first_tick, last_tick))
+}
 
-    info!(
-        "Downsampled to {} samples at 16kHz ({:.1}s)",
-        samples_16khz.len(),
-        duration_secs
-    );
+/// One user's recorded frames, merged across their SSRCs but not yet reconstructed
+///
+/// Lets a caller look at the session's extent before deciding which ticks to
+/// turn into audio, see `prepare_range`.
+pub struct UserFrames {
+    /// Discord user ID
+    pub user_id: u64,
+    /// All SSRCs the frames came from
+    pub ssrcs: Vec<u32>,
+    frames: BTreeMap<u64, Vec<i16>>,
+}
 
-    Ok(PreparedAudio {
-        user_id,
-        ssrcs: ssrcs.to_vec(),
-        samples_16khz,
-        duration_secs,
-        first_tick,
-        last_tick,
-    })
+impl UserFrames {
+    /// Tick of the user's first frame
+    pub fn first_tick(&self) -> u64 {
+        self.frames.keys().next().copied().unwrap_or(0)
+    }
+
+    /// Tick of the user's last frame
+    pub fn last_tick(&self) -> u64 {
+        self.frames.keys().next_back().copied().unwrap_or(0)
+    }
+
+    /// 16kHz audio for the ticks `from_tick..=to_tick`, or `None` if that's outside the user's audio
+    ///
+    /// The window is narrowed to the user's first and last frame. Only the ticks
+    /// inside it are reconstructed and resampled.
+    pub fn prepare_range(&self, from_tick: u64, to_tick: u64) -> Option<PreparedAudio> {
+        let first_tick = self.first_tick().max(from_tick);
+        let last_tick = self.last_tick().min(to_tick);
+        if first_tick > last_tick {
+            return None;
+        }
+
+        let audio_48k = reconstruct_audio_range(&self.frames, first_tick, last_tick);
+        let samples_16khz = prepare_for_transcription(&audio_48k, AudioFormat::RECORDED);
+        Some(PreparedAudio {
+            user_id: self.user_id,
+            ssrcs: self.ssrcs.clone(),
+            duration_secs: samples_16khz.len() as f32 / WHISPER_SAMPLE_RATE as f32,
+            samples_16khz,
+            first_tick,
+            last_tick,
+        })
+    }
 }
 
-/// Prepare all users in a session for transcription
+/// Load every user's frames in a session, in order of their first tick
 /// 
 /// This function:
 /// 1. Loads the SSRC map
 /// 2. Groups SSRCs by user ID (handling multiple SSRCs per user)
-/// 3. Merges audio from all SSRCs for each user
-/// 
-/// # Arguments
-/// * `session_dir` - Path to the recording session directory
-/// 
-/// # Returns
-/// * Vector of `PreparedAudio` for each unique user in the session
-pub fn prepare_session_for_transcription(
-    session_dir: &Path,
-) -> Result<Vec<PreparedAudio>, TranscribeError> {
+/// 3. Merges the frames from all SSRCs for each user
+pub fn load_session_frames(session_dir: &Path) -> Result<Vec<UserFrames>, TranscribeError> {
     if !session_dir.exists() {
         return Err(TranscribeError::SessionNotFound(session_dir.to_path_buf()));
     }
 
-    let users_dir = session_dir.join("users");
-    if !users_dir.exists() {
+    if !SessionPaths::new(session_dir).users_dir().exists() {
         return Err(TranscribeError::UsersNotFound);
     }
 
     // Load SSRC map and group by user
     let ssrc_map = load_ssrc_map(session_dir)?;
-    let user_ssrcs = group_ssrcs_by_user(&ssrc_map);
+    let mut user_ssrcs = group_ssrcs_by_user(&ssrc_map);
+
+    // The map only has the last owner of a reused SSRC, the history has all of them
+    if let Some(history) = SsrcHistory::load(&SessionPaths::new(session_dir).ssrc_history()) {
+        for (ssrc, owners) in history.reassigned() {
+            tracing::warn!("SSRC {} was used by users {:?}, attributing its audio by tick", ssrc, owners);
+        }
+        for (user_id, ssrcs) in history.ssrcs_by_user() {
+            let known = user_ssrcs.entry(user_id).or_default();
+            for ssrc in ssrcs {
+                if !known.contains(&ssrc) {
+                    known.push(ssrc);
+                }
+            }
+            known.sort();
+        }
+    }
 
     info!(
         "Found {} unique users from {} SSRCs",
@@ -595,31 +1066,49 @@ pub fn prepare_session_for_transcription(
         ssrc_map.len()
     );
 
-    let mut prepared = Vec::new();
+    let mut users = Vec::new();
 
     for (user_id, ssrcs) in user_ssrcs {
-        match load_user_audio_for_transcription(session_dir, user_id, &ssrcs) {
-            Ok(audio) => {
-                info!(
-                    "Prepared user {} ({} SSRCs): {:.1}s of audio",
-                    user_id,
-                    ssrcs.len(),
-                    audio.duration_secs
-                );
-                prepared.push(audio);
-            }
+        match load_user_frames(session_dir, user_id, &ssrcs) {
+            Ok(frames) => users.push(UserFrames { user_id, ssrcs, frames }),
             Err(e) => {
-                tracing::warn!("Failed to prepare user {}: {}", user_id, e);
+                tracing::warn!("Failed to load user {}: {}", user_id, e);
             }
         }
     }
 
-    if prepared.is_empty() {
+    if users.is_empty() {
         return Err(TranscribeError::NoAudioData);
     }
 
     // Sort by first tick for chronological order
-    prepared.sort_by_key(|a| a.first_tick);
+    users.sort_by_key(UserFrames::first_tick);
+
+    Ok(users)
+}
+
+/// Prepare all users in a session for transcription
+/// 
+/// # Arguments
+/// * `session_dir` - Path to the recording session directory
+/// 
+/// # Returns
+/// * Vector of `PreparedAudio` for each unique user in the session
+pub fn prepare_session_for_transcription(
+    session_dir: &Path,
+) -> Result<Vec<PreparedAudio>, TranscribeError> {
+    let prepared: Vec<PreparedAudio> = load_session_frames(session_dir)?
+        .iter()
+        .filter_map(|user| user.prepare_range(user.first_tick(), user.last_tick()))
+        .inspect(|audio| {
+            info!(
+                "Prepared user {} ({} SSRCs): {:.1}s of audio",
+                audio.user_id,
+                audio.ssrcs.len(),
+                audio.duration_secs
+            )
+        })
+        .collect();
 
     Ok(prepared)
 }
@@ -627,11 +1116,12 @@ pub fn prepare_session_for_transcription(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::voice::audio::read_wav;
 
     #[test]
-    fn test_downsample_48k_to_16k() {
+    fn test_prepare_mono_48k_for_transcription() {
         let samples_48k: Vec<i16> = vec![100, 200, 300, 400, 500, 600, 700, 800, 900];
-        let samples_16k = downsample_48k_to_16k(&samples_48k);
+        let samples_16k = prepare_for_transcription(&samples_48k, AudioFormat::RECORDED);
         
         assert_eq!(samples_16k.len(), 3);
         assert!((samples_16k[0] - (200.0 / 32768.0)).abs() < 0.001);
@@ -639,6 +1129,29 @@ mod tests {
         assert!((samples_16k[2] - (800.0 / 32768.0)).abs() < 0.001);
     }
 
+    #[test]
+    fn test_prepare_stereo_44k_for_transcription() {
+        // One second of a 440Hz tone in the left channel and silence in the right
+        let rate = 44_100;
+        let stereo: Vec<i16> = (0..rate)
+            .flat_map(|i| {
+                let tone = (2.0 * std::f32::consts::PI * 440.0 * i as f32 / rate as f32).sin();
+                [(tone * 16384.0) as i16, 0]
+            })
+            .collect();
+        let format = AudioFormat { sample_rate: rate, channels: 2 };
+
+        let samples_16k = prepare_for_transcription(&stereo, format);
+
+        // Still one second long, at half the level from averaging with the silent channel
+        assert_eq!(samples_16k.len(), WHISPER_SAMPLE_RATE as usize);
+        let peak = samples_16k.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!((peak - 0.25).abs() < 0.01, "peak {}", peak);
+        // And the tone is still at 440Hz: 880 zero crossings a second
+        let crossings = samples_16k.windows(2).filter(|w| (w[0] < 0.0) != (w[1] < 0.0)).count();
+        assert!((878..=882).contains(&crossings), "{} crossings", crossings);
+    }
+
     #[test]
     fn test_group_ssrcs_by_user() {
         let mut ssrc_map = HashMap::new();
@@ -653,22 +1166,329 @@ mod tests {
         assert_eq!(grouped.get(&67890).unwrap(), &vec![2000]);
     }
 
+    #[test]
+    fn test_reused_ssrc_split_between_users() {
+        use crate::voice::ssrc_history::SsrcAssignment;
+        use crate::voice::storage::write_chunk_log;
+
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path());
+        // The flat map only remembers the second user
+        fs::write(paths.ssrc_map(), r#"{"100": 3}"#).unwrap();
+        let mut history = SsrcHistory::default();
+        history.record(SsrcAssignment { ssrc: 100, user_id: 1, from_tick: 0 });
+        history.record(SsrcAssignment { ssrc: 100, user_id: 3, from_tick: 500 });
+        fs::write(paths.ssrc_history(), serde_json::to_string(&history).unwrap()).unwrap();
+
+        write_chunk_log(&paths, 100, (10..13).chain(600..605), 100);
+
+        let prepared = prepare_session_for_transcription(dir.path()).unwrap();
+
+        assert_eq!(prepared.len(), 2);
+        assert_eq!((prepared[0].user_id, prepared[0].first_tick, prepared[0].last_tick), (1, 10, 12));
+        assert_eq!((prepared[1].user_id, prepared[1].first_tick, prepared[1].last_tick), (3, 600, 604));
+    }
+
+    #[test]
+    fn test_merge_frame_maps_differing_lengths() {
+        let mut full = BTreeMap::new();
+        full.insert(10, vec![100i16; SAMPLES_PER_FRAME]);
+        let mut partial = BTreeMap::new();
+        partial.insert(10, vec![50i16; SAMPLES_PER_FRAME / 2]);
+
+        // Shorter frame first so the existing buffer has to grow
+        let merged = merge_frame_maps(vec![partial, full]);
+        let frame = merged.get(&10).unwrap();
+
+        assert_eq!(frame.len(), SAMPLES_PER_FRAME);
+        assert!(frame[..SAMPLES_PER_FRAME / 2].iter().all(|&s| s == 150));
+        assert!(frame[SAMPLES_PER_FRAME / 2..].iter().all(|&s| s == 100));
+    }
+
+    #[test]
+    fn test_reconstruct_audio_range_middle_window() {
+        // Frames at ticks 10-12 and 20, nothing in between
+        let frames: BTreeMap<u64, Vec<i16>> = [10u64, 11, 12, 20]
+            .into_iter()
+            .map(|tick| (tick, vec![tick as i16; SAMPLES_PER_FRAME]))
+            .collect();
+
+        let window = reconstruct_audio_range(&frames, 11, 15);
+        assert_eq!(window.len(), 5 * SAMPLES_PER_FRAME);
+        let at = |tick: usize| window[(tick - 11) * SAMPLES_PER_FRAME];
+        assert_eq!((at(11), at(12), at(13), at(15)), (11, 12, 0, 0));
+
+        // Ticks outside the frame set are silence too
+        let outside = reconstruct_audio_range(&frames, 0, 10);
+        assert_eq!(outside.len(), 11 * SAMPLES_PER_FRAME);
+        assert!(outside[..10 * SAMPLES_PER_FRAME].iter().all(|&s| s == 0));
+        assert_eq!(outside[10 * SAMPLES_PER_FRAME], 10);
+
+        let (full, first, last) = reconstruct_audio(&frames);
+        assert_eq!((first, last), (10, 20));
+        assert_eq!(full[SAMPLES_PER_FRAME..4 * SAMPLES_PER_FRAME], window[..3 * SAMPLES_PER_FRAME]);
+    }
+
+    #[test]
+    fn test_short_utterance_kept_as_chunk_or_merged() {
+        let rate = WHISPER_SAMPLE_RATE as usize;
+        let tenth = rate / 10;
+        // Speech, a 0.1s pause, a 0.3s "ja", a 0.1s pause, more speech
+        let mut samples = vec![0.1f32; 10 * tenth];
+        samples.extend(vec![0.0; tenth]);
+        samples.extend(vec![0.1; 3 * tenth]);
+        samples.extend(vec![0.0; tenth]);
+        samples.extend(vec![0.1; 15 * tenth]);
+
+        // The "ja" chunk (with half of each pause) is 0.4s, a lowered minimum keeps it
+        let chunks = split_on_silence(&samples, 0.1, 0.25, 0.0, None);
+        assert_eq!(chunks.len(), 3);
+        assert!((chunks[1].start_time_secs - 1.05).abs() < 1e-3);
+        assert!((chunks[1].duration_secs - 0.4).abs() < 1e-3);
+
+        // With the default minimum it joins the previous chunk instead of being dropped
+        let chunks = split_on_silence(&samples, 0.1, DEFAULT_MIN_CHUNK_SECS, 0.0, None);
+        assert_eq!(chunks.len(), 2);
+        assert!((chunks[0].end_time_secs - 1.45).abs() < 1e-3);
+        assert_eq!(chunks[0].samples.len() + chunks[1].samples.len(), samples.len());
+    }
+
+    #[test]
+    fn test_padded_chunks_reach_into_silence() {
+        let rate = WHISPER_SAMPLE_RATE as usize;
+        // 1s silence, 1s speech, 3s silence, 1s speech, 1s silence
+        let mut samples = vec![0.0f32; rate];
+        samples.extend(vec![0.1; rate]);
+        samples.extend(vec![0.0; 3 * rate]);
+        samples.extend(vec![0.1; rate]);
+        samples.extend(vec![0.0; rate]);
+
+        // Split at 0.5s (leading silence, skipped), 3.5s and 6.5s (trailing silence, skipped)
+        let chunks = split_on_silence(&samples, 1.0, DEFAULT_MIN_CHUNK_SECS, 0.0, None);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(pad_chunks(chunks.clone(), &samples, 0.0, 0.0)[0].samples, chunks[0].samples);
+
+        let padded = pad_chunks(chunks.clone(), &samples, 0.3, 0.3);
+        assert_eq!(padded[0].samples.len(), chunks[0].samples.len() + 2 * (3 * rate / 10));
+        assert!((padded[0].start_time_secs - 0.2).abs() < 1e-3);
+        assert!((padded[0].overlap_secs - 0.3).abs() < 1e-3);
+        assert!((padded[1].start_time_secs - 3.2).abs() < 1e-3);
+        assert!((padded[1].end_time_secs - 6.8).abs() < 1e-3);
+        assert_eq!(padded[1].samples[..3 * rate / 10], samples[3 * rate + rate / 5..3 * rate + rate / 2]);
+
+        // Long padding stops at the neighbor's speech and the start of the audio
+        let padded = pad_chunks(chunks, &samples, 2.0, 2.0);
+        assert!((padded[0].start_time_secs - 0.0).abs() < 1e-3);
+        assert!((padded[0].end_time_secs - 5.0).abs() < 1e-3);
+        assert!((padded[1].start_time_secs - 2.0).abs() < 1e-3);
+        assert!((padded[1].end_time_secs - 7.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_short_chunks_merge_toward_target() {
+        let rate = WHISPER_SAMPLE_RATE as usize;
+        // Ten 1s bursts of speech with 1s pauses, then a 10s pause and one more burst
+        let mut samples = Vec::new();
+        for _ in 0..10 {
+            samples.extend(vec![0.1f32; rate]);
+            samples.extend(vec![0.0; rate]);
+        }
+        samples.extend(vec![0.0; 9 * rate]);
+        samples.extend(vec![0.1; rate]);
+
+        let chunks = split_on_silence(&samples, 0.5, DEFAULT_MIN_CHUNK_SECS, 0.2, None);
+        assert_eq!(chunks.len(), 11);
+
+        let merged = merge_short_chunks(chunks, 8.0, MAX_MERGE_GAP_SECS);
+        assert!(merged.len() < 11);
+        assert_eq!(merged.iter().map(|c| c.index).collect::<Vec<_>>(), (0..merged.len()).collect::<Vec<_>>());
+        assert!(merged.iter().all(|c| c.duration_secs <= 8.0 + 1e-3));
+        // Merged chunks are contiguous audio without the repeated overlap
+        for c in &merged {
+            assert_eq!(c.samples.len(), (c.duration_secs * WHISPER_SAMPLE_RATE as f32).round() as usize);
+        }
+        // The long pause (split in its middle at 24s) keeps the last burst on its own
+        let last = merged.last().unwrap();
+        assert!((last.start_time_secs + last.overlap_secs - 24.0).abs() < 1e-3);
+        assert!((merged[merged.len() - 2].end_time_secs - 24.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_mix_and_speech_ranges() {
+        let rate = WHISPER_SAMPLE_RATE as usize;
+        let track = |user_id, first_tick, samples: Vec<f32>| PreparedAudio {
+            user_id,
+            ssrcs: vec![user_id as u32 * 10],
+            duration_secs: samples.len() as f32 / rate as f32,
+            samples_16khz: samples,
+            first_tick,
+            last_tick: first_tick,
+        };
+        // Alice talks, pauses for 2s and talks again; Bob starts one second (50 ticks) later
+        let mut alice = vec![0.1f32; rate];
+        alice.extend(vec![0.0; 2 * rate]);
+        alice.extend(vec![0.1; rate]);
+        let alice = track(1, 100, alice);
+        let bob = track(2, 150, vec![0.2; rate]);
+
+        let ranges = alice.speech_ranges(1.0);
+        assert_eq!(ranges, vec![(0.0, 1.0), (3.0, 4.0)]);
+
+        let mixed = mix_prepared_audio(&[&alice, &bob]).unwrap();
+        assert_eq!(mixed.first_tick, 100);
+        assert_eq!(mixed.ssrcs, vec![10, 20]);
+        assert_eq!(mixed.samples_16khz.len(), 4 * rate);
+        assert!((mixed.samples_16khz[rate / 2] - 0.1).abs() < 1e-6);
+        assert!((mixed.samples_16khz[rate + rate / 2] - 0.2).abs() < 1e-6);
+        assert_eq!(mixed.samples_16khz[2 * rate + rate / 2], 0.0);
+    }
+
+    #[test]
+    fn test_prepare_range() {
+        // 2 seconds (100 ticks) from tick 100, with the second half louder
+        let frames: BTreeMap<u64, Vec<i16>> = (100u64..200)
+            .map(|tick| (tick, vec![if tick < 150 { 3277 } else { 9830 }; SAMPLES_PER_FRAME]))
+            .collect();
+        let user = UserFrames { user_id: 1, ssrcs: vec![10], frames };
+
+        let full = user.prepare_range(user.first_tick(), user.last_tick()).unwrap();
+        assert_eq!((full.first_tick, full.last_tick), (100, 199));
+        assert_eq!(full.samples_16khz.len(), 2 * WHISPER_SAMPLE_RATE as usize);
+
+        let clipped = user.prepare_range(150, 1000).unwrap();
+        assert_eq!((clipped.first_tick, clipped.last_tick), (150, 199));
+        assert_eq!(clipped.samples_16khz.len(), WHISPER_SAMPLE_RATE as usize);
+        assert!((clipped.duration_secs - 1.0).abs() < 1e-6);
+        assert!((clipped.samples_16khz[0] - 0.3).abs() < 1e-3);
+
+        let head = user.prepare_range(0, 124).unwrap();
+        assert_eq!((head.first_tick, head.last_tick), (100, 124));
+        assert_eq!(head.samples_16khz.len(), 25 * 320);
+
+        assert!(user.prepare_range(200, 300).is_none());
+        assert!(user.prepare_range(0, 99).is_none());
+    }
+
+    #[test]
+    fn test_cap_chunk_duration() {
+        let rate = WHISPER_SAMPLE_RATE as usize;
+        let short = AudioChunk {
+            index: 0,
+            samples: vec![0.1; 2 * rate],
+            start_time_secs: 0.0,
+            end_time_secs: 2.0,
+            duration_secs: 2.0,
+            overlap_secs: 0.0,
+        };
+        let long = AudioChunk {
+            index: 1,
+            samples: vec![0.1; 25 * rate],
+            start_time_secs: 5.0,
+            end_time_secs: 30.0,
+            duration_secs: 25.0,
+            overlap_secs: 0.2,
+        };
+
+        let capped = cap_chunk_duration(vec![short, long], 10.0, 0.5);
+
+        assert_eq!(capped.len(), 4);
+        assert_eq!(capped.iter().map(|c| c.index).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        // The first piece keeps the original overlap, later pieces overlap each other
+        assert_eq!(capped[1].start_time_secs, 5.0);
+        assert_eq!(capped[1].overlap_secs, 0.2);
+        assert!((capped[2].overlap_secs - 0.5).abs() < 1e-6);
+        assert!((capped[2].start_time_secs - (capped[1].end_time_secs - 0.5)).abs() < 1e-3);
+        assert_eq!(capped[3].end_time_secs, 30.0);
+        assert!(capped.iter().all(|c| c.duration_secs <= 10.5));
+    }
+
+    #[test]
+    fn test_chunk_format() {
+        assert_eq!("MP3".parse::<ChunkFormat>(), Ok(ChunkFormat::Mp3));
+        assert_eq!(ChunkFormat::default().extension(), "wav");
+        assert!("ogg".parse::<ChunkFormat>().is_err());
+    }
+
     #[test]
     fn test_wav_bytes_header() {
-        let audio = PreparedAudio {
-            user_id: 12345,
-            ssrcs: vec![1234],
-            samples_16khz: vec![0.0, 0.5, -0.5],
+        let chunk = AudioChunk {
+            index: 0,
+            samples: vec![0.0, 0.5, -0.5],
+            start_time_secs: 0.0,
+            end_time_secs: 0.0001875,
             duration_secs: 0.0001875,
-            first_tick: 0,
-            last_tick: 0,
+            overlap_secs: 0.0,
         };
         
-        let wav = audio.as_wav_bytes();
+        let wav = chunk.as_wav_bytes(ClipMode::Hard);
         
         assert_eq!(&wav[0..4], b"RIFF");
         assert_eq!(&wav[8..12], b"WAVE");
         assert_eq!(&wav[12..16], b"fmt ");
         assert_eq!(&wav[36..40], b"data");
     }
+
+    #[test]
+    fn test_wav_bytes_header_per_depth() {
+        let samples = [0.0, 0.5, -0.5];
+        let u16_at = |wav: &[u8], at: usize| u16::from_le_bytes([wav[at], wav[at + 1]]);
+        let u32_at = |wav: &[u8], at: usize| u32::from_le_bytes(wav[at..at + 4].try_into().unwrap());
+
+        for (depth, format_tag, bits) in [
+            (WavDepth::Int16, 1, 16),
+            (WavDepth::Int24, 1, 24),
+            (WavDepth::Float32, 3, 32),
+        ] {
+            let wav = samples_to_wav_bytes(&samples, WHISPER_SAMPLE_RATE, depth, ClipMode::Hard);
+            let bytes_per_sample = bits as u32 / 8;
+
+            assert_eq!(u16_at(&wav, 20), format_tag);
+            assert_eq!(u32_at(&wav, 24), WHISPER_SAMPLE_RATE);
+            assert_eq!(u32_at(&wav, 28), WHISPER_SAMPLE_RATE * bytes_per_sample);
+            assert_eq!(u16_at(&wav, 32) as u32, bytes_per_sample);
+            assert_eq!(u16_at(&wav, 34), bits);
+            assert_eq!(u32_at(&wav, 40), 3 * bytes_per_sample);
+            assert_eq!(u32_at(&wav, 4), 36 + 3 * bytes_per_sample);
+            assert_eq!(wav.len() as u32, 44 + 3 * bytes_per_sample);
+
+            // hound agrees with the header and reads the samples back
+            let mut reader = hound::WavReader::new(std::io::Cursor::new(wav)).unwrap();
+            assert_eq!(reader.spec().bits_per_sample, bits);
+            assert_eq!(reader.len(), 3);
+            if depth == WavDepth::Float32 {
+                let read: Vec<f32> = reader.samples::<f32>().map(|s| s.unwrap()).collect();
+                assert_eq!(read, samples);
+            } else {
+                // Same full scale as the hound writers in voice::audio
+                let half_scale = 1 << (bits - 2);
+                let read: Vec<i32> = reader.samples::<i32>().map(|s| s.unwrap()).collect();
+                assert_eq!(read, vec![0, half_scale, -half_scale]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_original_rate_chunk_is_not_resampled() {
+        let audio_48k: Vec<i16> = (0..96_000).map(|i| (i % 2000) as i16 - 1000).collect();
+        let chunk = AudioChunk {
+            index: 0,
+            samples: vec![0.0; 8000],
+            start_time_secs: 0.5,
+            end_time_secs: 1.0,
+            duration_secs: 0.5,
+            overlap_secs: 0.0,
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chunk_0000_48k.wav");
+        chunk.save_original_rate(&path, &audio_48k).unwrap();
+        let (read, sample_rate, channels) = read_wav(&path).unwrap();
+        assert_eq!((sample_rate, channels), (SOURCE_SAMPLE_RATE, 1));
+        assert_eq!(read, audio_48k[24_000..48_000]);
+
+        // A chunk running past the loaded audio is cut at its end
+        let late = AudioChunk { start_time_secs: 1.5, end_time_secs: 2.5, ..chunk };
+        late.save_original_rate(&path, &audio_48k).unwrap();
+        assert_eq!(read_wav(&path).unwrap().0.len(), 24_000);
+    }
 }
@@ -0,0 +1,227 @@
+use super::{PreparedAudio, TranscribedSegment, WHISPER_SAMPLE_RATE};
+use crate::voice::audio::{SILENCE_DBFS, window_rms_db};
+use crate::voice::clock::tick_offset_secs;
+use tracing::info;
+
+/// Minimum level for a user to be considered speaking during a segment
+const MIN_SPEAKER_DB: f32 = SILENCE_DBFS;
+
+/// RMS level (dBFS) of a user's track over a time range relative to the mixed audio start
+fn track_level_db(audio: &PreparedAudio, origin_tick: u64, start_secs: f32, end_secs: f32) -> f32 {
+    let offset_secs = tick_offset_secs(audio.first_tick, origin_tick);
+
+    let local_start = (start_secs - offset_secs).max(0.0);
+    let local_end = end_secs - offset_secs;
+    if local_end <= local_start {
+        return f32::NEG_INFINITY;
+    }
+
+    let start = (local_start * WHISPER_SAMPLE_RATE as f32) as usize;
+    let end = ((local_end * WHISPER_SAMPLE_RATE as f32) as usize).min(audio.samples_16khz.len());
+    if end <= start {
+        return f32::NEG_INFINITY;
+    }
+
+    window_rms_db(&audio.samples_16khz[start..end])
+}
+
+/// Attribute segments of a mixed transcription to the loudest user at that time
+///
+/// Segment timestamps are relative to `origin_tick` (the first tick of the
+/// mixed audio). Each track is a user's prepared audio with their display name.
+/// Segments where nobody is above the speaking threshold stay unattributed,
+/// segments that already have a speaker are left as they are.
+///
+/// This is rough energy-based diarization: crosstalk is attributed to
+/// whoever is louder, and bleed between microphones can mislead it.
+pub fn attribute_speakers(
+    segments: &mut [TranscribedSegment],
+    tracks: &[(&PreparedAudio, &str)],
+    origin_tick: u64,
+) {
+    let mut attributed = 0;
+
+    for segment in segments.iter_mut().filter(|s| s.speaker_id.is_none()) {
+        let loudest = tracks
+            .iter()
+            .map(|(audio, name)| {
+                let level_db =
+                    track_level_db(audio, origin_tick, segment.start_secs, segment.end_secs);
+                (audio.user_id, *name, level_db)
+            })
+            .filter(|(_, _, level_db)| *level_db >= MIN_SPEAKER_DB)
+            .max_by(|a, b| a.2.total_cmp(&b.2));
+
+        if let Some((user_id, name, _)) = loudest {
+            segment.speaker_id = Some(user_id);
+            segment.speaker_name = Some(name.to_string());
+            attributed += 1;
+        }
+    }
+
+    info!(
+        "Attributed {}/{} segments to {} speakers",
+        attributed,
+        segments.len(),
+        tracks.len()
+    );
+}
+
+/// When a user was speaking, on the mixed audio's timeline
+#[derive(Debug, Clone)]
+pub struct SpeakerActivity<'a> {
+    pub user_id: u64,
+    pub name: &'a str,
+    /// Speech ranges in seconds, see `PreparedAudio::speech_ranges`
+    pub ranges: Vec<(f32, f32)>,
+}
+
+impl<'a> SpeakerActivity<'a> {
+    /// Activity of a user's track, shifted onto a timeline starting at `origin_tick`
+    pub fn from_audio(
+        audio: &PreparedAudio,
+        name: &'a str,
+        origin_tick: u64,
+        min_silence_secs: f32,
+    ) -> Self {
+        let offset_secs = tick_offset_secs(audio.first_tick, origin_tick);
+        Self {
+            user_id: audio.user_id,
+            name,
+            ranges: audio
+                .speech_ranges(min_silence_secs)
+                .into_iter()
+                .map(|(start, end)| (offset_secs + start, offset_secs + end))
+                .collect(),
+        }
+    }
+
+    /// Seconds of speech within `start_secs..end_secs`
+    fn overlap_secs(&self, start_secs: f32, end_secs: f32) -> f32 {
+        self.ranges
+            .iter()
+            .map(|&(start, end)| (end.min(end_secs) - start.max(start_secs)).max(0.0))
+            .sum()
+    }
+}
+
+/// Attribute segments of a mixed transcription to the user speaking most during them
+///
+/// Each segment goes to the speaker whose speech ranges overlap it longest;
+/// segments nobody spoke during stay unattributed. When people talk over each
+/// other, Whisper usually only transcribes one of them, but the segment still
+/// goes to whoever spoke longer in it, and a short interjection inside someone
+/// else's sentence is lost to them.
+pub fn attribute_speakers_by_overlap(
+    segments: &mut [TranscribedSegment],
+    speakers: &[SpeakerActivity],
+) {
+    let mut attributed = 0;
+
+    for segment in segments.iter_mut() {
+        let best = speakers
+            .iter()
+            .map(|speaker| {
+                (
+                    speaker,
+                    speaker.overlap_secs(segment.start_secs, segment.end_secs),
+                )
+            })
+            .filter(|(_, overlap)| *overlap > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        if let Some((speaker, _)) = best {
+            segment.speaker_id = Some(speaker.user_id);
+            segment.speaker_name = Some(speaker.name.to_string());
+            attributed += 1;
+        }
+    }
+
+    info!(
+        "Attributed {}/{} segments to {} speakers by overlap",
+        attributed,
+        segments.len(),
+        speakers.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(user_id: u64, first_tick: u64, samples: Vec<f32>) -> PreparedAudio {
+        let duration_secs = samples.len() as f32 / WHISPER_SAMPLE_RATE as f32;
+        PreparedAudio {
+            user_id,
+            ssrcs: vec![],
+            samples_16khz: samples,
+            duration_secs,
+            first_tick,
+            last_tick: first_tick,
+        }
+    }
+
+    fn segment(start_secs: f32, end_secs: f32) -> TranscribedSegment {
+        TranscribedSegment {
+            start_secs,
+            end_secs,
+            text: "hello".to_string(),
+            speaker_id: None,
+            speaker_name: None,
+            translation: None,
+        }
+    }
+
+    #[test]
+    fn test_attribute_speakers_picks_loudest() {
+        let rate = WHISPER_SAMPLE_RATE as usize;
+        // Alice speaks for the first second, then is silent
+        let mut alice = vec![0.5f32; rate];
+        alice.extend(vec![0.0f32; rate]);
+        // Bob starts one second (50 ticks) later and speaks for one second
+        let bob = vec![0.3f32; rate];
+
+        let alice = track(1, 100, alice);
+        let bob = track(2, 150, bob);
+
+        let mut segments = vec![segment(0.1, 0.9), segment(1.1, 1.9), segment(3.0, 4.0)];
+        attribute_speakers(&mut segments, &[(&alice, "Alice"), (&bob, "Bob")], 100);
+
+        assert_eq!(segments[0].speaker_id, Some(1));
+        assert_eq!(segments[0].speaker_name.as_deref(), Some("Alice"));
+        assert_eq!(segments[1].speaker_id, Some(2));
+        assert_eq!(segments[2].speaker_id, None);
+
+        // A segment attributed by overlap already keeps its speaker
+        let mut attributed = vec![segment(0.1, 0.9)];
+        attributed[0].speaker_id = Some(2);
+        attribute_speakers(&mut attributed, &[(&alice, "Alice"), (&bob, "Bob")], 100);
+        assert_eq!(attributed[0].speaker_id, Some(2));
+    }
+
+    #[test]
+    fn test_attribute_speakers_by_overlap() {
+        let rate = WHISPER_SAMPLE_RATE as usize;
+        // Alice speaks 0-2s, Bob (starting 50 ticks later) speaks 1.5-3s on the mixed timeline
+        let mut alice = vec![0.2f32; 2 * rate];
+        alice.extend(vec![0.0f32; 2 * rate]);
+        let mut bob = vec![0.0f32; rate / 2];
+        bob.extend(vec![0.2f32; 3 * rate / 2]);
+
+        let alice = track(1, 100, alice);
+        let bob = track(2, 150, bob);
+        let speakers = [
+            SpeakerActivity::from_audio(&alice, "Alice", 100, 0.5),
+            SpeakerActivity::from_audio(&bob, "Bob", 100, 0.5),
+        ];
+        assert_eq!(speakers[1].ranges, vec![(1.5, 3.0)]);
+
+        // The crosstalk segment overlaps Alice for 0.4s and Bob for 1s
+        let mut segments = vec![segment(0.0, 1.5), segment(1.6, 2.6), segment(3.5, 4.0)];
+        attribute_speakers_by_overlap(&mut segments, &speakers);
+
+        assert_eq!(segments[0].speaker_name.as_deref(), Some("Alice"));
+        assert_eq!(segments[1].speaker_id, Some(2));
+        assert_eq!(segments[2].speaker_id, None);
+    }
+}
@@ -0,0 +1,109 @@
+use super::UserTranscription;
+use super::chapters::speaker_turns;
+use serde::Serialize;
+
+/// How much one user spoke during a session (`speaking_stats.json`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpeakingStats {
+    pub user_id: u64,
+    pub display_name: String,
+    /// Summed duration of the user's transcribed segments
+    pub talk_secs: f32,
+    /// Speaker turns, see `speaker_turns`
+    pub turns: usize,
+    /// Share of the session's span, from its start to the last segment
+    pub session_percent: f32,
+}
+
+/// Talk time, turn count and share of the session per user, most talkative first
+///
+/// Talk time comes from the transcribed segments, so silence and audio that
+/// produced no text don't count.
+pub fn speaking_stats(tracks: &[(&UserTranscription, u64)]) -> Vec<SpeakingStats> {
+    let turns = speaker_turns(tracks);
+    let session_secs = turns.iter().map(|turn| turn.end_secs).fold(0.0, f32::max);
+
+    let mut stats: Vec<SpeakingStats> = tracks
+        .iter()
+        .map(|(transcription, _)| {
+            let talk_secs: f32 = transcription
+                .all_segments
+                .iter()
+                .map(|segment| (segment.end_secs - segment.start_secs).max(0.0))
+                .sum();
+            SpeakingStats {
+                user_id: transcription.user_id,
+                display_name: transcription.display_name.clone(),
+                talk_secs,
+                turns: turns
+                    .iter()
+                    .filter(|turn| turn.user_id == transcription.user_id)
+                    .count(),
+                session_percent: if session_secs > 0.0 {
+                    talk_secs / session_secs * 100.0
+                } else {
+                    0.0
+                },
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| b.talk_secs.total_cmp(&a.talk_secs));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcribe::TranscribedSegment;
+
+    fn transcription(user_id: u64, name: &str, times: &[(f32, f32)]) -> UserTranscription {
+        UserTranscription {
+            user_id,
+            display_name: name.to_string(),
+            model: "small".to_string(),
+            total_duration_secs: 0.0,
+            chunk_transcriptions: vec![],
+            all_segments: times
+                .iter()
+                .map(|&(start_secs, end_secs)| TranscribedSegment {
+                    start_secs,
+                    end_secs,
+                    text: "hello".to_string(),
+                    speaker_id: None,
+                    speaker_name: None,
+                    translation: None,
+                })
+                .collect(),
+            full_transcript: String::new(),
+            locked_language: None,
+        }
+    }
+
+    #[test]
+    fn test_speaking_stats() {
+        let alice = transcription(1, "Alice", &[(0.0, 2.0), (2.5, 4.0), (16.0, 18.0)]);
+        // Bob starts one second in, so his turn runs 5s to 10s on the shared timeline
+        let bob = transcription(2, "Bob", &[(4.0, 9.0)]);
+        let carol = transcription(3, "Carol", &[]);
+
+        let stats = speaking_stats(&[(&bob, 150), (&alice, 100), (&carol, 100)]);
+
+        assert_eq!(
+            stats
+                .iter()
+                .map(|s| s.display_name.as_str())
+                .collect::<Vec<_>>(),
+            ["Alice", "Bob", "Carol"]
+        );
+        assert_eq!(
+            (stats[0].user_id, stats[0].talk_secs, stats[0].turns),
+            (1, 5.5, 2)
+        );
+        assert_eq!((stats[1].talk_secs, stats[1].turns), (5.0, 1));
+        assert_eq!((stats[2].talk_secs, stats[2].turns), (0.0, 0));
+        // The session spans 18s, up to Alice's last segment
+        assert!((stats[0].session_percent - 5.5 / 18.0 * 100.0).abs() < 1e-4);
+        assert!((stats[1].session_percent - 5.0 / 18.0 * 100.0).abs() < 1e-4);
+        assert_eq!(stats[2].session_percent, 0.0);
+    }
+}
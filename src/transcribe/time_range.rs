@@ -0,0 +1,203 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveTime, Offset, TimeZone, Utc};
+
+const SECS_PER_DAY: f64 = 86_400.0;
+
+/// One end of a transcription time range
+///
+/// Either a wall-clock time, as people remember meetings, or an offset from
+/// the start of the recording for sessions without a recorded start time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeBound {
+    /// `+15:00`, seconds since the recording started
+    Offset(f64),
+    /// `14:05` or `14:05:30`, in the host's timezone unless an offset like `+02:00` or `Z` follows
+    TimeOfDay(NaiveTime, Option<FixedOffset>),
+    /// A full RFC 3339 timestamp
+    DateTime(DateTime<FixedOffset>),
+}
+
+/// `SS`, `MM:SS` or `H:MM:SS`, with fractional seconds allowed
+fn parse_clock_secs(s: &str) -> Option<f64> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    let secs: f64 = parts.last()?.parse().ok()?;
+    let whole: Option<Vec<u32>> = parts[..parts.len() - 1]
+        .iter()
+        .map(|p| p.parse().ok())
+        .collect();
+    let minutes = whole?.iter().fold(0.0, |acc, &n| acc * 60.0 + n as f64);
+    (secs >= 0.0).then_some(minutes * 60.0 + secs)
+}
+
+impl std::str::FromStr for TimeBound {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || {
+            format!(
+                "Invalid time: {}. Use 14:05, 14:05+02:00 or an offset like +15:00",
+                s
+            )
+        };
+
+        if let Some(offset) = s.strip_prefix('+') {
+            return parse_clock_secs(offset)
+                .map(TimeBound::Offset)
+                .ok_or_else(invalid);
+        }
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+            return Ok(TimeBound::DateTime(datetime));
+        }
+
+        let (time, zone) = match s.find(['+', '-', 'Z', 'z']) {
+            Some(at) => (&s[..at], Some(&s[at..])),
+            None => (s, None),
+        };
+        let time = NaiveTime::parse_from_str(time, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+            .map_err(|_| invalid())?;
+        let zone = match zone {
+            None => None,
+            Some("Z" | "z") => Some(Utc.fix()),
+            Some(offset) => Some(
+                DateTime::parse_from_rfc3339(&format!("2000-01-01T00:00:00{}", offset))
+                    .map_err(|_| invalid())?
+                    .offset()
+                    .fix(),
+            ),
+        };
+        Ok(TimeBound::TimeOfDay(time, zone))
+    }
+}
+
+impl TimeBound {
+    /// Seconds from the session start, checked to fall within the session
+    ///
+    /// A time of day earlier than the start is taken as the next day when the
+    /// session ran past midnight. Wall-clock bounds need the session's start time.
+    pub fn resolve(&self, start: Option<DateTime<Utc>>, duration_secs: f64) -> Result<f64, String> {
+        let start = || {
+            start.ok_or(
+                "This session has no recorded start time, use an offset from the start like +15:00",
+            )
+        };
+        let secs_between = |start: DateTime<Utc>, at: DateTime<FixedOffset>| {
+            (at.with_timezone(&Utc) - start).num_milliseconds() as f64 / 1000.0
+        };
+
+        let secs = match self {
+            TimeBound::Offset(secs) => *secs,
+            TimeBound::DateTime(at) => secs_between(start()?, *at),
+            TimeBound::TimeOfDay(time, zone) => {
+                let start = start()?;
+                let zone = zone.unwrap_or_else(|| start.with_timezone(&Local).offset().fix());
+                let same_day = start.with_timezone(&zone).date_naive().and_time(*time);
+                let at = zone
+                    .from_local_datetime(&same_day)
+                    .single()
+                    .ok_or("Ambiguous time of day")?;
+                let secs = secs_between(start, at);
+                if secs < 0.0 && secs + SECS_PER_DAY <= duration_secs {
+                    secs + SECS_PER_DAY
+                } else {
+                    secs
+                }
+            }
+        };
+
+        if secs < 0.0 {
+            return Err(format!("{} is before the session started", self));
+        }
+        if secs > duration_secs {
+            return Err(format!(
+                "{} is after the session ended ({:.0}s long)",
+                self, duration_secs
+            ));
+        }
+        Ok(secs)
+    }
+}
+
+impl std::fmt::Display for TimeBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeBound::Offset(secs) => write!(f, "+{}s", secs),
+            TimeBound::TimeOfDay(time, Some(zone)) => write!(f, "{}{}", time, zone),
+            TimeBound::TimeOfDay(time, None) => write!(f, "{}", time),
+            TimeBound::DateTime(at) => write!(f, "{}", at.to_rfc3339()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_time_bounds() {
+        assert_eq!("+90".parse::<TimeBound>(), Ok(TimeBound::Offset(90.0)));
+        assert_eq!(
+            "+1:02:03.5".parse::<TimeBound>(),
+            Ok(TimeBound::Offset(3723.5))
+        );
+        assert_eq!(
+            "14:05".parse::<TimeBound>(),
+            Ok(TimeBound::TimeOfDay(
+                NaiveTime::from_hms_opt(14, 5, 0).unwrap(),
+                None
+            ))
+        );
+        assert_eq!(
+            "14:05:30+02:00".parse::<TimeBound>(),
+            Ok(TimeBound::TimeOfDay(
+                NaiveTime::from_hms_opt(14, 5, 30).unwrap(),
+                Some(FixedOffset::east_opt(7200).unwrap())
+            ))
+        );
+        assert!(matches!(
+            "2026-01-03T14:05:00Z".parse::<TimeBound>(),
+            Ok(TimeBound::DateTime(_))
+        ));
+        assert!("lunch".parse::<TimeBound>().is_err());
+        assert!("+1:xx".parse::<TimeBound>().is_err());
+    }
+
+    #[test]
+    fn test_resolve_time_bounds() {
+        // Started 13:50 UTC (15:50 at +02:00), one hour long
+        let start = Utc.with_ymd_and_hms(2026, 1, 3, 13, 50, 0).unwrap();
+        let resolve = |s: &str| s.parse::<TimeBound>().unwrap().resolve(Some(start), 3600.0);
+
+        assert_eq!(resolve("14:05Z"), Ok(900.0));
+        assert_eq!(resolve("16:20+02:00"), Ok(1800.0));
+        assert_eq!(resolve("+10:00"), Ok(600.0));
+        assert_eq!(resolve("2026-01-03T14:20:00Z"), Ok(1800.0));
+        assert!(
+            resolve("13:00Z")
+                .unwrap_err()
+                .contains("before the session started")
+        );
+        assert!(
+            resolve("15:00Z")
+                .unwrap_err()
+                .contains("after the session ended")
+        );
+
+        // Past midnight the time of day belongs to the next day
+        let late = Utc.with_ymd_and_hms(2026, 1, 3, 23, 30, 0).unwrap();
+        let bound: TimeBound = "00:15Z".parse().unwrap();
+        assert_eq!(bound.resolve(Some(late), 3600.0), Ok(2700.0));
+
+        // Without a start time only offsets work
+        assert_eq!(TimeBound::Offset(60.0).resolve(None, 3600.0), Ok(60.0));
+        assert!(
+            bound
+                .resolve(None, 3600.0)
+                .unwrap_err()
+                .contains("no recorded start time")
+        );
+    }
+}
@@ -0,0 +1,429 @@
+use super::{TranscribedSegment, UserTranscription};
+use crate::voice::clock::tick_offset_secs;
+
+/// Cue text colors, assigned to speakers in user id order
+const SPEAKER_COLORS: [&str; 6] = [
+    "#ffd166", "#06d6a0", "#4cc9f0", "#f78c6b", "#c77dff", "#ef476f",
+];
+/// Cue settings placing a speaker on the left or right of the frame
+const SPEAKER_POSITIONS: [&str; 2] = [
+    "position:5% align:left size:45%",
+    "position:95% align:right size:45%",
+];
+
+/// A segment placed on the session timeline
+struct Cue<'a> {
+    start_secs: f32,
+    end_secs: f32,
+    speaker_index: usize,
+    speaker: &'a str,
+    segment: &'a TranscribedSegment,
+}
+
+/// Format seconds as `HH:MM:SS` plus milliseconds after `separator`
+fn format_subtitle_time(secs: f32, separator: char) -> String {
+    let millis = (secs.max(0.0) as f64 * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        (millis % 3_600_000) / 60_000,
+        (millis % 60_000) / 1000,
+        separator,
+        millis % 1000
+    )
+}
+
+/// Format seconds as a WebVTT timestamp (HH:MM:SS.mmm)
+fn format_vtt_time(secs: f32) -> String {
+    format_subtitle_time(secs, '.')
+}
+
+/// Format seconds as an SRT timestamp (HH:MM:SS,mmm)
+fn format_srt_time(secs: f32) -> String {
+    format_subtitle_time(secs, ',')
+}
+
+/// Escape text for a cue payload, where `<` starts a tag and `&` an entity
+fn escape_cue_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// WebVTT subtitles for a whole session, one voice per speaker
+///
+/// Each track is a user's transcription with the first tick of their audio, so
+/// cues line up with `reconstruct-audio`'s merged file. Speakers are ordered by
+/// user id; each gets a color in the `STYLE` block (by voice class, so display
+/// names never end up in CSS) and alternates between the left and right of the
+/// frame so overlapping speech stays readable.
+pub fn session_vtt(tracks: &[(&UserTranscription, u64)]) -> String {
+    let origin_tick = tracks.iter().map(|(_, tick)| *tick).min().unwrap_or(0);
+
+    let mut speakers: Vec<u64> = tracks.iter().map(|(t, _)| t.user_id).collect();
+    speakers.sort_unstable();
+    speakers.dedup();
+    let speaker_index = |user_id: u64| speakers.iter().position(|&id| id == user_id).unwrap_or(0);
+
+    let mut vtt = String::from("WEBVTT\n\nSTYLE\n");
+    for i in 0..speakers.len() {
+        vtt.push_str(&format!(
+            "::cue(v.speaker{}) {{\n  color: {};\n}}\n",
+            i,
+            SPEAKER_COLORS[i % SPEAKER_COLORS.len()]
+        ));
+    }
+    vtt.push('\n');
+
+    let mut cues: Vec<Cue> = tracks
+        .iter()
+        .flat_map(|(transcription, first_tick)| {
+            let offset_secs = tick_offset_secs(*first_tick, origin_tick);
+            let index = speaker_index(transcription.user_id);
+            transcription.all_segments.iter().map(move |segment| Cue {
+                start_secs: offset_secs + segment.start_secs,
+                end_secs: offset_secs + segment.end_secs,
+                speaker_index: index,
+                speaker: &transcription.display_name,
+                segment,
+            })
+        })
+        .collect();
+    cues.sort_by(|a, b| a.start_secs.total_cmp(&b.start_secs));
+
+    for cue in cues {
+        vtt.push_str(&format!(
+            "{} --> {} {}\n<v.speaker{} {}>{}",
+            format_vtt_time(cue.start_secs),
+            format_vtt_time(cue.end_secs),
+            SPEAKER_POSITIONS[cue.speaker_index % SPEAKER_POSITIONS.len()],
+            cue.speaker_index,
+            escape_cue_text(cue.speaker),
+            escape_cue_text(&cue.segment.text)
+        ));
+        if let Some(translation) = &cue.segment.translation {
+            vtt.push_str(&format!("\n{}", escape_cue_text(translation)));
+        }
+        vtt.push_str("</v>\n\n");
+    }
+
+    vtt
+}
+
+/// Who a segment is spoken by: the attributed speaker in mixed transcripts, else the track's user
+fn segment_speaker<'a>(
+    transcription: &'a UserTranscription,
+    segment: &'a TranscribedSegment,
+) -> &'a str {
+    segment
+        .speaker_name
+        .as_deref()
+        .unwrap_or(&transcription.display_name)
+}
+
+/// SRT subtitles for one transcription, with timestamps as stored
+///
+/// SRT has no speaker markup, so mixed-audio transcripts prefix each line with
+/// the attributed speaker's name.
+pub fn user_srt(transcription: &UserTranscription) -> String {
+    let mut srt = String::new();
+
+    for (i, segment) in transcription.all_segments.iter().enumerate() {
+        let mut text = match &segment.translation {
+            Some(translation) => format!("{}\n{}", segment.text, translation),
+            None => segment.text.clone(),
+        };
+        if let Some(speaker) = &segment.speaker_name {
+            text = format!("{}: {}", speaker, text);
+        }
+        srt.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            format_srt_time(segment.start_secs),
+            format_srt_time(segment.end_secs),
+            text
+        ));
+    }
+
+    srt
+}
+
+/// WebVTT subtitles for one transcription, with timestamps as stored
+///
+/// Every cue is a `<v Name>` voice, the user's own name or, in mixed-audio
+/// transcripts, the attributed speaker's, so players can show or filter by speaker.
+pub fn user_vtt(transcription: &UserTranscription) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+
+    for segment in &transcription.all_segments {
+        vtt.push_str(&format!(
+            "{} --> {}\n<v {}>{}",
+            format_vtt_time(segment.start_secs),
+            format_vtt_time(segment.end_secs),
+            escape_cue_text(segment_speaker(transcription, segment)),
+            escape_cue_text(&segment.text)
+        ));
+        if let Some(translation) = &segment.translation {
+            vtt.push_str(&format!("\n{}", escape_cue_text(translation)));
+        }
+        vtt.push_str("</v>\n\n");
+    }
+
+    vtt
+}
+
+/// Seconds from an SRT or WebVTT timestamp, `HH:MM:SS,mmm`, `HH:MM:SS.mmm` or `MM:SS.mmm`
+fn parse_subtitle_time(time: &str) -> Option<f32> {
+    let time = time.trim().replace(',', ".");
+    let parts: Vec<&str> = time.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return None;
+    }
+    let secs: f64 = parts.last()?.parse().ok()?;
+    let whole = parts[..parts.len() - 1]
+        .iter()
+        .try_fold(0u64, |acc, part| {
+            part.parse::<u64>().ok().map(|n| acc * 60 + n)
+        })?;
+    Some((whole as f64 * 60.0 + secs) as f32)
+}
+
+/// Start and end of a cue timing line, ignoring WebVTT cue settings after the end
+fn parse_timing_line(line: &str) -> Option<(f32, f32)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_subtitle_time(start)?, parse_subtitle_time(end)?))
+}
+
+/// Undo `escape_cue_text`
+fn unescape_cue_text(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Split a `[Name] text` prefix off a subtitle line
+fn split_bracket_speaker(text: &str) -> (Option<String>, String) {
+    if let Some(rest) = text.strip_prefix('[')
+        && let Some((name, text)) = rest.split_once(']')
+        && !name.trim().is_empty()
+    {
+        return (Some(name.trim().to_string()), text.trim().to_string());
+    }
+    (None, text.to_string())
+}
+
+/// Cues of a subtitle file as (start, end, text lines), skipping headers and blocks without timings
+fn subtitle_cues(content: &str) -> Vec<(f32, f32, Vec<&str>)> {
+    let mut cues = Vec::new();
+    let mut lines = content
+        .lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .peekable();
+
+    while lines.peek().is_some() {
+        let block: Vec<&str> = lines
+            .by_ref()
+            .take_while(|line| !line.trim().is_empty())
+            .collect();
+        // The timing line follows an optional cue number or identifier
+        let Some(timing_at) = block.iter().take(2).position(|line| line.contains("-->")) else {
+            continue;
+        };
+        if let Some((start, end)) = parse_timing_line(block[timing_at]) {
+            cues.push((start, end, block[timing_at + 1..].to_vec()));
+        }
+    }
+    cues
+}
+
+fn imported_segment(
+    start_secs: f32,
+    end_secs: f32,
+    speaker_name: Option<String>,
+    text: String,
+) -> TranscribedSegment {
+    TranscribedSegment {
+        start_secs,
+        end_secs,
+        text,
+        speaker_id: None,
+        speaker_name,
+        translation: None,
+    }
+}
+
+/// Segments from an SRT file, e.g. one fixed up in a subtitle editor
+///
+/// Speakers come from a `[Name]` prefix. Lines of a cue are joined into one
+/// segment text, as editors wrap long lines.
+pub fn segments_from_srt(content: &str) -> Result<Vec<TranscribedSegment>, String> {
+    let segments: Vec<TranscribedSegment> = subtitle_cues(content)
+        .into_iter()
+        .map(|(start, end, lines)| {
+            let (speaker, text) = split_bracket_speaker(&lines.join(" "));
+            imported_segment(start, end, speaker, text)
+        })
+        .collect();
+    if segments.is_empty() {
+        return Err(
+            "No subtitles found, expected SRT cues like `00:00:01,000 --> 00:00:02,500`"
+                .to_string(),
+        );
+    }
+    Ok(segments)
+}
+
+/// Segments from a WebVTT file, with speakers from `<v Name>` voices or a `[Name]` prefix
+///
+/// Other cue markup is dropped and, as for SRT, lines of a cue are joined.
+pub fn segments_from_vtt(content: &str) -> Result<Vec<TranscribedSegment>, String> {
+    if !content.trim_start_matches('\u{feff}').starts_with("WEBVTT") {
+        return Err("Not a WebVTT file, it must start with `WEBVTT`".to_string());
+    }
+
+    let voice = regex::Regex::new(r"<v(?:\.[^ >]*)?\s+([^>]+)>").expect("valid voice pattern");
+    let tag = regex::Regex::new(r"</?[^>]*>").expect("valid tag pattern");
+
+    let segments: Vec<TranscribedSegment> = subtitle_cues(content)
+        .into_iter()
+        .map(|(start, end, lines)| {
+            let text = lines.join(" ");
+            let voice_name = voice
+                .captures(&text)
+                .map(|c| unescape_cue_text(c[1].trim()));
+            let text = unescape_cue_text(tag.replace_all(&text, "").trim());
+            match voice_name {
+                Some(name) => imported_segment(start, end, Some(name), text),
+                None => {
+                    let (speaker, text) = split_bracket_speaker(&text);
+                    imported_segment(start, end, speaker, text)
+                }
+            }
+        })
+        .collect();
+    if segments.is_empty() {
+        return Err("No cues found in the WebVTT file".to_string());
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcription(user_id: u64, name: &str, segments: &[(f32, f32, &str)]) -> UserTranscription {
+        UserTranscription {
+            user_id,
+            display_name: name.to_string(),
+            model: "small".to_string(),
+            total_duration_secs: 0.0,
+            chunk_transcriptions: vec![],
+            all_segments: segments
+                .iter()
+                .map(|&(start_secs, end_secs, text)| TranscribedSegment {
+                    start_secs,
+                    end_secs,
+                    text: text.to_string(),
+                    speaker_id: None,
+                    speaker_name: None,
+                    translation: None,
+                })
+                .collect(),
+            full_transcript: String::new(),
+            locked_language: None,
+        }
+    }
+
+    #[test]
+    fn test_session_vtt_styles_and_positions_speakers() {
+        let alice = transcription(1, "Alice", &[(0.0, 2.0, "hi"), (65.5, 67.0, "bye")]);
+        // Bob's audio starts one second (50 ticks) after Alice's
+        let bob = transcription(2, "R&D <Bob>", &[(2.0, 3.25, "a < b")]);
+
+        let vtt = session_vtt(&[(&bob, 150), (&alice, 100)]);
+
+        assert!(vtt.starts_with("WEBVTT\n\nSTYLE\n"));
+        assert!(vtt.contains("::cue(v.speaker0) {\n  color: #ffd166;\n}"));
+        assert!(vtt.contains("::cue(v.speaker1) {\n  color: #06d6a0;\n}"));
+        // Alice has the lower user id, so she is speaker 0 on the left
+        assert!(vtt.contains(
+            "00:00:00.000 --> 00:00:02.000 position:5% align:left size:45%\n<v.speaker0 Alice>hi</v>\n\n"
+        ));
+        assert!(vtt.contains(
+            "00:00:03.000 --> 00:00:04.250 position:95% align:right size:45%\n<v.speaker1 R&amp;D &lt;Bob&gt;>a &lt; b</v>\n\n"
+        ));
+        assert!(vtt.contains("00:01:05.500 --> 00:01:07.000"));
+
+        // Cues are in timeline order
+        let hi = vtt.find(">hi<").unwrap();
+        let bob_cue = vtt.find(">a &lt; b<").unwrap();
+        let bye = vtt.find(">bye<").unwrap();
+        assert!(hi < bob_cue && bob_cue < bye);
+    }
+
+    #[test]
+    fn test_user_subtitles() {
+        let mut alice = transcription(1, "Alice", &[(0.0, 2.0, "hi"), (3661.25, 3662.5, "a < b")]);
+        alice.all_segments[0].translation = Some("hallo".to_string());
+
+        let srt = user_srt(&alice);
+        assert_eq!(
+            srt,
+            "1\n00:00:00,000 --> 00:00:02,000\nhi\nhallo\n\n\
+            2\n01:01:01,250 --> 01:01:02,500\na < b\n\n"
+        );
+
+        let vtt = user_vtt(&alice);
+        assert!(
+            vtt.starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:02.000\n<v Alice>hi\nhallo</v>\n\n")
+        );
+        assert!(vtt.contains("01:01:01.250 --> 01:01:02.500\n<v Alice>a &lt; b</v>\n\n"));
+
+        // Mixed transcripts voice the attributed speaker
+        alice.all_segments[1].speaker_name = Some("Bob".to_string());
+        assert!(user_vtt(&alice).contains("<v Bob>a &lt; b</v>"));
+        assert!(user_srt(&alice).contains("\nBob: a < b\n"));
+    }
+
+    #[test]
+    fn test_subtitle_import_round_trips() {
+        let alice = transcription(
+            1,
+            "Alice",
+            &[(0.0, 2.0, "hi there"), (3661.25, 3662.5, "a < b & c")],
+        );
+
+        let from_srt = segments_from_srt(&user_srt(&alice)).unwrap();
+        let from_vtt = segments_from_vtt(&user_vtt(&alice)).unwrap();
+        for segments in [&from_srt, &from_vtt] {
+            assert_eq!(segments.len(), 2);
+            for (imported, original) in segments.iter().zip(&alice.all_segments) {
+                assert_eq!(imported.text, original.text);
+                assert!((imported.start_secs - original.start_secs).abs() < 1e-3);
+                assert!((imported.end_secs - original.end_secs).abs() < 1e-3);
+            }
+        }
+        // Voices name the speaker in WebVTT
+        assert_eq!(from_vtt[0].speaker_name.as_deref(), Some("Alice"));
+        assert_eq!(from_srt[0].speaker_name, None);
+
+        // Hand-edited files: CRLF, bracketed speakers, wrapped lines, styled voices
+        let srt = "1\r\n00:00:01,500 --> 00:00:03,000\r\n[Bob] wrapped\r\nline\r\n\r\n";
+        let segments = segments_from_srt(srt).unwrap();
+        assert_eq!(segments[0].speaker_name.as_deref(), Some("Bob"));
+        assert_eq!(segments[0].text, "wrapped line");
+        assert!((segments[0].start_secs - 1.5).abs() < 1e-6);
+
+        let vtt = "WEBVTT\n\nSTYLE\n::cue { color: red; }\n\nintro\n00:05.000 --> 00:06.000 align:left\n<v.speaker1 R&amp;D>see <i>this</i></v>\n";
+        let segments = segments_from_vtt(vtt).unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].speaker_name.as_deref(), Some("R&D"));
+        assert_eq!(segments[0].text, "see this");
+        assert!((segments[0].start_secs - 5.0).abs() < 1e-6);
+
+        assert!(segments_from_srt("just some text").is_err());
+        assert!(segments_from_vtt("1\n00:00:01,000 --> 00:00:02,000\nhi").is_err());
+    }
+}
@@ -1,11 +1,36 @@
+use regex::Regex;
+use sha2::{Digest, Sha256};
 use std::fs::{self, File};
-use std::io::Write;
+use std::io::{Read, Write};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use tracing::{info, warn};
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
 
-use super::{AudioChunk, WHISPER_SAMPLE_RATE};
+use super::{AudioChunk, MIN_SILENCE_DURATION_SECS, TranscriptionBackend, WHISPER_SAMPLE_RATE};
+use crate::voice::clock::{NOMINAL_TICK_SECS, SessionClock};
+
+/// Multiple of the expected processing time a chunk may take before it's aborted
+const CHUNK_TIMEOUT_SLACK: f32 = 10.0;
+/// Shortest chunk timeout, so short chunks on a busy host aren't cut off
+const MIN_CHUNK_TIMEOUT_SECS: f32 = 60.0;
+/// Read size when streaming a model download to disk
+const DOWNLOAD_BUFFER_BYTES: usize = 1024 * 1024;
+/// One lock per `WhisperModel`, so a model is downloaded once however many jobs ask for it
+static DOWNLOAD_LOCKS: [Mutex<()>; WhisperModel::Large as usize + 1] =
+    [const { Mutex::new(()) }; WhisperModel::Large as usize + 1];
+/// Numbers the temporary files of downloads in this process
+static DOWNLOAD_COUNTER: AtomicU64 = AtomicU64::new(0);
+/// Threads the `approx_realtime_factor` speeds are typical for
+const REFERENCE_THREADS: usize = 8;
+/// Processing time automatic model selection aims to stay under
+const AUTO_TIME_BUDGET_SECS: f32 = 15.0 * 60.0;
+/// Silence run through the model once after loading (one second)
+const WARMUP_SAMPLES: usize = WHISPER_SAMPLE_RATE as usize;
 
 /// Available Whisper model sizes
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -50,6 +75,92 @@ impl WhisperModel {
             WhisperModel::Large => 3100,
         }
     }
+
+    /// SHA-256 of the model file on Hugging Face
+    ///
+    /// Needs updating if the upstream file is ever re-uploaded, a model
+    /// without a known hash is used unverified.
+    pub fn sha256(&self) -> Option<&'static str> {
+        match self {
+            WhisperModel::Tiny => Some("be07e048e1e599ad46341c8d2a135645097a538221678b7acdd1b1919c6e1b21"),
+            WhisperModel::Base => Some("60ed5bc3dd14eea856493d334349b405782ddcaf0028d4b5df4088345fba2efe"),
+            WhisperModel::Small => Some("1be3a9b2063867b937e64e2ec7483364a79917e157fa98c5d94b5c1fffea987b"),
+            WhisperModel::Medium => Some("6c14d5adee5f86394037b4e4e8b59f1673b6cee10e3cf0b11bbdbee79c156208"),
+            WhisperModel::Large => Some("64d182b440b98d5203c4f9bd541544d84c605196c4f7b845dfa11fb23594d1e2"),
+        }
+    }
+
+    /// Rough CPU processing speed as a multiple of realtime
+    ///
+    /// Only used for estimates; actual speed depends heavily on the host.
+    pub fn approx_realtime_factor(&self) -> f32 {
+        match self {
+            WhisperModel::Tiny => 30.0,
+            WhisperModel::Base => 15.0,
+            WhisperModel::Small => 6.0,
+            WhisperModel::Medium => 2.0,
+            WhisperModel::Large => 1.0,
+        }
+    }
+
+    /// Default minimum silence to split chunks on
+    ///
+    /// Slower models split on shorter pauses so chunks stay small; see `default_max_chunk_secs`.
+    pub fn default_min_silence_secs(&self) -> f32 {
+        match self {
+            WhisperModel::Tiny | WhisperModel::Base | WhisperModel::Small => MIN_SILENCE_DURATION_SECS,
+            WhisperModel::Medium => 1.5,
+            WhisperModel::Large => 1.0,
+        }
+    }
+
+    /// Default maximum chunk length in seconds
+    ///
+    /// Fast models handle long chunks fine and benefit from the extra context.
+    /// Slow models get shorter chunks so progress updates stay frequent and a
+    /// failed chunk loses less work. At `approx_realtime_factor` speeds this
+    /// keeps each chunk to roughly 30s of processing or less.
+    pub fn default_max_chunk_secs(&self) -> f32 {
+        match self {
+            WhisperModel::Tiny | WhisperModel::Base => 120.0,
+            WhisperModel::Small => 60.0,
+            WhisperModel::Medium => 45.0,
+            WhisperModel::Large => 30.0,
+        }
+    }
+
+    /// Largest model expected to transcribe `speech_secs` of speech within
+    /// `AUTO_TIME_BUDGET_SECS` on `threads` CPU threads
+    ///
+    /// Short clips get the large model, hours-long sessions a smaller one.
+    /// Returns the model and the reasoning, for the status message.
+    pub fn auto_select(speech_secs: f32, threads: usize) -> (WhisperModel, String) {
+        let speedup = threads.max(1) as f32 / REFERENCE_THREADS as f32;
+        let estimate_secs = |model: WhisperModel| speech_secs / (model.approx_realtime_factor() * speedup);
+
+        let largest = [WhisperModel::Large, WhisperModel::Medium, WhisperModel::Small, WhisperModel::Base]
+            .into_iter()
+            .find(|&model| estimate_secs(model) <= AUTO_TIME_BUDGET_SECS)
+            .unwrap_or(WhisperModel::Tiny);
+
+        let reason = format!(
+            "{:.0} min of speech on {} threads, ~{:.0} min estimated against a {:.0} min budget",
+            speech_secs / 60.0,
+            threads,
+            estimate_secs(largest) / 60.0,
+            AUTO_TIME_BUDGET_SECS / 60.0
+        );
+        (largest, reason)
+    }
+
+    /// Longest inference time allowed for a chunk before it is skipped
+    ///
+    /// Pathological audio (long noise, music) can make Whisper loop far slower
+    /// than realtime; this bounds how long one chunk can stall a session.
+    pub fn chunk_timeout(&self, audio_secs: f32) -> Duration {
+        let expected_secs = audio_secs / self.approx_realtime_factor();
+        Duration::from_secs_f32((expected_secs * CHUNK_TIMEOUT_SLACK).max(MIN_CHUNK_TIMEOUT_SECS))
+    }
 }
 
 impl std::fmt::Display for WhisperModel {
@@ -85,6 +196,8 @@ pub enum WhisperError {
     Io(#[from] std::io::Error),
     #[error("Failed to download model: {0}")]
     Download(String),
+    #[error("Whisper {0} model is not present and downloads are disabled, run /download-model first")]
+    ModelMissing(WhisperModel),
     #[error("Failed to initialize Whisper: {0}")]
     Init(String),
     #[error("Transcription failed: {0}")]
@@ -100,6 +213,15 @@ pub struct TranscribedSegment {
     pub end_secs: f32,
     /// The transcribed text
     pub text: String,
+    /// Attributed speaker (only set for mixed-audio transcriptions)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker_id: Option<u64>,
+    /// Attributed speaker display name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker_name: Option<String>,
+    /// English translation of `text` (only set in bilingual mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation: Option<String>,
 }
 
 /// Result of transcribing an audio chunk
@@ -113,6 +235,9 @@ pub struct ChunkTranscription {
     pub chunk_end_secs: f32,
     /// Detected language
     pub language: Option<String>,
+    /// Leading seconds that repeat the end of the previous chunk
+    #[serde(default)]
+    pub overlap_secs: f32,
     /// Transcribed segments within the chunk
     pub segments: Vec<TranscribedSegment>,
     /// Full text (all segments joined)
@@ -145,15 +270,113 @@ pub fn is_model_downloaded(model: WhisperModel) -> bool {
     false
 }
 
+/// Hex-encoded SHA-256 of a file, streamed so large models aren't held in memory
+fn sha256_file(path: &Path) -> Result<String, WhisperError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Verify a downloaded model against its known SHA-256
+///
+/// Returns `Ok(false)` on mismatch, so the caller can delete and re-download.
+/// A model without a known hash is accepted with a warning.
+pub fn verify_model_checksum(model: WhisperModel) -> Result<bool, WhisperError> {
+    let Some(expected) = model.sha256() else {
+        warn!("No known checksum for {} model, skipping verification", model);
+        return Ok(true);
+    };
+    let actual = sha256_file(&model_path(model))?;
+
+    if actual != expected {
+        warn!(
+            "Checksum mismatch for {} model: expected {}, got {}",
+            model, expected, actual
+        );
+        return Ok(false);
+    }
+
+    info!("Checksum verified for {} model", model);
+    Ok(true)
+}
+
+/// Whether models must be downloaded explicitly (`WHISPER_NO_DOWNLOAD`)
+///
+/// For air-gapped or metered hosts, where a transcription silently pulling
+/// gigabytes is unacceptable. Auto-download stays the default.
+pub fn downloads_disabled() -> bool {
+    crate::env_flag("WHISPER_NO_DOWNLOAD", false)
+}
+
+/// Path of a model that must already be present, never downloading it
+pub fn require_model(model: WhisperModel) -> Result<PathBuf, WhisperError> {
+    if !is_model_downloaded(model) {
+        return Err(WhisperError::ModelMissing(model));
+    }
+    Ok(model_path(model))
+}
+
 /// Download a Whisper model from Hugging Face
+///
+/// Set `WHISPER_VERIFY_MODEL` to also verify an already-downloaded model's
+/// checksum before use (hashing large models takes a few seconds).
 pub fn download_model(model: WhisperModel) -> Result<PathBuf, WhisperError> {
+    download_model_with_progress(model, &|_, _| {})
+}
+
+/// Download a Whisper model, calling `on_progress(downloaded_bytes, total_bytes)` as it streams
+///
+/// `total_bytes` is 0 when the server doesn't send a length. A retried
+/// download reports from 0 again. Concurrent calls for the same model wait for
+/// the first one and then find the model present.
+pub fn download_model_with_progress(
+    model: WhisperModel,
+    on_progress: &dyn Fn(u64, u64),
+) -> Result<PathBuf, WhisperError> {
+    let _lock = DOWNLOAD_LOCKS[model as usize]
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
     let path = model_path(model);
     
     if is_model_downloaded(model) {
-        info!("Model {} already downloaded at {:?}", model, path);
-        return Ok(path);
+        let verify = std::env::var("WHISPER_VERIFY_MODEL").is_ok();
+        if !verify || verify_model_checksum(model)? {
+            info!("Model {} already downloaded at {:?}", model, path);
+            return Ok(path);
+        }
+
+        warn!("Deleting corrupted {} model and downloading again", model);
+        fs::remove_file(&path)?;
     }
 
+    const MAX_ATTEMPTS: usize = 2;
+    for attempt in 1..=MAX_ATTEMPTS {
+        download_model_file(model, on_progress)?;
+
+        if verify_model_checksum(model)? {
+            return Ok(path);
+        }
+
+        warn!(
+            "Downloaded {} model failed checksum verification (attempt {}/{})",
+            model, attempt, MAX_ATTEMPTS
+        );
+        fs::remove_file(&path)?;
+    }
+
+    Err(WhisperError::Download(format!(
+        "{} model failed checksum verification after {} attempts",
+        model, MAX_ATTEMPTS
+    )))
+}
+
+/// Fetch a model file from Hugging Face to its models directory path
+///
+/// The body is streamed to a temporary file, so large models are never held in memory.
+fn download_model_file(model: WhisperModel, on_progress: &dyn Fn(u64, u64)) -> Result<(), WhisperError> {
+    let path = model_path(model);
+
     // Create models directory
     fs::create_dir_all(models_dir())?;
 
@@ -166,7 +389,7 @@ pub fn download_model(model: WhisperModel) -> Result<PathBuf, WhisperError> {
     let url = model.hf_url();
     
     // Use blocking reqwest for simplicity
-    let response = reqwest::blocking::Client::new()
+    let mut response = reqwest::blocking::Client::new()
         .get(url)
         .send()
         .map_err(|e| WhisperError::Download(format!("HTTP request failed: {}", e)))?;
@@ -191,17 +414,40 @@ pub fn download_model(model: WhisperModel) -> Result<PathBuf, WhisperError> {
     );
 
     // Download with progress
-    let temp_path = path.with_extension("bin.tmp");
+    // Unique per download, so another process fetching the same model can't interleave writes
+    let temp_path = path.with_extension(format!(
+        "bin.{}-{}.tmp",
+        std::process::id(),
+        DOWNLOAD_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
     let mut file = File::create(&temp_path)?;
     let mut downloaded: u64 = 0;
-    
-    let bytes = response.bytes()
-        .map_err(|e| WhisperError::Download(format!("Failed to read response: {}", e)))?;
-    
-    file.write_all(&bytes)?;
-    downloaded = bytes.len() as u64;
-    pb.set_position(downloaded);
-    
+    let mut buffer = vec![0u8; DOWNLOAD_BUFFER_BYTES];
+    on_progress(0, total_size);
+
+    loop {
+        let read = response
+            .read(&mut buffer)
+            .map_err(|e| WhisperError::Download(format!("Failed to read response: {}", e)))?;
+        if read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..read])?;
+        downloaded += read as u64;
+        crate::metrics::MODEL_DOWNLOAD_BYTES.inc_by(read as u64);
+        pb.set_position(downloaded);
+        on_progress(downloaded, total_size);
+    }
+    file.flush()?;
+
+    if total_size > 0 && downloaded != total_size {
+        let _ = fs::remove_file(&temp_path);
+        return Err(WhisperError::Download(format!(
+            "Download ended after {} of {} bytes",
+            downloaded, total_size
+        )));
+    }
+
     pb.finish_with_message("Download complete");
     
     // Rename temp file to final path
@@ -209,7 +455,7 @@ pub fn download_model(model: WhisperModel) -> Result<PathBuf, WhisperError> {
     
     info!("Model downloaded to {:?}", path);
     
-    Ok(path)
+    Ok(())
 }
 
 /// Language configuration for transcription
@@ -220,6 +466,10 @@ pub struct LanguageConfig {
     pub language: Option<String>,
     /// Whether to translate to English (false = keep original language)
     pub translate: bool,
+    /// Keep the original language and add an English translation to every segment
+    ///
+    /// Runs a second translation pass on chunks not detected as English.
+    pub bilingual: bool,
 }
 
 impl Default for LanguageConfig {
@@ -228,6 +478,7 @@ impl Default for LanguageConfig {
             // Auto-detect for mixed German/English
             language: None,
             translate: false,
+            bilingual: false,
         }
     }
 }
@@ -238,6 +489,7 @@ impl LanguageConfig {
         Self {
             language: None, // Auto-detect each segment
             translate: false, // Keep original language
+            bilingual: false,
         }
     }
     
@@ -246,6 +498,7 @@ impl LanguageConfig {
         Self {
             language: Some("de".to_string()),
             translate: false,
+            bilingual: false,
         }
     }
     
@@ -254,6 +507,7 @@ impl LanguageConfig {
         Self {
             language: Some("en".to_string()),
             translate: false,
+            bilingual: false,
         }
     }
     
@@ -262,15 +516,140 @@ impl LanguageConfig {
         Self {
             language: None,
             translate: true,
+            bilingual: false,
+        }
+    }
+
+    /// Transcribe in the original language and translate to English alongside
+    pub fn bilingual() -> Self {
+        Self {
+            language: None,
+            translate: false,
+            bilingual: true,
+        }
+    }
+
+    /// Whether a chunk transcribed in `language` still needs a translation pass
+    pub fn needs_translation(&self, language: Option<&str>) -> bool {
+        // whisper.cpp reports language codes, the OpenAI API full names
+        self.bilingual && !self.translate && !matches!(language, Some("en" | "english"))
+    }
+}
+
+/// How the language is chosen across a user's chunks when auto-detecting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LanguageStrategy {
+    /// Auto-detect independently for every chunk
+    #[default]
+    PerChunk,
+    /// Auto-detect on the first chunk, then force that language for the rest
+    ///
+    /// Stabilizes output for mostly-monolingual speakers whose language
+    /// detection flips between chunks in mixed calls.
+    DetectThenLock,
+}
+
+/// Hallucination filter configuration for transcription
+#[derive(Debug, Clone)]
+pub struct HallucinationConfig {
+    /// Maximum number of consecutive identical segments to keep
+    pub max_repeats: usize,
+    /// Skip segments whose no-speech probability exceeds this
+    pub no_speech_thold: f32,
+    /// Treat decoding as failed (and retry hotter) below this entropy
+    pub entropy_thold: f32,
+    /// Treat decoding as failed below this average log probability
+    pub logprob_thold: f32,
+}
+
+impl Default for HallucinationConfig {
+    fn default() -> Self {
+        Self {
+            max_repeats: 2,
+            no_speech_thold: 0.6,
+            entropy_thold: 2.4,
+            logprob_thold: -1.0,
+        }
+    }
+}
+
+impl HallucinationConfig {
+    /// Keep more output, for content that legitimately repeats (counting, lyrics)
+    pub fn lenient() -> Self {
+        Self {
+            max_repeats: 5,
+            no_speech_thold: 0.8,
+            entropy_thold: 2.0,
+            logprob_thold: -1.5,
+        }
+    }
+
+    /// Drop more output, for noisy recordings that hallucinate a lot
+    pub fn strict() -> Self {
+        Self {
+            max_repeats: 1,
+            no_speech_thold: 0.4,
+            entropy_thold: 2.8,
+            logprob_thold: -0.8,
+        }
+    }
+}
+
+/// Sampling settings for Whisper's decoder
+///
+/// The defaults decode greedily and only heat up when a pass fails the
+/// hallucination thresholds, which is deterministic and fast. Starting hotter
+/// or sampling several candidates (`best_of`) can recover difficult audio at
+/// the cost of reproducibility and speed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecodingConfig {
+    /// Initial sampling temperature, 0.0 is greedy (0.0-1.0)
+    pub temperature: f32,
+    /// Added to the temperature each time decoding fails, 0.0 disables fallback (0.0-1.0)
+    pub temperature_inc: f32,
+    /// Candidates sampled per fallback pass when the temperature is above 0 (1-10)
+    pub best_of: i32,
+}
+
+impl Default for DecodingConfig {
+    fn default() -> Self {
+        Self {
+            temperature: 0.0,
+            temperature_inc: 0.2,
+            best_of: 1,
         }
     }
 }
 
+impl DecodingConfig {
+    /// Check every setting is within the range Whisper handles sensibly
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.temperature) {
+            return Err(format!("Temperature must be between 0.0 and 1.0, got {}", self.temperature));
+        }
+        if !(0.0..=1.0).contains(&self.temperature_inc) {
+            return Err(format!(
+                "Temperature increment must be between 0.0 and 1.0, got {}",
+                self.temperature_inc
+            ));
+        }
+        if !(1..=10).contains(&self.best_of) {
+            return Err(format!("Best-of must be between 1 and 10, got {}", self.best_of));
+        }
+        Ok(())
+    }
+}
+
 /// Whisper transcriber
 pub struct Transcriber {
     ctx: WhisperContext,
     model: WhisperModel,
     language_config: LanguageConfig,
+    hallucination_config: HallucinationConfig,
+    decoding_config: DecodingConfig,
+    language_strategy: LanguageStrategy,
+    /// Set from another task to stop `transcribe_chunks` before its next chunk
+    cancel_flag: Option<Arc<AtomicBool>>,
     /// Number of threads to use (0 = auto)
     n_threads: i32,
 }
@@ -283,8 +662,11 @@ impl Transcriber {
     
     /// Create a new transcriber with specific language configuration
     pub fn with_language(model: WhisperModel, language_config: LanguageConfig) -> Result<Self, WhisperError> {
-        // Ensure model is downloaded
-        let path = download_model(model)?;
+        let path = if downloads_disabled() {
+            require_model(model)?
+        } else {
+            download_model(model)?
+        };
         
         info!("Loading Whisper {} model...", model);
         
@@ -302,12 +684,106 @@ impl Transcriber {
         info!("Whisper model loaded successfully (using {} threads)", n_threads);
         info!("Language config: {:?}", language_config);
         
-        Ok(Self { ctx, model, language_config, n_threads })
+        Ok(Self {
+            ctx,
+            model,
+            language_config,
+            hallucination_config: HallucinationConfig::default(),
+            decoding_config: DecodingConfig::default(),
+            language_strategy: LanguageStrategy::default(),
+            cancel_flag: None,
+            n_threads,
+        })
+    }
+
+    /// Use a specific hallucination filter configuration
+    pub fn with_hallucination_config(mut self, config: HallucinationConfig) -> Self {
+        info!("Hallucination config: {:?}", config);
+        self.hallucination_config = config;
+        self
+    }
+
+    /// Use specific decoder sampling settings
+    pub fn with_decoding_config(mut self, config: DecodingConfig) -> Self {
+        info!("Decoding config: {:?}", config);
+        self.decoding_config = config;
+        self
+    }
+
+    /// Use a specific language strategy when auto-detecting
+    pub fn with_language_strategy(mut self, strategy: LanguageStrategy) -> Self {
+        info!("Language strategy: {:?}", strategy);
+        self.language_strategy = strategy;
+        self
+    }
+
+    /// Stop transcribing between chunks once `flag` is set
+    pub fn with_cancel_flag(mut self, flag: Arc<AtomicBool>) -> Self {
+        self.cancel_flag = Some(flag);
+        self
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_flag
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// Run inference on a second of silence, discarding the result
+    ///
+    /// The first inference after loading allocates buffers and pages in the
+    /// model, which would otherwise make the first chunk slow and skew the
+    /// progress estimate.
+    pub fn warmup(&self) -> Result<(), WhisperError> {
+        let samples = vec![0.0; WARMUP_SAMPLES];
+        let chunk = AudioChunk {
+            index: 0,
+            duration_secs: samples.len() as f32 / WHISPER_SAMPLE_RATE as f32,
+            end_time_secs: samples.len() as f32 / WHISPER_SAMPLE_RATE as f32,
+            samples,
+            start_time_secs: 0.0,
+            overlap_secs: 0.0,
+        };
+        // A fixed language skips detection, which the real chunks may still do
+        let language = self.language_config.language.as_deref().unwrap_or("en");
+        self.run_chunk(&chunk, Some(language), false)?;
+        Ok(())
     }
 
     /// Transcribe an audio chunk (optimized for speed)
     pub fn transcribe_chunk(&self, chunk: &AudioChunk) -> Result<ChunkTranscription, WhisperError> {
-        let start_time = std::time::Instant::now();
+        self.transcribe_chunk_in(chunk, self.language_config.language.as_deref())
+    }
+
+    /// Transcribe an audio chunk, forcing `language` (None = auto-detect)
+    ///
+    /// In bilingual mode a second pass translates the chunk to English.
+    fn transcribe_chunk_in(
+        &self,
+        chunk: &AudioChunk,
+        language: Option<&str>,
+    ) -> Result<ChunkTranscription, WhisperError> {
+        let mut transcription = self.run_chunk(chunk, language, self.language_config.translate)?;
+
+        if self.language_config.needs_translation(transcription.language.as_deref()) {
+            let source_language = language.or(transcription.language.as_deref());
+            match self.run_chunk(chunk, source_language, true) {
+                Ok(translated) => attach_translations(&mut transcription.segments, &translated.segments),
+                Err(e) => warn!("Failed to translate chunk {}: {}", chunk.index, e),
+            }
+        }
+
+        Ok(transcription)
+    }
+
+    /// Run one inference pass over a chunk
+    fn run_chunk(
+        &self,
+        chunk: &AudioChunk,
+        language: Option<&str>,
+        translate: bool,
+    ) -> Result<ChunkTranscription, WhisperError> {
+        let start_time = Instant::now();
         
         info!(
             "Transcribing chunk {} ({:.2}s audio)",
@@ -315,7 +791,9 @@ impl Transcriber {
         );
 
         // Use greedy sampling for speed (beam search is 2-3x slower)
-        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        let mut params = FullParams::new(SamplingStrategy::Greedy {
+            best_of: self.decoding_config.best_of,
+        });
         
         // ===== SPEED OPTIMIZATIONS =====
         
@@ -333,17 +811,17 @@ impl Transcriber {
         // ===== HALLUCINATION PREVENTION =====
         
         // Detect silence/no speech - skip segments with high no_speech probability
-        params.set_no_speech_thold(0.6); // If >60% likely no speech, skip
+        params.set_no_speech_thold(self.hallucination_config.no_speech_thold);
         
         // Higher entropy threshold = more likely to stop on repetitive/uncertain output
-        params.set_entropy_thold(2.4);
+        params.set_entropy_thold(self.hallucination_config.entropy_thold);
         
         // Log probability threshold - reject low confidence outputs
-        params.set_logprob_thold(-1.0);
+        params.set_logprob_thold(self.hallucination_config.logprob_thold);
         
         // Temperature fallback for better quality (reduces hallucination)
-        params.set_temperature(0.0); // Start with greedy (deterministic)
-        params.set_temperature_inc(0.2); // Increase if decoding fails
+        params.set_temperature(self.decoding_config.temperature); // 0.0 = greedy (deterministic)
+        params.set_temperature_inc(self.decoding_config.temperature_inc); // Increase if decoding fails
         
         // Don't use previous context (prevents hallucination propagation)
         params.set_no_context(true);
@@ -355,19 +833,21 @@ impl Transcriber {
         params.set_max_len(80); // Max ~80 chars per segment
         
         // ===== LANGUAGE CONFIGURATION =====
-        match &self.language_config.language {
-            Some(lang) => params.set_language(Some(lang)),
-            None => params.set_language(Some("auto")), // Auto-detect
-        }
+        params.set_language(Some(language.unwrap_or("auto"))); // None = auto-detect
         
         // Translation setting
-        params.set_translate(self.language_config.translate);
+        params.set_translate(translate);
         
         // Print progress
         params.set_print_progress(false);
         params.set_print_realtime(false);
         params.set_print_timestamps(false);
         params.set_print_special(false);
+
+        // Abort runaway inference, the chunk is then skipped like any failed chunk
+        let timeout = self.model.chunk_timeout(chunk.duration_secs);
+        let deadline = start_time + timeout;
+        params.set_abort_callback_safe(move || Instant::now() >= deadline);
         
         // Create state and run
         let mut state = self.ctx.create_state()
@@ -376,7 +856,13 @@ impl Transcriber {
         // Run inference
         state
             .full(params, &chunk.samples)
-            .map_err(|e| WhisperError::Transcription(format!("Inference failed: {}", e)))?;
+            .map_err(|e| {
+                if Instant::now() >= deadline {
+                    WhisperError::Transcription(format!("Inference timed out after {:.0?}", timeout))
+                } else {
+                    WhisperError::Transcription(format!("Inference failed: {}", e))
+                }
+            })?;
 
         // Extract segments
         let num_segments = state.full_n_segments()
@@ -387,7 +873,7 @@ impl Transcriber {
         
         let mut last_text: Option<String> = None;
         let mut repeat_count = 0;
-        const MAX_REPEATS: usize = 2; // Allow max 2 consecutive identical segments
+        let mut repeats_dropped = 0;
         
         for i in 0..num_segments {
             let start_ts = state.full_get_segment_t0(i)
@@ -412,8 +898,9 @@ impl Transcriber {
             
             if is_repeat {
                 repeat_count += 1;
-                if repeat_count >= MAX_REPEATS {
+                if repeat_count >= self.hallucination_config.max_repeats {
                     // Skip this repeated segment - likely hallucination
+                    repeats_dropped += 1;
                     continue;
                 }
             } else {
@@ -426,6 +913,9 @@ impl Transcriber {
                 start_secs,
                 end_secs,
                 text: text.clone(),
+                speaker_id: None,
+                speaker_name: None,
+                translation: None,
             });
             
             if !full_text.is_empty() {
@@ -442,16 +932,19 @@ impl Transcriber {
         let elapsed = start_time.elapsed();
         let realtime_factor = chunk.duration_secs / elapsed.as_secs_f32();
         
-        // Log if we filtered hallucinations
+        // Log if we filtered hallucinations, and which filter caught them
         let filtered = num_segments as i32 - segments.len() as i32;
         if filtered > 0 {
             info!(
-                "Transcribed chunk {} in {:.1}s ({:.1}x realtime): {} segments ({} hallucinations filtered)",
+                "Transcribed chunk {} in {:.1}s ({:.1}x realtime): {} segments ({} hallucinations filtered: {} repeats > {}, {} empty)",
                 chunk.index,
                 elapsed.as_secs_f32(),
                 realtime_factor,
                 segments.len(),
-                filtered
+                filtered,
+                repeats_dropped,
+                self.hallucination_config.max_repeats,
+                filtered - repeats_dropped
             );
         } else {
             info!(
@@ -468,6 +961,7 @@ impl Transcriber {
             chunk_start_secs: chunk.start_time_secs,
             chunk_end_secs: chunk.end_time_secs,
             language,
+            overlap_secs: chunk.overlap_secs,
             segments,
             full_text,
         })
@@ -475,7 +969,14 @@ impl Transcriber {
 
     /// Transcribe multiple chunks
     /// Transcribe multiple chunks sequentially with progress tracking
-    pub fn transcribe_chunks(&self, chunks: &[AudioChunk]) -> Result<Vec<ChunkTranscription>, WhisperError> {
+    ///
+    /// `on_progress` is called with (chunks processed, total chunks) after every chunk.
+    #[tracing::instrument(skip_all, fields(model = %self.model, chunks = chunks.len()))]
+    pub fn transcribe_chunks(
+        &self,
+        chunks: &[AudioChunk],
+        on_progress: &dyn Fn(usize, usize),
+    ) -> Result<Vec<ChunkTranscription>, WhisperError> {
         let total_audio_secs: f32 = chunks.iter().map(|c| c.duration_secs).sum();
         info!(
             "Transcribing {} chunks ({:.1}s total audio)...",
@@ -486,10 +987,30 @@ impl Transcriber {
         let start_time = std::time::Instant::now();
         let mut transcriptions = Vec::new();
         let mut processed_audio = 0.0f32;
+        let mut locked_language: Option<String> = None;
         
         for (i, chunk) in chunks.iter().enumerate() {
-            match self.transcribe_chunk(chunk) {
+            if self.is_cancelled() {
+                info!("Cancelled after {}/{} chunks", i, chunks.len());
+                break;
+            }
+
+            let language = self
+                .language_config
+                .language
+                .as_deref()
+                .or(locked_language.as_deref());
+
+            match self.transcribe_chunk_in(chunk, language) {
                 Ok(t) => {
+                    if self.language_strategy == LanguageStrategy::DetectThenLock
+                        && locked_language.is_none()
+                        && let Some(detected) = &t.language
+                    {
+                        info!("Locking language to '{}' after chunk {}", detected, chunk.index);
+                        locked_language = Some(detected.clone());
+                    }
+
                     processed_audio += chunk.duration_secs;
                     let progress = (i + 1) as f32 / chunks.len() as f32 * 100.0;
                     let elapsed = start_time.elapsed().as_secs_f32();
@@ -511,6 +1032,7 @@ impl Transcriber {
                     // Continue with other chunks
                 }
             }
+            on_progress(i + 1, chunks.len());
         }
         
         let total_elapsed = start_time.elapsed();
@@ -537,6 +1059,33 @@ impl Transcriber {
     }
 }
 
+impl TranscriptionBackend for Transcriber {
+    fn name(&self) -> String {
+        self.model.to_string()
+    }
+
+    fn transcribe(&self, chunk: &AudioChunk) -> Result<ChunkTranscription, WhisperError> {
+        self.transcribe_chunk(chunk)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        Transcriber::is_cancelled(self)
+    }
+
+    fn warmup(&self) -> Result<(), WhisperError> {
+        Transcriber::warmup(self)
+    }
+
+    /// Uses whisper's own loop, which can lock the language after the first chunk
+    fn transcribe_chunks(
+        &self,
+        chunks: &[AudioChunk],
+        on_progress: &dyn Fn(usize, usize),
+    ) -> Result<Vec<ChunkTranscription>, WhisperError> {
+        Transcriber::transcribe_chunks(self, chunks, on_progress)
+    }
+}
+
 /// Full transcription result for a user
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct UserTranscription {
@@ -549,6 +1098,9 @@ pub struct UserTranscription {
     pub all_segments: Vec<TranscribedSegment>,
     /// Full transcript text
     pub full_transcript: String,
+    /// Language forced for this user's chunks (detect-then-lock strategy)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_language: Option<String>,
 }
 
 impl UserTranscription {
@@ -562,22 +1114,42 @@ impl UserTranscription {
     ) -> Self {
         let mut all_segments = Vec::new();
         let mut full_transcript = String::new();
+        let mut previous_text = "";
         
         for ct in &chunk_transcriptions {
-            for seg in &ct.segments {
+            // Words heard in the overlap were usually already transcribed at the end of the previous chunk
+            let mut segments = ct.segments.clone();
+            let repeated = if ct.overlap_secs > 0.0 {
+                overlap_word_count(previous_text, &ct.full_text)
+            } else {
+                0
+            };
+            drop_leading_words(&mut segments, repeated);
+            previous_text = &ct.full_text;
+
+            for seg in &segments {
                 // Convert to absolute timestamps
                 all_segments.push(TranscribedSegment {
                     start_secs: ct.chunk_start_secs + seg.start_secs,
                     end_secs: ct.chunk_start_secs + seg.end_secs,
                     text: seg.text.clone(),
+                    speaker_id: seg.speaker_id,
+                    speaker_name: seg.speaker_name.clone(),
+                    translation: seg.translation.clone(),
                 });
             }
             
-            if !ct.full_text.is_empty() {
+            let text = if repeated > 0 {
+                segments.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join(" ")
+            } else {
+                ct.full_text.clone()
+            };
+
+            if !text.is_empty() {
                 if !full_transcript.is_empty() {
                     full_transcript.push(' ');
                 }
-                full_transcript.push_str(&ct.full_text);
+                full_transcript.push_str(&text);
             }
         }
         
@@ -589,8 +1161,220 @@ impl UserTranscription {
             chunk_transcriptions,
             all_segments,
             full_transcript,
+            locked_language: None,
+        }
+    }
+
+    /// Case-insensitive substring search over segments
+    ///
+    /// Returns each matching segment with the byte range of its first match.
+    pub fn search(&self, query: &str) -> Vec<(&TranscribedSegment, Range<usize>)> {
+        let pattern = Regex::new(&format!("(?i){}", regex::escape(query)))
+            .expect("escaped query is a valid regex");
+        self.search_regex(&pattern)
+    }
+
+    /// Regex search over segments, returning each matching segment with the byte range of its first match
+    pub fn search_regex(&self, pattern: &Regex) -> Vec<(&TranscribedSegment, Range<usize>)> {
+        self.all_segments
+            .iter()
+            .filter_map(|segment| pattern.find(&segment.text).map(|m| (segment, m.range())))
+            .collect()
+    }
+
+    /// One transcription per attributed speaker of a mixed-audio transcription
+    ///
+    /// Segments keep the mixed audio's timestamps and unattributed ones are left
+    /// out. Speakers are in order of their first segment.
+    pub fn split_by_speaker(&self) -> Vec<UserTranscription> {
+        let mut speakers: Vec<UserTranscription> = Vec::new();
+
+        for segment in &self.all_segments {
+            let Some(user_id) = segment.speaker_id else {
+                continue;
+            };
+            let index = match speakers.iter().position(|t| t.user_id == user_id) {
+                Some(index) => index,
+                None => {
+                    let name = segment.speaker_name.clone().unwrap_or_else(|| user_id.to_string());
+                    speakers.push(UserTranscription::from_chunks(user_id, name, &self.model, self.total_duration_secs, vec![]));
+                    speakers.len() - 1
+                }
+            };
+
+            let speaker = &mut speakers[index];
+            if !speaker.full_transcript.is_empty() {
+                speaker.full_transcript.push(' ');
+            }
+            speaker.full_transcript.push_str(&segment.text);
+            speaker.all_segments.push(segment.clone());
+        }
+
+        speakers
+    }
+
+    /// Record the language locked by the detect-then-lock strategy (the first detected one)
+    pub fn lock_language_from_chunks(&mut self) {
+        self.locked_language = self
+            .chunk_transcriptions
+            .iter()
+            .find_map(|ct| ct.language.clone());
+    }
+
+    /// Split segments longer than `max_secs` at word boundaries for readable subtitles
+    ///
+    /// Whisper gives no word timing here, so time is divided in proportion to
+    /// character count. The resulting pieces are contiguous.
+    pub fn split_long_segments(&mut self, max_secs: f32) {
+        if max_secs <= 0.0 {
+            return;
         }
+
+        let mut split = Vec::with_capacity(self.all_segments.len());
+        for segment in self.all_segments.drain(..) {
+            split.extend(split_segment(segment, max_secs));
+        }
+        self.all_segments = split;
+    }
+
+    /// Replace the segments, e.g. with hand-corrected ones, and rebuild the full transcript
+    ///
+    /// The raw chunk output is kept as the model produced it.
+    pub fn replace_segments(&mut self, segments: Vec<TranscribedSegment>) {
+        self.full_transcript = segments
+            .iter()
+            .map(|s| s.text.trim())
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.all_segments = segments;
     }
+
+    /// Map segment times from nominal 20ms ticks onto the session's wall clock
+    ///
+    /// `first_tick` is the tick this user's audio starts at; times stay relative to it.
+    pub fn correct_clock(&mut self, clock: &SessionClock, first_tick: u64) {
+        let origin_secs = clock.tick_to_secs(first_tick as f64);
+        let to_wall_clock = |secs: f32| {
+            let tick = first_tick as f64 + secs as f64 / NOMINAL_TICK_SECS;
+            (clock.tick_to_secs(tick) - origin_secs) as f32
+        };
+
+        for segment in &mut self.all_segments {
+            segment.start_secs = to_wall_clock(segment.start_secs);
+            segment.end_secs = to_wall_clock(segment.end_secs);
+        }
+    }
+}
+
+/// Most words that can plausibly be repeated across a chunk overlap
+const MAX_OVERLAP_WORDS: usize = 8;
+
+/// Lowercase alphanumeric form of a word, for comparing across chunks
+fn normalize_word(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Number of leading words of `current` that repeat the trailing words of `previous`
+fn overlap_word_count(previous: &str, current: &str) -> usize {
+    let previous: Vec<String> = previous.split_whitespace().map(normalize_word).collect();
+    let current: Vec<String> = current.split_whitespace().map(normalize_word).collect();
+    let max = MAX_OVERLAP_WORDS.min(previous.len()).min(current.len());
+
+    (1..=max)
+        .rev()
+        .find(|&n| previous[previous.len() - n..] == current[..n])
+        .unwrap_or(0)
+}
+
+/// Remove the first `count` words from a chunk's segments, dropping segments left empty
+fn drop_leading_words(segments: &mut Vec<TranscribedSegment>, mut count: usize) {
+    while count > 0 && !segments.is_empty() {
+        let words: Vec<&str> = segments[0].text.split_whitespace().collect();
+        if words.len() <= count {
+            count -= words.len();
+            segments.remove(0);
+        } else {
+            segments[0].text = words[count..].join(" ");
+            count = 0;
+        }
+    }
+}
+
+/// Attach each translated segment to the original segment it overlaps most
+///
+/// The two passes segment the audio independently, so several translated
+/// segments can land on one original and some originals get none.
+pub(crate) fn attach_translations(segments: &mut [TranscribedSegment], translated: &[TranscribedSegment]) {
+    for t in translated {
+        let overlap = |s: &TranscribedSegment| s.end_secs.min(t.end_secs) - s.start_secs.max(t.start_secs);
+        let midpoint = (t.start_secs + t.end_secs) / 2.0;
+        let distance = |s: &TranscribedSegment| (((s.start_secs + s.end_secs) / 2.0) - midpoint).abs();
+
+        let target = segments
+            .iter_mut()
+            .max_by(|a, b| {
+                overlap(a)
+                    .total_cmp(&overlap(b))
+                    .then_with(|| distance(b).total_cmp(&distance(a)))
+            });
+        if let Some(segment) = target {
+            match &mut segment.translation {
+                Some(existing) => {
+                    existing.push(' ');
+                    existing.push_str(&t.text);
+                }
+                None => segment.translation = Some(t.text.clone()),
+            }
+        }
+    }
+}
+
+/// Split one segment into pieces of at most roughly `max_secs` each
+fn split_segment(segment: TranscribedSegment, max_secs: f32) -> Vec<TranscribedSegment> {
+    let duration = segment.end_secs - segment.start_secs;
+    let words: Vec<&str> = segment.text.split_whitespace().collect();
+    let pieces = (duration / max_secs).ceil() as usize;
+
+    if pieces <= 1 || words.len() < 2 {
+        return vec![segment];
+    }
+    let pieces = pieces.min(words.len());
+
+    // Assign words to pieces by their character offset in the space-joined text
+    let total_chars = words.iter().map(|w| w.chars().count()).sum::<usize>() + words.len() - 1;
+    let mut groups: Vec<(usize, usize, Vec<&str>)> = Vec::new(); // (piece, start offset, words)
+    let mut offset = 0;
+    for word in &words {
+        let piece = (offset * pieces / total_chars).min(pieces - 1);
+        match groups.last_mut() {
+            Some((last_piece, _, group)) if *last_piece == piece => group.push(word),
+            _ => groups.push((piece, offset, vec![word])),
+        }
+        offset += word.chars().count() + 1;
+    }
+
+    let offset_secs = |offset: usize| segment.start_secs + duration * offset as f32 / total_chars as f32;
+
+    groups
+        .iter()
+        .enumerate()
+        .map(|(i, (_, start, group))| TranscribedSegment {
+            start_secs: offset_secs(*start),
+            end_secs: match groups.get(i + 1) {
+                Some((_, next, _)) => offset_secs(*next),
+                None => segment.end_secs,
+            },
+            text: group.join(" "),
+            speaker_id: segment.speaker_id,
+            speaker_name: segment.speaker_name.clone(),
+            // The translation can't be split by words, it stays with the first piece
+            translation: if i == 0 { segment.translation.clone() } else { None },
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -604,6 +1388,251 @@ mod tests {
         assert!("invalid".parse::<WhisperModel>().is_err());
     }
 
+    #[test]
+    fn test_chunk_timeout_scales_with_model_and_duration() {
+        // Short chunks get the floor
+        assert_eq!(WhisperModel::Tiny.chunk_timeout(10.0), Duration::from_secs(60));
+        // Large runs at ~1x realtime, so 30s of audio may take 300s
+        assert_eq!(WhisperModel::Large.chunk_timeout(30.0), Duration::from_secs(300));
+        assert!(WhisperModel::Medium.chunk_timeout(120.0) > WhisperModel::Small.chunk_timeout(120.0));
+    }
+
+    #[test]
+    fn test_auto_select_model() {
+        let pick = |minutes: f32, threads| WhisperModel::auto_select(minutes * 60.0, threads).0;
+        assert_eq!(pick(5.0, 8), WhisperModel::Large);
+        assert_eq!(pick(20.0, 8), WhisperModel::Medium);
+        assert_eq!(pick(60.0, 8), WhisperModel::Small);
+        // Fewer cores push the same audio to a smaller model
+        assert_eq!(pick(20.0, 2), WhisperModel::Small);
+        assert_eq!(pick(600.0, 8), WhisperModel::Tiny);
+
+        let (_, reason) = WhisperModel::auto_select(1200.0, 8);
+        assert!(reason.starts_with("20 min of speech on 8 threads, ~10 min estimated"));
+    }
+
+    #[test]
+    fn test_hallucination_presets_ordering() {
+        let lenient = HallucinationConfig::lenient();
+        let default = HallucinationConfig::default();
+        let strict = HallucinationConfig::strict();
+
+        assert!(lenient.max_repeats > default.max_repeats);
+        assert!(strict.max_repeats < default.max_repeats);
+        assert!(lenient.no_speech_thold > strict.no_speech_thold);
+        assert!(lenient.logprob_thold < strict.logprob_thold);
+    }
+
+    #[test]
+    fn test_decoding_config_validation() {
+        assert!(DecodingConfig::default().validate().is_ok());
+        let hot = DecodingConfig { temperature: 0.4, temperature_inc: 0.0, best_of: 5 };
+        assert!(hot.validate().is_ok());
+
+        assert!(DecodingConfig { temperature: 1.5, ..DecodingConfig::default() }.validate().is_err());
+        assert!(DecodingConfig { temperature_inc: -0.1, ..DecodingConfig::default() }.validate().is_err());
+        assert!(DecodingConfig { best_of: 0, ..DecodingConfig::default() }.validate().is_err());
+        assert!(DecodingConfig { best_of: 11, ..DecodingConfig::default() }.validate().is_err());
+        assert!(DecodingConfig { temperature: f32::NAN, ..DecodingConfig::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_split_long_segments() {
+        let text = "one two three four five six seven eight nine ten eleven twelve";
+        let mut transcription = UserTranscription {
+            user_id: 1,
+            display_name: "Test".to_string(),
+            model: "tiny".to_string(),
+            total_duration_secs: 20.0,
+            chunk_transcriptions: vec![],
+            all_segments: vec![TranscribedSegment {
+                start_secs: 3.0,
+                end_secs: 15.0,
+                text: text.to_string(),
+                speaker_id: None,
+                speaker_name: None,
+                translation: None,
+            }],
+            full_transcript: text.to_string(),
+            locked_language: None,
+        };
+
+        transcription.split_long_segments(5.0);
+        let segments = &transcription.all_segments;
+
+        assert!(segments.len() >= 3);
+        assert_eq!(segments[0].start_secs, 3.0);
+        assert_eq!(segments.last().unwrap().end_secs, 15.0);
+        for pair in segments.windows(2) {
+            assert_eq!(pair[0].end_secs, pair[1].start_secs);
+        }
+        let rejoined: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(rejoined.join(" "), text);
+    }
+
+    #[test]
+    fn test_lock_language_from_chunks() {
+        let chunk = |index, language: Option<&str>| ChunkTranscription {
+            chunk_index: index,
+            chunk_start_secs: 0.0,
+            chunk_end_secs: 1.0,
+            language: language.map(str::to_string),
+            overlap_secs: 0.0,
+            segments: vec![],
+            full_text: String::new(),
+        };
+
+        let mut transcription = UserTranscription::from_chunks(
+            1,
+            "Alice".to_string(),
+            "small",
+            3.0,
+            vec![chunk(0, None), chunk(1, Some("de")), chunk(2, Some("en"))],
+        );
+        assert_eq!(transcription.locked_language, None);
+
+        transcription.lock_language_from_chunks();
+        assert_eq!(transcription.locked_language.as_deref(), Some("de"));
+    }
+
+    #[test]
+    fn test_attach_translations_by_overlap() {
+        let segment = |start_secs, end_secs, text: &str| TranscribedSegment {
+            start_secs,
+            end_secs,
+            text: text.to_string(),
+            speaker_id: None,
+            speaker_name: None,
+            translation: None,
+        };
+        let mut original = vec![
+            segment(0.0, 2.0, "Guten Morgen"),
+            segment(2.5, 6.0, "Wie geht es euch heute"),
+            segment(8.0, 9.0, "Ja"),
+        ];
+        // The translation pass splits the second sentence differently
+        let translated = vec![
+            segment(0.0, 1.8, "Good morning"),
+            segment(2.4, 4.0, "How are"),
+            segment(4.0, 6.2, "you all today"),
+        ];
+
+        attach_translations(&mut original, &translated);
+
+        assert_eq!(original[0].translation.as_deref(), Some("Good morning"));
+        assert_eq!(original[1].translation.as_deref(), Some("How are you all today"));
+        assert_eq!(original[2].translation, None);
+        assert_eq!(original[1].text, "Wie geht es euch heute");
+    }
+
+    #[test]
+    fn test_overlap_words_deduplicated() {
+        let segment = |start_secs, end_secs, text: &str| TranscribedSegment {
+            start_secs,
+            end_secs,
+            text: text.to_string(),
+            speaker_id: None,
+            speaker_name: None,
+            translation: None,
+        };
+        let chunk = |index, start, overlap_secs, segments: Vec<TranscribedSegment>| ChunkTranscription {
+            chunk_index: index,
+            chunk_start_secs: start,
+            chunk_end_secs: start + 5.0,
+            language: None,
+            overlap_secs,
+            full_text: segments.iter().map(|s| s.text.clone()).collect::<Vec<_>>().join(" "),
+            segments,
+        };
+
+        let chunks = vec![
+            chunk(0, 0.0, 0.0, vec![segment(0.0, 4.0, "Let's meet at the station")]),
+            chunk(1, 4.8, 0.2, vec![segment(0.0, 1.0, "Station."), segment(1.0, 3.0, "See you there")]),
+            // Repeated words without overlap are kept
+            chunk(2, 12.0, 0.0, vec![segment(0.0, 1.0, "there we go")]),
+        ];
+
+        let transcription = UserTranscription::from_chunks(1, "Alice".to_string(), "small", 20.0, chunks);
+
+        assert_eq!(
+            transcription.full_transcript,
+            "Let's meet at the station See you there there we go"
+        );
+        assert_eq!(transcription.all_segments.len(), 3);
+        assert_eq!(transcription.all_segments[1].start_secs, 5.8);
+    }
+
+    #[test]
+    fn test_search_segments() {
+        let segment = |start_secs: f32, text: &str| TranscribedSegment {
+            start_secs,
+            end_secs: start_secs + 2.0,
+            text: text.to_string(),
+            speaker_id: None,
+            speaker_name: None,
+            translation: None,
+        };
+        let mut transcription = UserTranscription::from_chunks(1, "Alice".to_string(), "small", 10.0, vec![]);
+        transcription.all_segments = vec![
+            segment(0.0, "Das Budget für Q3 steht"),
+            segment(2.0, "nothing here"),
+            segment(4.0, "the BUDGET review (v2.1)"),
+        ];
+
+        let matches = transcription.search("budget");
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].1, 4..10);
+        assert_eq!(&matches[1].0.text[matches[1].1.clone()], "BUDGET");
+
+        // Regex metacharacters in plain queries are literal
+        assert_eq!(transcription.search("(v2.1)").len(), 1);
+        assert!(transcription.search("v2.1.").is_empty());
+
+        let pattern = Regex::new(r"Q\d").unwrap();
+        let matches = transcription.search_regex(&pattern);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].0.start_secs, 0.0);
+    }
+
+    #[test]
+    fn test_split_by_speaker() {
+        let segment = |start_secs: f32, text: &str, speaker: Option<(u64, &str)>| TranscribedSegment {
+            start_secs,
+            end_secs: start_secs + 1.0,
+            text: text.to_string(),
+            speaker_id: speaker.map(|(id, _)| id),
+            speaker_name: speaker.map(|(_, name)| name.to_string()),
+            translation: None,
+        };
+        let mut mixed = UserTranscription::from_chunks(0, "Mixed".to_string(), "small", 10.0, vec![]);
+        mixed.all_segments = vec![
+            segment(0.0, "hi", Some((2, "Bob"))),
+            segment(1.0, "hello", Some((1, "Alice"))),
+            segment(2.0, "mumble", None),
+            segment(3.0, "bye", Some((2, "Bob"))),
+        ];
+
+        let speakers = mixed.split_by_speaker();
+
+        assert_eq!(speakers.len(), 2);
+        assert_eq!((speakers[0].user_id, speakers[0].display_name.as_str()), (2, "Bob"));
+        assert_eq!(speakers[0].full_transcript, "hi bye");
+        assert_eq!(speakers[0].all_segments[1].start_secs, 3.0);
+        assert_eq!(speakers[1].full_transcript, "hello");
+    }
+
+    #[test]
+    fn test_sha256_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.bin");
+        fs::write(&path, b"abc").unwrap();
+
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
     #[test]
     fn test_model_paths() {
         assert!(model_path(WhisperModel::Tiny).to_str().unwrap().contains("ggml-tiny.bin"));
@@ -1,12 +1,1040 @@
-pub fn stereo_to_mono(stereo: &[i16]) -> Vec<i16> {
+use flacenc::component::BitRepr;
+use flacenc::error::Verify;
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Magnitude of full scale for 16-bit samples
+const I16_FULL_SCALE: f32 = 32768.0;
+/// Magnitude of full scale for 24-bit samples
+const I24_FULL_SCALE: f32 = 8_388_608.0;
+
+/// Sample format of WAV output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WavDepth {
+    #[default]
+    Int16,
+    /// Finer quantization for mastering
+    Int24,
+    /// Keeps levels above full scale instead of clipping them
+    Float32,
+}
+
+impl WavDepth {
+    pub fn bits_per_sample(&self) -> u16 {
+        match self {
+            WavDepth::Int16 => 16,
+            WavDepth::Int24 => 24,
+            WavDepth::Float32 => 32,
+        }
+    }
+
+    pub fn spec(&self, sample_rate: u32, channels: u16) -> WavSpec {
+        WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: self.bits_per_sample(),
+            sample_format: match self {
+                WavDepth::Float32 => SampleFormat::Float,
+                WavDepth::Int16 | WavDepth::Int24 => SampleFormat::Int,
+            },
+        }
+    }
+}
+
+/// Provenance tags for a WAV file's RIFF `LIST`/`INFO` chunk
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WavInfo {
+    /// `IART`, the speaker
+    pub artist: Option<String>,
+    /// `ICMT`, the session
+    pub comment: Option<String>,
+    /// `ICRD`, the recording date (`YYYY-MM-DD`)
+    pub created: Option<String>,
+}
+
+impl WavInfo {
+    fn tags(&self) -> [(&'static [u8; 4], Option<&String>); 3] {
+        [
+            (b"IART", self.artist.as_ref()),
+            (b"ICMT", self.comment.as_ref()),
+            (b"ICRD", self.created.as_ref()),
+        ]
+    }
+
+    /// The `LIST` chunk, with NUL-terminated values padded to even lengths
+    pub fn to_list_chunk(&self) -> Vec<u8> {
+        let mut body = b"INFO".to_vec();
+        for (id, value) in self.tags() {
+            let Some(value) = value else {
+                continue;
+            };
+            let mut data = value.as_bytes().to_vec();
+            data.push(0);
+            body.extend_from_slice(id);
+            body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            body.extend_from_slice(&data);
+            if data.len() % 2 == 1 {
+                body.push(0);
+            }
+        }
+
+        let mut chunk = b"LIST".to_vec();
+        chunk.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(&body);
+        chunk
+    }
+
+    /// Append the `LIST` chunk to a finished WAV file and fix up the RIFF size
+    ///
+    /// Readers that don't know the chunk skip it, hound included.
+    pub fn append_to(&self, path: &Path) -> io::Result<()> {
+        let mut file = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+        let mut end = file.seek(SeekFrom::End(0))?;
+        // Chunks start at even offsets
+        if end % 2 == 1 {
+            file.write_all(&[0])?;
+            end += 1;
+        }
+        let chunk = self.to_list_chunk();
+        file.write_all(&chunk)?;
+
+        let riff_size = (end + chunk.len() as u64 - 8) as u32;
+        file.seek(SeekFrom::Start(4))?;
+        file.write_all(&riff_size.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Read the `INFO` tags of a WAV file, `None` if it has none
+    ///
+    /// Only chunk headers are read on the way, not the audio data.
+    pub fn read(path: &Path) -> io::Result<Option<WavInfo>> {
+        let mut file = io::BufReader::new(std::fs::File::open(path)?);
+        let mut header = [0u8; 12];
+        file.read_exact(&mut header)?;
+        if &header[..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a WAV file"));
+        }
+
+        let mut chunk_header = [0u8; 8];
+        while file.read_exact(&mut chunk_header).is_ok() {
+            let size = u32::from_le_bytes([chunk_header[4], chunk_header[5], chunk_header[6], chunk_header[7]]);
+            if &chunk_header[..4] == b"LIST" {
+                let mut data = vec![0u8; size as usize];
+                file.read_exact(&mut data)?;
+                if data.starts_with(b"INFO") {
+                    return Ok(Some(parse_info(&data[4..])));
+                }
+                if size % 2 == 1 {
+                    file.seek_relative(1)?;
+                }
+            } else {
+                file.seek_relative(size as i64 + (size % 2) as i64)?;
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Tags of an `INFO` list body (after the `INFO` id)
+fn parse_info(mut data: &[u8]) -> WavInfo {
+    let mut info = WavInfo::default();
+    while data.len() >= 8 {
+        let size = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+        let Some(value) = data.get(8..8 + size) else {
+            break;
+        };
+        let value = String::from_utf8_lossy(value).trim_end_matches('\0').to_string();
+        match &data[..4] {
+            b"IART" => info.artist = Some(value),
+            b"ICMT" => info.comment = Some(value),
+            b"ICRD" => info.created = Some(value),
+            _ => {}
+        }
+        data = data.get(8 + size + size % 2..).unwrap_or_default();
+    }
+    info
+}
+
+/// 16-bit sample as floating point, full scale at 1.0
+pub fn i16_to_f32(sample: i16) -> f32 {
+    sample as f32 / I16_FULL_SCALE
+}
+
+/// Round and clamp a floating point sample (full scale at 1.0) to 16 bits
+pub fn f32_to_i16(sample: f32) -> i16 {
+    (sample * I16_FULL_SCALE).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+/// Round and clamp a floating point sample (full scale at 1.0) to 24 bits
+pub fn f32_to_i24(sample: f32) -> i32 {
+    (sample * I24_FULL_SCALE).round().clamp(-I24_FULL_SCALE, I24_FULL_SCALE - 1.0) as i32
+}
+
+/// Write one floating point sample (full scale at 1.0) at the writer's depth
+///
+/// Integer depths clip at full scale, `Float32` keeps the value as is.
+pub fn write_wav_sample<W: Write + Seek>(
+    writer: &mut WavWriter<W>,
+    sample: f32,
+    depth: WavDepth,
+) -> Result<(), hound::Error> {
+    match depth {
+        WavDepth::Int16 => writer.write_sample(f32_to_i16(sample)),
+        WavDepth::Int24 => writer.write_sample(f32_to_i24(sample)),
+        WavDepth::Float32 => writer.write_sample(sample),
+    }
+}
+
+/// Level where soft clipping starts bending samples toward full scale
+const SOFT_CLIP_KNEE: f32 = 0.8;
+
+/// How samples beyond full scale are brought into range for integer output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClipMode {
+    /// Clamp to full scale, leaving everything below it untouched
+    #[default]
+    Hard,
+    /// Bend samples above `SOFT_CLIP_KNEE` along a tanh curve, so overs distort less audibly
+    Soft,
+}
+
+impl ClipMode {
+    /// Map a sample (full scale at 1.0) into [-1.0, 1.0]
+    pub fn apply(&self, sample: f32) -> f32 {
+        match self {
+            ClipMode::Hard => sample.clamp(-1.0, 1.0),
+            ClipMode::Soft => {
+                let magnitude = sample.abs();
+                if magnitude <= SOFT_CLIP_KNEE {
+                    return sample;
+                }
+                // Continuous in level and slope at the knee, approaching full scale asymptotically
+                let headroom = 1.0 - SOFT_CLIP_KNEE;
+                let bent = SOFT_CLIP_KNEE + headroom * ((magnitude - SOFT_CLIP_KNEE) / headroom).tanh();
+                bent.copysign(sample)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for ClipMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hard" | "clamp" => Ok(ClipMode::Hard),
+            "soft" | "tanh" => Ok(ClipMode::Soft),
+            _ => Err(format!("Unknown clip mode: {}. Use hard or soft", s)),
+        }
+    }
+}
+
+/// How interleaved stereo is reduced to mono
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StereoDownmix {
+    /// Average of both channels (cancels out-of-phase content)
+    #[default]
+    Average,
+    /// Left channel only
+    Left,
+    /// Right channel only
+    Right,
+    /// Whichever channel has the larger magnitude, per sample
+    MaxChannel,
+}
+
+impl std::fmt::Display for StereoDownmix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StereoDownmix::Average => write!(f, "average"),
+            StereoDownmix::Left => write!(f, "left"),
+            StereoDownmix::Right => write!(f, "right"),
+            StereoDownmix::MaxChannel => write!(f, "max"),
+        }
+    }
+}
+
+impl std::str::FromStr for StereoDownmix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "average" | "avg" => Ok(StereoDownmix::Average),
+            "left" => Ok(StereoDownmix::Left),
+            "right" => Ok(StereoDownmix::Right),
+            "max" | "max_channel" => Ok(StereoDownmix::MaxChannel),
+            _ => Err(format!("Unknown downmix: {}. Use average, left, right, or max", s)),
+        }
+    }
+}
+
+/// Reduce interleaved stereo samples to mono using the given mode
+pub fn downmix(stereo: &[i16], mode: StereoDownmix) -> Vec<i16> {
     stereo
         .chunks(2)
         .map(|chunk| {
-            if chunk.len() == 2 {
-                ((chunk[0] as i32 + chunk[1] as i32) / 2) as i16
-            } else {
-                chunk[0]
+            if chunk.len() < 2 {
+                return chunk[0];
+            }
+
+            let (left, right) = (chunk[0], chunk[1]);
+            match mode {
+                StereoDownmix::Average => ((left as i32 + right as i32) / 2) as i16,
+                StereoDownmix::Left => left,
+                StereoDownmix::Right => right,
+                StereoDownmix::MaxChannel => {
+                    if (left as i32).abs() >= (right as i32).abs() {
+                        left
+                    } else {
+                        right
+                    }
+                }
             }
         })
         .collect()
 }
+
+/// Mix tracks with per-track weights into floating point samples (full scale at 1.0)
+///
+/// A weight of 1.0 keeps a track at its original level and 0.0 mutes it.
+/// The output is as long as the longest track; shorter tracks count as silence.
+/// The sum is not clamped, so it can exceed full scale until the final write.
+pub fn mix_tracks_weighted(tracks: &[(&[i16], f32)]) -> Vec<f32> {
+    let len = tracks.iter().map(|(samples, _)| samples.len()).max().unwrap_or(0);
+    let mut mixed = vec![0.0f32; len];
+
+    for (samples, weight) in tracks {
+        for (out, &sample) in mixed.iter_mut().zip(samples.iter()) {
+            *out += i16_to_f32(sample) * weight;
+        }
+    }
+
+    mixed
+}
+
+/// Weight of each new 20ms frame in a `LevelMeter` (about a one second time constant)
+const LEVEL_METER_SMOOTHING: f64 = 0.02;
+/// Rolling level below which a stream counts as near-silent
+pub const NEAR_SILENT_DBFS: f32 = -55.0;
+
+/// Rolling level of a live stream, updated once per 20ms frame without keeping samples
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LevelMeter {
+    mean_square: f64,
+    /// Frames since the rolling level was last above `NEAR_SILENT_DBFS`
+    quiet_frames: u64,
+}
+
+impl LevelMeter {
+    /// Feed one frame of audio
+    pub fn update(&mut self, samples: &[i16]) {
+        let frame_mean_square = if samples.is_empty() {
+            0.0
+        } else {
+            samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / samples.len() as f64
+        };
+        self.push(frame_mean_square);
+    }
+
+    /// Feed a frame in which the stream sent no audio
+    pub fn update_silent(&mut self) {
+        self.push(0.0);
+    }
+
+    fn push(&mut self, frame_mean_square: f64) {
+        self.mean_square += (frame_mean_square - self.mean_square) * LEVEL_METER_SMOOTHING;
+        if self.rms_dbfs() < NEAR_SILENT_DBFS {
+            self.quiet_frames += 1;
+        } else {
+            self.quiet_frames = 0;
+        }
+    }
+
+    /// Rolling RMS level relative to full scale
+    pub fn rms_dbfs(&self) -> f32 {
+        amplitude_to_dbfs(self.mean_square.sqrt())
+    }
+
+    /// How long the stream has been near-silent
+    pub fn quiet_secs(&self) -> f32 {
+        self.quiet_frames as f32 * 0.02
+    }
+}
+
+/// Level statistics for one speaker's recorded audio
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserAudioStats {
+    /// Highest absolute sample value
+    pub peak: u16,
+    /// Samples at full scale (likely clipped)
+    pub clipped_samples: u64,
+    pub total_samples: u64,
+    sum_squares: f64,
+}
+
+impl UserAudioStats {
+    pub fn from_samples(samples: &[i16]) -> Self {
+        let mut stats = Self::default();
+        stats.update(samples);
+        stats
+    }
+
+    /// Accumulate more samples
+    pub fn update(&mut self, samples: &[i16]) {
+        for &sample in samples {
+            let magnitude = sample.unsigned_abs();
+            self.peak = self.peak.max(magnitude);
+            if magnitude >= i16::MAX as u16 {
+                self.clipped_samples += 1;
+            }
+            self.sum_squares += (sample as f64).powi(2);
+        }
+        self.total_samples += samples.len() as u64;
+    }
+
+    /// Combine with another set of statistics (e.g. a second SSRC of the same user)
+    pub fn merge(&mut self, other: &UserAudioStats) {
+        self.peak = self.peak.max(other.peak);
+        self.clipped_samples += other.clipped_samples;
+        self.total_samples += other.total_samples;
+        self.sum_squares += other.sum_squares;
+    }
+
+    /// Peak level relative to full scale
+    pub fn peak_dbfs(&self) -> f32 {
+        amplitude_to_dbfs(self.peak as f64)
+    }
+
+    /// Integrated RMS level relative to full scale
+    pub fn rms_dbfs(&self) -> f32 {
+        if self.total_samples == 0 {
+            return f32::NEG_INFINITY;
+        }
+        amplitude_to_dbfs((self.sum_squares / self.total_samples as f64).sqrt())
+    }
+}
+
+fn amplitude_to_dbfs(amplitude: f64) -> f32 {
+    if amplitude <= 0.0 {
+        return f32::NEG_INFINITY;
+    }
+    (20.0 * (amplitude / i16::MAX as f64).log10()) as f32
+}
+
+/// Write interleaved 16-bit PCM samples to a WAV file at the given depth
+pub fn save_wav(
+    path: &Path,
+    samples: &[i16],
+    sample_rate: u32,
+    channels: u16,
+    depth: WavDepth,
+) -> Result<(), hound::Error> {
+    let mut writer = WavWriter::create(path, depth.spec(sample_rate, channels))?;
+    for &sample in samples {
+        write_wav_sample(&mut writer, i16_to_f32(sample), depth)?;
+    }
+    writer.finalize()
+}
+
+/// Encode interleaved 16-bit PCM samples to a FLAC file
+pub fn save_flac(path: &Path, samples: &[i16], sample_rate: u32, channels: u16) -> io::Result<()> {
+    let config = flacenc::config::Encoder::default()
+        .into_verified()
+        .map_err(|(_, e)| io::Error::other(format!("Invalid FLAC encoder config: {:?}", e)))?;
+
+    let samples: Vec<i32> = samples.iter().map(|&s| s as i32).collect();
+    let source =
+        flacenc::source::MemSource::from_samples(&samples, channels as usize, 16, sample_rate as usize);
+
+    let stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|e| io::Error::other(format!("FLAC encoding failed: {:?}", e)))?;
+
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    stream
+        .write(&mut sink)
+        .map_err(|e| io::Error::other(format!("FLAC output failed: {:?}", e)))?;
+
+    std::fs::write(path, sink.as_slice())
+}
+
+/// MP3 bitrate for exported speech, small enough to attach to Discord messages
+const MP3_BITRATE: mp3lame_encoder::Bitrate = mp3lame_encoder::Bitrate::Kbps96;
+/// Size of one second of MP3 at `MP3_BITRATE`
+pub const MP3_BYTES_PER_SEC: usize = 96_000 / 8;
+
+/// Encode interleaved 16-bit PCM samples (mono or stereo) to an MP3 file
+pub fn save_mp3(path: &Path, samples: &[i16], sample_rate: u32, channels: u16) -> io::Result<()> {
+    std::fs::write(path, encode_mp3(samples, sample_rate, channels)?)
+}
+
+/// Encode interleaved 16-bit PCM samples (mono or stereo) to MP3 in memory
+pub fn encode_mp3(samples: &[i16], sample_rate: u32, channels: u16) -> io::Result<Vec<u8>> {
+    let build_error = |e| io::Error::other(format!("Invalid MP3 encoder config: {:?}", e));
+
+    let mut builder = mp3lame_encoder::Builder::new()
+        .ok_or_else(|| io::Error::other("Failed to create MP3 encoder"))?;
+    builder.set_num_channels(channels as u8).map_err(build_error)?;
+    builder.set_sample_rate(sample_rate).map_err(build_error)?;
+    builder.set_brate(MP3_BITRATE).map_err(build_error)?;
+    builder.set_quality(mp3lame_encoder::Quality::Good).map_err(build_error)?;
+    let mut encoder = builder.build().map_err(build_error)?;
+
+    let encode_error = |e| io::Error::other(format!("MP3 encoding failed: {:?}", e));
+    let mut mp3 = Vec::with_capacity(mp3lame_encoder::max_required_buffer_size(samples.len()));
+    match channels {
+        1 => encoder.encode_to_vec(mp3lame_encoder::MonoPcm(samples), &mut mp3),
+        2 => encoder.encode_to_vec(mp3lame_encoder::InterleavedPcm(samples), &mut mp3),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("MP3 supports 1 or 2 channels, got {}", channels),
+            ));
+        }
+    }
+    .map_err(encode_error)?;
+    encoder
+        .flush_to_vec::<mp3lame_encoder::FlushNoGap>(&mut mp3)
+        .map_err(encode_error)?;
+
+    Ok(mp3)
+}
+
+/// Read a 16-bit PCM WAV file, returning (interleaved samples, sample rate, channels)
+pub fn read_wav(path: &Path) -> Result<(Vec<i16>, u32, u16), hound::Error> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+
+    if spec.sample_format != SampleFormat::Int || spec.bits_per_sample > 16 {
+        return Err(hound::Error::Unsupported);
+    }
+
+    let samples = reader.samples::<i16>().collect::<Result<Vec<_>, _>>()?;
+
+    Ok((samples, spec.sample_rate, spec.channels))
+}
+
+/// High-pass cutoff for the speech profile, removes rumble and handling noise
+const SPEECH_HIGH_PASS_HZ: f32 = 100.0;
+/// Centre of the presence boost, where consonant intelligibility lives
+const SPEECH_PRESENCE_HZ: f32 = 3000.0;
+const SPEECH_PRESENCE_GAIN_DB: f32 = 4.0;
+/// Peak level the speech profile normalizes to
+const SPEECH_TARGET_PEAK: f32 = 0.9;
+/// Cap on normalization gain so quiet tracks don't get their noise floor blown up
+const SPEECH_MAX_GAIN: f32 = 4.0;
+
+/// Second-order IIR filter (coefficients from the RBJ audio EQ cookbook)
+#[derive(Debug, Clone)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    fn from_coefficients(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    /// Second-order high-pass filter
+    pub fn high_pass(sample_rate: u32, cutoff_hz: f32, q: f32) -> Self {
+        let w0 = 2.0 * std::f32::consts::PI * cutoff_hz / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos_w0 = w0.cos();
+
+        Self::from_coefficients(
+            (1.0 + cos_w0) / 2.0,
+            -(1.0 + cos_w0),
+            (1.0 + cos_w0) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos_w0,
+            1.0 - alpha,
+        )
+    }
+
+    /// Peaking EQ boosting (or cutting) `gain_db` around `center_hz`
+    pub fn peaking(sample_rate: u32, center_hz: f32, q: f32, gain_db: f32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        let w0 = 2.0 * std::f32::consts::PI * center_hz / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos_w0 = w0.cos();
+
+        Self::from_coefficients(
+            1.0 + alpha * a,
+            -2.0 * cos_w0,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos_w0,
+            1.0 - alpha / a,
+        )
+    }
+
+    /// Filter one sample (transposed direct form II)
+    pub fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+
+    /// Filter a buffer in place
+    pub fn process_buffer(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+}
+
+/// Scale samples so the peak reaches `target_peak`, with gain capped at `max_gain`
+pub fn normalize_peak(samples: &mut [f32], target_peak: f32, max_gain: f32) {
+    let peak = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    if peak <= 0.0 {
+        return;
+    }
+
+    let gain = (target_peak / peak).min(max_gain);
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+}
+
+/// Speech intelligibility profile for muffled, low-bitrate voice audio
+///
+/// High-pass at 100Hz, +4dB presence boost around 3kHz, then peak normalization.
+pub fn apply_speech_profile(samples: &mut [f32], sample_rate: u32) {
+    Biquad::high_pass(sample_rate, SPEECH_HIGH_PASS_HZ, std::f32::consts::FRAC_1_SQRT_2)
+        .process_buffer(samples);
+    Biquad::peaking(sample_rate, SPEECH_PRESENCE_HZ, 1.0, SPEECH_PRESENCE_GAIN_DB)
+        .process_buffer(samples);
+    normalize_peak(samples, SPEECH_TARGET_PEAK, SPEECH_MAX_GAIN);
+}
+
+/// Windows quieter than this are silence for fixed-threshold detection (1% of full scale)
+pub const SILENCE_DBFS: f32 = -40.0;
+
+/// A user whose loudest sample stays below this never spoke (a few steps of 16-bit audio)
+pub const SILENT_USER_PEAK_DBFS: f32 = -80.0;
+
+/// Whether a whole recording is silence, with no sample peaking above `SILENT_USER_PEAK_DBFS`
+///
+/// Users who stayed muted can still produce frames, but only of digital silence.
+pub fn is_all_silence(samples: impl IntoIterator<Item = f32>) -> bool {
+    let peak = samples.into_iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    amplitude_to_db(peak) < SILENT_USER_PEAK_DBFS
+}
+
+/// Level of an amplitude (full scale at 1.0) in dBFS
+pub fn amplitude_to_db(amplitude: f32) -> f32 {
+    if amplitude <= 0.0 {
+        return f32::NEG_INFINITY;
+    }
+    20.0 * amplitude.log10()
+}
+
+/// RMS level of a window of samples (full scale at 1.0) in dBFS, `-inf` when empty or silent
+pub fn window_rms_db(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return f32::NEG_INFINITY;
+    }
+    let sum_squares: f32 = samples.iter().map(|s| s * s).sum();
+    amplitude_to_db((sum_squares / samples.len() as f32).sqrt())
+}
+
+/// Whether a window's RMS level is below `threshold_db` (dBFS)
+pub fn is_silent(samples: &[f32], threshold_db: f32) -> bool {
+    window_rms_db(samples) < threshold_db
+}
+
+/// Settings for energy-based voice activity detection with an adaptive noise floor
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveVadConfig {
+    /// How quickly the noise floor follows non-speech energy (0..1, per window)
+    pub adaptation_rate: f32,
+    /// A window is speech when its energy exceeds the noise floor by this many dB
+    pub margin_db: f32,
+    /// The noise floor never drops below this level (digital silence would pin it at -inf)
+    pub min_floor_db: f32,
+}
+
+impl Default for AdaptiveVadConfig {
+    fn default() -> Self {
+        Self {
+            adaptation_rate: 0.05,
+            margin_db: 10.0,
+            min_floor_db: -60.0,
+        }
+    }
+}
+
+impl AdaptiveVadConfig {
+    /// Check the floor can adapt at all and speech has to stand out from it
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.adaptation_rate > 0.0 && self.adaptation_rate <= 1.0) {
+            return Err(format!(
+                "VAD adaptation rate must be above 0 and at most 1, got {}",
+                self.adaptation_rate
+            ));
+        }
+        if self.margin_db <= 0.0 || self.margin_db.is_nan() {
+            return Err(format!("VAD margin must be above 0 dB, got {}", self.margin_db));
+        }
+        Ok(())
+    }
+}
+
+/// Per-window voice activity, judged against an exponential moving-average noise floor
+///
+/// The floor is seeded from the first window and only adapts on non-speech windows:
+/// it drops immediately to quieter windows and rises slowly towards louder ones, so
+/// rising background noise (fans, wind) is tracked without being mistaken for speech.
+pub fn detect_voice_activity_adaptive(
+    samples: &[f32],
+    window_size: usize,
+    config: &AdaptiveVadConfig,
+) -> Vec<bool> {
+    let mut floor_db: Option<f32> = None;
+
+    samples
+        .chunks(window_size.max(1))
+        .map(|window| {
+            let energy_db = window_rms_db(window).max(config.min_floor_db);
+
+            let floor = *floor_db.get_or_insert(energy_db);
+            let active = energy_db > floor + config.margin_db;
+            if !active {
+                let next = if energy_db < floor {
+                    energy_db
+                } else {
+                    floor + config.adaptation_rate * (energy_db - floor)
+                };
+                floor_db = Some(next.max(config.min_floor_db));
+            }
+            active
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wav_info_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tagged.wav");
+        let mut writer = WavWriter::create(&path, WavDepth::Int16.spec(48000, 1)).unwrap();
+        for sample in [1i16, -2, 3] {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        assert_eq!(WavInfo::read(&path).unwrap(), None);
+
+        let info = WavInfo {
+            artist: Some("Zoë".to_string()),
+            comment: Some("writey session 2026_01_03_18_49_53".to_string()),
+            created: Some("2026-01-03".to_string()),
+        };
+        info.append_to(&path).unwrap();
+
+        assert_eq!(WavInfo::read(&path).unwrap(), Some(info));
+        let riff_size = u32::from_le_bytes(std::fs::read(&path).unwrap()[4..8].try_into().unwrap());
+        assert_eq!(riff_size as u64, std::fs::metadata(&path).unwrap().len() - 8);
+        // Samples are untouched for readers that skip the tags
+        let samples: Vec<i16> = WavReader::open(&path).unwrap().into_samples().map(|s| s.unwrap()).collect();
+        assert_eq!(samples, [1, -2, 3]);
+    }
+
+    #[test]
+    fn test_db_conversions_and_silence() {
+        assert!((amplitude_to_db(1.0)).abs() < 1e-6);
+        assert!((amplitude_to_db(0.01) - SILENCE_DBFS).abs() < 1e-4);
+        assert!((amplitude_to_db(0.5) + 6.0206).abs() < 1e-3);
+        assert_eq!(amplitude_to_db(0.0), f32::NEG_INFINITY);
+
+        // Same decisions as the former normalized RMS threshold of 0.01
+        assert!(is_silent(&[0.0099; 100], SILENCE_DBFS));
+        assert!(!is_silent(&[0.0101; 100], SILENCE_DBFS));
+        assert!(!is_silent(&[0.02, -0.02, 0.0, 0.0], SILENCE_DBFS));
+        assert!(is_silent(&[], SILENCE_DBFS));
+
+        assert!(is_all_silence([0.0, 0.00005, -0.00009]));
+        assert!(!is_all_silence([0.0, 0.0, -0.0002, 0.0]));
+        assert!(is_all_silence([]));
+        assert!((window_rms_db(&[0.5, -0.5]) - amplitude_to_db(0.5)).abs() < 1e-6);
+    }
+
+    fn sine(freq_hz: f32, sample_rate: u32, amplitude: f32) -> Vec<f32> {
+        (0..sample_rate)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                amplitude * (2.0 * std::f32::consts::PI * freq_hz * t).sin()
+            })
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        // Skip the filter's settling time
+        let settled = &samples[samples.len() / 10..];
+        (settled.iter().map(|s| s * s).sum::<f32>() / settled.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_high_pass_attenuates_rumble() {
+        let mut rumble = sine(30.0, 16000, 0.5);
+        let mut voice = sine(1000.0, 16000, 0.5);
+        let (rumble_before, voice_before) = (rms(&rumble), rms(&voice));
+
+        Biquad::high_pass(16000, 100.0, std::f32::consts::FRAC_1_SQRT_2).process_buffer(&mut rumble);
+        Biquad::high_pass(16000, 100.0, std::f32::consts::FRAC_1_SQRT_2).process_buffer(&mut voice);
+
+        assert!(rms(&rumble) < rumble_before * 0.2);
+        assert!((rms(&voice) - voice_before).abs() < voice_before * 0.05);
+    }
+
+    #[test]
+    fn test_presence_boost_raises_3khz_band() {
+        let mut presence = sine(3000.0, 16000, 0.2);
+        let mut low_mid = sine(300.0, 16000, 0.2);
+        let (presence_before, low_mid_before) = (rms(&presence), rms(&low_mid));
+
+        Biquad::peaking(16000, 3000.0, 1.0, 4.0).process_buffer(&mut presence);
+        Biquad::peaking(16000, 3000.0, 1.0, 4.0).process_buffer(&mut low_mid);
+
+        // +4dB is roughly 1.58x in amplitude
+        assert!(rms(&presence) > presence_before * 1.5);
+        assert!((rms(&low_mid) - low_mid_before).abs() < low_mid_before * 0.1);
+    }
+
+    #[test]
+    fn test_speech_profile_normalizes_with_capped_gain() {
+        let mut loud = sine(1000.0, 16000, 0.3);
+        apply_speech_profile(&mut loud, 16000);
+        let peak = loud.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!((peak - SPEECH_TARGET_PEAK).abs() < 0.01);
+
+        let mut quiet = sine(1000.0, 16000, 0.01);
+        apply_speech_profile(&mut quiet, 16000);
+        let peak = quiet.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        // Gain is capped, so a very quiet track stays well below the target
+        assert!(peak < SPEECH_TARGET_PEAK / 10.0);
+    }
+
+    #[test]
+    fn test_downmix_modes() {
+        let stereo: Vec<i16> = vec![100, 300, -400, 200, 7];
+
+        assert_eq!(downmix(&stereo, StereoDownmix::Average), vec![200, -100, 7]);
+        assert_eq!(downmix(&stereo, StereoDownmix::Left), vec![100, -400, 7]);
+        assert_eq!(downmix(&stereo, StereoDownmix::Right), vec![300, 200, 7]);
+        assert_eq!(downmix(&stereo, StereoDownmix::MaxChannel), vec![300, -400, 7]);
+    }
+
+    #[test]
+    fn test_out_of_phase_average_cancels() {
+        let stereo: Vec<i16> = vec![1000, -1000, -500, 500];
+
+        assert_eq!(downmix(&stereo, StereoDownmix::Average), vec![0, 0]);
+        assert_eq!(downmix(&stereo, StereoDownmix::Left), vec![1000, -500]);
+    }
+
+    #[test]
+    fn test_mix_tracks_weighted() {
+        let a: Vec<i16> = vec![1000, -2000, 3000];
+        let b: Vec<i16> = vec![500, 500];
+
+        let to_i16 = |mixed: Vec<f32>| mixed.into_iter().map(f32_to_i16).collect::<Vec<_>>();
+
+        // A zero-weight track contributes nothing
+        assert_eq!(to_i16(mix_tracks_weighted(&[(&a, 1.0), (&b, 0.0)])), a);
+
+        // Weights scale linearly, shorter tracks are padded with silence
+        assert_eq!(to_i16(mix_tracks_weighted(&[(&a, 0.5), (&b, 2.0)])), vec![1500, 0, 1500]);
+
+        // The weighted sum keeps its headroom and is only clamped when converted to i16
+        let loud = mix_tracks_weighted(&[(&a, 40.0)]);
+        assert!((loud[2] - 120_000.0 / 32768.0).abs() < 1e-4);
+        assert_eq!(to_i16(loud), vec![i16::MAX, i16::MIN, i16::MAX]);
+    }
+
+    #[test]
+    fn test_user_audio_stats() {
+        let mut stats = UserAudioStats::from_samples(&[i16::MAX, i16::MIN, 0, 100]);
+        assert_eq!(stats.peak, 32768);
+        assert_eq!(stats.clipped_samples, 2);
+        assert!(stats.peak_dbfs().abs() < 0.01);
+
+        // A full-scale square wave has an RMS of 0 dBFS, half amplitude is about -6 dBFS
+        let half = UserAudioStats::from_samples(&[16384, -16384, 16384, -16384]);
+        assert!((half.rms_dbfs() + 6.02).abs() < 0.05);
+        assert_eq!(half.clipped_samples, 0);
+
+        stats.merge(&half);
+        assert_eq!(stats.total_samples, 8);
+        assert_eq!(stats.clipped_samples, 2);
+
+        assert_eq!(UserAudioStats::default().rms_dbfs(), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_level_meter_flags_dead_mic() {
+        let mut meter = LevelMeter::default();
+        let speech = [8000i16, -8000].repeat(480);
+
+        // Five seconds of speech settle near the frame level (about -12 dBFS)
+        for _ in 0..250 {
+            meter.update(&speech);
+        }
+        assert!((meter.rms_dbfs() + 12.3).abs() < 0.5, "{}", meter.rms_dbfs());
+        assert_eq!(meter.quiet_secs(), 0.0);
+
+        // The mic goes dead: the level decays and quiet time accumulates
+        for _ in 0..1000 {
+            meter.update(&[0; 960]);
+        }
+        meter.update_silent();
+        assert!(meter.rms_dbfs() < NEAR_SILENT_DBFS);
+        assert!(meter.quiet_secs() > 10.0 && meter.quiet_secs() < 20.0);
+
+        meter.update(&speech);
+        meter.update(&speech);
+        assert!(meter.rms_dbfs() > NEAR_SILENT_DBFS);
+        assert_eq!(meter.quiet_secs(), 0.0);
+    }
+
+    #[test]
+    fn test_wav_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("roundtrip.wav");
+        let samples: Vec<i16> = vec![0, 1000, -1000, i16::MAX, i16::MIN, 42];
+
+        save_wav(&path, &samples, 48000, 2, WavDepth::Int16).unwrap();
+        let (read, sample_rate, channels) = read_wav(&path).unwrap();
+
+        assert_eq!(read, samples);
+        assert_eq!(sample_rate, 48000);
+        assert_eq!(channels, 2);
+    }
+
+    #[test]
+    fn test_wav_depths() {
+        let dir = tempfile::tempdir().unwrap();
+        let samples: Vec<i16> = vec![0, 1000, i16::MIN];
+
+        for (depth, bits, format) in [
+            (WavDepth::Int16, 16, SampleFormat::Int),
+            (WavDepth::Int24, 24, SampleFormat::Int),
+            (WavDepth::Float32, 32, SampleFormat::Float),
+        ] {
+            let path = dir.path().join(format!("{}.wav", bits));
+            save_wav(&path, &samples, 48000, 1, depth).unwrap();
+
+            let mut reader = WavReader::open(&path).unwrap();
+            let spec = reader.spec();
+            assert_eq!((spec.bits_per_sample, spec.sample_format), (bits, format));
+            assert_eq!(spec.sample_rate, 48000);
+            assert_eq!(reader.len(), 3);
+
+            match depth {
+                WavDepth::Float32 => {
+                    let read: Vec<f32> = reader.samples::<f32>().map(|s| s.unwrap()).collect();
+                    assert_eq!(read, vec![0.0, 1000.0 / 32768.0, -1.0]);
+                }
+                _ => {
+                    let read: Vec<i32> = reader.samples::<i32>().map(|s| s.unwrap()).collect();
+                    let scale = 1 << (bits - 16);
+                    assert_eq!(read, vec![0, 1000 * scale, i16::MIN as i32 * scale]);
+                }
+            }
+        }
+
+        // Levels above full scale survive only in float
+        let mut writer = WavWriter::create(dir.path().join("hot.wav"), WavDepth::Float32.spec(48000, 1)).unwrap();
+        write_wav_sample(&mut writer, 1.5, WavDepth::Float32).unwrap();
+        writer.finalize().unwrap();
+        let hot: Vec<f32> = WavReader::open(dir.path().join("hot.wav"))
+            .unwrap()
+            .samples::<f32>()
+            .map(|s| s.unwrap())
+            .collect();
+        assert_eq!(hot, vec![1.5]);
+    }
+
+    #[test]
+    fn test_soft_clip() {
+        assert_eq!(ClipMode::Hard.apply(1.5), 1.0);
+        assert_eq!(ClipMode::Hard.apply(-0.5), -0.5);
+
+        // Below the knee samples pass through unchanged
+        assert_eq!(ClipMode::Soft.apply(0.5), 0.5);
+        assert_eq!(ClipMode::Soft.apply(-0.8), -0.8);
+
+        let over = ClipMode::Soft.apply(1.5);
+        assert!(over > 0.99 && over < 1.0, "{}", over);
+        assert_eq!(ClipMode::Soft.apply(-1.5), -over);
+        assert!(f32_to_i16(over) < i16::MAX);
+
+        // Smooth: monotonic, with no jump at the knee, never past full scale
+        let curve: Vec<f32> = (0..=300).map(|i| ClipMode::Soft.apply(i as f32 * 0.01)).collect();
+        assert!(curve.windows(2).all(|w| w[1] >= w[0] && w[1] - w[0] <= 0.01 + 1e-6));
+        assert!(curve.iter().all(|&s| s <= 1.0));
+    }
+
+    #[test]
+    fn test_adaptive_vad_tracks_rising_noise() {
+        let sample_rate = 16000;
+        let window = 1600;
+        // 30s of hum rising from 0.005 to 0.03, well past a fixed 0.01 threshold
+        let mut samples: Vec<f32> = (0..sample_rate * 30)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                let level = 0.005 + 0.025 * (t / 30.0);
+                level * (2.0 * std::f32::consts::PI * 120.0 * t).sin()
+            })
+            .collect();
+        // One second of speech-level signal at 10s and at 25s
+        for start_sec in [10, 25] {
+            let start = start_sec * sample_rate as usize;
+            for (i, s) in samples[start..start + sample_rate as usize].iter_mut().enumerate() {
+                *s += 0.3 * (2.0 * std::f32::consts::PI * 300.0 * i as f32 / sample_rate as f32).sin();
+            }
+        }
+
+        let activity = detect_voice_activity_adaptive(&samples, window, &AdaptiveVadConfig::default());
+        let windows_per_sec = sample_rate as usize / window;
+        let active_secs: Vec<usize> = activity
+            .iter()
+            .enumerate()
+            .filter(|(_, active)| **active)
+            .map(|(i, _)| i / windows_per_sec)
+            .collect();
+
+        assert_eq!(activity.len(), 30 * windows_per_sec);
+        assert_eq!(active_secs.len(), 2 * windows_per_sec);
+        assert!(active_secs.iter().all(|&s| s == 10 || s == 25));
+
+        // A floor that never moves or a zero margin would call everything speech
+        assert!(AdaptiveVadConfig::default().validate().is_ok());
+        let config = |adaptation_rate, margin_db| AdaptiveVadConfig {
+            adaptation_rate,
+            margin_db,
+            ..AdaptiveVadConfig::default()
+        };
+        assert!(config(0.0, 10.0).validate().is_err());
+        assert!(config(1.5, 10.0).validate().is_err());
+        assert!(config(0.2, 0.0).validate().is_err());
+        assert!(config(1.0, 3.0).validate().is_ok());
+    }
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Nominal duration of one voice tick (20ms frames)
+pub const NOMINAL_TICK_SECS: f64 = 0.02;
+/// Ticks between wall-clock anchors (one minute at the nominal rate)
+pub const ANCHOR_INTERVAL_TICKS: u64 = 3000;
+
+/// Seconds from `origin_tick` to `tick` at the nominal rate, 0 for ticks before the origin
+///
+/// Places a user's track on the session timeline, where the session starts at
+/// the earliest first tick of any track.
+pub fn tick_offset_secs(tick: u64, origin_tick: u64) -> f32 {
+    (tick.saturating_sub(origin_tick) as f64 * NOMINAL_TICK_SECS) as f32
+}
+
+/// Wall-clock time at which a tick was received
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TickAnchor {
+    pub tick_index: u64,
+    pub unix_ms: i64,
+}
+
+/// Tick to wall-clock calibration for a recording session (`clock.json`)
+///
+/// Timestamps assume every tick is exactly 20ms. The driver's timer follows the
+/// host clock closely, so on a healthy host the drift stays in the tens of
+/// milliseconds per hour; an overloaded host that delays or drops ticks can
+/// drift by seconds per hour. Anchors recorded every minute measure the real
+/// rate so timestamps can be mapped back to wall-clock time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionClock {
+    pub anchors: Vec<TickAnchor>,
+}
+
+impl SessionClock {
+    pub fn load(path: &Path) -> Option<Self> {
+        let json = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn record(&mut self, anchor: TickAnchor) {
+        self.anchors.push(anchor);
+    }
+
+    /// Wall-clock start of the recording
+    pub fn start_unix_ms(&self) -> Option<i64> {
+        self.anchors.first().map(|a| a.unix_ms)
+    }
+
+    /// Measured seconds per tick over the whole recording (nominal without enough anchors)
+    pub fn secs_per_tick(&self) -> f64 {
+        match (self.anchors.first(), self.anchors.last()) {
+            (Some(first), Some(last)) if last.tick_index > first.tick_index => {
+                (last.unix_ms - first.unix_ms) as f64
+                    / 1000.0
+                    / (last.tick_index - first.tick_index) as f64
+            }
+            _ => NOMINAL_TICK_SECS,
+        }
+    }
+
+    /// Wall-clock seconds since the first anchor at a (fractional) tick
+    ///
+    /// Interpolates between the surrounding anchors and extrapolates past the
+    /// ends using the nearest pair.
+    pub fn tick_to_secs(&self, tick: f64) -> f64 {
+        let Some(first) = self.anchors.first() else {
+            return tick * NOMINAL_TICK_SECS;
+        };
+        if self.anchors.len() < 2 {
+            return (tick - first.tick_index as f64) * NOMINAL_TICK_SECS;
+        }
+
+        let pair = self
+            .anchors
+            .windows(2)
+            .find(|pair| tick < pair[1].tick_index as f64)
+            .unwrap_or(&self.anchors[self.anchors.len() - 2..]);
+        let (a, b) = (pair[0], pair[1]);
+
+        let ms_per_tick = (b.unix_ms - a.unix_ms) as f64 / (b.tick_index - a.tick_index) as f64;
+        let unix_ms = a.unix_ms as f64 + (tick - a.tick_index as f64) * ms_per_tick;
+        (unix_ms - first.unix_ms as f64) / 1000.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_clock_corrects_slow_ticks() {
+        let mut clock = SessionClock::default();
+        // Ticks arrive 1% slow for the first minute, on time for the second
+        clock.record(TickAnchor {
+            tick_index: 0,
+            unix_ms: 1_000_000,
+        });
+        clock.record(TickAnchor {
+            tick_index: 3000,
+            unix_ms: 1_060_600,
+        });
+        clock.record(TickAnchor {
+            tick_index: 6000,
+            unix_ms: 1_120_600,
+        });
+
+        assert_eq!(clock.start_unix_ms(), Some(1_000_000));
+        assert!((clock.secs_per_tick() - 120.6 / 6000.0).abs() < 1e-9);
+        assert!((clock.tick_to_secs(1500.0) - 30.3).abs() < 1e-9);
+        assert!((clock.tick_to_secs(4500.0) - 90.6).abs() < 1e-9);
+        // Past the last anchor the last measured rate is used
+        assert!((clock.tick_to_secs(9000.0) - 180.6).abs() < 1e-9);
+
+        assert_eq!(SessionClock::default().tick_to_secs(50.0), 1.0);
+    }
+
+    #[test]
+    fn test_tick_offset_secs() {
+        assert_eq!(tick_offset_secs(150, 100), 1.0);
+        assert_eq!(tick_offset_secs(100, 100), 0.0);
+        assert_eq!(tick_offset_secs(50, 100), 0.0);
+    }
+}
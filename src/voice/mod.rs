@@ -1,6 +1,10 @@
 pub mod audio;
+pub mod clock;
 pub mod receiver;
+pub mod reconnect;
+pub mod ssrc_history;
 pub mod storage;
+pub mod waveform;
 
 pub use receiver::{Receiver, SharedRecordingState, create_recording_session};
 pub use storage::StorageWriter;
@@ -1,16 +1,181 @@
-use super::audio::stereo_to_mono;
-use super::storage::{AudioFrame, StorageHandle};
+use super::audio::{LevelMeter, StereoDownmix, downmix};
+use super::clock::{ANCHOR_INTERVAL_TICKS, NOMINAL_TICK_SECS, TickAnchor};
+use super::ssrc_history::SsrcAssignment;
+use super::storage::{AudioFrame, ResumePoint, SAMPLES_PER_TICK, StorageHandle};
+use crate::paths::{PacketStats, RecordingGap};
+use chrono::{DateTime, Utc};
 use songbird::{
-    Event, EventContext, EventHandler, events::context_data::VoiceTick, model::payload::Speaking,
+    Event, EventContext, EventHandler,
+    driver::{Channels, SampleRate},
+    events::context_data::VoiceTick,
+    model::payload::Speaking,
 };
-use std::{collections::HashMap, sync::Arc};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Layout songbird decodes voice to, the driver config must use these
+pub const DECODE_CHANNELS: Channels = Channels::Stereo;
+pub const DECODE_SAMPLE_RATE: SampleRate = SampleRate::Hz48000;
+
+/// A decoded 20ms packet as the mono `SAMPLES_PER_TICK` frame storage writes
+///
+/// The channel count is taken from the length, so audio already decoded to mono
+/// isn't downmixed again, which would halve it and play back at double speed.
+fn decoded_to_mono(decoded: &[i16], mode: StereoDownmix) -> Vec<i16> {
+    if decoded.len() == SAMPLES_PER_TICK {
+        decoded.to_vec()
+    } else {
+        downmix(decoded, mode)
+    }
+}
+
+/// What to store for ticks where a known user produced no audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SilencePolicy {
+    /// Store nothing; readers fill missing ticks with silence
+    #[default]
+    Gaps,
+    /// Store explicit silence frames for every known user on every tick
+    ///
+    /// Produces continuous logs at the cost of much larger files.
+    WriteSilence,
+}
+
+impl std::fmt::Display for SilencePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SilencePolicy::Gaps => write!(f, "gaps"),
+            SilencePolicy::WriteSilence => write!(f, "write"),
+        }
+    }
+}
+
+impl std::str::FromStr for SilencePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gaps" => Ok(SilencePolicy::Gaps),
+            "write" | "write_silence" => Ok(SilencePolicy::WriteSilence),
+            _ => Err(format!("Unknown silence policy: {}. Use gaps or write", s)),
+        }
+    }
+}
+
+/// What to store for ticks where a speaking user's audio could not be decoded
+///
+/// Happens when packets are lost or arrive corrupt. Loss is usually sparse, a
+/// tick or two at a time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecodeFailurePolicy {
+    /// Store nothing, leaving a gap readers fill with silence
+    #[default]
+    Skip,
+    /// Store an explicit silence frame
+    Silence,
+    /// Fade from the last good frame into the next one, for up to `MAX_INTERPOLATED_TICKS`
+    Interpolate,
+}
+
+impl std::fmt::Display for DecodeFailurePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeFailurePolicy::Skip => write!(f, "skip"),
+            DecodeFailurePolicy::Silence => write!(f, "silence"),
+            DecodeFailurePolicy::Interpolate => write!(f, "interpolate"),
+        }
+    }
+}
+
+impl std::str::FromStr for DecodeFailurePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(DecodeFailurePolicy::Skip),
+            "silence" => Ok(DecodeFailurePolicy::Silence),
+            "interpolate" => Ok(DecodeFailurePolicy::Interpolate),
+            _ => Err(format!(
+                "Unknown decode failure policy: {}. Use skip, silence or interpolate",
+                s
+            )),
+        }
+    }
+}
+
+/// Longer runs of undecodable ticks are left as gaps, a fade over them would smear
+pub const MAX_INTERPOLATED_TICKS: usize = 5;
+/// Minimum time between decode failure warnings for one SSRC
+const DECODE_WARNING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Rate limit for decode failure warnings, one SSRC
+///
+/// Packet loss can fail every other tick, a warning each time buries the log.
+#[derive(Debug, Default)]
+pub struct DecodeFailureLog {
+    last_warned: Option<Instant>,
+    since_warned: u64,
+}
+
+impl DecodeFailureLog {
+    /// Count a failure, returning how many to report if a warning is due
+    fn record(&mut self, now: Instant) -> Option<u64> {
+        self.since_warned += 1;
+        if self
+            .last_warned
+            .is_some_and(|at| now.duration_since(at) < DECODE_WARNING_INTERVAL)
+        {
+            return None;
+        }
+        self.last_warned = Some(now);
+        Some(std::mem::take(&mut self.since_warned))
+    }
+}
+
+/// A frame between `prev` and `next`, `weight` of the way from one to the other
+fn interpolate_frame(prev: &[i16], next: &[i16], weight: f32) -> Vec<i16> {
+    prev.iter()
+        .zip(next)
+        .map(|(&a, &b)| (a as f32 + (b as f32 - a as f32) * weight).round() as i16)
+        .collect()
+}
+
+/// Audio of one SSRC waiting on its next good frame to interpolate the ticks in between
+#[derive(Debug, Default)]
+struct PendingInterpolation {
+    /// Last decoded frame and its tick
+    last_frame: Option<(u64, Vec<i16>)>,
+    /// Undecodable ticks since then
+    missing_ticks: Vec<u64>,
+}
 
 pub struct RecordingState {
     pub active: bool,
     pub tick_index: u64,
     pub ssrc_map: HashMap<u32, u64>,
     pub storage: Option<StorageHandle>,
+    pub downmix: StereoDownmix,
+    pub silence_policy: SilencePolicy,
+    pub decode_policy: DecodeFailurePolicy,
+    /// Live level per SSRC, for `/recording-status`
+    pub levels: HashMap<u32, LevelMeter>,
+    /// Received, lost and undecodable RTP packets per SSRC, saved to the session metadata
+    pub packet_stats: HashMap<u32, PacketStats>,
+    decode_failure_logs: HashMap<u32, DecodeFailureLog>,
+    interpolation: HashMap<u32, PendingInterpolation>,
+    /// When the last voice tick arrived, to measure connection gaps
+    pub last_tick_at: Option<Instant>,
+    /// Why the voice connection was last lost, until it is back
+    pub disconnect_reason: Option<String>,
+    /// Connection gaps skipped over in the tick timeline, saved to the session metadata
+    pub gaps: Vec<RecordingGap>,
+    /// A rejoin is in progress, so further disconnect events are from its attempts
+    pub rejoining: bool,
+    /// Users who opted in and when, for consent-gated recordings; `None` records everyone
+    pub consented: Option<BTreeMap<u64, DateTime<Utc>>>,
 }
 
 impl RecordingState {
@@ -20,18 +185,199 @@ impl RecordingState {
             tick_index: 0,
             ssrc_map: HashMap::new(),
             storage: None,
+            downmix: StereoDownmix::default(),
+            silence_policy: SilencePolicy::default(),
+            decode_policy: DecodeFailurePolicy::default(),
+            levels: HashMap::new(),
+            packet_stats: HashMap::new(),
+            decode_failure_logs: HashMap::new(),
+            interpolation: HashMap::new(),
+            last_tick_at: None,
+            disconnect_reason: None,
+            gaps: Vec::new(),
+            rejoining: false,
+            consented: None,
         }
     }
 
-    pub fn start(&mut self, storage: StorageHandle) {
+    pub fn start(
+        &mut self,
+        storage: StorageHandle,
+        downmix: StereoDownmix,
+        silence_policy: SilencePolicy,
+        decode_policy: DecodeFailurePolicy,
+    ) {
         self.active = true;
         self.tick_index = 0;
         self.ssrc_map.clear();
         self.storage = Some(storage);
+        self.downmix = downmix;
+        self.silence_policy = silence_policy;
+        self.decode_policy = decode_policy;
+        self.levels.clear();
+        self.packet_stats.clear();
+        self.decode_failure_logs.clear();
+        self.interpolation.clear();
+        self.last_tick_at = None;
+        self.disconnect_reason = None;
+        self.gaps.clear();
+        self.rejoining = false;
+        self.consented = None;
+    }
+
+    /// Whether an SSRC's audio may be kept: always without a consent gate, else
+    /// only once it belongs to a user who opted in
+    pub fn has_consent(&self, ssrc: u32) -> bool {
+        match &self.consented {
+            None => true,
+            Some(users) => self
+                .ssrc_map
+                .get(&ssrc)
+                .is_some_and(|user_id| users.contains_key(user_id)),
+        }
+    }
+
+    /// Continue the tick timeline after the voice connection comes back
+    ///
+    /// Songbird only produces ticks while connected, so without this the audio
+    /// after a drop would be placed right after the audio before it.
+    pub fn resume(&mut self) -> Option<RecordingGap> {
+        let missed = self.last_tick_at?.elapsed();
+        self.resume_after(missed)
+    }
+
+    /// Pick up the tick timeline of a session recorded before a restart
+    ///
+    /// The time the bot was away is recorded as a gap, and an anchor ties the
+    /// first new tick to the wall clock.
+    pub fn continue_from(&mut self, resume: ResumePoint) {
+        let skipped = resume.next_tick - resume.last_tick - 1;
+        if skipped > 0 {
+            self.gaps.push(RecordingGap {
+                from_tick: resume.last_tick + 1,
+                ticks: skipped,
+                reason: "bot restarted".to_string(),
+            });
+        }
+        self.tick_index = resume.next_tick;
+        if let Some(ref storage) = self.storage {
+            storage.record_tick_anchor(TickAnchor {
+                tick_index: resume.next_tick,
+                unix_ms: chrono::Utc::now().timestamp_millis(),
+            });
+        }
+    }
+
+    /// Skip the ticks that fit in `missed`, recording them as a gap
+    pub fn resume_after(&mut self, missed: Duration) -> Option<RecordingGap> {
+        let reason = self
+            .disconnect_reason
+            .take()
+            .unwrap_or_else(|| "reconnected".to_string());
+        // The tick that should have followed the last one isn't missing yet
+        let ticks = ((missed.as_secs_f64() / NOMINAL_TICK_SECS) as u64).saturating_sub(1);
+        if !self.active || ticks == 0 {
+            return None;
+        }
+
+        let gap = RecordingGap {
+            from_tick: self.tick_index,
+            ticks,
+            reason,
+        };
+        self.tick_index += ticks;
+        self.gaps.push(gap.clone());
+        Some(gap)
+    }
+
+    /// Frames to store for an SSRC's tick, given its decoded audio if any
+    ///
+    /// Applies the decode failure policy, counting and rate-limit logging failures.
+    /// With interpolation a good frame can come with the faded frames of the
+    /// ticks before it, in tick order.
+    pub fn frames_for_tick(
+        &mut self,
+        ssrc: u32,
+        tick: u64,
+        decoded: Option<Vec<i16>>,
+    ) -> Vec<AudioFrame> {
+        let write_silence = self.silence_policy == SilencePolicy::WriteSilence;
+        let interpolate = self.decode_policy == DecodeFailurePolicy::Interpolate;
+
+        let Some(samples) = decoded else {
+            self.packet_stats.entry(ssrc).or_default().undecodable += 1;
+            if let Some(count) = self
+                .decode_failure_logs
+                .entry(ssrc)
+                .or_default()
+                .record(Instant::now())
+            {
+                warn!(
+                    "SSRC {} has {} undecodable frame(s) since the last warning, {} them",
+                    ssrc, count, self.decode_policy
+                );
+            }
+
+            let pending = self.interpolation.entry(ssrc).or_default();
+            if interpolate
+                && pending.last_frame.is_some()
+                && pending.missing_ticks.len() < MAX_INTERPOLATED_TICKS
+            {
+                pending.missing_ticks.push(tick);
+                return Vec::new();
+            }
+            // Too long to fade over, the ticks so far are lost as well
+            let mut lost: Vec<u64> = std::mem::take(&mut pending.missing_ticks);
+            pending.last_frame = None;
+            lost.push(tick);
+
+            return if self.decode_policy == DecodeFailurePolicy::Silence || write_silence {
+                lost.into_iter()
+                    .map(|tick_index| AudioFrame {
+                        tick_index,
+                        samples: vec![0; SAMPLES_PER_TICK],
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+        };
+
+        let mut frames = Vec::new();
+        if interpolate {
+            let pending = self.interpolation.entry(ssrc).or_default();
+            let missing_ticks = std::mem::take(&mut pending.missing_ticks);
+            // A user who went quiet in between has nothing to fade across
+            if let Some((last_tick, last)) = &pending.last_frame
+                && tick - last_tick <= MAX_INTERPOLATED_TICKS as u64 + 1
+            {
+                let span = (tick - last_tick) as f32;
+                frames.extend(missing_ticks.into_iter().map(|missing| AudioFrame {
+                    tick_index: missing,
+                    samples: interpolate_frame(last, &samples, (missing - last_tick) as f32 / span),
+                }));
+            }
+            pending.last_frame = Some((tick, samples.clone()));
+        }
+
+        if write_silence || samples.iter().any(|&sample| sample != 0) {
+            frames.push(AudioFrame {
+                tick_index: tick,
+                samples,
+            });
+        }
+        frames
     }
 
     pub fn stop(&mut self) -> Option<StorageHandle> {
         self.active = false;
+        // A final anchor so drift is measured over the whole recording
+        if let Some(ref storage) = self.storage {
+            storage.record_tick_anchor(TickAnchor {
+                tick_index: self.tick_index,
+                unix_ms: chrono::Utc::now().timestamp_millis(),
+            });
+        }
         self.storage.take()
     }
 }
@@ -66,17 +412,30 @@ impl EventHandler for Receiver {
             }) => {
                 if let Some(user_id) = user_id {
                     let mut state = self.state.lock().await;
-                    state.ssrc_map.insert(*ssrc, user_id.0);
+                    let previous = state.ssrc_map.insert(*ssrc, user_id.0);
+                    if previous == Some(user_id.0) {
+                        return None;
+                    }
+
+                    if let Some(previous) = previous {
+                        warn!(
+                            "SSRC {} moved from user {} to user {} at tick {}",
+                            ssrc, previous, user_id.0, state.tick_index
+                        );
+                    }
 
                     if let Some(ref storage) = state.storage {
+                        storage.record_ssrc_assignment(SsrcAssignment {
+                            ssrc: *ssrc,
+                            user_id: user_id.0,
+                            from_tick: state.tick_index,
+                        });
                         storage.update_ssrc_map(state.ssrc_map.clone());
                     }
                 }
             }
             EventContext::VoiceTick(VoiceTick {
-                speaking,
-                silent: _,
-                ..
+                speaking, silent, ..
             }) => {
                 let mut state = self.state.lock().await;
 
@@ -86,37 +445,61 @@ impl EventHandler for Receiver {
 
                 let current_tick = state.tick_index;
                 state.tick_index += 1;
+                state.last_tick_at = Some(Instant::now());
+                let write_silence = state.silence_policy == SilencePolicy::WriteSilence;
 
-                for (ssrc, voice_data) in speaking {
-                    let decoded = match &voice_data.decoded_voice {
-                        Some(d) => d,
-                        None => continue,
-                    };
+                if current_tick % ANCHOR_INTERVAL_TICKS == 0
+                    && let Some(ref storage) = state.storage
+                {
+                    storage.record_tick_anchor(TickAnchor {
+                        tick_index: current_tick,
+                        unix_ms: chrono::Utc::now().timestamp_millis(),
+                    });
+                }
 
-                    if decoded.is_empty() {
+                for ssrc in silent {
+                    state.levels.entry(*ssrc).or_default().update_silent();
+                }
+
+                for (ssrc, voice_data) in speaking {
+                    // Audio of users who haven't consented is dropped before anything is kept
+                    if !state.has_consent(*ssrc) {
                         continue;
                     }
 
-                    let samples = stereo_to_mono(decoded);
-
-                    let mut is_every_sample_zero = true;
-                    for sample in samples {
-                        if sample != 0 {
-                            is_every_sample_zero = false;
-                            break;
-                        }
+                    let packets = state.packet_stats.entry(*ssrc).or_default();
+                    match voice_data.packet {
+                        Some(_) => packets.received += 1,
+                        None => packets.lost += 1,
                     }
 
-                    if is_every_sample_zero {
-                        continue;
+                    let decoded = voice_data
+                        .decoded_voice
+                        .as_ref()
+                        .filter(|decoded| !decoded.is_empty())
+                        .map(|decoded| decoded_to_mono(decoded, state.downmix));
+
+                    let meter = state.levels.entry(*ssrc).or_default();
+                    match &decoded {
+                        Some(samples) => meter.update(samples),
+                        None => meter.update_silent(),
                     }
 
+                    let frames = state.frames_for_tick(*ssrc, current_tick, decoded);
                     if let Some(ref storage) = state.storage {
+                        for frame in frames {
+                            storage.buffer_frame(*ssrc, frame);
+                        }
+                    }
+                }
+
+                if write_silence && let Some(ref storage) = state.storage {
+                    for ssrc in silent.iter().filter(|ssrc| state.has_consent(**ssrc)) {
                         storage.buffer_frame(
                             *ssrc,
                             AudioFrame {
                                 tick_index: current_tick,
-                                samples: stereo_to_mono(decoded),
+                                samples: vec![0; SAMPLES_PER_TICK],
                             },
                         );
                     }
@@ -132,3 +515,117 @@ impl EventHandler for Receiver {
 pub fn create_recording_session() -> SharedRecordingState {
     Arc::new(Mutex::new(RecordingState::new()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_skips_missed_ticks() {
+        let mut state = RecordingState::new();
+        state.active = true;
+        state.tick_index = 500;
+
+        // Regular tick spacing is not a gap
+        assert_eq!(state.resume_after(Duration::from_millis(25)), None);
+        assert_eq!(state.tick_index, 500);
+
+        state.disconnect_reason = Some("Io".to_string());
+        let gap = state.resume_after(Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            gap,
+            RecordingGap {
+                from_tick: 500,
+                ticks: 249,
+                reason: "Io".to_string(),
+            }
+        );
+        assert_eq!(state.tick_index, 749);
+        assert_eq!(state.gaps, vec![gap]);
+        assert_eq!(state.disconnect_reason, None);
+
+        // A stopped recording keeps its timeline
+        state.active = false;
+        assert_eq!(state.resume_after(Duration::from_secs(5)), None);
+        assert_eq!(state.tick_index, 749);
+    }
+
+    #[test]
+    fn test_decode_failure_policies() {
+        let frame = |level: i16| vec![level; SAMPLES_PER_TICK];
+        let ticks = |frames: &[AudioFrame]| frames.iter().map(|f| f.tick_index).collect::<Vec<_>>();
+
+        let mut state = RecordingState::new();
+        assert!(state.frames_for_tick(1, 0, Some(frame(100))).len() == 1);
+        assert!(state.frames_for_tick(1, 1, None).is_empty());
+        assert_eq!(state.packet_stats[&1].undecodable, 1);
+
+        state.decode_policy = DecodeFailurePolicy::Silence;
+        let silence = state.frames_for_tick(1, 2, None);
+        assert_eq!(ticks(&silence), vec![2]);
+        assert!(silence[0].samples.iter().all(|&s| s == 0));
+
+        // Two lost ticks between 100 and 400 fade in thirds
+        state.decode_policy = DecodeFailurePolicy::Interpolate;
+        state.frames_for_tick(1, 10, Some(frame(100)));
+        assert!(state.frames_for_tick(1, 11, None).is_empty());
+        assert!(state.frames_for_tick(1, 12, None).is_empty());
+        let frames = state.frames_for_tick(1, 13, Some(frame(400)));
+        assert_eq!(ticks(&frames), vec![11, 12, 13]);
+        assert_eq!(frames[0].samples[0], 200);
+        assert_eq!(frames[1].samples[0], 300);
+        assert_eq!(state.packet_stats[&1].undecodable, 4);
+
+        // Runs too long to fade over are dropped
+        for tick in 20..20 + MAX_INTERPOLATED_TICKS as u64 + 1 {
+            state.frames_for_tick(1, tick, None);
+        }
+        assert_eq!(
+            ticks(&state.frames_for_tick(1, 30, Some(frame(100)))),
+            vec![30]
+        );
+    }
+
+    #[test]
+    fn test_decoded_frames_match_storage_layout() {
+        // What songbird hands over for 20ms at 48kHz, stereo and mono
+        let stereo: Vec<i16> = (0..SAMPLES_PER_TICK).flat_map(|_| [100, 300]).collect();
+        let mono = vec![250i16; SAMPLES_PER_TICK];
+        assert!(matches!(DECODE_CHANNELS, Channels::Stereo));
+        assert!(matches!(DECODE_SAMPLE_RATE, SampleRate::Hz48000));
+
+        let from_stereo = decoded_to_mono(&stereo, StereoDownmix::Average);
+        assert_eq!(from_stereo.len(), SAMPLES_PER_TICK);
+        assert!(from_stereo.iter().all(|&s| s == 200));
+        assert_eq!(decoded_to_mono(&mono, StereoDownmix::Average), mono);
+
+        // Whole ticks are what storage keeps, nothing is padded or cut
+        let mut state = RecordingState::new();
+        let frames = state.frames_for_tick(1, 5, Some(from_stereo));
+        assert!(frames.iter().all(|f| f.samples.len() == SAMPLES_PER_TICK));
+    }
+
+    #[test]
+    fn test_decode_failure_log_rate_limits() {
+        let mut log = DecodeFailureLog::default();
+        let start = Instant::now();
+        assert_eq!(log.record(start), Some(1));
+        assert_eq!(log.record(start + Duration::from_secs(1)), None);
+        assert_eq!(log.record(start + Duration::from_secs(2)), None);
+        assert_eq!(log.record(start + DECODE_WARNING_INTERVAL), Some(3));
+    }
+
+    #[test]
+    fn test_consent_gate() {
+        let mut state = RecordingState::new();
+        state.ssrc_map.insert(10, 1);
+        state.ssrc_map.insert(20, 2);
+        assert!(state.has_consent(10) && state.has_consent(30));
+
+        state.consented = Some(BTreeMap::from([(1, Utc::now())]));
+        assert!(state.has_consent(10));
+        assert!(!state.has_consent(20));
+        // Unknown SSRCs could be anyone
+        assert!(!state.has_consent(30));
+    }
+}
@@ -0,0 +1,164 @@
+use super::receiver::SharedRecordingState;
+use songbird::events::context_data::{DisconnectKind, DisconnectReason};
+use songbird::id::{ChannelId, GuildId};
+use songbird::model::CloseCode;
+use songbird::{Event, EventContext, EventHandler, Songbird};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+/// How long to keep trying to rejoin before the recording is finalized
+const REJOIN_TIMEOUT: Duration = Duration::from_secs(60);
+/// Wait between rejoin attempts
+const REJOIN_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Called with the reason when the connection is gone for good, to finalize the recording
+pub type OnConnectionLost = Arc<dyn Fn(String) + Send + Sync>;
+
+/// Rejoins the voice channel when the connection drops mid-recording
+///
+/// Frames stop arriving while the bot is disconnected, so without this a long
+/// recording silently ends early. Songbird retries briefly on its own; this
+/// handles the disconnects it gives up on.
+pub struct ConnectionWatcher {
+    state: SharedRecordingState,
+    manager: Arc<Songbird>,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    on_lost: OnConnectionLost,
+}
+
+impl ConnectionWatcher {
+    pub fn new(
+        state: SharedRecordingState,
+        manager: Arc<Songbird>,
+        guild_id: impl Into<GuildId>,
+        channel_id: impl Into<ChannelId>,
+        on_lost: OnConnectionLost,
+    ) -> Self {
+        Self {
+            state,
+            manager,
+            guild_id: guild_id.into(),
+            channel_id: channel_id.into(),
+            on_lost,
+        }
+    }
+
+    /// Retry joining until it works, the recording stops, or `REJOIN_TIMEOUT` passes
+    async fn rejoin(
+        state: SharedRecordingState,
+        manager: Arc<Songbird>,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        on_lost: OnConnectionLost,
+        reason: String,
+    ) {
+        let deadline = Instant::now() + REJOIN_TIMEOUT;
+        let mut attempt = 0;
+
+        loop {
+            {
+                let mut state = state.lock().await;
+                if !state.active {
+                    // Stopped while disconnected, nothing to rejoin for
+                    state.rejoining = false;
+                    return;
+                }
+            }
+
+            attempt += 1;
+            match manager.join(guild_id, channel_id).await {
+                Ok(_) => {
+                    let mut state = state.lock().await;
+                    state.rejoining = false;
+                    match state.resume() {
+                        Some(gap) => info!(
+                            "Rejoined voice channel in guild {} after {} attempt(s), skipped {} ticks",
+                            guild_id, attempt, gap.ticks
+                        ),
+                        None => info!(
+                            "Rejoined voice channel in guild {} after {} attempt(s)",
+                            guild_id, attempt
+                        ),
+                    }
+                    return;
+                }
+                Err(e) => warn!(
+                    "Rejoin attempt {} in guild {} failed: {:?}",
+                    attempt, guild_id, e
+                ),
+            }
+
+            if Instant::now() + REJOIN_RETRY_INTERVAL > deadline {
+                break;
+            }
+            tokio::time::sleep(REJOIN_RETRY_INTERVAL).await;
+        }
+
+        error!(
+            "Could not rejoin voice channel in guild {} within {:?}, finalizing recording",
+            guild_id, REJOIN_TIMEOUT
+        );
+        state.lock().await.rejoining = false;
+        on_lost(reason);
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for ConnectionWatcher {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        match ctx {
+            EventContext::DriverConnect(_) | EventContext::DriverReconnect(_) => {
+                if let Some(gap) = self.state.lock().await.resume() {
+                    warn!(
+                        "Voice connection in guild {} resumed after {} missed ticks ({})",
+                        self.guild_id, gap.ticks, gap.reason
+                    );
+                }
+            }
+            EventContext::DriverDisconnect(data) => {
+                // No reason means we left on purpose, e.g. /stop-recording
+                let reason = match data.reason {
+                    None | Some(DisconnectReason::Requested) => return None,
+                    Some(reason) => reason,
+                };
+
+                let mut state = self.state.lock().await;
+                if !state.active || state.rejoining {
+                    return None;
+                }
+                let description = format!("{:?}", reason);
+                warn!(
+                    "Voice connection in guild {} lost ({:?}, {})",
+                    self.guild_id, data.kind, description
+                );
+                state.disconnect_reason = Some(description.clone());
+
+                // Kicked or the channel was deleted: Discord asks not to reconnect
+                if data.kind == DisconnectKind::Runtime
+                    && reason == DisconnectReason::WsClosed(Some(CloseCode::Disconnected))
+                {
+                    drop(state);
+                    (self.on_lost)("disconnected from the channel".to_string());
+                    return None;
+                }
+
+                state.rejoining = true;
+                drop(state);
+                tokio::spawn(Self::rejoin(
+                    self.state.clone(),
+                    self.manager.clone(),
+                    self.guild_id,
+                    self.channel_id,
+                    self.on_lost.clone(),
+                    description,
+                ));
+            }
+            _ => {}
+        }
+
+        None
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// An SSRC belonging to a user from a tick on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SsrcAssignment {
+    pub ssrc: u32,
+    pub user_id: u64,
+    pub from_tick: u64,
+}
+
+/// Which user owned each SSRC over the session (`ssrc_history.json`)
+///
+/// `ssrc_map.json` only keeps the last owner of each SSRC. Discord can hand a
+/// reconnecting client an SSRC another user had earlier, so attributing audio
+/// by that map alone credits the first user's speech to the second.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SsrcHistory {
+    /// In the order they were observed, so `from_tick` never decreases per SSRC
+    pub assignments: Vec<SsrcAssignment>,
+}
+
+impl SsrcHistory {
+    pub fn load(path: &Path) -> Option<Self> {
+        let json = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    pub fn record(&mut self, assignment: SsrcAssignment) {
+        self.assignments.push(assignment);
+    }
+
+    /// User owning `ssrc` at `tick`
+    ///
+    /// Audio can arrive shortly before the speaking event naming its user, so
+    /// ticks before the first assignment belong to the first owner.
+    pub fn owner_at(&self, ssrc: u32, tick: u64) -> Option<u64> {
+        let mut owners = self.assignments.iter().filter(|a| a.ssrc == ssrc);
+        let first = owners.next()?;
+        Some(
+            owners
+                .take_while(|a| a.from_tick <= tick)
+                .last()
+                .unwrap_or(first)
+                .user_id,
+        )
+    }
+
+    /// SSRCs that moved between users, with every owner in order
+    pub fn reassigned(&self) -> Vec<(u32, Vec<u64>)> {
+        let mut owners: HashMap<u32, Vec<u64>> = HashMap::new();
+        for a in &self.assignments {
+            let users = owners.entry(a.ssrc).or_default();
+            if users.last() != Some(&a.user_id) {
+                users.push(a.user_id);
+            }
+        }
+
+        let mut reassigned: Vec<(u32, Vec<u64>)> = owners
+            .into_iter()
+            .filter(|(_, users)| users.len() > 1)
+            .collect();
+        reassigned.sort_by_key(|(ssrc, _)| *ssrc);
+        reassigned
+    }
+
+    /// Every SSRC a user owned at some point, sorted
+    pub fn ssrcs_by_user(&self) -> HashMap<u64, Vec<u32>> {
+        let mut user_ssrcs: HashMap<u64, Vec<u32>> = HashMap::new();
+        for a in &self.assignments {
+            let ssrcs = user_ssrcs.entry(a.user_id).or_default();
+            if !ssrcs.contains(&a.ssrc) {
+                ssrcs.push(a.ssrc);
+            }
+        }
+        for ssrcs in user_ssrcs.values_mut() {
+            ssrcs.sort();
+        }
+        user_ssrcs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reused_ssrc_is_attributed_by_tick() {
+        let mut history = SsrcHistory::default();
+        history.record(SsrcAssignment {
+            ssrc: 100,
+            user_id: 1,
+            from_tick: 50,
+        });
+        history.record(SsrcAssignment {
+            ssrc: 200,
+            user_id: 2,
+            from_tick: 60,
+        });
+        // User 1 left, user 3 reconnected and got their SSRC
+        history.record(SsrcAssignment {
+            ssrc: 100,
+            user_id: 3,
+            from_tick: 1000,
+        });
+
+        assert_eq!(history.owner_at(100, 10), Some(1));
+        assert_eq!(history.owner_at(100, 999), Some(1));
+        assert_eq!(history.owner_at(100, 1000), Some(3));
+        assert_eq!(history.owner_at(200, 5000), Some(2));
+        assert_eq!(history.owner_at(300, 0), None);
+
+        assert_eq!(history.reassigned(), vec![(100, vec![1, 3])]);
+        let by_user = history.ssrcs_by_user();
+        assert_eq!(by_user[&1], vec![100]);
+        assert_eq!(by_user[&3], vec![100]);
+        assert_eq!(by_user[&2], vec![200]);
+    }
+}
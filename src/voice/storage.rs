@@ -1,15 +1,22 @@
+use super::audio::UserAudioStats;
+use super::clock::{NOMINAL_TICK_SECS, SessionClock, TickAnchor};
+use super::ssrc_history::{SsrcAssignment, SsrcHistory};
+use crate::paths::{SessionMetadata, SessionPaths};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{File, OpenOptions};
-use std::io::{self, BufWriter, Write};
-use std::path::PathBuf;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
-use tracing::{error, info, warn};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
 
 const TICK_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
 const SSRC_MAP_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
 const CHUNK_DURATION: Duration = Duration::from_secs(10 * 60);
+/// Mono samples in one 20ms tick at 48kHz
+pub const SAMPLES_PER_TICK: usize = 960;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioFrame {
@@ -17,10 +24,218 @@ pub struct AudioFrame {
     pub samples: Vec<i16>,
 }
 
+/// Join frames that share a tick into one full frame per tick, sorted by tick
+///
+/// The receiver writes one `SAMPLES_PER_TICK` frame per tick, but a speaker's
+/// audio can also arrive as several shorter fragments for the same tick.
+/// Fragments are concatenated in the order given, then padded with silence or
+/// trimmed to exactly one tick, so every tick lines up on the shared timeline.
+/// Logs stay usable if they were written out of order, such frames are put
+/// back in place with a warning.
+pub fn compact_frames(frames: impl IntoIterator<Item = AudioFrame>) -> Vec<AudioFrame> {
+    let mut ticks: BTreeMap<u64, Vec<i16>> = BTreeMap::new();
+    let mut last_tick = None;
+    let mut out_of_order = 0usize;
+    for frame in frames {
+        if last_tick.is_some_and(|last| frame.tick_index < last) {
+            out_of_order += 1;
+        }
+        last_tick = Some(frame.tick_index);
+        ticks.entry(frame.tick_index).or_default().extend(frame.samples);
+    }
+    if out_of_order > 0 {
+        warn!("Reordered {} frame(s) that were logged before an earlier tick", out_of_order);
+    }
+
+    ticks
+        .into_iter()
+        .map(|(tick_index, mut samples)| {
+            if samples.len() != SAMPLES_PER_TICK {
+                debug!(
+                    "Compacting tick {}: {} samples to {}",
+                    tick_index,
+                    samples.len(),
+                    SAMPLES_PER_TICK
+                );
+                samples.resize(SAMPLES_PER_TICK, 0);
+            }
+            AudioFrame { tick_index, samples }
+        })
+        .collect()
+}
+
+/// Tick range and frame count of recorded audio, without the samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameScan {
+    pub first_tick: u64,
+    pub last_tick: u64,
+    pub frame_count: u64,
+}
+
+impl FrameScan {
+    /// Span from the first to the end of the last tick, including gaps
+    pub fn duration_secs(&self) -> f64 {
+        (self.last_tick - self.first_tick + 1) as f64 * NOMINAL_TICK_SECS
+    }
+
+    fn merge(self, other: FrameScan) -> FrameScan {
+        FrameScan {
+            first_tick: self.first_tick.min(other.first_tick),
+            last_tick: self.last_tick.max(other.last_tick),
+            frame_count: self.frame_count + other.frame_count,
+        }
+    }
+}
+
+/// Scan a chunk log, reading only the tick index of each line
+///
+/// Samples are skipped without being parsed, which is where nearly all the
+/// cost of loading a log goes. `None` if the log has no frames.
+pub fn scan_chunk_log(path: &Path) -> io::Result<Option<FrameScan>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut line = Vec::new();
+    let mut scan: Option<FrameScan> = None;
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(scan);
+        }
+
+        let tick = line
+            .split(|&b| b == b' ')
+            .next()
+            .and_then(|tick| std::str::from_utf8(tick).ok())
+            .and_then(|tick| tick.trim().parse::<u64>().ok());
+        let Some(tick) = tick else {
+            continue;
+        };
+
+        let frame = FrameScan {
+            first_tick: tick,
+            last_tick: tick,
+            frame_count: 1,
+        };
+        scan = Some(scan.map_or(frame, |scan| scan.merge(frame)));
+    }
+}
+
+/// Scan every chunk log of a session, see `scan_chunk_log`
+///
+/// Counts are per SSRC, so a tick two users spoke in counts twice.
+pub fn scan_session(paths: &SessionPaths) -> io::Result<Option<FrameScan>> {
+    let Ok(ssrc_dirs) = std::fs::read_dir(paths.users_dir()) else {
+        return Ok(None);
+    };
+
+    let mut scan: Option<FrameScan> = None;
+    for ssrc_dir in ssrc_dirs.filter_map(|e| e.ok()).filter(|e| e.path().is_dir()) {
+        for log in std::fs::read_dir(ssrc_dir.path())?.filter_map(|e| e.ok()) {
+            let path = log.path();
+            if path.extension().is_none_or(|ext| ext != "log") {
+                continue;
+            }
+            if let Some(found) = scan_chunk_log(&path)? {
+                scan = Some(scan.map_or(found, |scan| scan.merge(found)));
+            }
+        }
+    }
+
+    Ok(scan)
+}
+
+/// Where a resumed session's tick timeline continues
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResumePoint {
+    /// Last tick with audio or a clock anchor before the session was interrupted
+    pub last_tick: u64,
+    /// First tick to record now; the ticks in between are the time the bot was away
+    pub next_tick: u64,
+}
+
+/// Continue ticks after the last one stored, skipping the wall-clock time
+/// since the last anchor so later timestamps stay right
+fn resume_point(scan: Option<FrameScan>, clock: &SessionClock, now_unix_ms: i64) -> ResumePoint {
+    let last_anchor = clock.anchors.last();
+    let last_tick = scan
+        .map(|scan| scan.last_tick)
+        .into_iter()
+        .chain(last_anchor.map(|anchor| anchor.tick_index))
+        .max()
+        .unwrap_or(0);
+
+    let by_clock = last_anchor.map_or(0, |anchor| {
+        let away_secs = (now_unix_ms - anchor.unix_ms).max(0) as f64 / 1000.0;
+        anchor.tick_index + (away_secs / NOMINAL_TICK_SECS) as u64
+    });
+    ResumePoint {
+        last_tick,
+        next_tick: by_clock.max(last_tick + 1),
+    }
+}
+
+/// Check the first frame of a chunk log is a tick followed by at most one tick of samples
+fn check_chunk_format(path: &Path) -> io::Result<()> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{:?} isn't a chunk log in the current format", path),
+        )
+    };
+
+    let reader = BufReader::new(File::open(path)?);
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (tick, samples) = line.split_once(' ').ok_or_else(invalid)?;
+        tick.parse::<u64>().map_err(|_| invalid())?;
+        let count = samples
+            .split(',')
+            .map(|sample| sample.trim().parse::<i16>())
+            .try_fold(0usize, |count, sample| sample.map(|_| count + 1))
+            .map_err(|_| invalid())?;
+        return if count <= SAMPLES_PER_TICK { Ok(()) } else { Err(invalid()) };
+    }
+    Ok(())
+}
+
+/// Highest chunk number per SSRC, checking each chunk log can be appended to
+fn existing_chunks(paths: &SessionPaths) -> io::Result<HashMap<u32, u32>> {
+    let mut chunks = HashMap::new();
+    let Ok(ssrc_dirs) = std::fs::read_dir(paths.users_dir()) else {
+        return Ok(chunks);
+    };
+
+    for ssrc_dir in ssrc_dirs.filter_map(|e| e.ok()).filter(|e| e.path().is_dir()) {
+        let Some(ssrc) = ssrc_dir.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        for log in std::fs::read_dir(ssrc_dir.path())?.filter_map(|e| e.ok()) {
+            let chunk = log
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("chunk-")?.strip_suffix(".log")?.parse::<u32>().ok());
+            let Some(chunk) = chunk else {
+                continue;
+            };
+            check_chunk_format(&log.path())?;
+            let highest = chunks.entry(ssrc).or_insert(chunk);
+            *highest = (*highest).max(chunk);
+        }
+    }
+    Ok(chunks)
+}
+
 #[derive(Debug)]
 pub enum StorageMessage {
     Frame { ssrc: u32, frame: AudioFrame },
     SsrcMap(HashMap<u32, u64>),
+    SsrcAssignment(SsrcAssignment),
+    TickAnchor(TickAnchor),
     Flush,
     Shutdown,
 }
@@ -39,6 +254,14 @@ impl StorageHandle {
         let _ = self.tx.send(StorageMessage::SsrcMap(ssrc_map));
     }
 
+    pub fn record_ssrc_assignment(&self, assignment: SsrcAssignment) {
+        let _ = self.tx.send(StorageMessage::SsrcAssignment(assignment));
+    }
+
+    pub fn record_tick_anchor(&self, anchor: TickAnchor) {
+        let _ = self.tx.send(StorageMessage::TickAnchor(anchor));
+    }
+
     pub fn shutdown(&self) {
         let _ = self.tx.send(StorageMessage::Shutdown);
     }
@@ -50,55 +273,126 @@ struct SsrcChunkState {
 }
 
 pub struct StorageWriter {
-    session_dir: PathBuf,
-    users_dir: PathBuf,
+    paths: SessionPaths,
     buffers: HashMap<u32, Vec<AudioFrame>>,
     ssrc_map: HashMap<u32, u64>,
     ssrc_chunks: HashMap<u32, SsrcChunkState>,
     session_start: Instant,
     last_tick_flush: Instant,
     last_ssrc_map_flush: Instant,
+    /// Blocking write tasks that may still be running
+    pending_writes: Vec<JoinHandle<io::Result<()>>>,
+    /// Level statistics per SSRC for the whole session
+    stats: HashMap<u32, UserAudioStats>,
+    /// Every SSRC to user mapping seen this session
+    user_ids: HashMap<u32, u64>,
+    /// SSRC mappings from before a resume, kept in `ssrc_map.json` alongside new ones
+    resumed_ssrc_map: HashMap<u32, u64>,
+    clock: SessionClock,
+    /// Anchors recorded since `clock` was last written
+    clock_dirty: bool,
+    ssrc_history: SsrcHistory,
+    ssrc_history_dirty: bool,
     rx: mpsc::UnboundedReceiver<StorageMessage>,
 }
 
 impl StorageWriter {
     pub fn new(session_dir: PathBuf) -> io::Result<(StorageHandle, Self)> {
-        std::fs::create_dir_all(&session_dir)?;
-        let users_dir = session_dir.join("users");
-        std::fs::create_dir_all(&users_dir)?;
-        info!("Created session storage at {:?}", session_dir);
+        let paths = SessionPaths::new(session_dir);
+        std::fs::create_dir_all(paths.users_dir())?;
+        info!("Created session storage at {:?}", paths.root());
 
         let (tx, rx) = mpsc::unbounded_channel();
 
         let handle = StorageHandle { tx };
         let now = Instant::now();
         let writer = Self {
-            session_dir,
-            users_dir,
+            paths,
             buffers: HashMap::new(),
             ssrc_map: HashMap::new(),
             ssrc_chunks: HashMap::new(),
             session_start: now,
             last_tick_flush: now,
             last_ssrc_map_flush: now,
+            pending_writes: Vec::new(),
+            stats: HashMap::new(),
+            user_ids: HashMap::new(),
+            resumed_ssrc_map: HashMap::new(),
+            clock: SessionClock::default(),
+            clock_dirty: false,
+            ssrc_history: SsrcHistory::default(),
+            ssrc_history_dirty: false,
             rx,
         };
 
         Ok((handle, writer))
     }
 
-    pub async fn run(mut self) {
+    /// Continue writing an existing session, e.g. after the bot restarted mid-recording
+    ///
+    /// `new` leaves chunk logs alone but starts the clock, SSRC history and
+    /// map afresh, which overwrites them on the first flush. This loads them
+    /// instead and appends each SSRC's frames to the end of its last chunk
+    /// log. Fails if the directory isn't a recording session or its logs
+    /// aren't in the current format.
+    pub fn open_append(session_dir: PathBuf) -> io::Result<(StorageHandle, Self, ResumePoint)> {
+        let paths = SessionPaths::new(&session_dir);
+        if SessionMetadata::load(&paths.metadata()).is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{:?} is not a recording session", paths.root()),
+            ));
+        }
+        let chunks = existing_chunks(&paths)?;
+        let clock = SessionClock::load(&paths.clock()).unwrap_or_default();
+        let resume = resume_point(scan_session(&paths)?, &clock, chrono::Utc::now().timestamp_millis());
+        let resumed_ssrc_map: HashMap<u32, u64> = std::fs::read_to_string(paths.ssrc_map())
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        let (handle, mut writer) = Self::new(session_dir)?;
+        let now = Instant::now();
+        writer.ssrc_chunks = chunks
+            .into_iter()
+            .map(|(ssrc, current_chunk)| (ssrc, SsrcChunkState { current_chunk, chunk_start: now }))
+            .collect();
+        writer.ssrc_history = SsrcHistory::load(&paths.ssrc_history()).unwrap_or_default();
+        writer.clock = clock;
+        writer.resumed_ssrc_map = resumed_ssrc_map;
+        info!(
+            "Resuming session storage at {:?} from tick {} (last stored tick {})",
+            paths.root(),
+            resume.next_tick,
+            resume.last_tick
+        );
+
+        Ok((handle, writer, resume))
+    }
+
+    /// Write frames until shut down, returning per-user level statistics for the session
+    pub async fn run(mut self) -> HashMap<u64, UserAudioStats> {
         info!("Storage writer task started");
 
         loop {
             match tokio::time::timeout(Duration::from_secs(5), self.rx.recv()).await {
                 Ok(Some(msg)) => match msg {
                     StorageMessage::Frame { ssrc, frame } => {
+                        self.stats.entry(ssrc).or_default().update(&frame.samples);
                         self.buffers.entry(ssrc).or_default().push(frame);
                     }
                     StorageMessage::SsrcMap(map) => {
+                        self.user_ids.extend(map.iter().map(|(k, v)| (*k, *v)));
                         self.ssrc_map = map;
                     }
+                    StorageMessage::SsrcAssignment(assignment) => {
+                        self.ssrc_history.record(assignment);
+                        self.ssrc_history_dirty = true;
+                    }
+                    StorageMessage::TickAnchor(anchor) => {
+                        self.clock.record(anchor);
+                        self.clock_dirty = true;
+                    }
                     StorageMessage::Flush => {
                         if let Err(e) = self.flush_all() {
                             error!("Failed to flush: {}", e);
@@ -109,12 +403,14 @@ impl StorageWriter {
                         if let Err(e) = self.flush_all() {
                             error!("Failed to flush on shutdown: {}", e);
                         }
+                        self.wait_for_pending_writes().await;
                         break;
                     }
                 },
                 Ok(None) => {
                     info!("Storage channel closed, flushing and exiting");
                     let _ = self.flush_all();
+                    self.wait_for_pending_writes().await;
                     break;
                 }
                 Err(_) => {}
@@ -126,13 +422,40 @@ impl StorageWriter {
         }
 
         info!("Storage writer task ended");
+        self.user_stats()
+    }
+
+    /// Statistics merged per user; SSRCs never mapped to a user are left out
+    fn user_stats(&self) -> HashMap<u64, UserAudioStats> {
+        let mut per_user: HashMap<u64, UserAudioStats> = HashMap::new();
+        for (ssrc, stats) in &self.stats {
+            if let Some(user_id) = self.user_ids.get(ssrc) {
+                per_user.entry(*user_id).or_default().merge(stats);
+            }
+        }
+        per_user
+    }
+
+    /// Wait for in-flight disk writes so nothing is lost when the writer exits
+    async fn wait_for_pending_writes(&mut self) {
+        for task in self.pending_writes.drain(..) {
+            match task.await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => error!("Pending write failed: {}", e),
+                Err(e) => error!("Pending write task panicked: {:?}", e),
+            }
+        }
     }
 
     fn try_flush(&mut self) -> io::Result<()> {
+        self.pending_writes.retain(|task| !task.is_finished());
+
         if self.last_tick_flush.elapsed() >= TICK_FLUSH_INTERVAL {
             self.flush_ticks()?;
         }
         if self.last_ssrc_map_flush.elapsed() >= SSRC_MAP_FLUSH_INTERVAL {
+            self.flush_clock();
+            self.flush_ssrc_history();
             self.flush_ssrc_map()?;
         }
         Ok(())
@@ -140,6 +463,8 @@ impl StorageWriter {
 
     fn flush_all(&mut self) -> io::Result<()> {
         self.flush_ticks()?;
+        self.flush_clock();
+        self.flush_ssrc_history();
         self.flush_ssrc_map()?;
         Ok(())
     }
@@ -177,19 +502,19 @@ impl StorageWriter {
         }
 
         let frames_to_flush: Vec<(u32, Vec<AudioFrame>)> = self.buffers.drain().collect();
-        let users_dir = self.users_dir.clone();
+        let paths = self.paths.clone();
 
-        tokio::task::spawn_blocking(move || {
+        let task = tokio::task::spawn_blocking(move || {
             for (ssrc, frames) in frames_to_flush {
                 let chunk_num = ssrc_chunk_map.get(&ssrc).copied().unwrap_or(0);
-                let ssrc_dir = users_dir.join(ssrc.to_string());
+                let ssrc_dir = paths.ssrc_dir(ssrc);
 
                 if let Err(e) = std::fs::create_dir_all(&ssrc_dir) {
                     error!("Failed to create ssrc dir {:?}: {}", ssrc_dir, e);
                     continue;
                 }
 
-                let chunk_path = ssrc_dir.join(format!("chunk-{}.log", chunk_num));
+                let chunk_path = paths.chunk_log(ssrc, chunk_num);
 
                 let file = match OpenOptions::new()
                     .create(true)
@@ -205,6 +530,7 @@ impl StorageWriter {
 
                 let mut writer = BufWriter::new(file);
 
+                let mut written = 0;
                 for frame in frames {
                     let samples_str: String = frame
                         .samples
@@ -213,10 +539,12 @@ impl StorageWriter {
                         .collect::<Vec<_>>()
                         .join(",");
 
-                    if let Err(e) = writeln!(writer, "{} {}", frame.tick_index, samples_str) {
-                        error!("Failed to write frame: {}", e);
+                    match writeln!(writer, "{} {}", frame.tick_index, samples_str) {
+                        Ok(()) => written += 1,
+                        Err(e) => error!("Failed to write frame: {}", e),
                     }
                 }
+                crate::metrics::FRAMES_WRITTEN.inc_by(written);
 
                 if let Err(e) = writer.flush() {
                     error!("Failed to flush writer: {}", e);
@@ -224,11 +552,50 @@ impl StorageWriter {
             }
             Ok::<(), io::Error>(())
         });
+        self.pending_writes.push(task);
 
         self.last_tick_flush = Instant::now();
         Ok(())
     }
 
+    fn flush_clock(&mut self) {
+        if !self.clock_dirty {
+            return;
+        }
+
+        let clock = self.clock.clone();
+        let path = self.paths.clock();
+
+        let task = tokio::task::spawn_blocking(move || {
+            let file = File::create(&path)?;
+            let writer = BufWriter::new(file);
+            serde_json::to_writer_pretty(writer, &clock)?;
+            Ok::<(), io::Error>(())
+        });
+        self.pending_writes.push(task);
+
+        self.clock_dirty = false;
+    }
+
+    fn flush_ssrc_history(&mut self) {
+        if !self.ssrc_history_dirty {
+            return;
+        }
+
+        let history = self.ssrc_history.clone();
+        let path = self.paths.ssrc_history();
+
+        let task = tokio::task::spawn_blocking(move || {
+            let file = File::create(&path)?;
+            let writer = BufWriter::new(file);
+            serde_json::to_writer_pretty(writer, &history)?;
+            Ok::<(), io::Error>(())
+        });
+        self.pending_writes.push(task);
+
+        self.ssrc_history_dirty = false;
+    }
+
     fn flush_ssrc_map(&mut self) -> io::Result<()> {
         if self.ssrc_map.is_empty() {
             self.last_ssrc_map_flush = Instant::now();
@@ -237,18 +604,171 @@ impl StorageWriter {
 
         info!("Flushing ssrc_map with {} entries", self.ssrc_map.len());
 
-        let ssrc_map = self.ssrc_map.clone();
-        self.ssrc_map.clear();
-        let path = self.session_dir.join("ssrc_map.json");
+        let mut ssrc_map = self.resumed_ssrc_map.clone();
+        ssrc_map.extend(self.ssrc_map.drain());
+        let path = self.paths.ssrc_map();
 
-        tokio::task::spawn_blocking(move || {
+        let task = tokio::task::spawn_blocking(move || {
             let file = File::create(&path)?;
             let writer = BufWriter::new(file);
             serde_json::to_writer_pretty(writer, &ssrc_map)?;
             Ok::<(), io::Error>(())
         });
+        self.pending_writes.push(task);
 
         self.last_ssrc_map_flush = Instant::now();
         Ok(())
     }
 }
+
+/// Write an SSRC's first chunk log, one frame at a constant `level` per tick
+#[cfg(test)]
+pub(crate) fn write_chunk_log(paths: &SessionPaths, ssrc: u32, ticks: impl IntoIterator<Item = u64>, level: i16) {
+    std::fs::create_dir_all(paths.ssrc_dir(ssrc)).unwrap();
+    let frame = vec![level.to_string(); SAMPLES_PER_TICK].join(",");
+    let log: String = ticks.into_iter().map(|tick| format!("{} {}\n", tick, frame)).collect();
+    std::fs::write(paths.chunk_log(ssrc, 0), log).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_matches_parsed_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path());
+
+        let frames: Vec<(u32, u32, u64)> = vec![
+            (1111, 0, 10),
+            (1111, 0, 11),
+            (1111, 1, 40),
+            (2222, 0, 5),
+            (2222, 0, 7),
+        ];
+        for &(ssrc, chunk, tick) in &frames {
+            std::fs::create_dir_all(paths.ssrc_dir(ssrc)).unwrap();
+            let mut log = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(paths.chunk_log(ssrc, chunk))
+                .unwrap();
+            writeln!(log, "{} {}", tick, vec!["-12"; SAMPLES_PER_TICK].join(",")).unwrap();
+            writeln!(log).unwrap();
+        }
+
+        // The same log read in full, the way reconstruction parses it
+        let full = std::fs::read_to_string(paths.chunk_log(1111, 0)).unwrap();
+        let parsed: Vec<u64> = full
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.split_once(' ').unwrap().0.parse().unwrap())
+            .collect();
+        let scan = scan_chunk_log(&paths.chunk_log(1111, 0)).unwrap().unwrap();
+        assert_eq!(scan.frame_count as usize, parsed.len());
+        assert_eq!((scan.first_tick, scan.last_tick), (10, 11));
+
+        let session = scan_session(&paths).unwrap().unwrap();
+        assert_eq!(
+            session,
+            FrameScan { first_tick: 5, last_tick: 40, frame_count: frames.len() as u64 }
+        );
+        assert!((session.duration_secs() - 36.0 * NOMINAL_TICK_SECS).abs() < 1e-9);
+
+        let empty = tempfile::tempdir().unwrap();
+        assert_eq!(scan_session(&SessionPaths::new(empty.path())).unwrap(), None);
+    }
+
+    #[test]
+    fn test_resume_point_skips_time_away() {
+        let mut clock = SessionClock::default();
+        clock.record(TickAnchor { tick_index: 3000, unix_ms: 1_000_000 });
+        let scan = FrameScan { first_tick: 10, last_tick: 3100, frame_count: 5 };
+
+        // Ten seconds after the last anchor
+        assert_eq!(
+            resume_point(Some(scan), &clock, 1_010_000),
+            ResumePoint { last_tick: 3100, next_tick: 3500 }
+        );
+        // A host clock behind the stored audio never rewinds the timeline
+        assert_eq!(resume_point(Some(scan), &clock, 999_000).next_tick, 3101);
+        assert_eq!(resume_point(Some(scan), &SessionClock::default(), 0).next_tick, 3101);
+    }
+
+    #[tokio::test]
+    async fn test_open_append_continues_existing_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = SessionPaths::new(dir.path());
+        let frame = |tick| format!("{} {}\n", tick, vec!["-12"; SAMPLES_PER_TICK].join(","));
+
+        // Not a recording session
+        assert!(StorageWriter::open_append(dir.path().to_path_buf()).is_err());
+
+        std::fs::write(
+            paths.metadata(),
+            r#"{"guild_id": 42, "session_id": "2026_01_03_18_49_53", "started_at": "2026-01-03T18:49:53Z"}"#,
+        )
+        .unwrap();
+        write_chunk_log(&paths, 1111, [10, 11], -12);
+        std::fs::write(paths.chunk_log(1111, 1), frame(40)).unwrap();
+        std::fs::write(paths.ssrc_map(), r#"{"1111": 42}"#).unwrap();
+
+        let (handle, writer, resume) = StorageWriter::open_append(dir.path().to_path_buf()).unwrap();
+        assert_eq!(resume, ResumePoint { last_tick: 40, next_tick: 41 });
+
+        let task = tokio::spawn(writer.run());
+        for (ssrc, tick) in [(1111, 41), (2222, 42)] {
+            handle.buffer_frame(ssrc, AudioFrame { tick_index: tick, samples: vec![5; SAMPLES_PER_TICK] });
+        }
+        handle.update_ssrc_map(HashMap::from([(2222, 7)]));
+        handle.shutdown();
+        task.await.unwrap();
+
+        // Appended to the end of the last chunk, earlier chunks untouched
+        let scan = |chunk| scan_chunk_log(&paths.chunk_log(1111, chunk)).unwrap().unwrap();
+        assert_eq!(scan(0), FrameScan { first_tick: 10, last_tick: 11, frame_count: 2 });
+        assert_eq!(scan(1), FrameScan { first_tick: 40, last_tick: 41, frame_count: 2 });
+        let ssrc_map: HashMap<u32, u64> =
+            serde_json::from_str(&std::fs::read_to_string(paths.ssrc_map()).unwrap()).unwrap();
+        assert_eq!(ssrc_map, HashMap::from([(1111, 42), (2222, 7)]));
+
+        // Logs in another format aren't appended to
+        std::fs::create_dir_all(paths.ssrc_dir(3333)).unwrap();
+        std::fs::write(paths.chunk_log(3333, 0), "10 a,b\n").unwrap();
+        let error = StorageWriter::open_append(dir.path().to_path_buf()).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_compact_frames_joins_fragments_per_tick() {
+        let frame = |tick_index, len, value| AudioFrame {
+            tick_index,
+            samples: vec![value; len],
+        };
+        let frames = vec![
+            frame(11, SAMPLES_PER_TICK, 7),
+            // Tick 10 arrived in two fragments, after tick 11
+            frame(10, 400, 1),
+            frame(10, 400, 2),
+            // Tick 12 has more samples than fit in one tick
+            frame(12, 700, 3),
+            frame(12, 700, 4),
+        ];
+
+        let compacted = compact_frames(frames);
+
+        assert_eq!(compacted.iter().map(|f| f.tick_index).collect::<Vec<_>>(), [10, 11, 12]);
+        assert!(compacted.iter().all(|f| f.samples.len() == SAMPLES_PER_TICK));
+
+        let tick10 = &compacted[0].samples;
+        assert!(tick10[..400].iter().all(|&s| s == 1));
+        assert!(tick10[400..800].iter().all(|&s| s == 2));
+        assert!(tick10[800..].iter().all(|&s| s == 0));
+
+        assert!(compacted[1].samples.iter().all(|&s| s == 7));
+
+        let tick12 = &compacted[2].samples;
+        assert!(tick12[..700].iter().all(|&s| s == 3));
+        assert!(tick12[700..].iter().all(|&s| s == 4));
+    }
+}
@@ -0,0 +1,163 @@
+use super::audio::{AdaptiveVadConfig, detect_voice_activity_adaptive, i16_to_f32};
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Size of a rendered waveform in pixels
+pub const WAVEFORM_WIDTH: usize = 1200;
+pub const WAVEFORM_HEIGHT: usize = 160;
+
+const BACKGROUND: [u8; 3] = [0x20, 0x22, 0x25];
+/// Columns with detected speech
+const SPEECH_COLOR: [u8; 3] = [0x06, 0xd6, 0xa0];
+/// Columns with only background noise
+const SILENCE_COLOR: [u8; 3] = [0x6c, 0x75, 0x7d];
+/// Center line, so fully silent stretches still show the timeline
+const AXIS_COLOR: [u8; 3] = [0x40, 0x44, 0x48];
+
+/// Peak level (0..1) and whether any tick held speech, per image column
+///
+/// Frames are keyed by tick; ticks without a frame are silence. Speech is
+/// detected per tick against an adaptive noise floor, so a noisy mic doesn't
+/// color everything as speech.
+pub fn peak_envelope(frames: &BTreeMap<u64, Vec<i16>>, columns: usize) -> Vec<(f32, bool)> {
+    let (Some(&first_tick), Some(&last_tick)) = (frames.keys().next(), frames.keys().next_back())
+    else {
+        return Vec::new();
+    };
+    let ticks = (last_tick - first_tick + 1) as usize;
+
+    // Per-tick peak and RMS amplitude; the VAD treats each RMS value as a one-sample window
+    let mut peaks = vec![0.0f32; ticks];
+    let mut rms = vec![0.0f32; ticks];
+    for (&tick, samples) in frames {
+        let index = (tick - first_tick) as usize;
+        peaks[index] = samples
+            .iter()
+            .map(|&s| i16_to_f32(s).abs())
+            .fold(0.0, f32::max);
+        let sum_squares: f32 = samples.iter().map(|&s| i16_to_f32(s).powi(2)).sum();
+        rms[index] = (sum_squares / samples.len().max(1) as f32).sqrt();
+    }
+    let speech = detect_voice_activity_adaptive(&rms, 1, &AdaptiveVadConfig::default());
+
+    (0..columns)
+        .map(|column| {
+            let start = column * ticks / columns;
+            let end = ((column + 1) * ticks / columns).max(start + 1).min(ticks);
+            if start >= ticks {
+                return (0.0, false);
+            }
+            let peak = peaks[start..end].iter().copied().fold(0.0, f32::max);
+            (peak, speech[start..end].iter().any(|&s| s))
+        })
+        .collect()
+}
+
+/// Waveform image of a user's frames as PNG, speech in green and silence in gray
+pub fn render_waveform_png(frames: &BTreeMap<u64, Vec<i16>>) -> Vec<u8> {
+    let (width, height) = (WAVEFORM_WIDTH, WAVEFORM_HEIGHT);
+    let mut pixels: Vec<u8> = BACKGROUND.repeat(width * height);
+    let mut set = |x: usize, y: usize, color: [u8; 3]| {
+        let at = (y * width + x) * 3;
+        pixels[at..at + 3].copy_from_slice(&color);
+    };
+
+    let middle = height / 2;
+    for x in 0..width {
+        set(x, middle, AXIS_COLOR);
+    }
+    for (x, (peak, speech)) in peak_envelope(frames, width).into_iter().enumerate() {
+        let half = ((peak.min(1.0) * (height / 2) as f32).round() as usize).min(middle);
+        let color = if speech { SPEECH_COLOR } else { SILENCE_COLOR };
+        for y in middle - half..=(middle + half).min(height - 1) {
+            set(x, y, color);
+        }
+    }
+
+    encode_png_rgb(width, height, &pixels)
+}
+
+/// Append a PNG chunk: length, type, data and the CRC of type and data
+fn write_png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend_from_slice(&crc.finalize().to_be_bytes());
+}
+
+/// Encode 8-bit RGB pixels, row by row from the top, as a PNG file
+fn encode_png_rgb(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8-bit depth, truecolor, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_png_chunk(&mut png, b"IHDR", &header);
+
+    // Every row starts with its filter type, 0 for none
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in pixels.chunks(width * 3) {
+        encoder
+            .write_all(&[0])
+            .expect("writing to a Vec can't fail");
+        encoder.write_all(row).expect("writing to a Vec can't fail");
+    }
+    let data = encoder.finish().expect("writing to a Vec can't fail");
+    write_png_chunk(&mut png, b"IDAT", &data);
+
+    write_png_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_render_waveform_png() {
+        // Quiet noise with a loud burst in the middle
+        let mut frames = BTreeMap::new();
+        for tick in 100..400u64 {
+            let level = if (200..250).contains(&tick) {
+                16000
+            } else {
+                30
+            };
+            frames.insert(tick, vec![level; 960]);
+        }
+
+        let envelope = peak_envelope(&frames, 30);
+        assert_eq!(envelope.len(), 30);
+        assert!(!envelope[0].1 && envelope[0].0 < 0.01);
+        assert!(envelope[12].1 && envelope[12].0 > 0.4);
+
+        let png = render_waveform_png(&frames);
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(&png[12..16], b"IHDR");
+        let width = u32::from_be_bytes(png[16..20].try_into().unwrap());
+        let height = u32::from_be_bytes(png[20..24].try_into().unwrap());
+        assert_eq!(
+            (width as usize, height as usize),
+            (WAVEFORM_WIDTH, WAVEFORM_HEIGHT)
+        );
+
+        // One filter byte and RGB pixels per row
+        let idat_len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let mut raw = Vec::new();
+        ZlibDecoder::new(&png[41..41 + idat_len])
+            .read_to_end(&mut raw)
+            .unwrap();
+        assert_eq!(raw.len(), (WAVEFORM_WIDTH * 3 + 1) * WAVEFORM_HEIGHT);
+        assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+    }
+}